@@ -27,7 +27,9 @@ fn main() {
     let mut mag_response = [0.; 7];
     let mut phase_response = [0.; 7];
 
-    biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+    biquad
+        .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+        .unwrap();
 
     println!("=================================");
     println!("Biquad filter frequency response:");
@@ -54,7 +56,9 @@ fn main() {
     // enjoy listening
     std::thread::sleep(std::time::Duration::from_secs(4));
 
-    biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+    biquad
+        .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+        .unwrap();
     println!("=================================");
     println!("Biquad filter frequency response:");
     println!("=================================");
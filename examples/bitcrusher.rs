@@ -0,0 +1,131 @@
+use web_audio_api::alloc::AudioBuffer;
+use web_audio_api::context::{AsBaseAudioContext, AudioContext};
+use web_audio_api::node::AudioScheduledSourceNode;
+use web_audio_api::param::{AudioParamOptions, AutomationRate};
+use web_audio_api::worklet::{
+    AudioParamDescriptor, AudioWorkletNodeOptions, Worklet, WorkletParameters,
+};
+use web_audio_api::SampleRate;
+
+/// Bitcrusher, reducing both the bit depth and the sample rate of its input
+///
+/// Port of the `AudioWorkletProcessor` example of the Web Audio API specification
+struct Bitcrusher {
+    /// position in the current sample-and-hold period, in the range [0, 1)
+    phase: f32,
+    /// value held for the current period, per channel
+    last_sample_value: Vec<f32>,
+}
+
+impl Worklet for Bitcrusher {
+    type ProcessorOptions = ();
+
+    fn parameter_descriptors() -> Vec<AudioParamDescriptor> {
+        vec![
+            AudioParamDescriptor {
+                name: "bit_depth",
+                options: AudioParamOptions {
+                    automation_rate: AutomationRate::A,
+                    default_value: 12.,
+                    min_value: 1.,
+                    max_value: 16.,
+                },
+            },
+            AudioParamDescriptor {
+                name: "frequency_reduction",
+                options: AudioParamOptions {
+                    automation_rate: AutomationRate::A,
+                    default_value: 0.5,
+                    min_value: 0.,
+                    max_value: 1.,
+                },
+            },
+        ]
+    }
+
+    fn constructor(_options: ()) -> Self {
+        Self {
+            phase: 0.,
+            last_sample_value: vec![],
+        }
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[AudioBuffer],
+        outputs: &mut [AudioBuffer],
+        params: WorkletParameters,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        *output = input.clone();
+
+        let bit_depth = params.get("bit_depth");
+        let frequency_reduction = params.get("frequency_reduction");
+
+        self.last_sample_value
+            .resize(output.number_of_channels(), 0.);
+
+        let start_phase = self.phase;
+        for (channel, last) in output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.last_sample_value.iter_mut())
+        {
+            // all channels advance the same phase
+            let mut phase = start_phase;
+
+            for ((sample, depth), reduction) in
+                channel.iter_mut().zip(bit_depth).zip(frequency_reduction)
+            {
+                let step = 0.5f32.powf(*depth);
+
+                phase += reduction;
+                if phase >= 1. {
+                    phase -= 1.;
+                    *last = step * (*sample / step + 0.5).floor();
+                }
+                *sample = *last;
+            }
+
+            self.phase = phase;
+        }
+
+        false
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let context = AudioContext::new(None);
+
+    // a crushed sine
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(220.);
+
+    let bitcrusher = context.register_worklet::<Bitcrusher>(AudioWorkletNodeOptions::default());
+
+    // sweep the bit depth down from 12 to 2 bits
+    let bit_depth = bitcrusher.parameter("bit_depth").unwrap();
+    bit_depth.set_value_at_time(12., 0.);
+    bit_depth.linear_ramp_to_value_at_time(2., 4.);
+
+    // lower the sample rate by a factor of 4
+    bitcrusher
+        .parameter("frequency_reduction")
+        .unwrap()
+        .set_value(0.25);
+
+    let gain = context.create_gain();
+    gain.gain().set_value(0.5);
+
+    context
+        .chain(&[&osc, &bitcrusher, &gain, &context.destination()])
+        .unwrap();
+    osc.start();
+
+    std::thread::sleep(std::time::Duration::from_secs(4));
+}
@@ -6,6 +6,7 @@ fn main() {
         sample_rate: Some(48_000),
         channels: Some(2),
         latency_hint: Some(LatencyHint::Playback),
+        device_id: None,
     };
 
     let context = AudioContext::new(Some(options));
@@ -1,14 +1,18 @@
 use web_audio_api::context::{AsBaseAudioContext, AudioContext};
-use web_audio_api::media::Microphone;
 use web_audio_api::node::AudioNode;
 
 fn main() {
     env_logger::init();
     let context = AudioContext::new(None);
 
-    let stream = Microphone::new();
-    // register as media element in the audio context
-    let background = context.create_media_stream_source(stream);
+    // capture the default input, resampled to the sample rate of the context
+    let background = match context.create_media_stream_source_from_default_input() {
+        Ok(node) => node,
+        Err(e) => {
+            eprintln!("Unable to open the microphone: {}", e);
+            return;
+        }
+    };
     // connect the node to the destination node (speakers)
     background.connect(&context.destination());
 
@@ -203,6 +203,17 @@ impl AudioBuffer {
         &mut self.channels[index]
     }
 
+    /// Get the samples from this specific channel, or `None` if the index is out of range.
+    pub fn try_channel_data(&self, index: usize) -> Option<&ChannelData> {
+        self.channels.get(index)
+    }
+
+    /// Get the samples (mutable) from this specific channel, or `None` if the index is out of
+    /// range.
+    pub fn try_channel_data_mut(&mut self, index: usize) -> Option<&mut ChannelData> {
+        self.channels.get_mut(index)
+    }
+
     /// Channel data as slice
     pub fn channels(&self) -> &[ChannelData] {
         &self.channels[..]
@@ -214,12 +225,15 @@ impl AudioBuffer {
     }
 
     /// Up/Down-mix to the desired number of channels
+    ///
+    /// Speaker layouts (mono, stereo, quad and 5.1) are mixed with the matrices of the
+    /// specification, other channel counts are mixed as discrete channels.
     pub fn mix(
         &mut self,
         computed_number_of_channels: usize,
         interpretation: ChannelInterpretation,
     ) {
-        assert!(computed_number_of_channels < MAX_CHANNELS);
+        assert!(computed_number_of_channels <= MAX_CHANNELS);
 
         if self.number_of_channels() == computed_number_of_channels {
             return;
@@ -425,16 +439,9 @@ impl AudioBuffer {
                         .for_each(|(r, c)| *r += sqrt05 * c);
                 }
 
-                _ => panic!(
-                    "{mixing} from {from} to {to} channels not supported",
-                    mixing = if self.number_of_channels() < computed_number_of_channels {
-                        "Up-mixing"
-                    } else {
-                        "Down-mixing"
-                    },
-                    from = self.number_of_channels(),
-                    to = computed_number_of_channels,
-                ),
+                // other layouts are not covered by the specification, mix them as discrete
+                // channels
+                _ => self.mix(computed_number_of_channels, ChannelInterpretation::Discrete),
             }
         }
     }
@@ -1313,6 +1320,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_audiobuffer_mix_speakers_fallback_to_discrete() {
+        let alloc = Alloc::with_capacity(1);
+
+        {
+            // 1 -> 3 is not a speaker layout, fill with silence
+            let mut signal = alloc.silence();
+            signal.copy_from_slice(&[1.; BUFFER_SIZE]);
+            let mut buffer = AudioBuffer::new(signal);
+
+            buffer.mix(3, ChannelInterpretation::Speakers);
+            assert_eq!(buffer.number_of_channels(), 3);
+            assert_float_eq!(
+                &buffer.channel_data(0)[..],
+                &[1.; BUFFER_SIZE][..],
+                ulps_all <= 0
+            );
+            assert_float_eq!(
+                &buffer.channel_data(1)[..],
+                &[0.; BUFFER_SIZE][..],
+                ulps_all <= 0
+            );
+            assert_float_eq!(
+                &buffer.channel_data(2)[..],
+                &[0.; BUFFER_SIZE][..],
+                ulps_all <= 0
+            );
+        }
+
+        {
+            // 3 -> 2 is not a speaker layout, truncate
+            let mut left = alloc.silence();
+            left.copy_from_slice(&[0.25; BUFFER_SIZE]);
+            let mut right = alloc.silence();
+            right.copy_from_slice(&[0.5; BUFFER_SIZE]);
+            let mut buffer = AudioBuffer::new(left);
+            buffer.channels.push(right);
+            buffer.channels.push(alloc.silence());
+
+            buffer.mix(2, ChannelInterpretation::Speakers);
+            assert_eq!(buffer.number_of_channels(), 2);
+            assert_float_eq!(
+                &buffer.channel_data(0)[..],
+                &[0.25; BUFFER_SIZE][..],
+                ulps_all <= 0
+            );
+            assert_float_eq!(
+                &buffer.channel_data(1)[..],
+                &[0.5; BUFFER_SIZE][..],
+                ulps_all <= 0
+            );
+        }
+    }
+
+    #[test]
+    fn test_audiobuffer_mix_max_channels() {
+        let alloc = Alloc::with_capacity(1);
+        let mut buffer = AudioBuffer::new(alloc.silence());
+
+        buffer.mix(MAX_CHANNELS, ChannelInterpretation::Discrete);
+        assert_eq!(buffer.number_of_channels(), MAX_CHANNELS);
+    }
+
     #[test]
     fn test_audiobuffer_add() {
         let alloc = Alloc::with_capacity(1);
@@ -1,12 +1,80 @@
+//! Frequency-domain analysis, with pluggable FFT backends
 use crate::alloc::ChannelData;
 use crate::BUFFER_SIZE;
 
-use realfft::{num_complex::Complex, RealFftPlanner};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
 
 use std::f32::consts::PI;
+use std::sync::atomic::{fence, AtomicU32, AtomicUsize, Ordering};
 
 const MAX_QUANTA: usize = 256;
 const MAX_SAMPLES: usize = MAX_QUANTA * BUFFER_SIZE as usize;
+/// Twice the largest FFT size, so the render thread can keep writing during a snapshot
+const RING_BUFFER_SIZE: usize = 2 * MAX_SAMPLES;
+
+/// Real-to-complex FFT, the backend of the frequency-domain analysis and convolution
+///
+/// Implement this trait to replace the default [`RealFftProcessor`], e.g. with a smaller FFT
+/// for embedded targets or an accelerated one. The processor runs on the render thread, so it
+/// should not allocate or block once warmed up. Both transforms are not normalized.
+pub trait FftProcessor: Send {
+    /// Compute the spectrum of `input`, the FFT size is given by the length of `input`
+    ///
+    /// `output` has length `input.len() / 2 + 1`. The contents of `input` may be overwritten.
+    fn process(&mut self, input: &mut [f32], output: &mut [Complex<f32>]);
+
+    /// Compute the signal of the spectrum `input`, the FFT size is given by the length of `output`
+    ///
+    /// `input` has length `output.len() / 2 + 1`. The contents of `input` may be overwritten.
+    fn process_inverse(&mut self, input: &mut [Complex<f32>], output: &mut [f32]);
+}
+
+/// Default FFT backend, based on the `realfft` crate (a wrapper around `rustfft`)
+pub struct RealFftProcessor {
+    planner: RealFftPlanner<f32>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl RealFftProcessor {
+    /// Create a new processor, with enough scratch space for all FFT sizes of the analyser
+    pub fn new() -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let scratch = planner.plan_fft_forward(MAX_SAMPLES).make_scratch_vec();
+
+        Self { planner, scratch }
+    }
+}
+
+impl Default for RealFftProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RealFftProcessor {
+    /// Scratch space of at least `len` values, only allocates for a size that was not used before
+    fn scratch(&mut self, len: usize) -> &mut [Complex<f32>] {
+        if self.scratch.len() < len {
+            self.scratch.resize(len, Complex::default());
+        }
+        &mut self.scratch[..len]
+    }
+}
+
+impl FftProcessor for RealFftProcessor {
+    fn process(&mut self, input: &mut [f32], output: &mut [Complex<f32>]) {
+        let r2c = self.planner.plan_fft_forward(input.len());
+        let scratch = self.scratch(r2c.get_scratch_len());
+        r2c.process_with_scratch(input, output, scratch).unwrap();
+    }
+
+    fn process_inverse(&mut self, input: &mut [Complex<f32>], output: &mut [f32]) {
+        let c2r = self.planner.plan_fft_inverse(output.len());
+        let scratch = self.scratch(c2r.get_scratch_len());
+        c2r.process_with_scratch(input, output, scratch).unwrap();
+    }
+}
 
 /// Blackman window values iterator with alpha = 0.16
 pub fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
@@ -21,6 +89,72 @@ pub fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
     })
 }
 
+/// Time domain history, shared between the render thread (writer) and control thread (reader)
+///
+/// The samples are stored as `f32` bits in atomics, so the render thread never blocks. A
+/// snapshot is retried when the render thread overwrote the samples being read.
+pub(crate) struct AnalyserRingBuffer {
+    samples: Box<[AtomicU32]>,
+    /// number of samples written so far, updated after writing
+    written: AtomicUsize,
+    /// number of samples written once the current write is done, updated before writing
+    writing: AtomicUsize,
+}
+
+impl AnalyserRingBuffer {
+    pub fn new() -> Self {
+        Self {
+            samples: (0..RING_BUFFER_SIZE).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            writing: AtomicUsize::new(0),
+        }
+    }
+
+    /// Append samples, only called from the render thread
+    pub fn write(&self, data: &[f32]) {
+        let written = self.written.load(Ordering::Relaxed);
+        let end = written + data.len();
+        self.writing.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        data.iter().enumerate().for_each(|(i, v)| {
+            self.samples[(written + i) % RING_BUFFER_SIZE].store(v.to_bits(), Ordering::Relaxed)
+        });
+
+        self.written.store(end, Ordering::Release);
+    }
+
+    /// Copy the most recent samples, aligned on the end of `buffer`
+    ///
+    /// The start of the buffer is padded with zeros when fewer samples were written.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `buffer` is larger than the largest FFT size
+    pub fn read(&self, buffer: &mut [f32]) {
+        assert!(buffer.len() <= MAX_SAMPLES);
+
+        loop {
+            let end = self.written.load(Ordering::Acquire);
+            let available = end.min(buffer.len());
+            let start = end - available;
+
+            let (padding, data) = buffer.split_at_mut(buffer.len() - available);
+            padding.iter_mut().for_each(|v| *v = 0.);
+            data.iter_mut().enumerate().for_each(|(i, v)| {
+                let bits = self.samples[(start + i) % RING_BUFFER_SIZE].load(Ordering::Relaxed);
+                *v = f32::from_bits(bits);
+            });
+
+            // done if the oldest sample read was not overwritten meanwhile
+            fence(Ordering::Acquire);
+            if self.writing.load(Ordering::Relaxed) - start <= RING_BUFFER_SIZE {
+                return;
+            }
+        }
+    }
+}
+
 /// Ring buffer for time domain analysis
 struct TimeAnalyser {
     buffer: Vec<ChannelData>,
@@ -55,7 +189,7 @@ impl TimeAnalyser {
         let processed_samples = processed as usize * BUFFER_SIZE as usize;
 
         // cycle is complete when divisible by fft_size
-        if processed_samples % fft_size == 0 {
+        if processed_samples.is_multiple_of(fft_size) {
             self.previous_cycle_index = self.index;
             return true;
         }
@@ -78,14 +212,14 @@ impl TimeAnalyser {
             .rev()
             .chain(std::iter::repeat(&silence));
 
-        // split the output buffer in same sized chunks
+        // split the output buffer in same sized chunks, aligned on its end
         let true_size = fft_size.min(buffer.len());
-        let buf_chunks = buffer[0..true_size].chunks_mut(BUFFER_SIZE as usize).rev();
+        let buf_chunks = buffer[0..true_size].rchunks_mut(BUFFER_SIZE as usize);
 
-        // copy data from internal buffer to output buffer
+        // copy the most recent data from internal buffer to output buffer
         buf_chunks
             .zip(data_chunks)
-            .for_each(|(b, d)| b.copy_from_slice(&d[..b.len()]));
+            .for_each(|(b, d)| b.copy_from_slice(&d[d.len() - b.len()..]));
     }
 }
 
@@ -93,9 +227,8 @@ impl TimeAnalyser {
 pub(crate) struct Analyser {
     time: TimeAnalyser,
 
-    fft_planner: RealFftPlanner<f32>,
+    fft: Box<dyn FftProcessor>,
     fft_input: Vec<f32>,
-    fft_scratch: Vec<Complex<f32>>,
     fft_output: Vec<Complex<f32>>,
 
     current_fft_size: usize,
@@ -104,14 +237,15 @@ pub(crate) struct Analyser {
 }
 
 impl Analyser {
-    /// Create a new analyser kernel
+    /// Create a new analyser kernel, using the default FFT backend
     pub fn new(initial_fft_size: usize) -> Self {
-        let mut fft_planner = RealFftPlanner::<f32>::new();
-        let max_fft = fft_planner.plan_fft_forward(MAX_SAMPLES);
+        Self::with_fft(initial_fft_size, Box::new(RealFftProcessor::new()))
+    }
 
-        let fft_input = max_fft.make_input_vec();
-        let fft_scratch = max_fft.make_scratch_vec();
-        let fft_output = max_fft.make_output_vec();
+    /// Create a new analyser kernel with the given FFT backend
+    pub fn with_fft(initial_fft_size: usize, fft: Box<dyn FftProcessor>) -> Self {
+        let fft_input = vec![0.; MAX_SAMPLES];
+        let fft_output = vec![Complex::default(); MAX_SAMPLES / 2 + 1];
         let previous_block = vec![0.; fft_output.len()];
 
         // precalculate Blackman window values, reserve enough space for all input sizes
@@ -120,9 +254,8 @@ impl Analyser {
 
         Self {
             time: TimeAnalyser::new(),
-            fft_planner,
+            fft,
             fft_input,
-            fft_scratch,
             fft_output,
             current_fft_size: initial_fft_size,
             previous_block,
@@ -139,26 +272,40 @@ impl Analyser {
         self.time.add_data(data);
     }
 
-    /// Read out the time domain ring buffer (max `fft_size samples)
-    pub fn get_float_time(&self, buffer: &mut [f32], fft_size: usize) {
-        self.time.get_float_time(buffer, fft_size);
-    }
-
     /// Check if we have completed a full round of `fft_size` samples
     pub fn check_complete_cycle(&mut self, fft_size: usize) -> bool {
         self.time.check_complete_cycle(fft_size)
     }
 
-    /// Copy the frequency data
+    /// The frequency data in dB, `fft_size / 2` values
+    fn decibels(&self) -> impl Iterator<Item = f32> + '_ {
+        // nomalizing and conversion to dB
+        let norm = 20. * (self.current_fft_size as f32).sqrt().log10();
+        self.previous_block[..self.current_fft_size / 2]
+            .iter()
+            .map(move |o| 20. * o.log10() - norm)
+    }
+
+    /// Copy the frequency data, in dB
+    ///
+    /// At most `fft_size / 2` values are written, the rest of the buffer is left unaltered.
     pub fn get_float_frequency(&mut self, buffer: &mut [f32]) {
-        let previous_block = &mut self.previous_block[..self.current_fft_size / 2 + 1];
+        buffer
+            .iter_mut()
+            .zip(self.decibels())
+            .for_each(|(b, d)| *b = d);
+    }
 
-        // nomalizing, conversion to dB and fill buffer
-        let norm = 20. * (self.current_fft_size as f32).sqrt().log10();
+    /// Copy the frequency data, scaled from the `[min_decibels, max_decibels]` range to bytes
+    ///
+    /// Values outside the range are clamped to 0 and 255. At most `fft_size / 2` values are
+    /// written, the rest of the buffer is left unaltered.
+    pub fn get_byte_frequency(&mut self, buffer: &mut [u8], min_decibels: f32, max_decibels: f32) {
+        let scale = 255. / (max_decibels - min_decibels);
         buffer
             .iter_mut()
-            .zip(previous_block.iter())
-            .for_each(|(b, o)| *b = 20. * o.log10() - norm);
+            .zip(self.decibels())
+            .for_each(|(b, d)| *b = (scale * (d - min_decibels)).floor().clamp(0., 255.) as u8);
     }
 
     /// Calculate the frequency data
@@ -177,12 +324,9 @@ impl Analyser {
             self.current_fft_size = fft_size;
         }
 
-        let r2c = self.fft_planner.plan_fft_forward(fft_size);
-
         // setup proper sized buffers
         let input = &mut self.fft_input[..fft_size];
         let output = &mut self.fft_output[..fft_size / 2 + 1];
-        let scratch = &mut self.fft_scratch[..r2c.get_scratch_len()];
         let previous_block = &mut self.previous_block[..fft_size / 2 + 1];
 
         // put time domain data in fft_input
@@ -195,7 +339,7 @@ impl Analyser {
             .for_each(|(i, b)| *i *= *b);
 
         // calculate frequency data
-        r2c.process_with_scratch(input, output, scratch).unwrap();
+        self.fft.process(input, output);
 
         // smoothing over time
         previous_block
@@ -263,6 +407,62 @@ mod tests {
         assert_float_eq!(&buffer[..], &[257.; 32][..], ulps_all <= 0);
     }
 
+    #[test]
+    fn test_time_domain_alignment() {
+        let alloc = Alloc::with_capacity(256);
+        let mut analyser = TimeAnalyser::new();
+
+        for quantum in 0..2 {
+            let mut signal = alloc.silence();
+            signal
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, s)| *s = (quantum * LEN + i) as f32);
+            analyser.add_data(signal);
+        }
+
+        // a small fft_size yields the most recent samples
+        let mut buffer = [0.; 32];
+        analyser.get_float_time(&mut buffer[..], 32);
+        let expected: Vec<f32> = (2 * LEN - 32..2 * LEN).map(|i| i as f32).collect();
+        assert_float_eq!(&buffer[..], &expected[..], ulps_all <= 0);
+
+        // a size in between quanta spans them
+        let mut buffer = [0.; 160];
+        analyser.get_float_time(&mut buffer[..], 160);
+        let expected: Vec<f32> = (2 * LEN - 160..2 * LEN).map(|i| i as f32).collect();
+        assert_float_eq!(&buffer[..], &expected[..], ulps_all <= 0);
+
+        // fewer samples than requested, the start is zero padded
+        let mut buffer = vec![-1.; 4 * LEN];
+        analyser.get_float_time(&mut buffer[..], 4 * LEN);
+        assert_float_eq!(&buffer[..2 * LEN], &[0.; 2 * LEN][..], ulps_all <= 0);
+        let expected: Vec<f32> = (0..2 * LEN).map(|i| i as f32).collect();
+        assert_float_eq!(&buffer[2 * LEN..], &expected[..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let ring_buffer = AnalyserRingBuffer::new();
+
+        // zero padded at the start
+        ring_buffer.write(&[1., 2., 3.]);
+        let mut buffer = [-1.; 5];
+        ring_buffer.read(&mut buffer);
+        assert_float_eq!(buffer, [0., 0., 1., 2., 3.], ulps_all <= 0);
+
+        // wraps around, the most recent samples are read
+        let data: Vec<f32> = (0..RING_BUFFER_SIZE + 100).map(|i| i as f32).collect();
+        data.chunks(LEN).for_each(|chunk| ring_buffer.write(chunk));
+        let mut buffer = vec![0.; MAX_SAMPLES];
+        ring_buffer.read(&mut buffer);
+        assert_float_eq!(
+            &buffer[..],
+            &data[data.len() - MAX_SAMPLES..],
+            ulps_all <= 0
+        );
+    }
+
     #[test]
     fn test_complete_cycle() {
         let alloc = Alloc::with_capacity(256);
@@ -300,13 +500,9 @@ mod tests {
         analyser.calculate_float_frequency(fft_size, 0.8);
         analyser.get_float_frequency(&mut buffer[..]);
 
-        // only N / 2 + 1 values should contain frequency data, rest is unaltered
-        assert!(buffer[0..LEN * 2 + 1] == [f32::NEG_INFINITY; LEN * 2 + 1]);
-        assert_float_eq!(
-            &buffer[2 * LEN + 1..],
-            &[-1.; 2 * LEN - 1][..],
-            ulps_all <= 0
-        );
+        // only N / 2 values should contain frequency data, rest is unaltered
+        assert!(buffer[0..LEN * 2] == [f32::NEG_INFINITY; LEN * 2]);
+        assert_float_eq!(&buffer[2 * LEN..], &[-1.; 2 * LEN][..], ulps_all <= 0);
 
         // feed data for more than 256 times (the ring buffer size)
         for i in 0..258 {
@@ -319,7 +515,115 @@ mod tests {
         // this should return other data now
         analyser.calculate_float_frequency(fft_size, 0.8);
         analyser.get_float_frequency(&mut buffer[..]);
-        assert!(buffer[0..LEN * 2 + 1] != [f32::NEG_INFINITY; LEN * 2 + 1]);
+        assert!(buffer[0..LEN * 2] != [f32::NEG_INFINITY; LEN * 2]);
+    }
+
+    #[test]
+    fn test_byte_freq_domain() {
+        let alloc = Alloc::with_capacity(256);
+
+        let fft_size: usize = LEN * 4;
+        let mut analyser = Analyser::new(fft_size);
+
+        // full scale sine, centered on bin 32
+        let frequency = 32. / fft_size as f32;
+        for quantum in 0..4 {
+            let mut signal = alloc.silence();
+            signal.iter_mut().enumerate().for_each(|(i, s)| {
+                let n = (quantum * LEN + i) as f32;
+                *s = (2. * PI * frequency * n).sin();
+            });
+            analyser.add_data(signal);
+        }
+        analyser.calculate_float_frequency(fft_size, 0.);
+
+        let mut decibels = vec![0.; fft_size / 2];
+        analyser.get_float_frequency(&mut decibels[..]);
+
+        // the byte values are the decibels mapped from [min, max] to [0, 255]
+        let (min_decibels, max_decibels) = (-100., -30.);
+        let mut bytes = vec![1; fft_size];
+        analyser.get_byte_frequency(&mut bytes[..], min_decibels, max_decibels);
+
+        for (&b, &d) in bytes.iter().zip(decibels.iter()) {
+            let expected = 255. / 70. * (d - min_decibels);
+            if expected >= 255. {
+                assert_eq!(b, 255);
+            } else if expected < 0. {
+                assert_eq!(b, 0);
+            } else {
+                assert_eq!(b, expected.floor() as u8);
+            }
+        }
+
+        // the peak saturates, far away bins are floored, excess capacity is unaltered
+        assert_eq!(bytes[32], 255);
+        assert_eq!(bytes[200], 0);
+        assert_eq!(&bytes[fft_size / 2..], &vec![1; fft_size / 2][..]);
+    }
+
+    #[test]
+    fn test_custom_fft_backend() {
+        use std::sync::{Arc, Mutex};
+
+        /// Records the requested FFT sizes, outputs a flat spectrum
+        struct MockFft {
+            sizes: Arc<Mutex<Vec<(usize, usize)>>>,
+        }
+
+        impl FftProcessor for MockFft {
+            fn process(&mut self, input: &mut [f32], output: &mut [Complex<f32>]) {
+                self.sizes.lock().unwrap().push((input.len(), output.len()));
+                output.iter_mut().for_each(|o| *o = Complex::new(1., 0.));
+            }
+
+            fn process_inverse(&mut self, _input: &mut [Complex<f32>], _output: &mut [f32]) {
+                unreachable!() // not used by the analyser
+            }
+        }
+
+        let alloc = Alloc::with_capacity(256);
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let mock = MockFft {
+            sizes: sizes.clone(),
+        };
+
+        let fft_size = LEN * 4;
+        let mut analyser = Analyser::with_fft(fft_size, Box::new(mock));
+        analyser.add_data(alloc.silence());
+
+        analyser.calculate_float_frequency(fft_size, 0.);
+        analyser.calculate_float_frequency(LEN, 0.);
+        assert_eq!(
+            &*sizes.lock().unwrap(),
+            &[(fft_size, fft_size / 2 + 1), (LEN, LEN / 2 + 1)]
+        );
+
+        // the backend output is used for the frequency data
+        let mut buffer = vec![0.; LEN / 2];
+        analyser.get_float_frequency(&mut buffer[..]);
+        let expected = -20. * (LEN as f32).sqrt().log10();
+        assert_float_eq!(&buffer[..], &[expected; LEN / 2][..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_real_fft_round_trip() {
+        let mut fft = RealFftProcessor::new();
+
+        // larger than the sizes of the analyser, e.g. for the convolver
+        let len = 2 * MAX_SAMPLES;
+        let signal: Vec<f32> = (0..len).map(|i| (i % 7) as f32 - 3.).collect();
+
+        let mut input = signal.clone();
+        let mut spectrum = vec![Complex::default(); len / 2 + 1];
+        fft.process(&mut input, &mut spectrum);
+        // sum of the signal, the last (incomplete) period contributes -3 - 2
+        assert_float_eq!(spectrum[0].re, -5., abs <= 1e-2);
+
+        let mut output = vec![0.; len];
+        fft.process_inverse(&mut spectrum, &mut output);
+        output.iter_mut().for_each(|v| *v /= len as f32);
+        assert_float_eq!(&output[..], &signal[..], abs_all <= 1e-3);
     }
 
     #[test]
@@ -4,13 +4,18 @@ use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use crate::alloc::AudioBuffer as FixedAudioBuffer;
+use crate::filter::RateConverter;
 use crate::media::MediaStream;
-use crate::SampleRate;
+use crate::rng::XorShift;
+use crate::{FilterQuality, IndexSizeError, SampleRate};
 
 /// Memory-resident audio asset, basically a matrix of channels * samples
 ///
 /// An AudioBuffer has copy-on-write semantics, so it is cheap to clone.
+///
+/// With the `serde` feature, it can be serialized along with its sample rate.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AudioBuffer {
     channels: Vec<ChannelData>,
     sample_rate: SampleRate,
@@ -81,6 +86,97 @@ impl AudioBuffer {
         &mut self.channels[index]
     }
 
+    /// Get the samples from this specific channel, or `None` if the index is out of range.
+    pub fn try_channel_data(&self, index: usize) -> Option<&ChannelData> {
+        self.channels.get(index)
+    }
+
+    /// Get the samples (mutable) from this specific channel, or `None` if the index is out of
+    /// range.
+    pub fn try_channel_data_mut(&mut self, index: usize) -> Option<&mut ChannelData> {
+        self.channels.get_mut(index)
+    }
+
+    /// Copy the samples of the channel at `channel_number` into `destination`
+    ///
+    /// Copies `destination.len()` samples, or less if the channel is shorter.
+    ///
+    /// Returns an `IndexSizeError` if the channel does not exist
+    pub fn copy_from_channel(
+        &self,
+        destination: &mut [f32],
+        channel_number: usize,
+    ) -> Result<(), IndexSizeError> {
+        self.copy_from_channel_with_offset(destination, channel_number, 0)
+    }
+
+    /// Copy the samples of the channel at `channel_number` into `destination`, starting at
+    /// sample-frame `offset` of the channel
+    ///
+    /// Nothing is copied when the offset lies beyond the end of the channel.
+    ///
+    /// Returns an `IndexSizeError` if the channel does not exist
+    pub fn copy_from_channel_with_offset(
+        &self,
+        destination: &mut [f32],
+        channel_number: usize,
+        offset: usize,
+    ) -> Result<(), IndexSizeError> {
+        let channel = self
+            .channels
+            .get(channel_number)
+            .ok_or(IndexSizeError {})?
+            .as_slice();
+
+        let source = channel.get(offset..).unwrap_or(&[]);
+        let len = source.len().min(destination.len());
+        destination[..len].copy_from_slice(&source[..len]);
+
+        Ok(())
+    }
+
+    /// Copy the samples of `source` into the channel at `channel_number`
+    ///
+    /// Copies `source.len()` samples, or less if the channel is shorter. The channel data is
+    /// modified in place, unless it is shared with another buffer (copy-on-write).
+    ///
+    /// Returns an `IndexSizeError` if the channel does not exist
+    pub fn copy_to_channel(
+        &mut self,
+        source: &[f32],
+        channel_number: usize,
+    ) -> Result<(), IndexSizeError> {
+        self.copy_to_channel_with_offset(source, channel_number, 0)
+    }
+
+    /// Copy the samples of `source` into the channel at `channel_number`, starting at
+    /// sample-frame `offset` of the channel
+    ///
+    /// Nothing is copied when the offset lies beyond the end of the channel.
+    ///
+    /// Returns an `IndexSizeError` if the channel does not exist
+    pub fn copy_to_channel_with_offset(
+        &mut self,
+        source: &[f32],
+        channel_number: usize,
+        offset: usize,
+    ) -> Result<(), IndexSizeError> {
+        let channel = self
+            .channels
+            .get_mut(channel_number)
+            .ok_or(IndexSizeError {})?;
+
+        if offset >= channel.len() {
+            return Ok(());
+        }
+
+        let destination = &mut channel.as_mut_slice()[offset..];
+        let len = source.len().min(destination.len());
+        destination[..len].copy_from_slice(&source[..len]);
+
+        Ok(())
+    }
+
     /// Modify every channel in the same way
     pub fn modify_channels<F: Fn(&mut ChannelData)>(&mut self, fun: F) {
         // todo, optimize for Arcs that are equal
@@ -118,8 +214,13 @@ impl AudioBuffer {
 
     /// Split an AudioBuffer in chunks with length `sample_len`.
     ///
-    /// The last chunk may be shorter than `sample_len`
+    /// The last chunk may be shorter than `sample_len`. An empty buffer yields no chunks.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `sample_len` is zero
     pub fn split(mut self, sample_len: u32) -> Vec<AudioBuffer> {
+        assert!(sample_len > 0, "chunk length should be positive");
         let sample_len = sample_len as usize;
         let total_len = self.sample_len();
         let sample_rate = self.sample_rate();
@@ -156,9 +257,345 @@ impl AudioBuffer {
         AudioBuffer::from_channels(channels, sample_rate)
     }
 
+    /// Create a new AudioBuffer containing the sample frames `start..end` of this buffer.
+    ///
+    /// When the range spans the full buffer, the channel data is shared (copy-on-write),
+    /// otherwise the selected range is copied.
+    ///
+    /// Returns an `IndexSizeError` if `start > end` or `end > self.sample_len()`
+    pub fn trim(&self, start: usize, end: usize) -> Result<AudioBuffer, IndexSizeError> {
+        if start > end || end > self.sample_len() {
+            return Err(IndexSizeError {});
+        }
+
+        if start == 0 && end == self.sample_len() {
+            return Ok(self.clone());
+        }
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|channel_data| ChannelData::from(channel_data.as_slice()[start..end].to_vec()))
+            .collect();
+
+        Ok(AudioBuffer::from_channels(channels, self.sample_rate))
+    }
+
+    /// Create a new AudioBuffer with `left` frames of silence before, and `right` frames of
+    /// silence after the contents of this buffer.
+    ///
+    /// Without padding, the channel data is shared (copy-on-write).
+    pub fn pad(&self, left: usize, right: usize) -> AudioBuffer {
+        if left == 0 && right == 0 {
+            return self.clone();
+        }
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|channel_data| {
+                let mut data = Vec::with_capacity(left + channel_data.len() + right);
+                data.resize(left, 0.);
+                data.extend_from_slice(channel_data.as_slice());
+                data.resize(left + channel_data.len() + right, 0.);
+                ChannelData::from(data)
+            })
+            .collect();
+
+        AudioBuffer::from_channels(channels, self.sample_rate)
+    }
+
+    /// Compare with another buffer, allowing each sample to differ by `ulps` units in the last place
+    ///
+    /// The buffers must have the same sample rate, number of channels and length to be
+    /// considered equal.
+    pub fn approx_eq(&self, other: &Self, ulps: u32) -> bool {
+        self.first_mismatch(other, ulps).is_none()
+    }
+
+    /// Position of the first differing sample (channel, index) of two buffers of equal shape
+    fn first_mismatch(&self, other: &Self, ulps: u32) -> Option<(usize, usize)> {
+        if self.sample_rate != other.sample_rate
+            || self.number_of_channels() != other.number_of_channels()
+            || self.sample_len() != other.sample_len()
+        {
+            return Some((0, 0));
+        }
+
+        self.channels
+            .iter()
+            .zip(other.channels.iter())
+            .enumerate()
+            .find_map(|(channel, (a, b))| {
+                a.as_slice()
+                    .iter()
+                    .zip(b.as_slice())
+                    .position(|(a, b)| !float_eq::float_eq!(*a, *b, ulps <= ulps))
+                    .map(|index| (channel, index))
+            })
+    }
+
+    /// Highest absolute sample value over all channels
+    pub fn peak(&self) -> f32 {
+        self.channels
+            .iter()
+            .flat_map(|channel_data| channel_data.as_slice())
+            .fold(0., |peak: f32, v| peak.max(v.abs()))
+    }
+
+    /// Apply the same gain to all channels
+    fn apply_gain(&mut self, gain: f32) {
+        #[allow(clippy::float_cmp)]
+        if gain == 1. {
+            return;
+        }
+
+        self.modify_channels(|channel_data| {
+            channel_data
+                .as_mut_slice()
+                .iter_mut()
+                .for_each(|v| *v *= gain)
+        });
+    }
+
+    /// Scale all channels uniformly so the peak sample value reaches `target_dbfs`
+    ///
+    /// A silent buffer is left untouched.
+    pub fn normalize_peak(&mut self, target_dbfs: f32) {
+        let peak = self.peak();
+        if peak == 0. {
+            return;
+        }
+
+        self.apply_gain(10_f32.powf(target_dbfs / 20.) / peak);
+    }
+
+    /// Integrated loudness in LUFS, following ITU-R BS.1770-4
+    ///
+    /// The signal is K-weighted and measured in gated blocks of 400 ms. Returns
+    /// `f32::NEG_INFINITY` for buffers that are silent or shorter than a single block.
+    pub fn integrated_loudness(&self) -> f32 {
+        let sample_rate = self.sample_rate.0 as f64;
+        let block_len = (0.4 * sample_rate) as usize;
+        let step = block_len / 4; // 75% overlap
+        let len = self.sample_len();
+        if block_len == 0 || len < block_len {
+            return f32::NEG_INFINITY;
+        }
+
+        // channel weights, surround channels of a 5.1 layout are boosted, the LFE is ignored
+        let weight = |channel: usize| match (self.number_of_channels(), channel) {
+            (6, 3) => 0.,
+            (6, 4) | (6, 5) => 1.41,
+            _ => 1.,
+        };
+
+        // K-weighted squared signal, summed over the weighted channels
+        let mut power = vec![0.; len];
+        self.channels
+            .iter()
+            .enumerate()
+            .for_each(|(i, channel_data)| {
+                let g = weight(i);
+                k_weighting(channel_data.as_slice(), sample_rate)
+                    .zip(power.iter_mut())
+                    .for_each(|(v, p)| *p += g * v * v);
+            });
+
+        // mean power of each block, with a running sum
+        let mut prefix = Vec::with_capacity(len + 1);
+        prefix.push(0.);
+        power.iter().fold(0., |sum, p| {
+            let sum = sum + p;
+            prefix.push(sum);
+            sum
+        });
+        let blocks: Vec<f64> = (0..=(len - block_len) / step)
+            .map(|j| (prefix[j * step + block_len] - prefix[j * step]) / block_len as f64)
+            .collect();
+
+        let loudness = |z: f64| -0.691 + 10. * z.log10();
+        let gated_mean = |threshold: f64| {
+            let gated: Vec<_> = blocks
+                .iter()
+                .filter(|&&z| loudness(z) > threshold)
+                .collect();
+            if gated.is_empty() {
+                None
+            } else {
+                Some(gated.iter().copied().sum::<f64>() / gated.len() as f64)
+            }
+        };
+
+        // absolute gate at -70 LUFS, then relative gate 10 LU below the absolute-gated loudness
+        let integrated = gated_mean(-70.)
+            .and_then(|z| gated_mean(loudness(z) - 10.))
+            .map_or(f64::NEG_INFINITY, loudness);
+
+        integrated as f32
+    }
+
+    /// Scale all channels uniformly so the integrated loudness reaches `target_lufs`
+    ///
+    /// See [`integrated_loudness`](Self::integrated_loudness) for the measurement. Buffers without
+    /// measurable loudness are left untouched. The result is not limited, so the samples may
+    /// exceed the range [-1, 1].
+    pub fn normalize_loudness(&mut self, target_lufs: f32) {
+        let loudness = self.integrated_loudness();
+        if !loudness.is_finite() {
+            return;
+        }
+
+        self.apply_gain(10_f32.powf((target_lufs - loudness) / 20.));
+    }
+
+    /// Mono compatibility of a stereo buffer, detecting phase cancellation
+    ///
+    /// Returns the energy of the mono down-mix `0.5 * (L + R)` relative to the average energy of
+    /// the two channels: `1.` when both channels are identical, `0.5` (-3 dB) for uncorrelated
+    /// channels and `0.` when they cancel out completely (`L = -R`). Values well below `0.5`
+    /// indicate that content will get lost when played back in mono. Silent buffers are
+    /// considered compatible.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the buffer does not have exactly two channels
+    pub fn mono_compatibility(&self) -> f32 {
+        assert_eq!(
+            self.number_of_channels(),
+            2,
+            "NotSupportedError: mono compatibility requires a stereo buffer"
+        );
+
+        let left = self.channels[0].as_slice();
+        let right = self.channels[1].as_slice();
+
+        let (mut mono_energy, mut channel_energy) = (0_f64, 0_f64);
+        left.iter().zip(right).for_each(|(&l, &r)| {
+            let (l, r) = (f64::from(l), f64::from(r));
+            let mono = 0.5 * (l + r);
+            mono_energy += mono * mono;
+            channel_energy += 0.5 * (l * l + r * r);
+        });
+
+        if channel_energy == 0. {
+            return 1.;
+        }
+
+        (mono_energy / channel_energy) as f32
+    }
+
+    /// Interleaved 16-bit integer samples, e.g. for writing a WAV file
+    ///
+    /// The samples are scaled to the full integer range, dithered and rounded. Values outside
+    /// the range [-1, 1] are clipped.
+    pub fn to_interleaved_i16(&self, dither: DitherType) -> Vec<i16> {
+        let mut noise = DitherNoise::new();
+        let channels = self.number_of_channels();
+        let mut interleaved = Vec::with_capacity(channels * self.sample_len());
+
+        for i in 0..self.sample_len() {
+            for channel_data in &self.channels {
+                let dither = match dither {
+                    DitherType::None => 0.,
+                    DitherType::Rectangular => noise.next_uniform(),
+                    DitherType::Triangular => noise.next_uniform() + noise.next_uniform(),
+                };
+                let v = channel_data.as_slice()[i] * 32_768. + dither;
+                interleaved.push(v.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+            }
+        }
+
+        interleaved
+    }
+
+    /// Write the buffer to a WAV file, e.g. to save the output of an `OfflineAudioContext`
+    ///
+    /// The channels are interleaved, and the header holds the sample rate of the buffer. The
+    /// samples are written one by one, so wrap unbuffered writers in a `std::io::BufWriter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if the data exceeds the 4 GiB limit of the
+    /// format, and passes on the errors of the writer.
+    pub fn write_wav(
+        &self,
+        mut writer: impl std::io::Write,
+        format: SampleFormat,
+    ) -> std::io::Result<()> {
+        let channels = self.number_of_channels() as u16;
+        let (format_tag, bits_per_sample): (u16, u16) = match format {
+            SampleFormat::I16(_) => (WAVE_FORMAT_PCM, 16),
+            SampleFormat::F32 => (WAVE_FORMAT_IEEE_FLOAT, 32),
+        };
+        let block_align = channels * bits_per_sample / 8;
+        let byte_rate = self.sample_rate.0 * u32::from(block_align);
+
+        // the float format has an extended fmt chunk and a fact chunk
+        let header_len = match format {
+            SampleFormat::I16(_) => 36,
+            SampleFormat::F32 => 50,
+        };
+        let data_len = (self.sample_len() as u64) * u64::from(block_align);
+        if data_len + header_len > u64::from(u32::MAX) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "buffer too large for a WAV file",
+            ));
+        }
+        let data_len = data_len as u32;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(header_len as u32 + data_len).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        match format {
+            SampleFormat::I16(_) => writer.write_all(&16_u32.to_le_bytes())?,
+            SampleFormat::F32 => writer.write_all(&18_u32.to_le_bytes())?,
+        }
+        writer.write_all(&format_tag.to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&self.sample_rate.0.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&bits_per_sample.to_le_bytes())?;
+
+        if format == SampleFormat::F32 {
+            // no extension
+            writer.write_all(&0_u16.to_le_bytes())?;
+
+            writer.write_all(b"fact")?;
+            writer.write_all(&4_u32.to_le_bytes())?;
+            writer.write_all(&(self.sample_len() as u32).to_le_bytes())?;
+        }
+
+        writer.write_all(b"data")?;
+        writer.write_all(&data_len.to_le_bytes())?;
+
+        match format {
+            SampleFormat::I16(dither) => {
+                for sample in self.to_interleaved_i16(dither) {
+                    writer.write_all(&sample.to_le_bytes())?;
+                }
+            }
+            SampleFormat::F32 => {
+                for i in 0..self.sample_len() {
+                    for channel_data in &self.channels {
+                        writer.write_all(&channel_data.as_slice()[i].to_le_bytes())?;
+                    }
+                }
+            }
+        }
+
+        writer.flush()
+    }
+
     /// Resample to the desired sample rate.
     ///
-    /// This changes the sample_length of the buffer.
+    /// This changes the sample_length of the buffer. Up-sampling interpolates linearly between
+    /// the source samples, down-sampling averages the source samples covered by each output
+    /// sample to limit aliasing.
     ///
     /// ```
     /// use web_audio_api::SampleRate;
@@ -172,7 +609,7 @@ impl AudioBuffer {
     ///
     /// assert_eq!(
     ///     buffer.channel_data(0),
-    ///     &ChannelData::from(vec![1., 1., 2., 2., 3., 3., 4., 4., 5., 5.,])
+    ///     &ChannelData::from(vec![1., 1.5, 2., 2.5, 3., 3.5, 4., 4.5, 5., 5.,])
     /// );
     ///
     /// assert_eq!(buffer.sample_rate().0, 96_000);
@@ -182,20 +619,27 @@ impl AudioBuffer {
             return;
         }
 
-        let rate = sample_rate.0 as f32 / self.sample_rate.0 as f32;
+        let (from, to) = (u64::from(self.sample_rate.0), u64::from(sample_rate.0));
+        let rate = to as f64 / from as f64;
         self.modify_channels(|channel_data| {
-            let mut current = 0;
-            let resampled = channel_data
-                .data
-                .iter()
-                .enumerate()
-                .flat_map(|(i, v)| {
-                    let target = ((i + 1) as f32 * rate) as usize;
-                    let take = target - current.min(target);
-                    current += take;
-                    std::iter::repeat(*v).take(take)
-                })
-                .collect();
+            let input = channel_data.as_slice();
+            if input.is_empty() {
+                return;
+            }
+
+            // do not drop all content when downsampling very short buffers
+            let len = ((input.len() as u64 * to / from) as usize).max(1);
+
+            let resampled = if rate > 1. {
+                (0..len)
+                    .map(|i| interpolate(input, i as f64 / rate))
+                    .collect()
+            } else {
+                (0..len)
+                    .map(|i| average(input, i as f64 / rate, 1. / rate))
+                    .collect()
+            };
+
             channel_data.data = Arc::new(resampled);
         });
 
@@ -203,6 +647,170 @@ impl AudioBuffer {
     }
 }
 
+/// Value of the signal at the fractional sample `position`, interpolated linearly
+///
+/// The last sample is held after the end of the signal.
+fn interpolate(input: &[f32], position: f64) -> f32 {
+    let index = position.floor() as usize;
+    match (input.get(index), input.get(index + 1)) {
+        (Some(&a), Some(&b)) => a + (b - a) * (position - index as f64) as f32,
+        _ => input[input.len() - 1],
+    }
+}
+
+/// Average of the signal over a window of `width` samples centered on `position`
+///
+/// Each sample covers the interval of one sample period centered on it, and is weighted by its
+/// overlap with the window. The window is truncated at the boundaries of the signal.
+fn average(input: &[f32], position: f64, width: f64) -> f32 {
+    let start = position - width / 2.;
+    let end = position + width / 2.;
+
+    let first = (start + 0.5).floor().max(0.) as usize;
+    let last = ((end + 0.5).ceil() as usize).min(input.len());
+
+    let (sum, weights) =
+        input[first..last]
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(sum, weights), (i, &v)| {
+                let center = (first + i) as f64;
+                let overlap = (end.min(center + 0.5) - start.max(center - 0.5)).max(0.);
+                (sum + overlap * f64::from(v), weights + overlap)
+            });
+
+    (sum / weights) as f32
+}
+
+/// Noise added to the samples before quantization to integers
+///
+/// Quantizing without dither correlates the rounding error with the signal, which produces
+/// harmonic distortion that is audible on quiet passages and fades. Dither turns this error into
+/// a constant, signal independent noise floor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DitherType {
+    /// No dither, round to the nearest integer
+    None,
+    /// Uniform noise of 1 LSB peak-to-peak (RPDF), removes the distortion but not the noise
+    /// modulation
+    Rectangular,
+    /// Triangular noise of 2 LSB peak-to-peak (TPDF), also makes the noise floor independent of the
+    /// signal
+    #[default]
+    Triangular,
+}
+
+/// Sample format of a WAV file written by [`AudioBuffer::write_wav`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// 16-bit integer PCM, quantized with the given dither
+    I16(DitherType),
+    /// 32-bit IEEE float, lossless
+    F32,
+}
+
+/// Format tags of the WAV fmt chunk
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+/// Deterministic white noise generator for dithering
+struct DitherNoise {
+    rng: XorShift,
+}
+
+impl DitherNoise {
+    fn new() -> Self {
+        Self {
+            rng: XorShift::new(XorShift::DEFAULT_SEED),
+        }
+    }
+
+    /// Uniformly distributed value in the range [-0.5, 0.5)
+    fn next_uniform(&mut self) -> f32 {
+        0.5 * self.rng.next_f32()
+    }
+}
+
+/// Assert two buffers are equal up to `ulps` units in the last place per sample
+///
+/// See [`AudioBuffer::approx_eq`]. Available with the `testing` feature.
+///
+/// # Panics
+///
+/// Panics with the first differing sample if the buffers are not approximately equal
+#[cfg(any(test, feature = "testing"))]
+#[track_caller]
+pub fn assert_buffers_close(actual: &AudioBuffer, expected: &AudioBuffer, ulps: u32) {
+    assert_eq!(
+        actual.sample_rate, expected.sample_rate,
+        "sample rate differs"
+    );
+    assert_eq!(
+        actual.number_of_channels(),
+        expected.number_of_channels(),
+        "number of channels differs"
+    );
+    assert_eq!(actual.sample_len(), expected.sample_len(), "length differs");
+
+    if let Some((channel, index)) = actual.first_mismatch(expected, ulps) {
+        panic!(
+            "buffers differ at channel {}, index {}: {} != {} (ulps <= {})",
+            channel,
+            index,
+            actual.channels[channel].as_slice()[index],
+            expected.channels[channel].as_slice()[index],
+            ulps
+        );
+    }
+}
+
+/// K-weighting filter of ITU-R BS.1770-4 (high shelf followed by a high pass)
+///
+/// The filter coefficients are derived for the given sample rate, the output is in f64
+fn k_weighting(signal: &[f32], sample_rate: f64) -> impl Iterator<Item = f64> + '_ {
+    use std::f64::consts::PI;
+
+    // stage 1: high shelf, +4 dB above ~1.7 kHz
+    let k = (PI * 1_681.974_450_955_533 / sample_rate).tan();
+    let q = 0.707_175_236_955_419_6;
+    let vh = 10_f64.powf(3.999_843_853_973_347 / 20.);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1. + k / q + k * k;
+    let shelf = [
+        (vh + vb * k / q + k * k) / a0,
+        2. * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2. * (k * k - 1.) / a0,
+        (1. - k / q + k * k) / a0,
+    ];
+
+    // stage 2: high pass at ~38 Hz
+    let k = (PI * 38.135_470_876_024_44 / sample_rate).tan();
+    let q = 0.500_327_037_323_877_3;
+    let a0 = 1. + k / q + k * k;
+    let high_pass = [
+        1.,
+        -2.,
+        1.,
+        2. * (k * k - 1.) / a0,
+        (1. - k / q + k * k) / a0,
+    ];
+
+    // direct form 2 transposed biquad
+    let biquad = |[b0, b1, b2, a1, a2]: [f64; 5]| {
+        let mut state = (0., 0.);
+        move |x: f64| {
+            let y = b0 * x + state.0;
+            state = (b1 * x - a1 * y + state.1, b2 * x - a2 * y);
+            y
+        }
+    };
+
+    let mut shelf = biquad(shelf);
+    let mut high_pass = biquad(high_pass);
+    signal.iter().map(move |&x| high_pass(shelf(f64::from(x))))
+}
+
 /// Single channel audio samples, basically wraps a `Arc<Vec<f32>>`
 ///
 /// ChannelData has copy-on-write semantics, so it is cheap to clone.
@@ -242,6 +850,21 @@ impl ChannelData {
     }
 }
 
+/// Serialized as a flat array of samples
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChannelData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChannelData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(ChannelData::from)
+    }
+}
+
 /// How channels must be matched between the node's inputs and outputs.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum ChannelCountMode {
@@ -412,6 +1035,18 @@ pub struct Resampler<I> {
     input: I,
     /// internal buffer
     buffer: Option<AudioBuffer>,
+    /// sample rate of the input the converters are set up for
+    input_rate: Option<SampleRate>,
+    /// one rate converter per channel, empty when the input is at the desired sample rate
+    converters: Vec<RateConverter>,
+    /// quality of the anti-aliasing filters
+    quality: FilterQuality,
+    /// the tail of the converters has been appended after the last input chunk
+    flushed: bool,
+    /// trim the leading group delay from the output
+    compensate_latency: bool,
+    /// number of leading sample-frames still to be trimmed
+    trim_pending: usize,
 }
 
 impl<M: MediaStream> Resampler<M> {
@@ -421,6 +1056,142 @@ impl<M: MediaStream> Resampler<M> {
             sample_len,
             input,
             buffer: None,
+            input_rate: None,
+            converters: vec![],
+            quality: FilterQuality::default(),
+            flushed: false,
+            compensate_latency: false,
+            trim_pending: 0,
+        }
+    }
+
+    /// Group delay introduced by the sample rate conversion, in sample-frames of the output
+    ///
+    /// An impulse at the start of the input appears at this offset in the output. Use it to align
+    /// a resampled stream with a stream that is not resampled.
+    ///
+    /// The delay of the anti-aliasing filter depends on the input sample rate, so this returns 0
+    /// until the first chunk is pulled from the resampler, and when the input is already at the
+    /// desired sample rate.
+    pub fn latency_samples(&self) -> usize {
+        self.converters.first().map_or(0, RateConverter::latency)
+    }
+
+    /// Quality of the anti-aliasing filters
+    pub fn quality(&self) -> FilterQuality {
+        self.quality
+    }
+
+    /// Set the quality of the anti-aliasing filters (`FilterQuality::Medium` by default)
+    ///
+    /// A higher quality also increases the [`latency_samples`](Self::latency_samples). This must
+    /// be set before the first chunk is pulled from the resampler.
+    pub fn set_quality(&mut self, quality: FilterQuality) {
+        self.quality = quality;
+    }
+
+    /// Trim the leading group delay from the output (disabled by default)
+    ///
+    /// When enabled, the first [`latency_samples`](Self::latency_samples) sample-frames are
+    /// dropped so the output lines up with the input. This must be set before the first chunk is
+    /// pulled from the resampler.
+    pub fn set_compensate_latency(&mut self, compensate: bool) {
+        self.compensate_latency = compensate;
+        self.trim_pending = if compensate {
+            self.latency_samples()
+        } else {
+            0
+        };
+    }
+
+    /// Indicates if the leading group delay is trimmed from the output
+    pub fn compensate_latency(&self) -> bool {
+        self.compensate_latency
+    }
+
+    /// Convert a chunk of the input to the desired sample rate
+    fn convert(&mut self, data: AudioBuffer) -> AudioBuffer {
+        let channels = data.number_of_channels();
+        let rate_changed = self.input_rate != Some(data.sample_rate());
+        let layout_changed = !self.converters.is_empty() && self.converters.len() != channels;
+
+        if rate_changed || layout_changed {
+            self.input_rate = Some(data.sample_rate());
+            self.converters = if data.sample_rate() == self.sample_rate {
+                vec![]
+            } else {
+                (0..channels)
+                    .map(|_| {
+                        RateConverter::new(
+                            data.sample_rate().0,
+                            self.sample_rate.0,
+                            self.quality.taps_per_phase(),
+                        )
+                    })
+                    .collect()
+            };
+            if self.compensate_latency {
+                self.trim_pending = self.latency_samples();
+            }
+        }
+
+        if self.converters.is_empty() {
+            return data;
+        }
+
+        let channels = data
+            .channels()
+            .iter()
+            .zip(self.converters.iter_mut())
+            .map(|(channel, converter)| {
+                let mut output = vec![];
+                converter.process(channel.as_slice(), &mut output);
+                ChannelData::from(output)
+            })
+            .collect();
+
+        AudioBuffer::from_channels(channels, self.sample_rate)
+    }
+
+    /// Append the delayed tail of the converters, once the input is exhausted
+    fn flush(&mut self) -> Option<AudioBuffer> {
+        if self.flushed || self.converters.is_empty() {
+            return None;
+        }
+        self.flushed = true;
+
+        let channels = self
+            .converters
+            .iter_mut()
+            .map(|converter| {
+                let mut output = vec![];
+                converter.flush(&mut output);
+                ChannelData::from(output)
+            })
+            .collect();
+
+        Some(AudioBuffer::from_channels(channels, self.sample_rate))
+    }
+
+    /// Pull the next chunk from the input and convert it to the desired sample rate
+    fn next_resampled(&mut self) -> Option<Result<AudioBuffer, Box<dyn Error + Send>>> {
+        loop {
+            let mut data = match self.input.next() {
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(data)) => self.convert(data),
+                None => self.flush()?,
+            };
+
+            if self.trim_pending == 0 {
+                return Some(Ok(data));
+            }
+
+            let trim = self.trim_pending.min(data.sample_len());
+            self.trim_pending -= trim;
+            let data = data.split_off(trim);
+            if data.sample_len() > 0 {
+                return Some(Ok(data));
+            }
         }
     }
 }
@@ -430,20 +1201,17 @@ impl<M: MediaStream> Iterator for Resampler<M> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut buffer = match self.buffer.take() {
-            None => match self.input.next() {
+            None => match self.next_resampled() {
                 None => return None,
                 Some(Err(e)) => return Some(Err(e)),
-                Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
-                    data
-                }
+                Some(Ok(data)) => data,
             },
             Some(data) => data,
         };
 
         while (buffer.sample_len() as u32) < self.sample_len {
             // buffer is smaller than desired len
-            match self.input.next() {
+            match self.next_resampled() {
                 None => {
                     let padding = AudioBuffer::new(
                         buffer.number_of_channels(),
@@ -454,11 +1222,12 @@ impl<M: MediaStream> Iterator for Resampler<M> {
 
                     return Some(Ok(buffer));
                 }
-                Some(Err(e)) => return Some(Err(e)),
-                Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
-                    buffer.extend(&data)
+                Some(Err(e)) => {
+                    // keep the partial chunk, e.g. when a live input is depleted
+                    self.buffer = Some(buffer);
+                    return Some(Err(e));
                 }
+                Some(Ok(data)) => buffer.extend(&data),
             }
         }
 
@@ -490,6 +1259,102 @@ mod tests {
         assert_eq!(b.channels().get(2), None);
     }
 
+    #[test]
+    fn test_copy_channel() {
+        let mut b = AudioBuffer::new(2, 5, SampleRate(44_100));
+
+        b.copy_to_channel(&[1., 2.], 0).unwrap();
+        b.copy_to_channel_with_offset(&[3., 4., 5.], 0, 3).unwrap();
+        assert_float_eq!(
+            b.channel_data(0).as_slice(),
+            &[1., 2., 0., 3., 4.][..],
+            ulps_all <= 0
+        );
+        assert_float_eq!(b.channel_data(1).as_slice(), &[0.; 5][..], ulps_all <= 0);
+
+        let mut dest = [9.; 3];
+        b.copy_from_channel_with_offset(&mut dest, 0, 3).unwrap();
+        assert_float_eq!(dest, [3., 4., 9.], ulps_all <= 0);
+        b.copy_from_channel(&mut dest, 0).unwrap();
+        assert_float_eq!(dest, [1., 2., 0.], ulps_all <= 0);
+
+        // out of range offsets copy nothing, missing channels are an error
+        b.copy_to_channel_with_offset(&[1.], 1, 5).unwrap();
+        b.copy_from_channel_with_offset(&mut dest, 1, 6).unwrap();
+        assert_float_eq!(dest, [1., 2., 0.], ulps_all <= 0);
+        assert!(b.copy_to_channel(&[1.], 2).is_err());
+        assert!(b.copy_from_channel(&mut dest, 2).is_err());
+    }
+
+    #[test]
+    fn test_try_channel_data() {
+        let mut b = AudioBuffer::new(2, 10, SampleRate(44_100));
+
+        assert!(b.try_channel_data(0).is_some());
+        assert!(b.try_channel_data(1).is_some());
+        assert!(b.try_channel_data(2).is_none());
+
+        b.try_channel_data_mut(1).unwrap().as_mut_slice()[0] = 1.;
+        assert_float_eq!(b.channel_data(1).as_slice()[0], 1., abs <= 0.);
+        assert!(b.try_channel_data_mut(2).is_none());
+    }
+
+    #[test]
+    fn test_trim() {
+        let channel = ChannelData::from((0..10).map(|i| i as f32).collect());
+        let buffer = AudioBuffer::from_channels(vec![channel; 2], SampleRate(44_100));
+
+        let trimmed = buffer.trim(2, 5).unwrap();
+        assert_eq!(trimmed.sample_len(), 3);
+        assert_eq!(trimmed.number_of_channels(), 2);
+        assert_eq!(trimmed.sample_rate(), SampleRate(44_100));
+        assert_float_eq!(
+            trimmed.channel_data(1).as_slice(),
+            &[2., 3., 4.][..],
+            ulps_all <= 0
+        );
+
+        // full range shares the channel data
+        let full = buffer.trim(0, 10).unwrap();
+        assert!(Arc::ptr_eq(
+            &full.channel_data(0).data,
+            &buffer.channel_data(0).data
+        ));
+
+        // empty range
+        assert_eq!(buffer.trim(4, 4).unwrap().sample_len(), 0);
+
+        // invalid ranges
+        assert!(buffer.trim(5, 4).is_err());
+        assert!(buffer.trim(0, 11).is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        let channel = ChannelData::from(vec![1., 2., 3.]);
+        let buffer = AudioBuffer::from_channels(vec![channel; 2], SampleRate(44_100));
+
+        let padded = buffer.pad(2, 2);
+        assert_eq!(padded.sample_len(), buffer.sample_len() + 4);
+        assert_eq!(padded.number_of_channels(), 2);
+        assert_float_eq!(
+            padded.channel_data(0).as_slice(),
+            &[0., 0., 1., 2., 3., 0., 0.][..],
+            ulps_all <= 0
+        );
+
+        // original content is recovered by trimming
+        let trimmed = padded.trim(2, 5).unwrap();
+        assert_eq!(trimmed.channel_data(1), buffer.channel_data(1));
+
+        // no padding shares the channel data
+        let same = buffer.pad(0, 0);
+        assert!(Arc::ptr_eq(
+            &same.channel_data(0).data,
+            &buffer.channel_data(0).data
+        ));
+    }
+
     #[test]
     fn test_concat_split() {
         let mut b1 = AudioBuffer::new(2, 5, SampleRate(44_100));
@@ -534,7 +1399,7 @@ mod tests {
         buffer.resample(SampleRate(200));
         assert_float_eq!(
             buffer.channel_data(0).as_slice(),
-            &[1., 1., 2., 2., 3., 3., 4., 4., 5., 5.,][..],
+            &[1., 1.5, 2., 2.5, 3., 3.5, 4., 4.5, 5., 5.,][..],
             ulps_all <= 0
         );
         assert_eq!(buffer.sample_rate().0, 200);
@@ -547,8 +1412,9 @@ mod tests {
         buffer.resample(SampleRate(100));
         assert_float_eq!(
             buffer.channel_data(0).as_slice(),
-            &[2., 4.][..],
-            ulps_all <= 0
+            // the first output sample only averages the samples within the buffer
+            &[4. / 3., 3.][..],
+            ulps_all <= 1
         );
         assert_eq!(buffer.sample_rate().0, 100);
     }
@@ -579,6 +1445,24 @@ mod tests {
         assert!(resampler.next().is_none());
     }
 
+    #[test]
+    fn test_resampler_depleted_input() {
+        let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
+        let input_buf = AudioBuffer::from_channels(vec![channel], SampleRate(44_100));
+        let depleted: Box<dyn Error + Send> = Box::new(crate::BufferDepletedError {});
+        let input = vec![Ok(input_buf.clone()), Err(depleted), Ok(input_buf)].into_iter();
+        let mut resampler = Resampler::new(SampleRate(44_100), 10, input);
+
+        // the partial chunk is kept until the input catches up
+        assert!(resampler.next().unwrap().is_err());
+        let next = resampler.next().unwrap().unwrap();
+        assert_float_eq!(
+            next.channel_data(0).as_slice(),
+            &[1., 2., 3., 4., 5., 1., 2., 3., 4., 5.,][..],
+            ulps_all <= 0
+        );
+    }
+
     #[test]
     fn test_resampler_split() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5., 6., 7., 8., 9., 10.]);
@@ -607,4 +1491,472 @@ mod tests {
 
         assert!(resampler.next().is_none());
     }
+
+    #[test]
+    fn test_resampler_latency() {
+        // impulse at 32 kHz, upsampled to 48 kHz
+        let mut data = vec![0.; 20];
+        data[4] = 1.;
+        let channel = ChannelData::from(data);
+        let input_buf = AudioBuffer::from_channels(vec![channel], SampleRate(32_000));
+
+        let peak = |compensate: bool| {
+            let input = vec![Ok(input_buf.clone())].into_iter();
+            let mut resampler = Resampler::new(SampleRate(48_000), 30, input);
+            resampler.set_compensate_latency(compensate);
+            assert_eq!(resampler.compensate_latency(), compensate);
+
+            let next = resampler.next().unwrap().unwrap();
+            let peak = next
+                .channel_data(0)
+                .as_slice()
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .unwrap()
+                .0;
+            (peak, resampler.latency_samples())
+        };
+
+        // input offset 4 is at output offset 6, delayed by the filter
+        let (delayed, latency) = peak(false);
+        assert!(latency > 0);
+        assert_eq!(delayed, 6 + latency);
+
+        // the leading delay is trimmed
+        let (compensated, _) = peak(true);
+        assert_eq!(compensated, 6);
+
+        // a higher quality has a longer delay
+        let input = vec![Ok(input_buf)].into_iter();
+        let mut resampler = Resampler::new(SampleRate(48_000), 30, input);
+        resampler.set_quality(FilterQuality::High);
+        assert_eq!(resampler.quality(), FilterQuality::High);
+        resampler.next();
+        assert!(resampler.latency_samples() > latency);
+    }
+
+    #[test]
+    fn test_resampler_compensated_alignment() {
+        // a sine at 44.1 kHz, resampled to 48 kHz in chunks of 128
+        let frequency = 440.;
+        let sine = |n: usize, sample_rate: f64| {
+            (2. * std::f64::consts::PI * frequency * n as f64 / sample_rate).sin() as f32
+        };
+        let input: Vec<_> = (0..4)
+            .map(|i| {
+                let data: Vec<_> = (i * 441..(i + 1) * 441).map(|n| sine(n, 44_100.)).collect();
+                Ok(AudioBuffer::from_channels(
+                    vec![ChannelData::from(data)],
+                    SampleRate(44_100),
+                ))
+            })
+            .collect();
+
+        let mut resampler = Resampler::new(SampleRate(48_000), 128, input.into_iter());
+        resampler.set_compensate_latency(true);
+        let output: Vec<f32> = resampler
+            .by_ref()
+            .flat_map(|chunk| chunk.unwrap().channel_data(0).as_slice().to_vec())
+            .collect();
+        assert!(resampler.latency_samples() > 0);
+
+        // the output lines up with the input, apart from the filter ramp at both ends
+        let len = 1764 * 48_000 / 44_100;
+        let expected: Vec<_> = (0..len).map(|n| sine(n, 48_000.)).collect();
+        assert_float_eq!(
+            output[100..len - 100],
+            expected[100..len - 100],
+            abs_all <= 1e-3
+        );
+
+        // the tail is flushed, so the trimmed delay does not shorten the output
+        assert_eq!(output.len(), len);
+    }
+
+    #[test]
+    fn test_normalize_peak() {
+        let channel = ChannelData::from(vec![0.1, -0.4, 0.2]);
+        let mut buffer =
+            AudioBuffer::from_channels(vec![channel.clone(), channel], SampleRate(100));
+
+        buffer.normalize_peak(0.);
+        assert_float_eq!(buffer.peak(), 1., abs <= 1e-6);
+        assert_float_eq!(
+            buffer.channel_data(1).as_slice(),
+            &[0.25, -1., 0.5][..],
+            abs_all <= 1e-6
+        );
+
+        // -6 dBFS is about half amplitude
+        buffer.normalize_peak(-6.);
+        assert_float_eq!(buffer.peak(), 0.501, abs <= 1e-3);
+
+        // silence stays silent
+        let mut silence = AudioBuffer::new(1, 10, SampleRate(100));
+        silence.normalize_peak(0.);
+        assert_eq!(silence.peak(), 0.);
+    }
+
+    #[test]
+    fn test_normalize_loudness() {
+        let sample_rate = SampleRate(48_000);
+        // 2 seconds of 1 kHz sine
+        let sine: Vec<f32> = (0..2 * sample_rate.0)
+            .map(|i| 0.1 * (2. * std::f32::consts::PI * 1000. * i as f32 / 48_000.).sin())
+            .collect();
+        let channel = ChannelData::from(sine);
+        let mut buffer = AudioBuffer::from_channels(vec![channel.clone(), channel], sample_rate);
+
+        // a full scale 1 kHz sine in both channels measures 0 LUFS, this one is 20 dB lower
+        let loudness = buffer.integrated_loudness();
+        assert_float_eq!(loudness, -20., abs <= 0.2);
+
+        buffer.normalize_loudness(-23.);
+        assert_float_eq!(buffer.integrated_loudness(), -23., abs <= 0.01);
+
+        // too short to measure
+        let mut short =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![0.5; 100])], sample_rate);
+        assert_eq!(short.integrated_loudness(), f32::NEG_INFINITY);
+        short.normalize_loudness(-23.);
+        assert_float_eq!(
+            short.channel_data(0).as_slice(),
+            &[0.5; 100][..],
+            ulps_all <= 0
+        );
+    }
+
+    #[test]
+    fn test_mono_compatibility() {
+        let sample_rate = SampleRate(44_100);
+        let signal: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.05).sin()).collect();
+        let inverted: Vec<f32> = signal.iter().map(|v| -v).collect();
+        let stereo = |left: Vec<f32>, right: Vec<f32>| {
+            let channels = vec![ChannelData::from(left), ChannelData::from(right)];
+            AudioBuffer::from_channels(channels, sample_rate)
+        };
+
+        let identical = stereo(signal.clone(), signal.clone());
+        assert_float_eq!(identical.mono_compatibility(), 1., abs <= 1e-6);
+
+        let anti_correlated = stereo(signal.clone(), inverted);
+        assert_float_eq!(anti_correlated.mono_compatibility(), 0., abs <= 1e-6);
+
+        // a single channel loses half of its energy
+        let one_sided = stereo(signal, vec![0.; 1000]);
+        assert_float_eq!(one_sided.mono_compatibility(), 0.5, abs <= 1e-6);
+
+        let silent = AudioBuffer::new(2, 1000, sample_rate);
+        assert_float_eq!(silent.mono_compatibility(), 1., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mono_compatibility_mono() {
+        let buffer = AudioBuffer::new(1, 10, SampleRate(44_100));
+        buffer.mono_compatibility();
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let a = AudioBuffer::from_channels(
+            vec![
+                ChannelData::from(vec![1., 0.5]),
+                ChannelData::from(vec![-0.25, 2.]),
+            ],
+            SampleRate(100),
+        );
+
+        // off by a single ulp
+        let mut b = a.clone();
+        b.channel_data_mut(1).as_mut_slice()[1] = f32::from_bits(2_f32.to_bits() + 1);
+
+        assert!(a.approx_eq(&a, 0));
+        assert!(!a.approx_eq(&b, 0));
+        assert!(a.approx_eq(&b, 1));
+        assert_buffers_close(&a, &b, 1);
+
+        // clearly different
+        let mut c = a.clone();
+        c.channel_data_mut(0).as_mut_slice()[0] = 0.9;
+        assert!(!a.approx_eq(&c, 4));
+
+        // different shapes
+        let mut d = a.clone();
+        d.extend(&a);
+        assert!(!a.approx_eq(&d, 4));
+        let e = AudioBuffer::from_channels(a.channels.clone(), SampleRate(200));
+        assert!(!a.approx_eq(&e, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "buffers differ at channel 1, index 0")]
+    fn test_assert_buffers_close() {
+        let a = AudioBuffer::new(2, 10, SampleRate(100));
+        let mut b = a.clone();
+        b.channel_data_mut(1).as_mut_slice()[0] = 0.5;
+
+        assert_buffers_close(&a, &b, 4);
+    }
+
+    #[test]
+    fn test_split_edge_cases() {
+        let empty = AudioBuffer::new(2, 0, SampleRate(44_100));
+        assert!(empty.split(128).is_empty());
+
+        let single =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![0.5])], SampleRate(100));
+        let split = single.split(128);
+        assert_eq!(split.len(), 1);
+        assert_float_eq!(
+            split[0].channel_data(0).as_slice(),
+            &[0.5][..],
+            ulps_all <= 0
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_split_zero_length() {
+        let buffer = AudioBuffer::new(1, 10, SampleRate(44_100));
+        let _ = buffer.split(0);
+    }
+
+    #[test]
+    fn test_resample_sine() {
+        // 1 kHz sine
+        let sine = |sample_rate: u32, len: usize| {
+            let data = (0..len)
+                .map(|i| (2. * std::f32::consts::PI * 1000. * i as f32 / sample_rate as f32).sin())
+                .collect();
+            AudioBuffer::from_channels(vec![ChannelData::from(data)], SampleRate(sample_rate))
+        };
+
+        // the signal is held or the averaging window is truncated at the boundaries, skip them
+        let max_error = |actual: &AudioBuffer, expected: &AudioBuffer| {
+            let len = expected.sample_len();
+            actual.channel_data(0).as_slice()[2..len - 2]
+                .iter()
+                .zip(&expected.channel_data(0).as_slice()[2..len - 2])
+                .map(|(a, e)| (a - e).abs())
+                .fold(0., f32::max)
+        };
+
+        // interpolation, the error of a linear interpolation is at most (w * dt)^2 / 8
+        for (from, to) in [(48_000, 96_000), (44_100, 48_000), (22_050, 44_100)] {
+            let mut buffer = sine(from, 441);
+            buffer.resample(SampleRate(to));
+            let bound = (2. * std::f32::consts::PI * 1000. / from as f32).powi(2) / 8.;
+            let error = max_error(&buffer, &sine(to, buffer.sample_len()));
+            assert!(error <= bound + 1e-6, "{} -> {}: {}", from, to, error);
+        }
+
+        // averaging slightly attenuates the signal, but does not shift it
+        for (from, to) in [(96_000, 48_000), (48_000, 44_100), (44_100, 22_050)] {
+            let mut buffer = sine(from, 441);
+            buffer.resample(SampleRate(to));
+            let error = max_error(&buffer, &sine(to, buffer.sample_len()));
+            assert!(error < 0.01, "{} -> {}: {}", from, to, error);
+        }
+    }
+
+    #[test]
+    fn test_resample_single_sample() {
+        let channel = ChannelData::from(vec![0.5]);
+        let buffer = AudioBuffer::from_channels(vec![channel], SampleRate(48_000));
+
+        let mut up = buffer.clone();
+        up.resample(SampleRate(96_000));
+        assert_float_eq!(
+            up.channel_data(0).as_slice(),
+            &[0.5, 0.5][..],
+            ulps_all <= 0
+        );
+
+        let mut down = buffer.clone();
+        down.resample(SampleRate(44_100));
+        assert_float_eq!(down.channel_data(0).as_slice(), &[0.5][..], ulps_all <= 0);
+
+        // empty buffers stay empty
+        let mut empty = AudioBuffer::new(1, 0, SampleRate(48_000));
+        empty.resample(SampleRate(44_100));
+        assert_eq!(empty.sample_len(), 0);
+        assert_eq!(empty.sample_rate(), SampleRate(44_100));
+    }
+
+    #[test]
+    fn test_resampler_edge_cases() {
+        // a single sample-frame is spread by the filter, and padded to full chunks
+        let channel = ChannelData::from(vec![0.5]);
+        let input = AudioBuffer::from_channels(vec![channel], SampleRate(48_000));
+        let mut resampler = Resampler::new(SampleRate(44_100), 4, std::iter::once(Ok(input)));
+
+        let output: Vec<_> = resampler.by_ref().map(Result::unwrap).collect();
+        assert!(output.iter().all(|c| c.sample_rate() == SampleRate(44_100)));
+        assert!(output.iter().all(|c| c.sample_len() == 4));
+
+        // the response is centered at the filter delay, with the energy of the input
+        let samples: Vec<f32> = output
+            .iter()
+            .flat_map(|c| c.channel_data(0).as_slice().to_vec())
+            .collect();
+        let latency = resampler.latency_samples();
+        assert!(samples.len() > latency);
+        let peak = samples
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert_eq!(peak, latency);
+        let sum: f32 = samples.iter().sum();
+        assert_float_eq!(sum, 0.5 * 44_100. / 48_000., abs <= 1e-3);
+
+        // an empty buffer yields silent chunks
+        let input = AudioBuffer::new(2, 0, SampleRate(48_000));
+        let resampler = Resampler::new(SampleRate(44_100), 4, std::iter::once(Ok(input)));
+
+        let output: Vec<_> = resampler.map(Result::unwrap).collect();
+        assert!(!output.is_empty());
+        for chunk in output {
+            assert_eq!(chunk.number_of_channels(), 2);
+            assert_float_eq!(
+                chunk.channel_data(1).as_slice(),
+                &[0.; 4][..],
+                ulps_all <= 0
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_interleaved_i16() {
+        let left = ChannelData::from(vec![0., 0.5, -1., 2.]);
+        let right = ChannelData::from(vec![1., -0.5, 0.25, -2.]);
+        let buffer = AudioBuffer::from_channels(vec![left, right], SampleRate(44_100));
+
+        assert_eq!(
+            buffer.to_interleaved_i16(DitherType::None),
+            vec![0, 32_767, 16_384, -16_384, -32_768, 8_192, 32_767, -32_768]
+        );
+
+        // dither only affects the least significant bits
+        let dithered = buffer.to_interleaved_i16(DitherType::Triangular);
+        assert_eq!(dithered.len(), 8);
+        assert!(dithered
+            .iter()
+            .zip(&[0, 32_767, 16_384, -16_384])
+            .all(|(a, b)| (a - b).abs() <= 1));
+    }
+
+    #[test]
+    fn test_write_wav_header() {
+        let left = ChannelData::from(vec![0., 0.5, -1.]);
+        let right = ChannelData::from(vec![1., -0.5, 0.25]);
+        let buffer = AudioBuffer::from_channels(vec![left, right], SampleRate(48_000));
+
+        let mut encoded = vec![];
+        buffer
+            .write_wav(&mut encoded, SampleFormat::I16(DitherType::None))
+            .unwrap();
+
+        // 44 byte header, 3 frames of 2 channels of 2 bytes
+        assert_eq!(encoded.len(), 44 + 12);
+        assert_eq!(&encoded[0..4], b"RIFF");
+        assert_eq!(&encoded[4..8], &(36_u32 + 12).to_le_bytes());
+        assert_eq!(&encoded[8..16], b"WAVEfmt ");
+        assert_eq!(&encoded[20..22], &1_u16.to_le_bytes()); // PCM
+        assert_eq!(&encoded[22..24], &2_u16.to_le_bytes()); // channels
+        assert_eq!(&encoded[24..28], &48_000_u32.to_le_bytes());
+        assert_eq!(&encoded[28..32], &(48_000_u32 * 4).to_le_bytes()); // byte rate
+        assert_eq!(&encoded[32..34], &4_u16.to_le_bytes()); // block align
+        assert_eq!(&encoded[34..36], &16_u16.to_le_bytes()); // bits per sample
+        assert_eq!(&encoded[36..40], b"data");
+        assert_eq!(&encoded[40..44], &12_u32.to_le_bytes());
+
+        // interleaved samples
+        assert_eq!(&encoded[44..46], &0_i16.to_le_bytes());
+        assert_eq!(&encoded[46..48], &32_767_i16.to_le_bytes());
+        assert_eq!(&encoded[48..50], &16_384_i16.to_le_bytes());
+
+        let mut encoded = vec![];
+        buffer.write_wav(&mut encoded, SampleFormat::F32).unwrap();
+
+        // extended fmt chunk and fact chunk, 3 frames of 2 channels of 4 bytes
+        assert_eq!(encoded.len(), 58 + 24);
+        assert_eq!(&encoded[20..22], &3_u16.to_le_bytes()); // IEEE float
+        assert_eq!(&encoded[36..46], b"\0\0fact\x04\0\0\0");
+        assert_eq!(&encoded[46..50], &3_u32.to_le_bytes()); // frames
+        assert_eq!(&encoded[50..54], b"data");
+        assert_eq!(&encoded[58..62], &0_f32.to_le_bytes());
+        assert_eq!(&encoded[62..66], &1_f32.to_le_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let left = ChannelData::from(vec![0., 0.5, -1.]);
+        let right = ChannelData::from(vec![1., -0.5, 0.25]);
+        let buffer = AudioBuffer::from_channels(vec![left, right], SampleRate(48_000));
+
+        let json = serde_json::to_string(&buffer).unwrap();
+        assert_eq!(
+            json,
+            r#"{"channels":[[0.0,0.5,-1.0],[1.0,-0.5,0.25]],"sample_rate":48000}"#
+        );
+
+        let decoded: AudioBuffer = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.sample_rate(), SampleRate(48_000));
+        assert_eq!(decoded.channels(), buffer.channels());
+
+        // copy-on-write: modifying a clone leaves the deserialized buffer intact
+        let mut copy = decoded.clone();
+        copy.channel_data_mut(0).as_mut_slice()[0] = 2.;
+        assert_eq!(decoded.channel_data(0).as_slice(), &[0., 0.5, -1.]);
+        assert_eq!(copy.channel_data(0).as_slice(), &[2., 0.5, -1.]);
+    }
+
+    #[test]
+    fn test_dither_removes_distortion() {
+        // a sine of 2 LSB amplitude, at exactly bin 100 of the spectrum
+        let len = 16_384;
+        let bin = 100;
+        let signal = (0..len)
+            .map(|i| {
+                2. / 32_768. * (2. * std::f32::consts::PI * (bin * i) as f32 / len as f32).sin()
+            })
+            .collect();
+        let buffer =
+            AudioBuffer::from_channels(vec![ChannelData::from(signal)], SampleRate(44_100));
+
+        let power_spectrum = |dither| {
+            let mut quantized: Vec<f32> = buffer
+                .to_interleaved_i16(dither)
+                .into_iter()
+                .map(f32::from)
+                .collect();
+            let mut planner = realfft::RealFftPlanner::<f32>::new();
+            let r2c = planner.plan_fft_forward(len);
+            let mut spectrum = r2c.make_output_vec();
+            r2c.process(&mut quantized, &mut spectrum).unwrap();
+            spectrum.iter().map(|c| c.norm_sqr()).collect::<Vec<_>>()
+        };
+
+        // ratio of the strongest odd harmonic to the average noise floor
+        let distortion = |power: &[f32]| {
+            let harmonic = [3, 5, 7].iter().map(|h| power[h * bin]).fold(0., f32::max);
+            let floor = power[2_000..4_000].iter().sum::<f32>() / 2_000.;
+            harmonic / floor
+        };
+
+        let undithered = power_spectrum(DitherType::None);
+        assert!(distortion(&undithered) > 100.);
+
+        for dither in [DitherType::Rectangular, DitherType::Triangular] {
+            let dithered = power_spectrum(dither);
+            assert!(distortion(&dithered) < 20., "{:?}", dither);
+            // the tone itself is still there
+            assert!(dithered[bin] > 1_000. * dithered[3 * bin]);
+        }
+    }
 }
@@ -1,4 +1,5 @@
-//! The `BaseAudioContext` interface and the `AudioContext` and `OfflineAudioContext` types
+//! The `BaseAudioContext` interface and the `AudioContext`, `OfflineAudioContext` and
+//! `ManualAudioContext` types
 #![warn(
     clippy::all,
     clippy::pedantic,
@@ -7,39 +8,46 @@
     clippy::missing_docs_in_private_items
 )]
 
-use std::ops::Range;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::{Range, RangeInclusive};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 // magic node values
 /// Destination node id is always at index 0
 const DESTINATION_NODE_ID: u64 = 0;
 /// listener node id is always at index 1
-const LISTENER_NODE_ID: u64 = 1;
+pub(crate) const LISTENER_NODE_ID: u64 = 1;
 /// listener audio parameters ids are always at index 2 through 12
 const LISTENER_PARAM_IDS: Range<u64> = 2..12;
 
 use crate::buffer::{AudioBuffer, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
+use crate::control::Scheduler;
 use crate::graph::{NodeIndex, RenderThread};
-use crate::media::{MediaElement, MediaStream};
-use crate::message::ControlMessage;
+#[cfg(all(not(test), feature = "cpal"))]
+use crate::media::Microphone;
+#[cfg(any(test, feature = "cpal"))]
+use crate::media::MicrophoneError;
+use crate::media::{DecodeError, MediaElement, MediaStream};
+use crate::message::{latency_channel, ControlMessage, LatencyReader};
 use crate::node::{
     self, AnalyserOptions, AudioBufferSourceNodeOptions, AudioNode, ChannelMergerOptions,
     ChannelSplitterOptions, ConstantSourceOptions, DelayOptions, GainOptions, IirFilterOptions,
-    PannerOptions, PeriodicWave, PeriodicWaveOptions,
+    PannerOptions, PeriodicWave, PeriodicWaveOptions, RunawayDetection,
 };
 use crate::param::{AudioParam, AudioParamOptions, AutomationEvent};
 use crate::process::AudioProcessor;
 use crate::spatial::{AudioListener, AudioListenerParams};
-use crate::{SampleRate, BUFFER_SIZE};
+use crate::{IndexSizeError, NotSupportedError, SampleRate, BUFFER_SIZE, MAX_CHANNELS};
+#[cfg(any(test, feature = "cpal"))]
+use crate::{InvalidStateError, NotFoundError};
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 use crate::io;
 
-#[cfg(not(test))]
-use cpal::{traits::StreamTrait, Stream};
-
-use crossbeam_channel::Sender;
+#[cfg(any(test, feature = "manual"))]
+use crossbeam_channel::TryRecvError;
+use crossbeam_channel::{Receiver, Select, Sender};
 
 /// The `BaseAudioContext` interface represents an audio-processing graph built from audio modules
 /// linked together, each represented by an `AudioNode`. An audio context controls both the creation
@@ -58,20 +66,230 @@ impl PartialEq for BaseAudioContext {
     }
 }
 
+/// Callback invoked with the new sample rate when the output device changes its rate
+#[cfg(any(test, feature = "cpal"))]
+type SampleRateChangeCallback = Box<dyn FnMut(SampleRate) + Send>;
+
+/// User callback for the end of playback of a source node
+type OnEndedCallback = Box<dyn FnOnce() + Send>;
+
+/// User callbacks of a context, shared with the thread running them
+#[derive(Default)]
+struct ContextCallbacks {
+    /// user callback for sample rate changes of the output device
+    #[cfg(any(test, feature = "cpal"))]
+    sample_rate_change: Mutex<Option<SampleRateChangeCallback>>,
+    /// user callbacks for the end of playback, by node id
+    onended: Mutex<HashMap<u64, OnEndedCallback>>,
+}
+
+/// Events handled by the dispatcher running the user callbacks of a context
+enum ContextEvent {
+    /// the output device switched to a new sample rate
+    #[cfg(any(test, feature = "cpal"))]
+    SampleRateChange(SampleRate),
+    /// run the callback of the node with this id once its playback has ended
+    WatchEnded {
+        /// id of the source node
+        id: u64,
+        /// notified by the render thread at the end of playback
+        ended: Receiver<()>,
+    },
+}
+
+/// Runs the user callbacks of a context for the events it receives
+///
+/// A single dispatcher watches the end of playback of all source nodes with an `onended`
+/// callback, so the number of threads does not grow with the number of nodes.
+#[derive(Default)]
+struct EventDispatcher {
+    /// source nodes whose end of playback is awaited, with their notification
+    watched: Vec<(u64, Receiver<()>)>,
+}
+
+impl EventDispatcher {
+    /// Handle an event sent by the control side of the context
+    #[cfg_attr(not(any(test, feature = "cpal")), allow(unused_variables))]
+    fn handle_event(&mut self, event: ContextEvent, callbacks: &ContextCallbacks) {
+        match event {
+            #[cfg(any(test, feature = "cpal"))]
+            ContextEvent::SampleRateChange(sample_rate) => {
+                if let Some(callback) = callbacks.sample_rate_change.lock().unwrap().as_mut() {
+                    (callback)(sample_rate);
+                }
+            }
+            ContextEvent::WatchEnded { id, ended } => self.watched.push((id, ended)),
+        }
+    }
+
+    /// Stop watching the node at `index`, and run its callback if its playback has `ended`
+    ///
+    /// The playback did not end when the node and its renderer were dropped before.
+    fn handle_ended(&mut self, index: usize, ended: bool, callbacks: &ContextCallbacks) {
+        let (id, _) = self.watched.swap_remove(index);
+        if ended {
+            let callback = callbacks.onended.lock().unwrap().remove(&id);
+            if let Some(callback) = callback {
+                (callback)();
+            }
+        }
+    }
+
+    /// Runs the user callbacks on the current thread, until the context is dropped
+    fn run(mut self, events: &Receiver<ContextEvent>, callbacks: &ContextCallbacks) {
+        loop {
+            let mut select = Select::new();
+            select.recv(events);
+            for (_, ended) in &self.watched {
+                select.recv(ended);
+            }
+
+            let operation = select.select();
+            let index = operation.index();
+            if index == 0 {
+                match operation.recv(events) {
+                    Ok(event) => self.handle_event(event, callbacks),
+                    // the context is gone
+                    Err(_) => return,
+                }
+            } else {
+                let ended = operation.recv(&self.watched[index - 1].1).is_ok();
+                self.handle_ended(index - 1, ended, callbacks);
+            }
+        }
+    }
+
+    /// Runs the user callbacks of the events received so far, without blocking
+    #[cfg(any(test, feature = "manual"))]
+    fn dispatch_pending(&mut self, events: &Receiver<ContextEvent>, callbacks: &ContextCallbacks) {
+        for event in events.try_iter() {
+            self.handle_event(event, callbacks);
+        }
+
+        let mut index = 0;
+        while index < self.watched.len() {
+            match self.watched[index].1.try_recv() {
+                Ok(()) => self.handle_ended(index, true, callbacks),
+                Err(TryRecvError::Disconnected) => self.handle_ended(index, false, callbacks),
+                Err(TryRecvError::Empty) => index += 1,
+            }
+        }
+    }
+}
+
+/// Connections of the audio graph, mirrored on the control thread to reject cycles without a
+/// `DelayNode` when they are made
+struct Connections {
+    /// audio connections: (from, output, to, input)
+    edges: BTreeSet<(u64, u32, u64, u32)>,
+    /// param connections: (param, node it controls)
+    param_edges: BTreeSet<(u64, u64)>,
+    /// nodes that can break a cycle, i.e. `DelayNode`s
+    cycle_breakers: HashSet<u64>,
+    /// ids of the nodes freed by the render thread
+    freed: Receiver<u64>,
+}
+
+impl Connections {
+    /// Empty connections, `freed` receives the ids of the nodes freed by the render thread
+    fn new(freed: Receiver<u64>) -> Self {
+        Self {
+            edges: BTreeSet::new(),
+            param_edges: BTreeSet::new(),
+            cycle_breakers: HashSet::new(),
+            freed,
+        }
+    }
+
+    /// Forget the connections of the nodes freed by the render thread since the last call
+    ///
+    /// A node is only freed once its handle is dropped and it finished rendering, so no new
+    /// connections to or from it can be made anymore.
+    fn remove_freed(&mut self) {
+        let Self {
+            edges,
+            param_edges,
+            cycle_breakers,
+            freed,
+        } = self;
+
+        for id in freed.try_iter() {
+            edges.retain(|&(source, _, dest, _)| source != id && dest != id);
+            param_edges.retain(|&(source, dest)| source != id && dest != id);
+            cycle_breakers.remove(&id);
+        }
+    }
+
+    /// Indicates if an audio connection from `from` to `to` would close a cycle that does not
+    /// pass through the audio input of a node able to break it
+    ///
+    /// The render thread breaks a cycle by reading the audio input of a `DelayNode` one render
+    /// quantum late, so a path through the `delayTime` param of a `DelayNode` does not count.
+    fn closes_cycle_without_delay(&self, from: u64, to: u64) -> bool {
+        let mut visited = HashSet::new();
+        // nodes to visit, flagged if they are reached through their audio input
+        let mut stack = vec![(to, true)];
+
+        while let Some((node, audio_input)) = stack.pop() {
+            if audio_input && self.cycle_breakers.contains(&node) {
+                continue;
+            }
+            if node == from {
+                return true;
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+
+            let audio = self
+                .edges
+                .range((node, 0, 0, 0)..=(node, u32::MAX, u64::MAX, u32::MAX))
+                .map(|&(_, _, dest, _)| (dest, true));
+            let params = self
+                .param_edges
+                .range((node, 0)..=(node, u64::MAX))
+                .map(|&(_, dest)| (dest, false));
+            stack.extend(audio.chain(params));
+        }
+
+        false
+    }
+}
+
 /// Inner representation of the `BaseAudioContext`
 struct BaseAudioContextInner {
-    /// sample rate in Hertz
-    sample_rate: SampleRate,
+    /// sample rate in Hertz, may change when the output device is reconfigured
+    sample_rate: AtomicU32,
+    /// current `AudioContextState`
+    state: AtomicU8,
+    /// user callbacks, run on the event thread, or inline by a `ManualAudioContext`
+    callbacks: Arc<ContextCallbacks>,
+    /// events for the user callbacks, the event thread is spawned on first use unless the
+    /// context dispatches them itself
+    events: Mutex<Option<Sender<ContextEvent>>>,
     /// number of speaker output channels
     channels: u32,
     /// incrementing id to assign to audio nodes
     node_id_inc: AtomicU64,
     /// message channel from control to render thread
     render_channel: Sender<ControlMessage>,
+    /// connections of the audio graph, as sent to the render thread
+    connections: Mutex<Connections>,
     /// number of frames played
     frames_played: Arc<AtomicU64>,
     /// AudioListener fields
     listener_params: Option<AudioListenerParams>,
+    /// limit the destination output to [-1, 1] - shared with the destination renderer
+    output_limiter: Arc<AtomicBool>,
+    /// bit mask of muted output channels, shared with the destination renderer
+    muted_channels: Arc<AtomicU32>,
+    /// runaway level detection, shared with the destination renderer
+    runaway_detection: Arc<RunawayDetection>,
+    /// latency from each node to the destination, published by the render thread
+    latencies: Mutex<LatencyReader>,
+    /// node ids in processing order, filled by the render thread
+    #[cfg(any(test, feature = "debug"))]
+    render_order: Arc<Mutex<Vec<u64>>>,
 }
 
 /// Retrieve the `BaseAudioContext` from the concrete `AudioContext`
@@ -91,6 +309,11 @@ pub trait AsBaseAudioContext {
         node::StereoPannerNode::new(self.base(), None)
     }
 
+    /// Creates a `HaasWidenerNode` to widen a mono source to stereo
+    fn create_haas_widener(&self) -> node::HaasWidenerNode {
+        node::HaasWidenerNode::new(self.base(), node::HaasWidenerOptions::default())
+    }
+
     /// Creates an `GainNode`, to control audio volume
     fn create_gain(&self) -> node::GainNode {
         node::GainNode::new(self.base(), GainOptions::default())
@@ -101,6 +324,11 @@ pub trait AsBaseAudioContext {
         node::ConstantSourceNode::new(self.base(), ConstantSourceOptions::default())
     }
 
+    /// Creates a `NoiseNode`, a source of white noise with a fixed seed
+    fn create_noise(&self) -> node::NoiseNode {
+        node::NoiseNode::new(self.base(), node::NoiseOptions::default())
+    }
+
     /// Creates an `IirFilterNode`
     ///
     /// # Arguments
@@ -191,6 +419,35 @@ pub trait AsBaseAudioContext {
         node::MediaElementAudioSourceNode::new(self.base(), opts)
     }
 
+    /// Creates a `MediaStreamAudioDestinationNode`, capturing its input as a `MediaStream`
+    fn create_media_stream_destination(&self) -> node::MediaStreamAudioDestinationNode {
+        let opts = node::MediaStreamAudioDestinationNodeOptions::default();
+        node::MediaStreamAudioDestinationNode::new(self.base(), opts)
+    }
+
+    /// Creates a silent `AudioBuffer` of `number_of_channels` channels of `length` sample-frames
+    ///
+    /// # Errors
+    ///
+    /// Will return a `NotSupportedError` if `number_of_channels` is zero or exceeds
+    /// `MAX_CHANNELS`, if `length` is zero, or if the sample rate is outside the range
+    /// `[3000, 768000]` Hz
+    fn create_buffer(
+        &self,
+        number_of_channels: usize,
+        length: usize,
+        sample_rate: SampleRate,
+    ) -> Result<AudioBuffer, NotSupportedError> {
+        if !(1..=MAX_CHANNELS).contains(&number_of_channels)
+            || length == 0
+            || !(3000..=768_000).contains(&sample_rate.0)
+        {
+            return Err(NotSupportedError {});
+        }
+
+        Ok(AudioBuffer::new(number_of_channels, length, sample_rate))
+    }
+
     /// Creates an `AudioBufferSourceNode`
     ///
     /// Note: do not forget to `start()` the node.
@@ -208,6 +465,89 @@ pub trait AsBaseAudioContext {
         node::AnalyserNode::new(self.base(), AnalyserOptions::default())
     }
 
+    /// Creates a `ConvolverNode`, without impulse response
+    fn create_convolver(&self) -> node::ConvolverNode {
+        node::ConvolverNode::new(self.base(), node::ConvolverOptions::default())
+    }
+
+    /// Creates a `MeterNode`, to measure signal levels
+    fn create_meter(&self) -> node::MeterNode {
+        node::MeterNode::new(self.base(), node::MeterOptions::default())
+    }
+
+    /// Creates a `TimeStretchNode`, without source buffer
+    fn create_time_stretch(&self) -> node::TimeStretchNode {
+        node::TimeStretchNode::new(self.base(), node::TimeStretchOptions::default())
+    }
+
+    /// Creates a `ParametricEqNode`, cascading the given bands
+    fn create_parametric_eq(&self, bands: Vec<node::BandSpec>) -> node::ParametricEqNode {
+        let options = node::ParametricEqOptions {
+            bands,
+            ..node::ParametricEqOptions::default()
+        };
+        node::ParametricEqNode::new(self.base(), options)
+    }
+
+    /// Creates a `GrainPlayerNode`, without source buffer
+    fn create_grain_player(&self) -> node::GrainPlayerNode {
+        node::GrainPlayerNode::new(self.base(), node::GrainPlayerOptions::default())
+    }
+
+    /// Creates a `ScriptProcessorNode`, processing audio with the given closure
+    ///
+    /// The closure is invoked on the render thread for every render quantum, it should not
+    /// allocate or block. See [`node::ScriptProcessorNode`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_size` - zero, or a power of two in the range `[256, 16384]`, for compatibility
+    ///   with the browser API
+    /// * `number_of_input_channels` - number of channels of the input, zero for a source node
+    /// * `number_of_output_channels` - number of channels of the output
+    /// * `callback` - closure receiving the input and output buffers
+    fn create_script_processor<F>(
+        &self,
+        buffer_size: usize,
+        number_of_input_channels: usize,
+        number_of_output_channels: usize,
+        callback: F,
+    ) -> node::ScriptProcessorNode
+    where
+        F: FnMut(&[crate::alloc::AudioBuffer], &mut [crate::alloc::AudioBuffer]) + Send + 'static,
+    {
+        let options = node::ScriptProcessorOptions {
+            buffer_size,
+            number_of_input_channels,
+            number_of_output_channels,
+        };
+        node::ScriptProcessorNode::new(self.base(), options, callback)
+    }
+
+    /// Creates an `AudioWorkletNode`, running the user-defined [`Worklet`](crate::worklet::Worklet)
+    ///
+    /// The `AudioParam`s of the node are created from the
+    /// [`parameter_descriptors`](crate::worklet::Worklet::parameter_descriptors) of the worklet.
+    fn register_worklet<P: crate::worklet::Worklet>(
+        &self,
+        options: crate::worklet::AudioWorkletNodeOptions<P::ProcessorOptions>,
+    ) -> crate::worklet::AudioWorkletNode {
+        crate::worklet::AudioWorkletNode::new::<P, _>(self.base(), options)
+    }
+
+    /// Decode an encoded audio file into an `AudioBuffer`, at the sample rate of the context
+    ///
+    /// The WAV (`wav` feature), Ogg Vorbis (`ogg` feature) and MP3 (`mp3` feature) formats are
+    /// supported, and are detected from the content. All channels of the file are preserved.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the input can not be read, if its format is not supported or if
+    /// the data is corrupt
+    fn decode_audio_data<R: std::io::Read>(&self, input: R) -> Result<AudioBuffer, DecodeError> {
+        crate::media::decode_audio_data(input, self.sample_rate())
+    }
+
     /// Creates a periodic wave
     fn create_periodic_wave(&self, options: Option<PeriodicWaveOptions>) -> PeriodicWave {
         PeriodicWave::new(self.base(), options)
@@ -227,13 +567,50 @@ pub trait AsBaseAudioContext {
             (node, Box::new(proc))
         });
 
-        // audio params are connected to the 'hidden' u32::MAX input. TODO make nicer
-        self.base().connect(param.id(), dest, 0, u32::MAX);
+        self.base().connect_param(param.id(), dest);
 
         let proc_id = AudioParamId(param.id().0);
         (param, proc_id)
     }
 
+    /// Connects the nodes in series, the first output of each node to the first input of the next
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{AsBaseAudioContext, AudioContext};
+    ///
+    /// let context = AudioContext::new(None);
+    /// let osc = context.create_oscillator();
+    /// let filter = context.create_biquad_filter();
+    /// let gain = context.create_gain();
+    ///
+    /// context
+    ///     .chain(&[&osc, &filter, &gain, &context.destination()])
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `IndexSizeError` and makes no connection if any node in the chain (except the
+    /// last) has no outputs, or any node (except the first) has no inputs
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the nodes belong to different contexts
+    fn chain(&self, nodes: &[&dyn AudioNode]) -> Result<(), IndexSizeError> {
+        let valid = nodes
+            .windows(2)
+            .all(|pair| pair[0].number_of_outputs() > 0 && pair[1].number_of_inputs() > 0);
+        if !valid {
+            return Err(IndexSizeError {});
+        }
+
+        for pair in nodes.windows(2) {
+            pair[0].connect_at(pair[1], 0, 0)?;
+        }
+
+        Ok(())
+    }
+
     /// Returns an `AudioDestinationNode` representing the final destination of all audio in the
     /// context. It can be thought of as the audio-rendering device.
     fn destination(&self) -> node::DestinationNode {
@@ -273,6 +650,11 @@ pub trait AsBaseAudioContext {
         self.base().sample_rate()
     }
 
+    /// The current state of the context
+    fn state(&self) -> AudioContextState {
+        self.base().state()
+    }
+
     /// This is the time in seconds of the sample frame immediately following the last sample-frame
     /// in the block of audio most recently processed by the context’s rendering graph.
     fn current_time(&self) -> f64 {
@@ -310,9 +692,37 @@ pub enum LatencyHint {
     Specific(f64),
 }
 
+/// Describes the current state of an `AudioContext`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// the naming comes from the web audio specfication
+#[allow(clippy::module_name_repetitions)]
+pub enum AudioContextState {
+    /// The context is suspended: time does not progress and the audio hardware is paused
+    Suspended,
+    /// Audio is being processed
+    Running,
+    /// The context is released, it can no longer be used to process audio
+    Closed,
+}
+
+impl From<u8> for AudioContextState {
+    fn from(i: u8) -> Self {
+        match i {
+            0 => Self::Suspended,
+            1 => Self::Running,
+            2 => Self::Closed,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Specify the playback configuration
 /// in non web context, it is the only way to specify
 /// the system configuration
+///
+/// Unset fields take the default of the output device. If the device does not support the
+/// requested configuration, the context falls back to the device default and logs a warning.
+#[derive(Default)]
 pub struct AudioContextOptions {
     /// Identify the type of playback, which affects
     /// tradeoffs between audio output latency and power consumption
@@ -321,21 +731,54 @@ pub struct AudioContextOptions {
     pub sample_rate: Option<u32>,
     /// Number of output channels of destination node and audio output hardware
     pub channels: Option<u16>,
+    /// Identifier of the output device, as listed by [`AudioContext::enumerate_devices`].
+    /// The default output device is used if unset.
+    pub device_id: Option<String>,
+}
+
+/// Description of an audio output device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Identifier of the device, to pass to [`AudioContext::new_with_device`]
+    ///
+    /// This is the name the host reports for the device, with the suffix ` (n)` for the n-th
+    /// device sharing that name. It is stable as long as the same devices stay connected to the
+    /// system.
+    pub id: String,
+    /// Human readable name of the device
+    pub name: String,
+    /// Ranges of sample rates supported by the device
+    pub sample_rates: Vec<RangeInclusive<u32>>,
+    /// Numbers of output channels supported by the device
+    pub channels: Vec<u16>,
 }
 
 /// This interface represents an audio graph whose `AudioDestinationNode` is routed to a real-time
 /// output device that produces a signal directed at the user.
 // the naming comes from the web audio specfication
 #[allow(clippy::module_name_repetitions)]
+#[cfg(any(test, feature = "cpal"))]
 pub struct AudioContext {
     /// represents the underlying `BaseAudioContext`
     base: BaseAudioContext,
 
     /// cpal stream (play/pause functionality)
     #[cfg(not(test))] // in tests, do not set up a cpal Stream
-    stream: Stream,
+    stream: io::OutputStreamHandle,
+}
+
+/// Closes the output stream and waits for the render thread to stop
+///
+/// Without this, the thread owning the stream would wind down in the background after the context
+/// is gone, possibly still rendering a few quanta.
+#[cfg(all(not(test), feature = "cpal"))] // in tests, there is no cpal Stream
+impl Drop for AudioContext {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
 
+#[cfg(any(test, feature = "cpal"))]
 impl AsBaseAudioContext for AudioContext {
     fn base(&self) -> &BaseAudioContext {
         &self.base
@@ -353,6 +796,37 @@ pub struct OfflineAudioContext {
     length: usize,
     /// the rendering 'thread', fully controlled by the offline context
     renderer: RenderThread,
+    /// scheduled suspensions, as frame number and callback, sorted by frame
+    suspensions: Vec<(usize, SuspendCallback)>,
+    /// notified of the rendering progress
+    onprogress: Mutex<Option<ProgressCallback>>,
+}
+
+/// Callback run by the `OfflineAudioContext` when rendering is suspended
+type SuspendCallback = Box<dyn FnOnce(&BaseAudioContext) + Send>;
+
+/// Callback notified by the `OfflineAudioContext` of the fraction of the rendering done
+type ProgressCallback = Box<dyn FnMut(f64) + Send>;
+
+/// Number of sample-frames rendered by the `OfflineAudioContext` between progress notifications
+const PROGRESS_INTERVAL: usize = 128 * BUFFER_SIZE as usize;
+
+/// Options for constructing an [`OfflineAudioContext`]
+// the naming comes from the web audio specfication
+#[allow(clippy::module_name_repetitions)]
+pub struct OfflineAudioContextOptions {
+    /// number of output channels to render
+    pub number_of_channels: u32,
+    /// length of the rendering audio buffer
+    pub length: usize,
+    /// output sample rate
+    pub sample_rate: SampleRate,
+    /// Custom processor taking the place of the destination at the root of the render graph
+    ///
+    /// It receives the mix of all nodes connected to the destination as its single input, and
+    /// its single output ends up in the rendered buffer. The output limiter and channel muting of
+    /// the regular destination do not apply.
+    pub root_processor: Option<Box<dyn AudioProcessor>>,
 }
 
 impl AsBaseAudioContext for OfflineAudioContext {
@@ -361,81 +835,345 @@ impl AsBaseAudioContext for OfflineAudioContext {
     }
 }
 
+/// The `ManualAudioContext` runs the control and render side on the same thread.
+///
+/// Rendering is driven by the caller, e.g. from the `process` callback of a browser
+/// `AudioWorklet` (via wasm-bindgen) on a single threaded `wasm32` target.
+///
+/// No audio device is opened and no threads are spawned. Control messages (node registration,
+/// connections, automation events) are queued and applied synchronously, either explicitly with
+/// [`apply_control_messages`](Self::apply_control_messages) or implicitly at the start of every
+/// rendered quantum. User callbacks, e.g. `onended`, run on the calling thread at the end of
+/// [`render`](Self::render).
+///
+/// Available with the `manual` feature (enabled by default).
+// the naming follows the other context types
+#[allow(clippy::module_name_repetitions)]
+#[cfg(any(test, feature = "manual"))]
+pub struct ManualAudioContext {
+    /// represents the underlying `BaseAudioContext`
+    base: BaseAudioContext,
+    /// the rendering 'thread', fully controlled by the caller
+    renderer: RenderThread,
+    /// events for the user callbacks, dispatched after rendering
+    events: Receiver<ContextEvent>,
+    /// runs the user callbacks, in place of the event thread of other contexts
+    dispatcher: EventDispatcher,
+}
+
+#[cfg(any(test, feature = "manual"))]
+impl AsBaseAudioContext for ManualAudioContext {
+    fn base(&self) -> &BaseAudioContext {
+        &self.base
+    }
+}
+
+#[cfg(any(test, feature = "cpal"))]
 impl AudioContext {
     /// Creates and returns a new `AudioContext` object.
     /// This will play live audio on the default output
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no output device is available, or the output stream cannot be built
+    #[must_use]
+    pub fn new(options: Option<AudioContextOptions>) -> Self {
+        Self::build(options, false).expect("no output device available")
+    }
+
+    /// Creates and returns a new `AudioContext` object, requesting the given output configuration
+    ///
+    /// The output device may not support the requested sample rate or number of channels, in which
+    /// case its default configuration is used. [`sample_rate`](BaseAudioContext::sample_rate)
+    /// always reports the negotiated value.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no output device is available, or the output stream cannot be built
+    #[must_use]
+    pub fn new_with_options(options: AudioContextOptions) -> Self {
+        Self::build(Some(options), false).expect("no output device available")
+    }
+
+    /// Creates and returns a new `AudioContext` object, in a suspended state
+    ///
+    /// The output stream is set up but stays paused until [`resume`](Self::resume) is called,
+    /// mirroring browsers which only allow audio after a user gesture. While suspended, no audio
+    /// plays, the graph is not rendered and the current time does not progress.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no output device is available, or the output stream cannot be built
+    #[must_use]
+    pub fn new_suspended(options: Option<AudioContextOptions>) -> Self {
+        Self::build(options, true).expect("no output device available")
+    }
+
+    /// Creates and returns a new `AudioContext` object, playing on the output device with the
+    /// given id
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotFoundError` if no output device with this id is listed by
+    /// [`enumerate_devices`](Self::enumerate_devices)
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the output stream cannot be built
+    pub fn new_with_device(device_id: &str) -> Result<Self, NotFoundError> {
+        let options = AudioContextOptions {
+            device_id: Some(device_id.to_string()),
+            ..AudioContextOptions::default()
+        };
+        Self::build(Some(options), false)
+    }
+
+    /// Creates a `MediaStreamAudioSourceNode` capturing the system default input device
+    ///
+    /// The input is resampled to the sample rate of the context if the device runs at another
+    /// rate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no input device, or if it cannot be opened, e.g. because
+    /// access was denied
+    pub fn create_media_stream_source_from_default_input(
+        &self,
+    ) -> Result<node::MediaStreamAudioSourceNode, MicrophoneError> {
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        return Microphone::try_new().map(|input| self.create_media_stream_source(input));
+        #[cfg(test)]
+        Err(MicrophoneError::NotFound)
+    }
+
+    /// Lists the audio output devices available on the system
+    #[must_use]
+    pub fn enumerate_devices() -> Vec<DeviceInfo> {
+        #[cfg(not(test))] // in tests, do not query the audio devices
+        return io::enumerate_output_devices();
+        #[cfg(test)]
+        vec![]
+    }
+
+    /// Indicates if the output device of the context is no longer available, e.g. because it was
+    /// unplugged
+    ///
+    /// The context does not switch to another device: no audio is played anymore and the current
+    /// time stops progressing. Create a new context to continue playback.
+    // false positive due to #[cfg(not(test))]
+    #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
+    #[must_use]
+    pub fn device_lost(&self) -> bool {
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        return self.stream.device_lost();
+        #[cfg(test)]
+        false
+    }
+
+    /// Sets up the context and its output stream, paused if `suspended`
+    ///
+    /// Returns a `NotFoundError` if the requested output device is not available
     // options is passed by value to be conform to the specification interface
     #[allow(clippy::needless_pass_by_value)]
     #[cfg(not(test))]
-    #[must_use]
-    pub fn new(options: Option<AudioContextOptions>) -> Self {
+    fn build(options: Option<AudioContextOptions>, suspended: bool) -> Result<Self, NotFoundError> {
         // track number of frames - synced from render thread to control thread
         let frames_played = Arc::new(AtomicU64::new(0));
         let frames_played_clone = frames_played.clone();
 
-        let (stream, config, sender) = io::build_output(frames_played_clone, options.as_ref());
+        // the output stream reports sample rate changes of the device to the context, which does
+        // not exist yet. Hold a weak reference to prevent a cycle via the stream.
+        let context_slot = Arc::new(Mutex::new(std::sync::Weak::new()));
+        let context_slot_clone = context_slot.clone();
+        let on_sample_rate_change = move |sample_rate| {
+            let inner = context_slot_clone.lock().unwrap().upgrade();
+            if let Some(inner) = inner {
+                BaseAudioContext { inner }.change_sample_rate(sample_rate);
+            }
+        };
+
+        let (stream, config, sender) = io::build_output(
+            frames_played_clone,
+            options.as_ref(),
+            Arc::new(on_sample_rate_change),
+            suspended,
+        )?;
         let channels = u32::from(config.channels);
         let sample_rate = SampleRate(config.sample_rate.0);
 
-        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender);
+        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender, None);
+        if suspended {
+            base.set_state(AudioContextState::Suspended);
+        }
+        *context_slot.lock().unwrap() = Arc::downgrade(&base.inner);
 
-        Self { base, stream }
+        Ok(Self { base, stream })
     }
 
     #[cfg(test)] // in tests, do not set up a cpal Stream
-    #[allow(clippy::must_use_candidate)]
-    pub fn new(options: Option<AudioContextOptions>) -> Self {
+    #[allow(clippy::needless_pass_by_value)]
+    fn build(options: Option<AudioContextOptions>, suspended: bool) -> Result<Self, NotFoundError> {
+        // no output devices are listed in tests
+        if options
+            .as_ref()
+            .and_then(|o| o.device_id.as_ref())
+            .is_some()
+        {
+            return Err(NotFoundError {});
+        }
+
         let options = options.unwrap_or(AudioContextOptions {
             latency_hint: Some(LatencyHint::Interactive),
             sample_rate: Some(44_100),
             channels: Some(2),
+            device_id: None,
         });
 
         let sample_rate = SampleRate(options.sample_rate.unwrap_or(44_100));
         let channels = u32::from(options.channels.unwrap_or(2));
         let (sender, _receiver) = crossbeam_channel::unbounded();
         let frames_played = Arc::new(AtomicU64::new(0));
-        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender);
+        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender, None);
+        if suspended {
+            base.set_state(AudioContextState::Suspended);
+        }
 
-        Self { base }
+        Ok(Self { base })
+    }
+
+    /// Register a callback to be notified when the output device changes its sample rate, e.g.
+    /// when switching from speakers to a headset.
+    ///
+    /// The output stream is rebuilt at the new rate, the context continues rendering at that rate
+    /// and [`sample_rate`](BaseAudioContext::sample_rate) reports the new value. Nodes that derived
+    /// buffers from the initial sample rate (e.g. the maximum delay time of a `DelayNode`) are not
+    /// reconfigured. The callback runs on a helper thread of the context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a previous callback panicked
+    pub fn set_on_sample_rate_change<F: FnMut(SampleRate) + Send + 'static>(&self, callback: F) {
+        self.base
+            .set_sample_rate_change_callback(Box::new(callback));
     }
 
     /// Suspends the progression of time in the audio context, temporarily halting audio hardware
     /// access and reducing CPU/battery usage in the process.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Will panic if:
+    /// Returns an `InvalidStateError` if the context is closed, or if the audio device is not
+    /// available anymore, see [`device_lost`](Self::device_lost)
     ///
-    /// * The audio device is not available
-    /// * For a `BackendSpecificError`
-    // false positive due to #[cfg(not(test))]
-    #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
-    pub fn suspend(&self) {
+    /// # Panics
+    ///
+    /// Will panic for a `BackendSpecificError`
+    pub fn suspend(&self) -> Result<(), InvalidStateError> {
+        self.check_running_device()?;
         #[cfg(not(test))] // in tests, do not set up a cpal Stream
         self.stream.pause().unwrap();
+        self.base.set_state(AudioContextState::Suspended);
+        Ok(())
     }
 
     /// Resumes the progression of time in an audio context that has previously been
     /// suspended/paused.
     ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidStateError` if the context is closed, or if the audio device is not
+    /// available anymore, see [`device_lost`](Self::device_lost)
+    ///
     /// # Panics
     ///
-    /// Will panic if:
+    /// Will panic for a `BackendSpecificError`
+    pub fn resume(&self) -> Result<(), InvalidStateError> {
+        self.check_running_device()?;
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        self.stream.play().expect("Audio device refuse to play");
+        self.base.set_state(AudioContextState::Running);
+        Ok(())
+    }
+
+    /// Closes the audio context, releasing the audio device and stopping the render thread
+    ///
+    /// Once closed, the context can no longer create nodes. Closing it again is a no-op.
+    // false positive due to #[cfg(not(test))]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn close(&self) {
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        self.stream.close();
+        self.base.set_state(AudioContextState::Closed);
+    }
+
+    /// Checks that the context is not closed and its output device is still available
+    fn check_running_device(&self) -> Result<(), InvalidStateError> {
+        if self.base.state() == AudioContextState::Closed || self.device_lost() {
+            return Err(InvalidStateError {});
+        }
+        Ok(())
+    }
+
+    /// The number of seconds of processing latency incurred by the `AudioContext` passing the
+    /// audio from the `AudioDestinationNode` to the audio subsystem, i.e. the duration of the
+    /// buffer of the output stream
     ///
-    /// * The audio device is not available
-    /// * For a `BackendSpecificError`
+    /// This changes when the buffer size is raised by [adaptive
+    /// latency](Self::set_adaptive_latency).
+    // false positive due to #[cfg(not(test))]
+    #[allow(clippy::missing_const_for_fn)]
+    #[must_use]
+    pub fn base_latency(&self) -> f64 {
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        return self.stream.base_latency();
+        #[cfg(test)]
+        {
+            f64::from(BUFFER_SIZE) / f64::from(self.base.sample_rate().0)
+        }
+    }
+
+    /// An estimation in seconds of the output latency, i.e. the interval between the time the
+    /// audio subsystem requests a buffer and the time its first sample is played by the audio
+    /// output device
+    ///
+    /// This is derived from the timestamps reported by the host to the output stream. Not all
+    /// hosts report the playback time, in which case this falls back to
+    /// [`base_latency`](Self::base_latency) and does not account for the hardware latency.
+    #[must_use]
+    pub fn output_latency(&self) -> f64 {
+        #[cfg(not(test))] // in tests, do not set up a cpal Stream
+        return self
+            .stream
+            .hardware_latency()
+            .unwrap_or_else(|| self.base_latency());
+        #[cfg(test)]
+        self.base_latency()
+    }
+
+    /// Enables or disables adaptive latency of the output stream.
+    ///
+    /// When enabled, the output stream restarts at the smallest buffer size supported by the
+    /// device. Render calls that miss their deadline (xruns) are counted, and every time new
+    /// xruns are observed the buffer size is doubled, up to the maximum of the device.
+    ///
+    /// This trades latency for stability: a small buffer size keeps the delay between control
+    /// changes and audible output low, but leaves little headroom to absorb load spikes. The
+    /// buffer size never shrinks back while adaptive latency is enabled. Disabling it keeps the
+    /// current buffer size.
     // false positive due to #[cfg(not(test))]
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
-    pub fn resume(&self) {
+    pub fn set_adaptive_latency(&self, enabled: bool) {
         #[cfg(not(test))] // in tests, do not set up a cpal Stream
-        self.stream.play().expect("Audio device refuse to play");
+        self.stream.set_adaptive_latency(enabled);
+        #[cfg(test)]
+        let _ = enabled;
     }
 }
 
 /// Unique identifier for audio nodes.
 ///
 /// Used for internal bookkeeping.
+#[derive(Debug, PartialEq, Eq)]
 pub struct AudioNodeId(u64);
 
 /// Unique identifier for audio params.
@@ -487,8 +1225,14 @@ impl Drop for AudioContextRegistration {
             || LISTENER_PARAM_IDS.contains(&self.id.0);
 
         if !magic {
+            // the callback is not run for dropped nodes
+            if let Ok(mut callbacks) = self.context.inner.callbacks.onended.lock() {
+                callbacks.remove(&self.id.0);
+            }
+
             let message = ControlMessage::FreeWhenFinished { id: self.id.0 };
-            self.context.inner.render_channel.send(message).unwrap();
+            // the render thread is gone once the context is closed
+            let _ = self.context.inner.render_channel.send(message);
         }
     }
 }
@@ -500,31 +1244,61 @@ impl BaseAudioContext {
         channels: u32,
         frames_played: Arc<AtomicU64>,
         render_channel: Sender<ControlMessage>,
+        root_processor: Option<Box<dyn AudioProcessor>>,
     ) -> Self {
+        let (latency_publisher, latency_reader) = latency_channel();
+        let (freed_sender, freed_receiver) = crossbeam_channel::unbounded();
         let base_inner = BaseAudioContextInner {
-            sample_rate,
+            sample_rate: AtomicU32::new(sample_rate.0),
+            state: AtomicU8::new(AudioContextState::Running as u8),
+            callbacks: Arc::new(ContextCallbacks::default()),
+            events: Mutex::new(None),
             channels,
             render_channel,
+            connections: Mutex::new(Connections::new(freed_receiver)),
             node_id_inc: AtomicU64::new(0),
             frames_played,
             listener_params: None,
+            output_limiter: Arc::new(AtomicBool::new(false)),
+            muted_channels: Arc::new(AtomicU32::new(0)),
+            runaway_detection: Arc::new(RunawayDetection::default()),
+            latencies: Mutex::new(latency_reader),
+            #[cfg(any(test, feature = "debug"))]
+            render_order: Arc::new(Mutex::new(Vec::new())),
         };
         let base = Self {
             inner: Arc::new(base_inner),
         };
 
+        let message = ControlMessage::ShareLatencies {
+            latencies: latency_publisher,
+        };
+        base.inner.render_channel.send(message).unwrap();
+
+        let message = ControlMessage::ShareFreedNodes {
+            freed: freed_sender,
+        };
+        base.inner.render_channel.send(message).unwrap();
+
+        #[cfg(any(test, feature = "debug"))]
+        {
+            let render_order = base.inner.render_order.clone();
+            let message = ControlMessage::ShareRenderOrder { render_order };
+            base.inner.render_channel.send(message).unwrap();
+        }
+
         let listener_params = {
             // Register magical nodes. We should not store the nodes inside our context since that
             // will create a cyclic reference, but we can reconstruct a new instance on the fly
             // when requested
 
-            let dest = node::DestinationNode::new(&base, channels as usize);
+            let dest =
+                node::DestinationNode::new_with_processor(&base, channels as usize, root_processor);
             let listener = crate::spatial::AudioListenerNode::new(&base);
 
             // hack: Connect the listener to the destination node to force it to render at each
-            // quantum. Abuse the magical u32::MAX port so it acts as an AudioParam and has no side
-            // effects
-            base.connect(listener.id(), dest.id(), 0, u32::MAX);
+            // quantum. Use a param connection so it has no side effects
+            base.connect_param(listener.id(), dest.id());
 
             let listener_params = listener.into_fields();
             let AudioListener {
@@ -562,7 +1336,21 @@ impl BaseAudioContext {
     /// The sample rate (in sample-frames per second) at which the `AudioContext` handles audio.
     #[must_use]
     pub fn sample_rate(&self) -> SampleRate {
-        self.inner.sample_rate
+        SampleRate(self.inner.sample_rate.load(Ordering::SeqCst))
+    }
+
+    /// The current state of the context
+    ///
+    /// Only an `AudioContext` can be suspended or closed, the other contexts are always running.
+    #[must_use]
+    pub fn state(&self) -> AudioContextState {
+        self.inner.state.load(Ordering::SeqCst).into()
+    }
+
+    /// Updates the state of the context
+    #[cfg(any(test, feature = "cpal"))]
+    fn set_state(&self, state: AudioContextState) {
+        self.inner.state.store(state as u8, Ordering::SeqCst);
     }
 
     /// This is the time in seconds of the sample frame immediately following the last sample-frame
@@ -573,7 +1361,7 @@ impl BaseAudioContext {
     // Currently, we have no other choice than casting an u64 into f64, with possible loss of precision
     #[allow(clippy::cast_precision_loss)]
     pub fn current_time(&self) -> f64 {
-        self.inner.frames_played.load(Ordering::SeqCst) as f64 / f64::from(self.inner.sample_rate.0)
+        self.inner.frames_played.load(Ordering::SeqCst) as f64 / f64::from(self.sample_rate().0)
     }
 
     /// Number of channels for the audio destination
@@ -582,6 +1370,170 @@ impl BaseAudioContext {
         self.inner.channels
     }
 
+    /// Register the callback for the end of playback of the source node `id`, replacing any
+    /// previous one
+    ///
+    /// The callback runs on the event thread of the context once the render thread signals the
+    /// end of playback through the `scheduler`, or on the rendering thread of a
+    /// `ManualAudioContext`.
+    pub(crate) fn set_onended(
+        &self,
+        id: &AudioNodeId,
+        scheduler: &Scheduler,
+        callback: OnEndedCallback,
+    ) {
+        let id = id.0;
+        let previous = self
+            .inner
+            .callbacks
+            .onended
+            .lock()
+            .unwrap()
+            .insert(id, callback);
+
+        // the end of playback of this node is already watched
+        if previous.is_none() {
+            let ended = scheduler.ended_receiver().clone();
+            self.send_event(ContextEvent::WatchEnded { id, ended });
+        }
+    }
+
+    /// Send the events to `sender` from now on, the event thread is then never spawned
+    #[cfg(any(test, feature = "manual"))]
+    fn redirect_events(&self, sender: Sender<ContextEvent>) {
+        *self.inner.events.lock().unwrap() = Some(sender);
+    }
+
+    /// Send an event to the thread running the user callbacks, spawning it if needed
+    fn send_event(&self, event: ContextEvent) {
+        // only the caller creating the channel gets the receiver, and spawns the thread
+        let mut receiver = None;
+        let mut events = self.inner.events.lock().unwrap();
+        let sender = events
+            .get_or_insert_with(|| {
+                let (sender, new_receiver) = crossbeam_channel::unbounded();
+                receiver = Some(new_receiver);
+                sender
+            })
+            .clone();
+        drop(events);
+
+        if let Some(receiver) = receiver {
+            let callbacks = self.inner.callbacks.clone();
+            std::thread::Builder::new()
+                .name("web-audio-events".into())
+                .spawn(move || EventDispatcher::default().run(&receiver, &callbacks))
+                .expect("failed to spawn the context event thread");
+        }
+
+        // the thread only exits once the context is dropped
+        sender.send(event).unwrap();
+    }
+
+    /// Register the callback for sample rate changes of the output device
+    #[cfg(any(test, feature = "cpal"))]
+    pub(crate) fn set_sample_rate_change_callback(&self, callback: SampleRateChangeCallback) {
+        *self.inner.callbacks.sample_rate_change.lock().unwrap() = Some(callback);
+    }
+
+    /// Switch the context to a new sample rate, e.g. when the output device was reconfigured
+    ///
+    /// The render thread continues at the new rate from its next render quantum on. The user
+    /// callback runs on the event thread of the context, not on the thread reporting the change.
+    #[cfg(any(test, feature = "cpal"))]
+    pub(crate) fn change_sample_rate(&self, sample_rate: SampleRate) {
+        let previous = self.inner.sample_rate.swap(sample_rate.0, Ordering::SeqCst);
+        if previous == sample_rate.0 {
+            return;
+        }
+
+        log::warn!(
+            "Sample rate changed from {} to {} Hz",
+            previous,
+            sample_rate.0
+        );
+
+        let message = ControlMessage::SampleRateChange { sample_rate };
+        self.inner.render_channel.send(message).unwrap();
+
+        if self
+            .inner
+            .callbacks
+            .sample_rate_change
+            .lock()
+            .unwrap()
+            .is_some()
+        {
+            self.send_event(ContextEvent::SampleRateChange(sample_rate));
+        }
+    }
+
+    /// Flag to enable the output limiter, shared with the destination renderer
+    pub(crate) fn output_limiter(&self) -> &Arc<AtomicBool> {
+        &self.inner.output_limiter
+    }
+
+    /// Bit mask of muted output channels, shared with the destination renderer
+    pub(crate) fn muted_channels(&self) -> &Arc<AtomicU32> {
+        &self.inner.muted_channels
+    }
+
+    /// Total processing latency from the given node to the destination, in seconds
+    ///
+    /// This is an extension to the Web Audio API specification, to compensate buffering nodes
+    /// (delay lines, partitioned convolution, oversampling filters) e.g. for lip-sync. The latencies
+    /// reported by the nodes along the path are summed up. When multiple paths lead to the
+    /// destination, the largest latency is returned.
+    ///
+    /// The value is updated by the render thread after the render quantum in which the graph or
+    /// the latency of a node changed. It is zero before the first quantum is rendered, and when
+    /// the node is not connected to the destination.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while reading the latencies
+    #[must_use]
+    pub fn graph_latency(&self, from: &dyn node::AudioNode) -> f64 {
+        let latency = self
+            .inner
+            .latencies
+            .lock()
+            .unwrap()
+            .latest()
+            .iter()
+            .find(|(id, _)| *id == from.id().0)
+            .map_or(0., |&(_, latency)| latency);
+
+        latency / f64::from(self.sample_rate().0)
+    }
+
+    /// The nodes of the render graph, in the order they are processed
+    ///
+    /// Available with the `debug` feature, to verify the processing order when diagnosing
+    /// feedback or ordering issues. The list reflects the ordering used by the render thread for
+    /// the most recent render quantum, it is empty before the first quantum is rendered. Nodes
+    /// inside cycles are not processed and thus not listed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the render thread panicked while publishing the order
+    #[cfg(any(test, feature = "debug"))]
+    #[must_use]
+    pub fn render_order(&self) -> Vec<AudioNodeId> {
+        self.inner
+            .render_order
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&id| AudioNodeId(id))
+            .collect()
+    }
+
+    /// Runaway level detection, shared with the destination renderer
+    pub(crate) fn runaway_detection(&self) -> &Arc<RunawayDetection> {
+        &self.inner.runaway_detection
+    }
+
     /// Construct a new pair of [`node::AudioNode`] and [`AudioProcessor`]
     ///
     /// The `AudioNode` lives in the user-facing control thread. The Processor is sent to the render thread.
@@ -590,6 +1542,7 @@ impl BaseAudioContext {
     ///
     /// Will panic if:
     ///
+    /// * the context is closed
     /// * Message send to the render thread is not received in less than 10 ms
     pub fn register<
         T: node::AudioNode,
@@ -598,6 +1551,12 @@ impl BaseAudioContext {
         &self,
         f: F,
     ) -> T {
+        assert_ne!(
+            self.state(),
+            AudioContextState::Closed,
+            "InvalidStateError: cannot create a node on a closed context"
+        );
+
         // create unique identifier for this node
         let id = self.inner.node_id_inc.fetch_add(1, Ordering::SeqCst);
         let node_id = AudioNodeId(id);
@@ -609,6 +1568,13 @@ impl BaseAudioContext {
         // create the node and its renderer
         let (node, render) = (f)(registration);
 
+        // taking the lock forgets the connections of freed nodes, so they do not pile up
+        let mut connections = self.connections();
+        if render.can_break_cycle() {
+            connections.cycle_breakers.insert(id);
+        }
+        drop(connections);
+
         // pass the renderer to the audio graph
         let message = ControlMessage::RegisterNode {
             id,
@@ -622,8 +1588,33 @@ impl BaseAudioContext {
         node
     }
 
+    /// Connections of the audio graph, without the ones of the nodes freed in the meantime
+    fn connections(&self) -> MutexGuard<'_, Connections> {
+        let mut connections = self.inner.connections.lock().unwrap();
+        connections.remove_freed();
+        connections
+    }
+
     /// connects the output of the `from` audio node to the input of the `to` audio node
+    ///
+    /// # Panics
+    ///
+    /// Panics with a `NotSupportedError` if the connection closes a cycle without a `DelayNode`
     pub(crate) fn connect(&self, from: &AudioNodeId, to: &AudioNodeId, output: u32, input: u32) {
+        // release the lock before panicking, the connections are left untouched
+        let closes_cycle = {
+            let mut connections = self.connections();
+            let closes_cycle = connections.closes_cycle_without_delay(from.0, to.0);
+            if !closes_cycle {
+                connections.edges.insert((from.0, output, to.0, input));
+            }
+            closes_cycle
+        };
+        assert!(
+            !closes_cycle,
+            "NotSupportedError: cycles in the audio graph should contain a DelayNode"
+        );
+
         let message = ControlMessage::ConnectNode {
             from: from.0,
             to: to.0,
@@ -633,17 +1624,74 @@ impl BaseAudioContext {
         self.inner.render_channel.send(message).unwrap();
     }
 
+    /// connects the `from` audio param to the `to` audio node it controls
+    ///
+    /// The param is rendered before the node, but it does not occupy any of its audio inputs
+    pub(crate) fn connect_param(&self, from: &AudioNodeId, to: &AudioNodeId) {
+        self.connections().param_edges.insert((from.0, to.0));
+
+        let message = ControlMessage::ConnectParam {
+            from: from.0,
+            to: to.0,
+        };
+        self.inner.render_channel.send(message).unwrap();
+    }
+
     /// connects the `from` audio node to the `to` audio node
     pub(crate) fn disconnect(&self, from: &AudioNodeId, to: &AudioNodeId) {
+        self.connections()
+            .edges
+            .retain(|&(source, _, dest, _)| (source, dest) != (from.0, to.0));
+
         let message = ControlMessage::DisconnectNode {
             from: from.0,
             to: to.0,
+            output: None,
+            input: None,
+        };
+        self.inner.render_channel.send(message).unwrap();
+    }
+
+    /// disconnects the `output` of the `from` audio node from the `input` of the `to` audio node
+    pub(crate) fn disconnect_at(
+        &self,
+        from: &AudioNodeId,
+        to: &AudioNodeId,
+        output: u32,
+        input: u32,
+    ) {
+        self.connections()
+            .edges
+            .remove(&(from.0, output, to.0, input));
+
+        let message = ControlMessage::DisconnectNode {
+            from: from.0,
+            to: to.0,
+            output: Some(output),
+            input: Some(input),
+        };
+        self.inner.render_channel.send(message).unwrap();
+    }
+
+    /// disconnects all the audio nodes connected to the `output` of the `from` audio node
+    pub(crate) fn disconnect_output(&self, from: &AudioNodeId, output: u32) {
+        self.connections()
+            .edges
+            .retain(|&(source, out, _, _)| (source, out) != (from.0, output));
+
+        let message = ControlMessage::DisconnectOutput {
+            from: from.0,
+            output,
         };
         self.inner.render_channel.send(message).unwrap();
     }
 
     /// disconnects all the audio nodes
     pub(crate) fn disconnect_all(&self, from: &AudioNodeId) {
+        self.connections()
+            .edges
+            .retain(|&(source, _, _, _)| source != from.0);
+
         let message = ControlMessage::DisconnectAll { from: from.0 };
         self.inner.render_channel.send(message).unwrap();
     }
@@ -666,18 +1714,11 @@ impl BaseAudioContext {
 
     /// Attach the 9 `AudioListener` coordinates to a `PannerNode`
     pub(crate) fn connect_listener_to_panner(&self, panner: &AudioNodeId) {
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 0, 1);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 1, 2);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 2, 3);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 3, 4);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 4, 5);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 5, 6);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 6, 7);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 7, 8);
-        self.connect(&AudioNodeId(LISTENER_NODE_ID), panner, 8, 9);
+        self.connect_param(&AudioNodeId(LISTENER_NODE_ID), panner);
     }
 }
 
+#[cfg(any(test, feature = "cpal"))]
 impl Default for AudioContext {
     fn default() -> Self {
         Self::new(None)
@@ -694,6 +1735,27 @@ impl OfflineAudioContext {
     /// * `sample_rate` - output sample rate
     #[must_use]
     pub fn new(channels: u32, length: usize, sample_rate: SampleRate) -> Self {
+        Self::new_with_options(OfflineAudioContextOptions {
+            number_of_channels: channels,
+            length,
+            sample_rate,
+            root_processor: None,
+        })
+    }
+
+    /// Creates an `OfflineAudioContext` instance from the given options
+    ///
+    /// Use this constructor to render through a custom root processor, see
+    /// [`OfflineAudioContextOptions::root_processor`].
+    #[must_use]
+    pub fn new_with_options(options: OfflineAudioContextOptions) -> Self {
+        let OfflineAudioContextOptions {
+            number_of_channels: channels,
+            length,
+            sample_rate,
+            root_processor,
+        } = options;
+
         // communication channel to the render thread
         let (sender, receiver) = crossbeam_channel::unbounded();
 
@@ -710,17 +1772,25 @@ impl OfflineAudioContext {
         );
 
         // first, setup the base audio context
-        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender);
+        let base =
+            BaseAudioContext::new(sample_rate, channels, frames_played, sender, root_processor);
 
         Self {
             base,
             length,
             renderer,
+            suspensions: Vec::new(),
+            onprogress: Mutex::new(None),
         }
     }
 
     /// `OfflineAudioContext` doesn't start rendering automatically
     /// You need to call this function to start the audio rendering
+    ///
+    /// Rendering is suspended at the times scheduled with [`suspend_at`](Self::suspend_at).
+    ///
+    /// The graph is rendered in single precision, only the automation of linear ramps is
+    /// evaluated in double precision so it does not drift over long renders.
     pub fn start_rendering(&mut self) -> AudioBuffer {
         // make buffer_size always a multiple of BUFFER_SIZE, so we can still render piecewise with
         // the desired number of frames.
@@ -728,11 +1798,154 @@ impl OfflineAudioContext {
         let buffer_size =
             (self.length + cast_buffer_size - 1) / cast_buffer_size * cast_buffer_size;
 
-        let mut buf = self.renderer.render_audiobuffer(buffer_size);
+        // render in segments, up to each suspension
+        // the current frame is a multiple of BUFFER_SIZE, far below 2^52
+        #[allow(clippy::cast_possible_truncation)]
+        let start = self.base.inner.frames_played.load(Ordering::SeqCst) as usize;
+        let mut buf = self.renderer.render_audiobuffer(0);
+        let mut rendered = 0;
+        for (frame, callback) in std::mem::take(&mut self.suspensions) {
+            let frame = frame.saturating_sub(start).max(rendered);
+            self.render_segment(&mut buf, rendered, frame);
+            rendered = frame;
+            (callback)(&self.base);
+        }
+        self.render_segment(&mut buf, rendered, buffer_size);
+
         let _split = buf.split_off(self.length);
         buf
     }
 
+    /// Renders the frames `from..to` of the rendering into `buf`, notifying the progress
+    /// callback every `PROGRESS_INTERVAL` frames
+    fn render_segment(&mut self, buf: &mut AudioBuffer, from: usize, to: usize) {
+        let Some(onprogress) = self.onprogress.get_mut().unwrap().as_mut() else {
+            // render in one go when nobody is listening
+            buf.extend(&self.renderer.render_audiobuffer(to - from));
+            return;
+        };
+
+        let mut rendered = from;
+        while rendered < to {
+            let frames = (to - rendered).min(PROGRESS_INTERVAL);
+            buf.extend(&self.renderer.render_audiobuffer(frames));
+            rendered += frames;
+
+            // lengths of rendered audio are far below 2^52
+            #[allow(clippy::cast_precision_loss)]
+            let progress = if self.length == 0 {
+                1.
+            } else {
+                rendered.min(self.length) as f64 / self.length as f64
+            };
+            (onprogress)(progress);
+        }
+    }
+
+    /// Register a callback to be notified of the progress of
+    /// [`start_rendering`](Self::start_rendering)
+    ///
+    /// The callback receives the fraction of the `length` rendered so far, in the range
+    /// `(0, 1]`, every 128 render quanta (16384 sample-frames) and at the end of the rendering.
+    /// It runs on the rendering thread, between render quanta. Without a callback, the
+    /// rendering is not interrupted.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the lock on the callback is poisoned
+    pub fn set_onprogress<F: FnMut(f64) + Send + 'static>(&self, callback: F) {
+        *self.onprogress.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Renders the audio graph on a new thread, without blocking the current thread
+    ///
+    /// The context is moved to the rendering thread, so several contexts can be rendered in
+    /// parallel. Joining the returned handle yields the same buffer as
+    /// [`start_rendering`](Self::start_rendering), with exactly `length` sample-frames.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the thread cannot be spawned
+    #[must_use]
+    pub fn start_rendering_in_background(mut self) -> std::thread::JoinHandle<AudioBuffer> {
+        std::thread::Builder::new()
+            .name("web-audio-offline".into())
+            .spawn(move || self.start_rendering())
+            .expect("failed to spawn the offline rendering thread")
+    }
+
+    /// Schedules a suspension of the rendering at the given time, to run `callback` on the
+    /// context before rendering resumes
+    ///
+    /// The callback can mutate the graph, e.g. create, connect or disconnect nodes, or change the
+    /// values of their params. The changes apply from the suspension time on. When nothing is
+    /// changed, the rendered buffer is identical to an uninterrupted render.
+    ///
+    /// The time is rounded up to the next render quantum boundary, i.e. a multiple of 128
+    /// sample-frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `when` - time of the suspension, in seconds
+    /// * `callback` - run when the rendering reaches the suspension time
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `when` is negative, or not after the current time once rounded
+    /// * `when` is not before the end of the rendering once rounded
+    /// * another suspension is scheduled at the same rounded time
+    pub fn suspend_at<F: FnOnce(&BaseAudioContext) + Send + 'static>(
+        &mut self,
+        when: f64,
+        callback: F,
+    ) {
+        assert!(
+            when >= 0.,
+            "InvalidStateError: suspend time should not be negative"
+        );
+
+        let quantum = f64::from(BUFFER_SIZE);
+        // truncation is the desired behavior, the time is rounded up first
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let frame = ((when * f64::from(self.sample_rate().0) / quantum).ceil() * quantum) as usize;
+        // the current frame is a multiple of BUFFER_SIZE, far below 2^52
+        #[allow(clippy::cast_possible_truncation)]
+        let current = self.base.inner.frames_played.load(Ordering::SeqCst) as usize;
+
+        assert!(
+            frame > current,
+            "InvalidStateError: suspend time should be after the current time"
+        );
+        assert!(
+            frame < self.length,
+            "InvalidStateError: suspend time should be before the end of the rendering"
+        );
+
+        let Err(index) = self.suspensions.binary_search_by_key(&frame, |&(f, _)| f) else {
+            panic!("InvalidStateError: a suspension is already scheduled at this time");
+        };
+        self.suspensions.insert(index, (frame, Box::new(callback)));
+    }
+
+    /// Reset the clock and the state of all nodes, so the next render starts afresh
+    ///
+    /// The current time goes back to zero, and the internal state of the nodes is cleared:
+    /// oscillator phases, filter memories, delay lines and convolution tails, and the playback
+    /// positions of buffer sources and time stretchers. Together with the unchanged start and stop
+    /// times of the sources, this allows rendering the same pattern repeatedly.
+    ///
+    /// Not reset are:
+    /// - `AudioParam` automation: events that were already processed are not replayed, the params
+    ///   keep their current value
+    /// - nodes that have been released after finishing playback
+    /// - media streams and media elements, which continue where they were
+    /// - the levels of meters and the data of analysers
+    pub fn reset(&mut self) {
+        self.renderer.reset();
+    }
+
     /// get the length of rendering audio buffer
     // false positive: OfflineAudioContext is not const
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
@@ -742,6 +1955,65 @@ impl OfflineAudioContext {
     }
 }
 
+#[cfg(any(test, feature = "manual"))]
+impl ManualAudioContext {
+    /// Creates a `ManualAudioContext` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - number of output channels to render
+    /// * `sample_rate` - output sample rate
+    #[must_use]
+    pub fn new(channels: u32, sample_rate: SampleRate) -> Self {
+        // communication channel to the renderer, drained on the same thread
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        // track number of frames - synced from renderer to control side
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let frames_played_clone = frames_played.clone();
+
+        let renderer = RenderThread::new(
+            sample_rate,
+            channels as usize,
+            receiver,
+            frames_played_clone,
+        );
+
+        let base = BaseAudioContext::new(sample_rate, channels, frames_played, sender, None);
+
+        // the events are dispatched on this thread, the event thread is never spawned
+        let (event_sender, events) = crossbeam_channel::unbounded();
+        base.redirect_events(event_sender);
+
+        Self {
+            base,
+            renderer,
+            events,
+            dispatcher: EventDispatcher::default(),
+        }
+    }
+
+    /// Apply all pending control messages to the audio graph right away
+    ///
+    /// This happens automatically when rendering, calling it is only needed to control exactly
+    /// when changes take effect.
+    pub fn apply_control_messages(&mut self) {
+        self.renderer.handle_control_messages();
+    }
+
+    /// Render the audio graph into the interleaved `buffer`
+    ///
+    /// The buffer length should be a multiple of the number of channels. It does not need to be
+    /// a multiple of the render quantum size, leftover frames are kept for the next call.
+    ///
+    /// The user callbacks of the nodes that ended meanwhile run before this returns.
+    pub fn render(&mut self, buffer: &mut [f32]) {
+        self.renderer.render(buffer);
+        self.dispatcher
+            .dispatch_pending(&self.events, &self.base.inner.callbacks);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -757,4 +2029,265 @@ mod tests {
         // Send Sync and 'static
         require_send_sync_static(registration);
     }
+
+    #[test]
+    fn test_audio_context_state() {
+        let context = AudioContext::new(None);
+        assert_eq!(context.state(), AudioContextState::Running);
+
+        context.suspend().unwrap();
+        assert_eq!(context.state(), AudioContextState::Suspended);
+        context.resume().unwrap();
+        assert_eq!(context.state(), AudioContextState::Running);
+
+        context.close();
+        assert_eq!(context.state(), AudioContextState::Closed);
+        assert!(context.suspend().is_err());
+        assert!(context.resume().is_err());
+        // closing again is a no-op
+        context.close();
+        assert_eq!(context.state(), AudioContextState::Closed);
+
+        let context = AudioContext::new_suspended(None);
+        assert_eq!(context.state(), AudioContextState::Suspended);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_create_node_on_closed_context() {
+        let context = AudioContext::new(None);
+        context.close();
+        let _gain = context.create_gain();
+    }
+
+    #[test]
+    fn test_render_order() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+        assert!(context.base().render_order().is_empty());
+
+        let src = context.create_constant_source();
+        let gain = context.create_gain();
+        let delay = context.create_delay(1.);
+        src.connect(&gain);
+        gain.connect(&delay);
+        delay.connect(&context.destination());
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+
+        let order = context.base().render_order();
+        let position = |id: &AudioNodeId| order.iter().position(|i| i == id).unwrap();
+
+        // sources first, destination last
+        assert!(position(src.id()) < position(gain.id()));
+        assert!(position(gain.id()) < position(delay.id()));
+        assert_eq!(position(context.destination().id()), order.len() - 1);
+
+        // the params of a node are processed before the node
+        assert!(position(gain.gain().id()) < position(gain.id()));
+    }
+
+    #[test]
+    fn test_manual_context_applies_messages_synchronously() {
+        let mut context = ManualAudioContext::new(2, SampleRate(44_100));
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+
+        let mut buffer = vec![0.; 2 * 64];
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| (v - 1.).abs() < 1e-6));
+        assert_eq!(context.current_time(), f64::from(BUFFER_SIZE) / 44_100.);
+
+        // leftover frames of the previous quantum are emitted first
+        src.offset().set_value(0.5);
+        context.apply_control_messages();
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| (v - 1.).abs() < 1e-6));
+
+        // a new quantum picks up the applied change
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| (v - 0.5).abs() < 1e-6));
+
+        src.disconnect_all();
+        context.render(&mut buffer);
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_create_buffer() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+
+        let buffer = context.create_buffer(2, 100, SampleRate(22_050)).unwrap();
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_eq!(buffer.sample_len(), 100);
+        assert_eq!(buffer.sample_rate(), SampleRate(22_050));
+        assert!(buffer.channel_data(1).as_slice().iter().all(|&v| v == 0.));
+
+        assert!(context.create_buffer(0, 100, SampleRate(44_100)).is_err());
+        assert!(context
+            .create_buffer(MAX_CHANNELS + 1, 100, SampleRate(44_100))
+            .is_err());
+        assert!(context.create_buffer(1, 0, SampleRate(44_100)).is_err());
+        assert!(context.create_buffer(1, 100, SampleRate(0)).is_err());
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        let src = context.create_constant_source();
+        let gain1 = context.create_gain();
+        gain1.gain().set_value(0.5);
+        let gain2 = context.create_gain();
+        gain2.gain().set_value(0.5);
+
+        // a stream destination has no outputs, a source no inputs
+        let stream = context.create_media_stream_destination();
+        assert!(context.chain(&[&src, &stream, &gain1]).is_err());
+        assert!(context.chain(&[&gain1, &src]).is_err());
+
+        context
+            .chain(&[&src, &gain1, &gain2, &context.destination()])
+            .unwrap();
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 0.25));
+    }
+
+    #[test]
+    fn test_connections_of_freed_nodes_are_forgotten() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        let delay = context.create_delay(1.);
+        delay.connect(&context.destination());
+        {
+            let gain = context.create_gain();
+            gain.connect(&delay);
+        }
+        assert_eq!(context.base().connections().edges.len(), 2);
+
+        // the render thread frees the dropped gain and reports it back
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        context.render(&mut buffer);
+
+        let connections = context.base().connections();
+        assert_eq!(connections.edges.len(), 1);
+        assert!(connections
+            .edges
+            .iter()
+            .all(|&(source, _, _, _)| source == delay.id().0));
+    }
+
+    #[test]
+    fn test_connect_is_chainable() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        let src = context.create_constant_source();
+        let gain1 = context.create_gain();
+        gain1.gain().set_value(0.5);
+        let gain2 = context.create_gain();
+        gain2.gain().set_value(0.5);
+
+        src.connect(&gain1)
+            .connect(&gain2)
+            .connect(&context.destination());
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 0.25));
+    }
+
+    #[test]
+    fn test_disconnect_single_connection() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        // fan out to three gains, each summed into the destination
+        let src = context.create_constant_source();
+        let gains: Vec<_> = [1., 2., 4.]
+            .iter()
+            .map(|&value| {
+                let gain = context.create_gain();
+                gain.gain().set_value(value);
+                src.connect(&gain);
+                gain.connect(&context.destination());
+                gain
+            })
+            .collect();
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 7.));
+
+        // cut the second branch only
+        assert!(src.disconnect_at(&gains[1], 0, 0).is_ok());
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 5.));
+
+        // invalid ports
+        assert!(src.disconnect_at(&gains[0], 1, 0).is_err());
+        assert!(src.disconnect_at(&gains[0], 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_disconnect_output() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        // up-mix to stereo, and sum the split channels into the destination
+        let src = context.create_constant_source();
+        let stereo = context.create_gain();
+        stereo.set_channel_count_mode(ChannelCountMode::Explicit);
+        stereo.set_channel_count(2);
+        let splitter = context.create_channel_splitter(2);
+        src.connect(&stereo);
+        stereo.connect(&splitter);
+        splitter.connect_at(&context.destination(), 0, 0).unwrap();
+        splitter.connect_at(&context.destination(), 1, 0).unwrap();
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 2.));
+
+        assert!(splitter.disconnect_output(1).is_ok());
+        context.render(&mut buffer);
+        assert!(buffer.iter().all(|&v| v == 1.));
+
+        assert!(splitter.disconnect_output(2).is_err());
+    }
+
+    #[test]
+    fn test_sample_rate_change() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+        let (sender, notified) = crossbeam_channel::unbounded();
+        context
+            .base()
+            .set_sample_rate_change_callback(Box::new(move |sample_rate| {
+                sender.send(sample_rate).unwrap();
+            }));
+
+        let mut buffer = [0.; BUFFER_SIZE as usize];
+        context.render(&mut buffer);
+        let time = context.current_time();
+
+        // simulate a sample rate change reported by the output device
+        context.base().change_sample_rate(SampleRate(48_000));
+        assert_eq!(context.sample_rate(), SampleRate(48_000));
+
+        // the render thread picks up the new rate, time does not jump
+        context.render(&mut buffer);
+        let expected = time + f64::from(BUFFER_SIZE) / 48_000.;
+        assert!((context.current_time() - expected).abs() < 1. / 48_000.);
+
+        // the callback of the manual context ran before rendering returned
+        assert_eq!(notified.try_recv(), Ok(SampleRate(48_000)));
+
+        // no notification when the rate is unchanged
+        context.base().change_sample_rate(SampleRate(48_000));
+        context.render(&mut buffer);
+        assert!(notified.try_recv().is_err());
+    }
 }
@@ -3,27 +3,65 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crate::AtomicF64;
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{AtomicF64, SampleRate, BUFFER_SIZE};
 
 /// Helper struct to start and stop audio streams
 #[derive(Clone, Debug)]
 pub struct Scheduler {
     start: Arc<AtomicF64>,
     stop: Arc<AtomicF64>,
+    /// raised once playback has ended
+    ended: Arc<AtomicBool>,
+    /// single notification of the end of playback, sent from the render thread
+    ended_sender: Sender<()>,
+    ended_receiver: Receiver<()>,
 }
 
 impl Scheduler {
     /// Create a new Scheduler. Initial playback state will be: inactive.
     pub fn new() -> Self {
+        let (ended_sender, ended_receiver) = crossbeam_channel::bounded(1);
+
         Self {
             start: Arc::new(AtomicF64::new(f64::MAX)),
             stop: Arc::new(AtomicF64::new(f64::MAX)),
+            ended: Arc::new(AtomicBool::new(false)),
+            ended_sender,
+            ended_receiver,
         }
     }
 
     /// Check if the stream should be active at this timestamp
+    ///
+    /// Past the stop time, playback is marked as ended.
     pub fn is_active(&self, ts: f64) -> bool {
-        ts >= self.start.load() && ts < self.stop.load()
+        let stop = self.stop.load();
+        if ts >= stop {
+            self.mark_ended();
+        }
+
+        ts >= self.start.load() && ts < stop
+    }
+
+    /// Mark playback as ended, e.g. when the end of the buffer is reached
+    ///
+    /// Only the first call notifies the control thread.
+    pub fn mark_ended(&self) {
+        if !self.ended.swap(true, Ordering::SeqCst) {
+            let _ = self.ended_sender.try_send(());
+        }
+    }
+
+    /// Indicates if playback has ended
+    pub fn ended(&self) -> bool {
+        self.ended.load(Ordering::SeqCst)
+    }
+
+    /// Receiver of the notification of the end of playback
+    pub(crate) fn ended_receiver(&self) -> &Receiver<()> {
+        &self.ended_receiver
     }
 
     /// Schedule playback start at this timestamp
@@ -31,10 +69,34 @@ impl Scheduler {
         self.start.store(start)
     }
 
+    /// The scheduled start time, `None` if playback was not scheduled yet
+    pub fn start_time(&self) -> Option<f64> {
+        let start = self.start.load();
+        if start == f64::MAX {
+            None
+        } else {
+            Some(start)
+        }
+    }
+
     /// Stop playback at this timestamp
     pub fn stop_at(&self, stop: f64) {
         self.stop.store(stop)
     }
+
+    /// Index of the first sample-frame at or after the stop time, in the render quantum starting
+    /// at timestamp `ts`
+    ///
+    /// Returns `None` if playback does not stop within this render quantum. Source renderers
+    /// should output silence from this index onward.
+    pub fn stop_index(&self, ts: f64, sample_rate: SampleRate) -> Option<usize> {
+        let offset = ((self.stop.load() - ts) * f64::from(sample_rate.0)).ceil();
+        if offset > 0. && offset < f64::from(BUFFER_SIZE) {
+            Some(offset as usize)
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for Scheduler {
@@ -100,6 +162,13 @@ impl Controller {
         self.loop_end.store(loop_end);
     }
 
+    /// Request playback to continue from the given timestamp
+    ///
+    /// When looping and playing forward, a timestamp past the loop end wraps into the loop
+    /// region, as if playback had reached it by looping: seeking to `loop_end + t` plays from
+    /// `loop_start + t % (loop_end - loop_start)`. Timestamps before the loop end are played as
+    /// is, so a seek before the loop start enters the loop when playback reaches it. Likewise,
+    /// playing in reverse from past the loop end enters the loop at its end.
     pub fn seek(&self, timestamp: f64) {
         self.seek.store(timestamp);
     }
@@ -112,6 +181,31 @@ impl Controller {
             Some(prev)
         }
     }
+
+    /// Wraps a seek timestamp past the loop end into the loop region, when looping forward, see
+    /// [`seek`](Self::seek)
+    ///
+    /// The loop region is clamped to the duration of the media. An empty region spans the whole
+    /// media.
+    pub(crate) fn wrap_into_loop(&self, timestamp: f64, duration: f64) -> f64 {
+        if !self.loop_() {
+            return timestamp;
+        }
+
+        let start = self.loop_start().max(0.).min(duration);
+        let end = self.loop_end().max(0.).min(duration);
+        let (start, end) = if start < end {
+            (start, end)
+        } else {
+            (0., duration)
+        };
+
+        if timestamp >= end && end > start && end.is_finite() {
+            start + (timestamp - end) % (end - start)
+        } else {
+            timestamp
+        }
+    }
 }
 
 impl Default for Controller {
@@ -137,4 +231,64 @@ mod tests {
         assert_eq!(controller.should_seek(), Some(1.));
         assert!(controller.should_seek().is_none());
     }
+
+    #[test]
+    fn test_seek_within_loop() {
+        let controller = Controller::new();
+        controller.set_loop_start(1.);
+        controller.set_loop_end(3.);
+
+        // no wrapping when not looping
+        assert_eq!(controller.wrap_into_loop(4.5, 10.), 4.5);
+
+        controller.set_loop(true);
+        for &(seek, expected) in &[(0.5, 0.5), (2., 2.), (3., 1.), (4.5, 2.5), (7.5, 1.5)] {
+            assert_eq!(controller.wrap_into_loop(seek, 10.), expected);
+        }
+
+        // the loop region is clamped to the duration
+        assert_eq!(controller.wrap_into_loop(2.5, 2.), 1.5);
+
+        // an empty region spans the whole media
+        controller.set_loop_end(0.);
+        assert_eq!(controller.wrap_into_loop(12., 10.), 2.);
+
+        // streams of unknown duration only wrap at an explicit loop end
+        controller.set_loop_end(f64::MAX);
+        assert_eq!(controller.wrap_into_loop(12., f64::INFINITY), 12.);
+    }
+
+    #[test]
+    fn test_stop_index() {
+        let scheduler = Scheduler::new();
+        let sample_rate = SampleRate(100);
+        assert_eq!(scheduler.stop_index(0., sample_rate), None);
+
+        scheduler.start_at(0.);
+        scheduler.stop_at(1.305);
+        // stop in a later quantum
+        assert_eq!(scheduler.stop_index(0., sample_rate), None);
+        // stop between the sample-frames at 1.30 and 1.31
+        assert_eq!(scheduler.stop_index(1.28, sample_rate), Some(3));
+        // already stopped
+        assert_eq!(scheduler.stop_index(1.31, sample_rate), None);
+    }
+
+    #[test]
+    fn test_ended() {
+        let scheduler = Scheduler::new();
+        scheduler.start_at(0.);
+        scheduler.stop_at(1.);
+
+        assert!(scheduler.is_active(0.5));
+        assert!(!scheduler.ended());
+        assert!(scheduler.ended_receiver().try_recv().is_err());
+
+        // notified once
+        assert!(!scheduler.is_active(1.));
+        assert!(!scheduler.is_active(2.));
+        assert!(scheduler.ended());
+        assert!(scheduler.ended_receiver().try_recv().is_ok());
+        assert!(scheduler.ended_receiver().try_recv().is_err());
+    }
 }
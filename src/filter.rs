@@ -0,0 +1,526 @@
+//! Anti-aliasing FIR filters, shared by the nodes processing audio at a different sample rate
+//!
+//! The filters are designed with the windowed-sinc method (Blackman window, about 74 dB of
+//! stopband attenuation). The quality of the up and down samplers and of the rate converter is
+//! configured with the number of taps per phase: more taps give a steeper transition band at the
+//! cost of CPU and latency.
+
+use std::f64::consts::PI;
+
+/// Quality of the anti-aliasing filters used when the sample rate is changed
+///
+/// A higher quality keeps more of the high frequencies below the nyquist frequency, at the cost
+/// of CPU and latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterQuality {
+    /// Short filters, with a flat passband up to about 45% of the nyquist frequency
+    Low,
+    /// Balance between quality and cost, with a flat passband up to about 75% of the nyquist
+    /// frequency
+    #[default]
+    Medium,
+    /// Long filters, with a flat passband up to about 85% of the nyquist frequency
+    High,
+}
+
+impl FilterQuality {
+    /// Number of taps per polyphase sub-filter
+    pub(crate) fn taps_per_phase(self) -> usize {
+        match self {
+            Self::Low => 8,
+            Self::Medium => 16,
+            Self::High => 32,
+        }
+    }
+}
+
+/// Design a low-pass FIR filter with the windowed-sinc method
+///
+/// The `cutoff` frequency is expressed as a fraction of the sample rate, in the range
+/// `(0, 0.5)`. It lies in the middle of the transition band, which is about `5.5 / len` wide. The
+/// taps are normalized for a unity gain at DC.
+///
+/// # Panics
+///
+/// Will panic if `len` is zero or `cutoff` is outside the range `(0, 0.5)`
+pub(crate) fn windowed_sinc(cutoff: f64, len: usize) -> Vec<f32> {
+    assert!(len > 0, "the filter should have at least one tap");
+    assert!(
+        cutoff > 0. && cutoff < 0.5,
+        "the cutoff frequency should be in the range (0, 0.5)"
+    );
+
+    let center = (len - 1) as f64 / 2.;
+    let taps: Vec<f64> = (0..len)
+        .map(|i| {
+            let x = i as f64 - center;
+            let sinc = if x == 0. {
+                2. * cutoff
+            } else {
+                (2. * PI * cutoff * x).sin() / (PI * x)
+            };
+
+            let phase = if len == 1 {
+                0.
+            } else {
+                2. * PI * i as f64 / (len - 1) as f64
+            };
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2. * phase).cos();
+
+            sinc * window
+        })
+        .collect();
+
+    let sum: f64 = taps.iter().sum();
+    taps.iter().map(|&t| (t / sum) as f32).collect()
+}
+
+/// Delay line of FIR input samples, stored twice so the latest `len` samples are contiguous
+struct History {
+    samples: Vec<f32>,
+    pos: usize,
+}
+
+impl History {
+    fn new(len: usize) -> Self {
+        Self {
+            samples: vec![0.; 2 * len],
+            pos: 0,
+        }
+    }
+
+    /// Push a sample, and return the latest samples, newest first
+    #[inline]
+    fn push(&mut self, sample: f32) -> &[f32] {
+        let len = self.samples.len() / 2;
+        self.pos = (self.pos + len - 1) % len;
+        self.samples[self.pos] = sample;
+        self.samples[self.pos + len] = sample;
+
+        &self.samples[self.pos..self.pos + len]
+    }
+
+    fn clear(&mut self) {
+        self.samples.iter_mut().for_each(|s| *s = 0.);
+        self.pos = 0;
+    }
+}
+
+#[inline]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Polyphase FIR interpolator, raising the sample rate by an integer factor
+pub(crate) struct Upsampler {
+    /// the prototype low-pass filter, split in one sub-filter per output phase
+    phases: Vec<Vec<f32>>,
+    history: History,
+}
+
+impl Upsampler {
+    /// Create an upsampler by `factor`, with `taps_per_phase` taps per polyphase sub-filter
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `factor` or `taps_per_phase` is zero
+    pub fn new(factor: usize, taps_per_phase: usize) -> Self {
+        assert!(factor > 0, "the upsampling factor should be positive");
+
+        // the signal is band limited to the original nyquist frequency
+        let prototype = if factor == 1 {
+            vec![1.]
+        } else {
+            windowed_sinc(0.5 / factor as f64, factor * taps_per_phase)
+        };
+
+        // compensate the energy lost by zero stuffing
+        let phases = (0..factor)
+            .map(|phase| {
+                prototype
+                    .iter()
+                    .skip(phase)
+                    .step_by(factor)
+                    .map(|&t| t * factor as f32)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            phases,
+            history: History::new(taps_per_phase.max(1)),
+        }
+    }
+
+    /// The upsampling factor
+    pub fn factor(&self) -> usize {
+        self.phases.len()
+    }
+
+    /// Group delay of the filter, in samples at the input rate
+    pub fn latency(&self) -> f64 {
+        let factor = self.factor();
+        if factor == 1 {
+            return 0.;
+        }
+        let len = factor * self.phases[0].len();
+        (len - 1) as f64 / 2. / factor as f64
+    }
+
+    /// Upsample `input` into `output`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `output` is not `factor` times as long as `input`
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        let factor = self.factor();
+        assert_eq!(input.len() * factor, output.len());
+
+        for (&sample, frame) in input.iter().zip(output.chunks_exact_mut(factor)) {
+            let history = self.history.push(sample);
+            for (o, phase) in frame.iter_mut().zip(&self.phases) {
+                *o = dot(history, phase);
+            }
+        }
+    }
+
+    /// Clear the internal state, as if only silence was processed
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+/// FIR decimator, lowering the sample rate by an integer factor
+///
+/// Only the retained output samples are computed.
+pub(crate) struct Downsampler {
+    factor: usize,
+    /// anti-aliasing low-pass filter, running at the input rate
+    taps: Vec<f32>,
+    history: History,
+}
+
+impl Downsampler {
+    /// Create a downsampler by `factor`, with `factor * taps_per_phase` taps in total
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `factor` or `taps_per_phase` is zero
+    pub fn new(factor: usize, taps_per_phase: usize) -> Self {
+        assert!(factor > 0, "the downsampling factor should be positive");
+
+        // remove the content above the nyquist frequency of the output rate
+        let taps = if factor == 1 {
+            vec![1.]
+        } else {
+            windowed_sinc(0.5 / factor as f64, factor * taps_per_phase)
+        };
+
+        Self {
+            factor,
+            history: History::new(taps.len()),
+            taps,
+        }
+    }
+
+    /// Group delay of the filter, in samples at the output rate
+    pub fn latency(&self) -> f64 {
+        (self.taps.len() - 1) as f64 / 2. / self.factor as f64
+    }
+
+    /// Downsample `input` into `output`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `input` is not `factor` times as long as `output`
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        assert_eq!(input.len(), output.len() * self.factor);
+
+        for (frame, o) in input.chunks_exact(self.factor).zip(output.iter_mut()) {
+            // only the first sample of each frame yields an output
+            *o = dot(self.history.push(frame[0]), &self.taps);
+            frame[1..].iter().for_each(|&sample| {
+                self.history.push(sample);
+            });
+        }
+    }
+
+    /// Clear the internal state, as if only silence was processed
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+}
+
+/// Greatest common divisor
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Polyphase FIR sample rate converter, for the rational ratio between two sample rates
+///
+/// The input is conceptually upsampled by `up`, low-pass filtered and decimated by `down`. Only
+/// the retained output samples are computed, each by a single sub-filter of the prototype.
+pub(crate) struct RateConverter {
+    /// upsampling factor
+    up: usize,
+    /// downsampling factor
+    down: usize,
+    /// the prototype low-pass filter, split in one sub-filter per phase of the upsampled signal
+    phases: Vec<Vec<f32>>,
+    history: History,
+    /// phase of the next output sample, relative to the latest input sample
+    phase: usize,
+    /// group delay, in samples at the output rate
+    latency: usize,
+}
+
+impl RateConverter {
+    /// Create a converter from the `from` to the `to` sample rate, with about `taps_per_phase`
+    /// taps per polyphase sub-filter
+    ///
+    /// The length of the prototype filter is chosen so its group delay is a whole number of
+    /// output samples.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a sample rate or `taps_per_phase` is zero
+    pub fn new(from: u32, to: u32, taps_per_phase: usize) -> Self {
+        assert!(from > 0 && to > 0, "the sample rates should be positive");
+        assert!(
+            taps_per_phase > 0,
+            "the filter should have at least one tap"
+        );
+
+        let divisor = gcd(from as usize, to as usize);
+        let (up, down) = (to as usize / divisor, from as usize / divisor);
+
+        if up == down {
+            return Self {
+                up,
+                down,
+                phases: vec![vec![1.]],
+                history: History::new(1),
+                phase: 0,
+                latency: 0,
+            };
+        }
+
+        // band limit to the lowest nyquist frequency, the delay of (len - 1) / 2 upsampled
+        // samples is `latency` output samples
+        let rate = up.max(down);
+        let latency = (rate * taps_per_phase).div_ceil(2 * down);
+        let len = 2 * down * latency + 1;
+        let prototype = windowed_sinc(0.5 / rate as f64, len);
+
+        // compensate the energy lost by zero stuffing
+        let phases: Vec<Vec<f32>> = (0..up)
+            .map(|phase| {
+                prototype
+                    .iter()
+                    .skip(phase)
+                    .step_by(up)
+                    .map(|&t| t * up as f32)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            up,
+            down,
+            history: History::new(phases[0].len()),
+            phases,
+            phase: 0,
+            latency,
+        }
+    }
+
+    /// Group delay of the filter, in samples at the output rate
+    pub fn latency(&self) -> usize {
+        self.latency
+    }
+
+    /// Convert `input`, appending the resulting samples to `output`
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        output.reserve(input.len() * self.up / self.down + 1);
+
+        for &sample in input {
+            let history = self.history.push(sample);
+            while self.phase < self.up {
+                let taps = &self.phases[self.phase];
+                output.push(dot(&history[..taps.len()], taps));
+                self.phase += self.down;
+            }
+            self.phase -= self.up;
+        }
+    }
+
+    /// Append the remaining response of the filter to `output`, as if silence followed the input
+    ///
+    /// Exactly [`latency`](Self::latency) samples are appended, so the total output length does
+    /// not depend on the delay of the filter.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let end = output.len() + self.latency;
+        while output.len() < end {
+            self.process(&[0.], output);
+        }
+        output.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+    use num_complex::Complex;
+
+    use super::*;
+
+    /// magnitude response of the FIR filter at the normalized `frequency`
+    fn magnitude(taps: &[f32], frequency: f64) -> f64 {
+        taps.iter()
+            .enumerate()
+            .map(|(n, &t)| Complex::from_polar(f64::from(t), -2. * PI * frequency * n as f64))
+            .sum::<Complex<f64>>()
+            .norm()
+    }
+
+    #[test]
+    fn test_windowed_sinc_design() {
+        let len = 64;
+        let taps = windowed_sinc(0.25, len);
+        assert_eq!(taps.len(), len);
+
+        // linear phase
+        for i in 0..len / 2 {
+            assert_float_eq!(taps[i], taps[len - 1 - i], abs <= 1e-7);
+        }
+
+        // flat passband, up to the start of the transition band
+        for i in 0..=40 {
+            let f = 0.2 * i as f64 / 40.;
+            assert_float_eq!(magnitude(&taps, f), 1., abs <= 1e-3);
+        }
+
+        // at least 70 dB of attenuation in the stopband
+        for i in 0..=40 {
+            let f = 0.3 + 0.2 * i as f64 / 40.;
+            assert!(20. * magnitude(&taps, f).log10() < -70.);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for factor in [2, 4] {
+            let mut upsampler = Upsampler::new(factor, FilterQuality::default().taps_per_phase());
+            let mut downsampler =
+                Downsampler::new(factor, FilterQuality::default().taps_per_phase());
+
+            // low frequency sine, processed in blocks
+            let frequency = 0.02;
+            let input: Vec<f32> = (0..1024)
+                .map(|n| (2. * PI * frequency * n as f64).sin() as f32)
+                .collect();
+            let mut output = vec![0.; input.len()];
+            let mut upsampled = vec![0.; 128 * factor];
+
+            for (i, o) in input.chunks(128).zip(output.chunks_mut(128)) {
+                upsampler.process(i, &mut upsampled);
+
+                // the upsampled signal is band limited and keeps its amplitude
+                let peak = upsampled.iter().fold(0f32, |m, v| m.max(v.abs()));
+                assert!(peak < 1.01);
+
+                downsampler.process(&upsampled, o);
+            }
+
+            // the output is the delayed input
+            let latency = upsampler.latency() + downsampler.latency();
+            let warmup = 2 * FilterQuality::default().taps_per_phase();
+            for (n, &o) in output.iter().enumerate().skip(warmup) {
+                let expected = (2. * PI * frequency * (n as f64 - latency)).sin();
+                assert_float_eq!(f64::from(o), expected, abs <= 1e-3);
+            }
+
+            // reset clears the history
+            upsampler.reset();
+            downsampler.reset();
+            let silence = [0.; 128];
+            upsampler.process(&silence, &mut upsampled);
+            downsampler.process(&upsampled, &mut output[..128]);
+            assert_float_eq!(output[..128], [0.; 128][..], abs_all <= 0.);
+        }
+    }
+
+    #[test]
+    fn test_filter_quality() {
+        // the passband (within 1%) gets closer to the nyquist frequency of 0.25 with a higher
+        // quality
+        let edge = |quality: FilterQuality| {
+            let downsampler = Downsampler::new(2, quality.taps_per_phase());
+            (1..250)
+                .map(|i| f64::from(i) / 1000.)
+                .find(|&f| (magnitude(&downsampler.taps, f) - 1.).abs() > 1e-2)
+                .unwrap()
+        };
+        let (low, medium, high) = (
+            edge(FilterQuality::Low),
+            edge(FilterQuality::Medium),
+            edge(FilterQuality::High),
+        );
+        assert!(low > 0.1);
+        assert!(low < medium && medium < high);
+
+        // at the cost of latency
+        let latency =
+            |quality: FilterQuality| Upsampler::new(4, quality.taps_per_phase()).latency();
+        assert!(latency(FilterQuality::Low) < latency(FilterQuality::Medium));
+        assert!(latency(FilterQuality::Medium) < latency(FilterQuality::High));
+    }
+
+    #[test]
+    fn test_rate_converter() {
+        for (from, to) in [
+            (44_100, 48_000),
+            (48_000, 44_100),
+            (32_000, 48_000),
+            (48_000, 16_000),
+        ] {
+            let mut converter =
+                RateConverter::new(from, to, FilterQuality::default().taps_per_phase());
+
+            // low frequency sine, processed in blocks
+            let frequency = 1000.;
+            let input: Vec<f32> = (0..4800)
+                .map(|n| (2. * PI * frequency * n as f64 / f64::from(from)).sin() as f32)
+                .collect();
+            let mut output = vec![];
+            input
+                .chunks(128)
+                .for_each(|chunk| converter.process(chunk, &mut output));
+
+            // one output sample per output period covered by the input
+            let expected_len = input.len() * to as usize / from as usize;
+            assert!(output.len().abs_diff(expected_len) <= 1);
+
+            // the output is the input at the new rate, delayed by the latency
+            let latency = converter.latency();
+            let warmup = 2 * latency + 1;
+            for (n, &o) in output.iter().enumerate().skip(warmup) {
+                let t = (n as f64 - latency as f64) / f64::from(to);
+                let expected = (2. * PI * frequency * t).sin();
+                assert_float_eq!(f64::from(o), expected, abs <= 2e-3);
+            }
+        }
+
+        // same rate, no filtering
+        let mut converter =
+            RateConverter::new(48_000, 48_000, FilterQuality::default().taps_per_phase());
+        let mut output = vec![];
+        converter.process(&[1., 2., 3.], &mut output);
+        assert_eq!(converter.latency(), 0);
+        assert_float_eq!(output[..], [1., 2., 3.][..], abs_all <= 0.);
+    }
+}
@@ -1,17 +1,32 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+#[cfg(feature = "cpal")]
 use cpal::Sample;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 
 use crate::alloc::{Alloc, AudioBuffer};
 use crate::buffer::{ChannelConfig, ChannelCountMode};
-use crate::message::ControlMessage;
+use crate::message::{ControlMessage, Latencies, LatencyPublisher};
 use crate::process::{AudioParamValues, AudioProcessor};
 use crate::{SampleRate, BUFFER_SIZE};
 
+/// Sample type of the rendered output, only `f32` without an audio backend
+#[cfg(all(any(test, feature = "manual"), not(feature = "cpal")))]
+pub(crate) trait Sample: Copy {
+    /// Convert from a rendered sample
+    fn from<S: Into<f32> + Copy>(sample: &S) -> Self;
+}
+
+#[cfg(all(any(test, feature = "manual"), not(feature = "cpal")))]
+impl Sample for f32 {
+    fn from<S: Into<f32> + Copy>(sample: &S) -> Self {
+        (*sample).into()
+    }
+}
+
 /// Operations running off the system-level audio callback
 pub(crate) struct RenderThread {
     graph: Graph,
@@ -46,7 +61,7 @@ impl RenderThread {
         }
     }
 
-    fn handle_control_messages(&mut self) {
+    pub fn handle_control_messages(&mut self) {
         for msg in self.receiver.try_iter() {
             use ControlMessage::*;
 
@@ -70,8 +85,20 @@ impl RenderThread {
                     self.graph
                         .add_edge((NodeIndex(from), output), (NodeIndex(to), input));
                 }
-                DisconnectNode { from, to } => {
-                    self.graph.remove_edge(NodeIndex(from), NodeIndex(to));
+                ConnectParam { from, to } => {
+                    self.graph.add_param_edge(NodeIndex(from), NodeIndex(to));
+                }
+                DisconnectNode {
+                    from,
+                    to,
+                    output,
+                    input,
+                } => {
+                    self.graph
+                        .remove_edge(NodeIndex(from), NodeIndex(to), output, input);
+                }
+                DisconnectOutput { from, output } => {
+                    self.graph.remove_edges_from_output(NodeIndex(from), output);
                 }
                 DisconnectAll { from } => {
                     self.graph.remove_edges_from(NodeIndex(from));
@@ -82,6 +109,26 @@ impl RenderThread {
                 AudioParamEvent { to, event } => {
                     to.send(event).expect("Audioparam disappeared unexpectedly")
                 }
+                #[cfg(any(test, feature = "cpal"))]
+                SampleRateChange { sample_rate } => {
+                    // continue rendering at the new rate, rescale the frame counter so the
+                    // context time progresses without jumps
+                    let frames = self.frames_played.load(Ordering::SeqCst) as f64;
+                    let rescaled = frames * sample_rate.0 as f64 / self.sample_rate.0 as f64;
+                    self.frames_played
+                        .store(rescaled.round() as u64, Ordering::SeqCst);
+                    self.sample_rate = sample_rate;
+                }
+                ShareLatencies { latencies } => {
+                    self.graph.share_latencies(latencies);
+                }
+                ShareFreedNodes { freed } => {
+                    self.graph.share_freed_nodes(freed);
+                }
+                #[cfg(any(test, feature = "debug"))]
+                ShareRenderOrder { render_order } => {
+                    self.graph.share_render_order(render_order);
+                }
             }
         }
     }
@@ -111,9 +158,14 @@ impl RenderThread {
         buf
     }
 
-    // This code is not dead: false positive from clippy
-    // due to the use of #[cfg(not(test))]
-    #[allow(dead_code)]
+    /// Reset the frame counter and the internal state of all nodes
+    pub fn reset(&mut self) {
+        self.frames_played.store(0, Ordering::SeqCst);
+        self.buffer_offset = None;
+        self.graph.reset();
+    }
+
+    #[cfg(any(test, feature = "cpal", feature = "manual"))]
     pub fn render<S: Sample>(&mut self, mut buffer: &mut [S]) {
         // There may be audio frames left over from the previous render call,
         // if the cpal buffer size did not align with our internal BUFFER_SIZE
@@ -132,9 +184,14 @@ impl RenderThread {
                 }
             }
 
+            // keep the frames that did not fit for the next call
+            let offset = offset + first.len() / self.channels;
+            if offset < BUFFER_SIZE as usize {
+                self.buffer_offset = Some((offset, prev_rendered));
+            }
+
             // exit early if we are done filling the buffer with the previously rendered data
             if next.is_empty() {
-                self.buffer_offset = Some((offset + first.len() / self.channels, prev_rendered));
                 return;
             }
 
@@ -179,7 +236,7 @@ impl RenderThread {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct NodeIndex(pub u64);
 
 /// Renderer Node in the Audio Graph
@@ -191,6 +248,8 @@ pub struct Node {
     /// Output buffers, consumed by subsequent Nodes in this graph
     outputs: Vec<AudioBuffer>,
     /// Channel configuration: determines up/down-mixing of inputs
+    ///
+    /// Shared with the control thread, it is re-read for every render quantum
     channel_config: ChannelConfig,
 
     // lifecycle management flags:
@@ -200,6 +259,15 @@ pub struct Node {
     has_inputs_connected: bool,
     /// Indicates if the output of this Node was consumed in the current render quantum
     has_outputs_connected: bool,
+    /// Latency from this Node to the destination, `None` if it does not reach the destination
+    latency: Option<f64>,
+    /// Latency of the processor itself, when the latencies were last published
+    own_latency: f64,
+    /// Indicates if the processor can break a cycle (`DelayNode`)
+    cycle_breaker: bool,
+    /// Indicates if the Node breaks a cycle: its inputs are mixed at the end of the render quantum
+    /// and processed in the next one
+    breaking_cycle: bool,
 }
 
 impl Node {
@@ -240,18 +308,45 @@ impl Node {
     pub fn get_buffer(&self) -> &AudioBuffer {
         self.outputs.get(0).unwrap()
     }
+
+    /// Get the current buffers of all outputs
+    pub fn get_outputs(&self) -> &[AudioBuffer] {
+        &self.outputs[..]
+    }
 }
 
+/// Connection from (node, output) to (node, input)
+type Edge = ((NodeIndex, u32), (NodeIndex, u32));
+
 pub(crate) struct Graph {
     // actual audio graph
     nodes: HashMap<NodeIndex, Node>,
-    edges: HashSet<((NodeIndex, u32), (NodeIndex, u32))>, // (node,output) to (node,input)
+    // (node,output) to (node,input), ordered so the inputs are mixed in a deterministic order
+    edges: BTreeSet<Edge>,
+    /// param connections (node to node): the source is rendered before the destination, but
+    /// its output is not mixed into the destination inputs
+    param_edges: BTreeSet<(NodeIndex, NodeIndex)>,
 
     // topological sorting
     marked: Vec<NodeIndex>,
     marked_temp: Vec<NodeIndex>,
     ordered: Vec<NodeIndex>,
     in_cycle: Vec<NodeIndex>,
+    /// nodes breaking a cycle, reading their audio input one render quantum late
+    breaking: Vec<NodeIndex>,
+    /// nodes found to break a cycle during the current pass of the sort
+    new_breakers: Vec<NodeIndex>,
+    /// latency from each node to the destination, for inspection from the control thread
+    latencies: Option<LatencyPublisher>,
+    /// table the latencies are computed in before they are published
+    latency_table: Latencies,
+    /// the graph or the latency of a node changed since the latencies were published
+    latencies_changed: bool,
+    /// ids of the freed nodes, for the control thread
+    freed: Option<Sender<u64>>,
+    /// copy of `ordered` for inspection from the control thread
+    #[cfg(any(test, feature = "debug"))]
+    render_order: Option<Arc<std::sync::Mutex<Vec<u64>>>>,
 
     // allocator for audio buffers
     alloc: Alloc,
@@ -261,11 +356,20 @@ impl Graph {
     pub fn new() -> Self {
         Graph {
             nodes: HashMap::new(),
-            edges: HashSet::new(),
+            edges: BTreeSet::new(),
+            param_edges: BTreeSet::new(),
             ordered: vec![],
             marked: vec![],
             marked_temp: vec![],
             in_cycle: vec![],
+            breaking: vec![],
+            new_breakers: vec![],
+            latencies: None,
+            latency_table: vec![],
+            latencies_changed: true,
+            freed: None,
+            #[cfg(any(test, feature = "debug"))]
+            render_order: None,
             alloc: Alloc::with_capacity(64),
         }
     }
@@ -281,6 +385,7 @@ impl Graph {
         // todo, allocate on control thread, make single alloc..?
         let inputs = vec![AudioBuffer::new(self.alloc.silence()); inputs];
         let outputs = vec![AudioBuffer::new(self.alloc.silence()); outputs];
+        let cycle_breaker = processor.can_break_cycle();
 
         self.nodes.insert(
             index,
@@ -292,17 +397,66 @@ impl Graph {
                 free_when_finished: false,
                 has_inputs_connected: true,
                 has_outputs_connected: true,
+                latency: None,
+                own_latency: 0.,
+                cycle_breaker,
+                breaking_cycle: false,
             },
         );
     }
 
+    /// Connect an output of the source node to an input of the destination node
+    ///
+    /// Connections referencing an unknown node (e.g. one that was already freed on the render
+    /// thread when the message arrived) or an invalid port are ignored, since rendering them
+    /// would panic.
     pub fn add_edge(&mut self, source: (NodeIndex, u32), dest: (NodeIndex, u32)) {
+        let valid_output = self
+            .nodes
+            .get(&source.0)
+            .is_some_and(|node| (source.1 as usize) < node.outputs.len());
+        let valid_input = self
+            .nodes
+            .get(&dest.0)
+            .is_some_and(|node| (dest.1 as usize) < node.inputs.len());
+        if !valid_output || !valid_input {
+            log::warn!(
+                "Ignoring connection from {:?} to {:?}: unknown node or port",
+                source,
+                dest
+            );
+            return;
+        }
+
         self.edges.insert((source, dest));
         self.ordered.clear(); // void current ordering
     }
 
-    pub fn remove_edge(&mut self, source: NodeIndex, dest: NodeIndex) {
-        self.edges.retain(|&(s, d)| s.0 != source || d.0 != dest);
+    pub fn add_param_edge(&mut self, source: NodeIndex, dest: NodeIndex) {
+        self.param_edges.insert((source, dest));
+        self.ordered.clear(); // void current ordering
+    }
+
+    /// Remove the edges from `source` to `dest`, only those from the given `output` and to the
+    /// given `input` when set
+    pub fn remove_edge(
+        &mut self,
+        source: NodeIndex,
+        dest: NodeIndex,
+        output: Option<u32>,
+        input: Option<u32>,
+    ) {
+        self.edges.retain(|&(s, d)| {
+            s.0 != source
+                || d.0 != dest
+                || output.is_some_and(|o| o != s.1)
+                || input.is_some_and(|i| i != d.1)
+        });
+        self.ordered.clear(); // void current ordering
+    }
+
+    pub fn remove_edges_from_output(&mut self, source: NodeIndex, output: u32) {
+        self.edges.retain(|&(s, _d)| s != (source, output));
         self.ordered.clear(); // void current ordering
     }
 
@@ -315,14 +469,41 @@ impl Graph {
         self.nodes.get_mut(&index).unwrap().free_when_finished = true;
     }
 
-    pub fn children(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
-        self.edges
+    /// Reset the internal state of all nodes
+    pub fn reset(&mut self) {
+        self.nodes
+            .values_mut()
+            .for_each(|node| node.processor.reset());
+    }
+
+    /// Nodes rendered before `node`, connected to its inputs or params
+    ///
+    /// The audio inputs of a node breaking a cycle are left out, since they are read one render
+    /// quantum late.
+    pub fn children(
+        &self,
+        node: NodeIndex,
+        breaking_cycle: bool,
+    ) -> impl Iterator<Item = NodeIndex> + '_ {
+        let audio = self
+            .edges
             .iter()
-            .filter(move |&(_s, d)| d.0 == node)
-            .map(|&(s, _d)| s.0)
+            .filter(move |&(_s, d)| !breaking_cycle && d.0 == node)
+            .map(|&(s, _d)| s.0);
+        let params = self
+            .param_edges
+            .iter()
+            .filter(move |&(_s, d)| *d == node)
+            .map(|&(s, _d)| s);
+
+        audio.chain(params)
     }
 
     /// Traverse node for topological sort
+    ///
+    /// A cycle containing a node that can break it, and is not breaking a cycle yet, adds this
+    /// node to `new_breakers`. Other cycles are added to `in_cycle`.
+    #[allow(clippy::too_many_arguments)]
     fn visit(
         &self,
         n: NodeIndex,
@@ -330,10 +511,19 @@ impl Graph {
         marked_temp: &mut Vec<NodeIndex>,
         ordered: &mut Vec<NodeIndex>,
         in_cycle: &mut Vec<NodeIndex>,
+        breaking: &[NodeIndex],
+        new_breakers: &mut Vec<NodeIndex>,
     ) {
         // detect cycles
         if let Some(pos) = marked_temp.iter().position(|&m| m == n) {
-            in_cycle.extend_from_slice(&marked_temp[pos..]);
+            let cycle = &marked_temp[pos..];
+            let breaker = cycle
+                .iter()
+                .find(|i| self.nodes[i].cycle_breaker && !breaking.contains(i));
+            match breaker {
+                Some(&breaker) => new_breakers.push(breaker),
+                None => in_cycle.extend_from_slice(cycle),
+            }
             return;
         }
         if marked.contains(&n) {
@@ -343,14 +533,26 @@ impl Graph {
         marked.push(n);
         marked_temp.push(n);
 
-        self.children(n)
-            .for_each(|c| self.visit(c, marked, marked_temp, ordered, in_cycle));
+        self.children(n, breaking.contains(&n)).for_each(|c| {
+            self.visit(
+                c,
+                marked,
+                marked_temp,
+                ordered,
+                in_cycle,
+                breaking,
+                new_breakers,
+            )
+        });
 
         marked_temp.retain(|marked| *marked != n);
         ordered.insert(0, n);
     }
 
-    /// Perform a topological sort of the graph. Mute nodes that are in a cycle
+    /// Perform a topological sort of the graph
+    ///
+    /// Cycles are broken at a `DelayNode`, which then reads its input one render quantum late.
+    /// Nodes in a cycle without `DelayNode` are muted.
     fn order_nodes(&mut self) {
         // For borrowck reasons, we need the `visit` call to be &self.
         // So move out the bookkeeping Vecs, and pass them around as &mut.
@@ -358,23 +560,56 @@ impl Graph {
         let mut marked = std::mem::take(&mut self.marked);
         let mut marked_temp = std::mem::take(&mut self.marked_temp);
         let mut in_cycle = std::mem::take(&mut self.in_cycle);
+        let mut breaking = std::mem::take(&mut self.breaking);
+        let mut new_breakers = std::mem::take(&mut self.new_breakers);
+        breaking.clear();
+
+        loop {
+            // clear previous administration
+            ordered.clear();
+            marked.clear();
+            marked_temp.clear();
+            in_cycle.clear();
+            new_breakers.clear();
+
+            // visit all registered nodes, depth first search
+            self.nodes.keys().for_each(|&i| {
+                self.visit(
+                    i,
+                    &mut marked,
+                    &mut marked_temp,
+                    &mut ordered,
+                    &mut in_cycle,
+                    &breaking,
+                    &mut new_breakers,
+                );
+            });
 
-        // clear previous administration
-        ordered.clear();
-        marked.clear();
-        marked_temp.clear();
-        in_cycle.clear();
+            // sort again without traversing the inputs of the new breakers
+            if new_breakers.is_empty() {
+                break;
+            }
+            new_breakers.sort();
+            new_breakers.dedup();
+            breaking.append(&mut new_breakers);
+        }
 
-        // visit all registered nodes, depth first search
-        self.nodes.keys().for_each(|&i| {
-            self.visit(
-                i,
-                &mut marked,
-                &mut marked_temp,
-                &mut ordered,
-                &mut in_cycle,
+        if !in_cycle.is_empty() {
+            log::error!(
+                "Cycles in the audio graph should contain a DelayNode, muting nodes {:?}",
+                in_cycle
             );
-        });
+        }
+
+        for (index, node) in self.nodes.iter_mut() {
+            let breaking_cycle = breaking.contains(index);
+            if node.breaking_cycle != breaking_cycle {
+                node.breaking_cycle = breaking_cycle;
+                node.processor.set_breaking_cycle(breaking_cycle);
+                // do not process the inputs of the previous render quantum twice
+                node.inputs.iter_mut().for_each(AudioBuffer::make_silent);
+            }
+        }
 
         // remove cycles from ordered nodes, leaving the ordering in place
         ordered.retain(|o| !in_cycle.contains(o));
@@ -394,9 +629,100 @@ impl Graph {
 
         // re-instate vecs
         self.ordered = ordered;
+        self.latencies_changed = true;
         self.marked = marked;
         self.marked_temp = marked_temp;
         self.in_cycle = in_cycle;
+        self.breaking = breaking;
+        self.new_breakers = new_breakers;
+
+        #[cfg(any(test, feature = "debug"))]
+        if let Some(render_order) = &self.render_order {
+            // debugging only, so locking on the render thread is acceptable
+            let mut render_order = render_order.lock().unwrap();
+            render_order.clear();
+            render_order.extend(self.ordered.iter().map(|index| index.0));
+        }
+    }
+
+    /// Report the ids of the freed nodes to the given channel
+    pub fn share_freed_nodes(&mut self, freed: Sender<u64>) {
+        self.freed = Some(freed);
+    }
+
+    /// Publish the latencies to the destination to the given channel, whenever they change
+    pub fn share_latencies(&mut self, latencies: LatencyPublisher) {
+        self.latencies = Some(latencies);
+        self.latencies_changed = true;
+    }
+
+    /// Compute the latency from each node to the destination and publish it, if the graph or the
+    /// latency of a node changed
+    ///
+    /// The latency of a node is its own latency plus the largest latency of the nodes it is
+    /// connected to, so it can be computed in reverse processing order. Nodes that do not reach
+    /// the destination are left out.
+    fn publish_latencies(&mut self) {
+        if !self.latencies_changed {
+            return;
+        }
+        let publisher = match &self.latencies {
+            Some(publisher) => publisher,
+            None => return,
+        };
+
+        // the control thread holds on to the previous table, publish again next quantum
+        let recycled = match publisher.recycled.try_recv() {
+            Ok(table) => table,
+            Err(_) => return,
+        };
+        let mut latencies = std::mem::replace(&mut self.latency_table, recycled);
+        latencies.clear();
+
+        // nodes inside cycles are not rendered
+        for index in &self.in_cycle {
+            self.nodes.get_mut(index).unwrap().latency = None;
+        }
+
+        for &index in self.ordered.iter().rev() {
+            let downstream = if index == NodeIndex(0) {
+                Some(0.)
+            } else {
+                let nodes = &self.nodes;
+                let edges = self
+                    .edges
+                    .range(((index, 0), (NodeIndex(0), 0))..)
+                    .take_while(|((s, _), _)| *s == index)
+                    .map(|(_, (d, _))| d);
+                let param_edges = self
+                    .param_edges
+                    .range((index, NodeIndex(0))..)
+                    .take_while(|(s, _)| *s == index)
+                    .map(|(_, d)| d);
+                edges
+                    .chain(param_edges)
+                    .filter_map(|d| nodes.get(d).and_then(|node| node.latency))
+                    .reduce(f64::max)
+            };
+
+            let node = self.nodes.get_mut(&index).unwrap();
+            node.own_latency = node.processor.latency_samples();
+            node.latency = downstream.map(|d| d + node.own_latency);
+            if let Some(latency) = node.latency {
+                latencies.push((index.0, latency));
+            }
+        }
+
+        // there is room for the table: the previous one was recycled
+        let _ = publisher.published.try_send(latencies);
+        self.latencies_changed = false;
+    }
+
+    /// Publish the processing order to the given list, every time the nodes are ordered
+    #[cfg(any(test, feature = "debug"))]
+    pub fn share_render_order(&mut self, render_order: Arc<std::sync::Mutex<Vec<u64>>>) {
+        self.render_order = Some(render_order);
+        self.ordered.clear(); // force publishing the current ordering
     }
 
     pub fn render(&mut self, timestamp: f64, sample_rate: SampleRate) -> &AudioBuffer {
@@ -411,53 +737,22 @@ impl Graph {
 
         // we will drop audio nodes if they are finished running
         let mut drop_nodes = vec![];
+        let mut latency_changed = false;
 
         ordered.iter().for_each(|index| {
             // remove node from map, re-insert later (for borrowck reasons)
             let mut node = nodes.remove(index).unwrap();
-            // for lifecycle management, check if any inputs are present
-            let mut has_inputs_connected = false;
-            // mix all inputs together
-            node.inputs.iter_mut().for_each(|i| i.make_silent());
 
-            edges
-                .iter()
-                .filter_map(move |(s, d)| {
-                    // audio params are connected to the 'hidden' u32::MAX input, ignore them
-                    if d.0 == *index && d.1 != u32::MAX {
-                        Some((s, d.1))
-                    } else {
-                        None
-                    }
-                })
-                .for_each(|(&(node_index, output), input)| {
-                    let input_node = nodes.get(&node_index).unwrap();
-                    let signal = &input_node.outputs[output as usize];
-
-                    node.inputs[input as usize].add(signal, node.channel_config.interpretation());
-
-                    has_inputs_connected = true;
-                });
-
-            // up/down-mix to the desired channel count
-            let mode = node.channel_config.count_mode();
-            let count = node.channel_config.count();
-            let interpretation = node.channel_config.interpretation();
-            node.inputs.iter_mut().for_each(|input_buf| {
-                let cur_channels = input_buf.number_of_channels();
-                let new_channels = match mode {
-                    ChannelCountMode::Max => cur_channels,
-                    ChannelCountMode::Explicit => count,
-                    ChannelCountMode::ClampedMax => cur_channels.min(count),
-                };
-                input_buf.mix(new_channels, interpretation);
-            });
+            // the inputs of a node breaking a cycle were mixed in the previous render quantum
+            if !node.breaking_cycle {
+                node.has_inputs_connected = mix_inputs(&mut node, *index, edges, nodes);
+            }
 
             let params = AudioParamValues::from(&*nodes);
             node.process(params, timestamp, sample_rate);
+            latency_changed |= node.processor.latency_samples() != node.own_latency;
 
             // check if the Node has reached end of lifecycle
-            node.has_inputs_connected = has_inputs_connected;
             if node.can_free() {
                 drop_nodes.push(*index);
             }
@@ -466,17 +761,79 @@ impl Graph {
             nodes.insert(*index, node);
         });
 
+        // provide the inputs of the nodes breaking a cycle in the next render quantum
+        ordered.iter().for_each(|index| {
+            if !nodes[index].breaking_cycle {
+                return;
+            }
+
+            let mut node = nodes.remove(index).unwrap();
+            node.has_inputs_connected = mix_inputs(&mut node, *index, edges, nodes);
+            nodes.insert(*index, node);
+        });
+
         for index in drop_nodes {
             self.remove_edges_from(index);
+            self.param_edges.retain(|&(s, d)| s != index && d != index);
             self.nodes.remove(&index);
+            if let Some(freed) = &self.freed {
+                // the receiving context may be dropped already, nothing to report then
+                let _ = freed.send(index.0);
+            }
         }
 
+        self.latencies_changed |= latency_changed;
+        self.publish_latencies();
+
         // return buffer of destination node
         // assume only 1 output (todo)
         &self.nodes.get(&NodeIndex(0)).unwrap().outputs[0]
     }
 }
 
+/// Mix the outputs connected to the inputs of the node, and up/down-mix them to the channel count
+///
+/// Returns whether any input is connected.
+fn mix_inputs(
+    node: &mut Node,
+    index: NodeIndex,
+    edges: &BTreeSet<Edge>,
+    nodes: &HashMap<NodeIndex, Node>,
+) -> bool {
+    // for lifecycle management, check if any inputs are present
+    let mut has_inputs_connected = false;
+    // mix all inputs together
+    node.inputs.iter_mut().for_each(|i| i.make_silent());
+
+    edges
+        .iter()
+        .filter_map(move |(s, d)| if d.0 == index { Some((s, d.1)) } else { None })
+        .for_each(|(&(node_index, output), input)| {
+            let input_node = nodes.get(&node_index).unwrap();
+            let signal = &input_node.outputs[output as usize];
+
+            node.inputs[input as usize].add(signal, node.channel_config.interpretation());
+
+            has_inputs_connected = true;
+        });
+
+    // up/down-mix to the desired channel count
+    let mode = node.channel_config.count_mode();
+    let count = node.channel_config.count();
+    let interpretation = node.channel_config.interpretation();
+    node.inputs.iter_mut().for_each(|input_buf| {
+        let cur_channels = input_buf.number_of_channels();
+        let new_channels = match mode {
+            ChannelCountMode::Max => cur_channels,
+            ChannelCountMode::Explicit => count,
+            ChannelCountMode::ClampedMax => cur_channels.min(count),
+        };
+        input_buf.mix(new_channels, interpretation);
+    });
+
+    has_inputs_connected
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -499,6 +856,27 @@ mod tests {
         }
     }
 
+    #[derive(Debug, Clone)]
+    struct TestDelayNode {}
+
+    impl AudioProcessor for TestDelayNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioBuffer],
+            _outputs: &mut [AudioBuffer],
+            _params: AudioParamValues,
+            _timestamp: f64,
+            _sample_rate: SampleRate,
+        ) {
+        }
+        fn tail_time(&self) -> bool {
+            false
+        }
+        fn can_break_cycle(&self) -> bool {
+            true
+        }
+    }
+
     fn config() -> ChannelConfig {
         crate::buffer::ChannelConfigOptions {
             count: 2,
@@ -541,7 +919,7 @@ mod tests {
         assert!(pos2 < pos1); // node 1 depends on node 2
 
         // Detach node 1 (and thus node 2) from the root node
-        graph.remove_edge(NodeIndex(1), NodeIndex(0));
+        graph.remove_edge(NodeIndex(1), NodeIndex(0), None, None);
         graph.order_nodes();
 
         // sorting is not deterministic, but this should uphold:
@@ -632,4 +1010,249 @@ mod tests {
         // a-cyclic part should be present
         assert!(pos3.unwrap() < pos0.unwrap());
     }
+
+    #[test]
+    fn test_cycle_with_delay() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(2), Box::new(TestDelayNode {}), 1, 1, config());
+        graph.add_node(NodeIndex(3), node, 1, 1, config());
+
+        // link 3->2, 2->1, 1->2, 2->0
+        graph.add_edge((NodeIndex(3), 0), (NodeIndex(2), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(1), 0));
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(2), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(0), 0));
+
+        graph.order_nodes();
+
+        // the cycle is broken at the delay node, which is rendered first
+        assert_eq!(graph.ordered.len(), 4);
+        let pos = |i| graph.ordered.iter().position(|&n| n == NodeIndex(i));
+        assert!(pos(2) < pos(1));
+        assert!(pos(2) < pos(0));
+        assert!(graph.nodes[&NodeIndex(2)].breaking_cycle);
+
+        // removing the feedback edge restores the regular ordering
+        graph.remove_edge(NodeIndex(1), NodeIndex(2), None, None);
+        graph.order_nodes();
+
+        assert_eq!(graph.ordered.len(), 4);
+        let pos = |i| graph.ordered.iter().position(|&n| n == NodeIndex(i));
+        assert!(pos(3) < pos(2));
+        assert!(!graph.nodes[&NodeIndex(2)].breaking_cycle);
+    }
+
+    #[test]
+    fn test_channel_config_changes_after_registration() {
+        use crate::buffer::{ChannelCountMode, ChannelInterpretation};
+        use crate::context::{AsBaseAudioContext, ManualAudioContext};
+        use crate::node::AudioNode;
+
+        let mut context = ManualAudioContext::new(2, SampleRate(44_100));
+
+        // stereo signal, left = 1, right = 3
+        let merger = context.create_channel_merger(2);
+        let left = context.create_constant_source();
+        left.connect_at(&merger, 0, 0).unwrap();
+        let right = context.create_constant_source();
+        right.offset().set_value(3.);
+        right.connect_at(&merger, 0, 1).unwrap();
+
+        let gain = context.create_gain();
+        merger.connect(&gain);
+        gain.connect(&context.destination());
+
+        let mut buffer = [0.; 2 * BUFFER_SIZE as usize];
+
+        // default config: stereo passes through
+        context.render(&mut buffer);
+        assert_eq!(&buffer[..2], &[1., 3.]);
+
+        // force mono, speakers interpretation: down-mix to (1 + 3) / 2
+        gain.set_channel_count_mode(ChannelCountMode::Explicit);
+        gain.set_channel_count(1);
+        context.render(&mut buffer);
+        assert_eq!(&buffer[..2], &[2., 2.]);
+
+        // discrete interpretation: keep first channel only
+        gain.set_channel_interpretation(ChannelInterpretation::Discrete);
+        context.render(&mut buffer);
+        assert_eq!(&buffer[..2], &[1., 1.]);
+    }
+
+    #[test]
+    fn test_latencies_published_on_change() {
+        let mut graph = Graph::new();
+        let (publisher, mut reader) = crate::message::latency_channel();
+        graph.share_latencies(publisher);
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node, 1, 1, config());
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+
+        graph.render(0., SampleRate(44_100));
+        assert_eq!(reader.latest().len(), 2);
+
+        // nothing changed, nothing published
+        graph.render(0., SampleRate(44_100));
+        assert!(graph.latencies.as_ref().unwrap().published.is_empty());
+
+        graph.remove_edge(NodeIndex(1), NodeIndex(0), None, None);
+        graph.render(0., SampleRate(44_100));
+        assert_eq!(reader.latest(), &vec![(0, 0.)]);
+    }
+
+    #[test]
+    fn test_render_odd_buffer_sizes() {
+        use crate::context::{AsBaseAudioContext, ManualAudioContext};
+        use crate::node::AudioNode;
+
+        const LENGTH: usize = 2048;
+        let mut context = ManualAudioContext::new(2, SampleRate(LENGTH as u32));
+
+        // left channel carries the frame index, right channel its negative
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+        let left = context.create_constant_source();
+        left.offset().set_value_at_time(0., 0.);
+        left.offset()
+            .linear_ramp_to_value_at_time(LENGTH as f32, 1.);
+        left.connect_at(&merger, 0, 0).unwrap();
+        let right = context.create_constant_source();
+        right.offset().set_value_at_time(0., 0.);
+        right
+            .offset()
+            .linear_ramp_to_value_at_time(-(LENGTH as f32), 1.);
+        right.connect_at(&merger, 0, 1).unwrap();
+
+        // callback sizes (in frames) not aligned with the render quantum
+        let mut output = vec![];
+        for &frames in &[1, 127, 128, 129, 300, 3, 0, 512, 99] {
+            let mut buffer = vec![0.; 2 * frames];
+            context.render(&mut buffer);
+            output.extend_from_slice(&buffer);
+        }
+
+        for (i, frame) in output.chunks(2).enumerate() {
+            assert!((frame[0] - i as f32).abs() < 1e-2, "{} {:?}", i, frame);
+            assert!((frame[1] + i as f32).abs() < 1e-2, "{} {:?}", i, frame);
+        }
+    }
+
+    #[test]
+    fn test_invalid_edges_are_ignored() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(NodeIndex(0), node.clone(), 1, 1, config());
+        graph.add_node(NodeIndex(1), node, 1, 1, config());
+
+        // unknown source and destination nodes
+        graph.add_edge((NodeIndex(5), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(6), 0));
+
+        // unknown ports
+        graph.add_edge((NodeIndex(1), 1), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 1));
+
+        assert!(graph.edges.is_empty());
+
+        // rendering does not panic
+        graph.render(0., SampleRate(44_100));
+        assert_eq!(graph.ordered.len(), 2);
+
+        // valid edges are still accepted
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        assert_eq!(graph.edges.len(), 1);
+        graph.render(0., SampleRate(44_100));
+    }
+
+    #[test]
+    fn test_param_edges_do_not_occupy_inputs() {
+        /// Emits a constant value
+        struct ConstNode(f32);
+
+        impl AudioProcessor for ConstNode {
+            fn process(
+                &mut self,
+                _inputs: &[AudioBuffer],
+                outputs: &mut [AudioBuffer],
+                _params: AudioParamValues,
+                _timestamp: f64,
+                _sample_rate: SampleRate,
+            ) {
+                outputs[0].force_mono();
+                outputs[0]
+                    .channel_data_mut(0)
+                    .iter_mut()
+                    .for_each(|v| *v = self.0);
+            }
+            fn tail_time(&self) -> bool {
+                true
+            }
+        }
+
+        /// Sums all inputs into a mono output
+        struct SumNode;
+
+        impl AudioProcessor for SumNode {
+            fn process(
+                &mut self,
+                inputs: &[AudioBuffer],
+                outputs: &mut [AudioBuffer],
+                _params: AudioParamValues,
+                _timestamp: f64,
+                _sample_rate: SampleRate,
+            ) {
+                let mut output = inputs[0].clone();
+                inputs[1..]
+                    .iter()
+                    .for_each(|i| output.add(i, crate::buffer::ChannelInterpretation::Speakers));
+                outputs[0] = output;
+            }
+            fn tail_time(&self) -> bool {
+                true
+            }
+        }
+
+        let mono = || -> ChannelConfig {
+            crate::buffer::ChannelConfigOptions {
+                count: 1,
+                mode: crate::buffer::ChannelCountMode::Explicit,
+                interpretation: crate::buffer::ChannelInterpretation::Speakers,
+            }
+            .into()
+        };
+
+        let mut graph = Graph::new();
+        graph.add_node(NodeIndex(0), Box::new(SumNode), 2, 1, mono());
+        graph.add_node(NodeIndex(1), Box::new(ConstNode(1.)), 0, 1, mono());
+        graph.add_node(NodeIndex(2), Box::new(ConstNode(2.)), 0, 1, mono());
+        graph.add_node(NodeIndex(3), Box::new(ConstNode(5.)), 0, 1, mono());
+
+        // audio inputs on both ports, param connection from node 3
+        graph.add_edge((NodeIndex(1), 0), (NodeIndex(0), 0));
+        graph.add_edge((NodeIndex(2), 0), (NodeIndex(0), 1));
+        graph.add_param_edge(NodeIndex(3), NodeIndex(0));
+
+        let output = graph.render(0., SampleRate(44_100));
+        assert_eq!(output.channel_data(0)[0], 3.);
+
+        // the param source is rendered before the node it controls
+        let pos0 = graph.ordered.iter().position(|&n| n == NodeIndex(0));
+        let pos3 = graph.ordered.iter().position(|&n| n == NodeIndex(3));
+        assert!(pos3.unwrap() < pos0.unwrap());
+
+        // disconnecting audio does not affect the param connection
+        graph.remove_edges_from(NodeIndex(3));
+        graph.remove_edge(NodeIndex(2), NodeIndex(0), None, None);
+        let output = graph.render(0., SampleRate(44_100));
+        assert_eq!(output.channel_data(0)[0], 1.);
+        assert!(graph.ordered.contains(&NodeIndex(3)));
+    }
 }
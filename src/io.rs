@@ -8,25 +8,155 @@
 )]
 #![allow(clippy::missing_const_for_fn)]
 
-use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crate::message::ControlMessage;
-use crate::{SampleRate, BUFFER_SIZE};
+use crate::{AtomicF64, NotFoundError, SampleRate, BUFFER_SIZE};
 
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    BuildStreamError, Device, SampleFormat, Stream, StreamConfig, SupportedBufferSize,
+    BuildStreamError, Device, PauseStreamError, PlayStreamError, Sample, SampleFormat, Stream,
+    StreamConfig, SupportedBufferSize,
 };
 
 use crate::buffer::AudioBuffer;
-use crate::context::{AudioContextOptions, LatencyHint};
+use crate::context::{AudioContextOptions, DeviceInfo, LatencyHint};
 use crate::graph::RenderThread;
-use crate::media::MicrophoneRender;
+use crate::latency::AdaptiveLatency;
+use crate::media::{MicrophoneError, MicrophoneRender};
 
 use crossbeam_channel::{Receiver, Sender};
 use log::warn;
 
+/// Upper bound of the adaptive buffer size when the device does not report one
+const MAX_ADAPTIVE_BUFFER_SIZE: u32 = 8192;
+
+/// Callback to notify the context of a new sample rate of the output device
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) type SampleRateChangeHandler = Arc<dyn Fn(SampleRate) + Send + Sync>;
+
+/// Lists the output devices of the default host with their ids
+///
+/// The id of a device is its name. Devices sharing a name get the suffix ` (n)` for the n-th
+/// occurrence, in the order the host lists them, so every id is unique.
+fn identified_output_devices() -> Vec<(String, Device)> {
+    let Ok(devices) = cpal::default_host().output_devices() else {
+        return vec![];
+    };
+
+    let mut identified: Vec<(String, Device)> = vec![];
+    for device in devices {
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let mut id = name.clone();
+        let mut occurrence = 1;
+        while identified.iter().any(|(other, _)| *other == id) {
+            occurrence += 1;
+            id = format!("{name} ({occurrence})");
+        }
+        identified.push((id, device));
+    }
+
+    identified
+}
+
+/// Find the output device with the given id, or the default output device if `None`
+///
+/// # Errors
+///
+/// Returns a `NotFoundError` if no such device is available
+fn output_device(device_id: Option<&str>) -> Result<Device, NotFoundError> {
+    let device = device_id.map_or_else(
+        || cpal::default_host().default_output_device(),
+        |id| {
+            identified_output_devices()
+                .into_iter()
+                .find(|(other, _)| other == id)
+                .map(|(_, device)| device)
+        },
+    );
+
+    device.ok_or(NotFoundError {})
+}
+
+/// Lists the output devices of the default host, with their supported configurations
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) fn enumerate_output_devices() -> Vec<DeviceInfo> {
+    identified_output_devices()
+        .into_iter()
+        .filter_map(|(id, device)| {
+            let name = device.name().ok()?;
+            let configs: Vec<_> = device.supported_output_configs().ok()?.collect();
+
+            let mut channels: Vec<_> = configs
+                .iter()
+                .map(cpal::SupportedStreamConfigRange::channels)
+                .collect();
+            channels.sort_unstable();
+            channels.dedup();
+
+            let mut sample_rates: Vec<_> = configs
+                .iter()
+                .map(|c| c.min_sample_rate().0..=c.max_sample_rate().0)
+                .collect();
+            sample_rates.sort_by_key(|range| (*range.start(), *range.end()));
+            sample_rates.dedup();
+
+            Some(DeviceInfo {
+                id,
+                name,
+                sample_rates,
+                channels,
+            })
+        })
+        .collect()
+}
+
+/// Latencies of the output stream, in seconds, updated by the stream callback
+struct OutputLatency {
+    /// duration of the buffer handed to the audio subsystem
+    base: AtomicF64,
+    /// delay between the stream callback and the playback of its first sample, negative if the
+    /// host does not report it
+    hardware: AtomicF64,
+}
+
+impl OutputLatency {
+    /// creates the latencies of a stream with the given configuration, before any callback
+    fn new(config: &StreamConfig) -> Self {
+        let frames = match config.buffer_size {
+            cpal::BufferSize::Fixed(frames) => frames,
+            cpal::BufferSize::Default => BUFFER_SIZE,
+        };
+
+        Self {
+            base: AtomicF64::new(f64::from(frames) / f64::from(config.sample_rate.0)),
+            hardware: AtomicF64::new(-1.),
+        }
+    }
+
+    /// records the latencies observed by a stream callback
+    ///
+    /// # Arguments:
+    ///
+    /// * `base` - duration of the output buffer
+    /// * `info` - timestamps of the callback
+    fn update(&self, base: f64, info: &cpal::OutputCallbackInfo) {
+        self.base.store(base);
+
+        let timestamp = info.timestamp();
+        let hardware = timestamp
+            .playback
+            .duration_since(&timestamp.callback)
+            .map_or(-1., |d| d.as_secs_f64());
+        self.hardware.store(hardware);
+    }
+}
+
 /// Creates an output stream
 ///
 /// # Arguments:
@@ -35,25 +165,98 @@ use log::warn;
 /// * `sample_format` - audio sample format of the stream
 /// * `config` - stream configuration
 /// * `render` - the render thread which process the audio data
+/// * `stream_errors` - notifies the thread owning the stream of errors, e.g. a new sample rate
+/// * `xruns` - counter of render calls that missed their deadline
+/// * `device_lost` - raised when the device is no longer available
+/// * `latency` - latencies of the stream, updated on every callback
+#[allow(clippy::too_many_arguments)]
 fn spawn_output_stream(
     device: &Device,
     sample_format: SampleFormat,
     config: &StreamConfig,
-    mut render: RenderThread,
+    render: Arc<Mutex<RenderThread>>,
+    stream_errors: Sender<()>,
+    xruns: Arc<AtomicU64>,
+    device_lost: Arc<AtomicBool>,
+    latency: Arc<OutputLatency>,
 ) -> Result<Stream, BuildStreamError> {
-    let err_fn = |err| log::error!("an error occurred on the output audio stream: {}", err);
+    // real time duration of a single sample in the interleaved output buffer
+    let sample_duration = 1. / f64::from(config.sample_rate.0 * u32::from(config.channels));
+    let err_fn = move |err| {
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            log::error!("the output device is no longer available");
+            device_lost.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        log::error!("an error occurred on the output audio stream: {}", err);
+
+        // Some OS audio stacks change the device sample rate at runtime (e.g. switching from
+        // speakers to a headset), which surfaces as a stream error. The thread owning the stream
+        // checks if that happened, a pending check covers this error as well.
+        let _ = stream_errors.try_send(());
+    };
 
     match sample_format {
-        SampleFormat::F32 => {
-            device.build_output_stream(config, move |d: &mut [f32], _c| render.render(d), err_fn)
-        }
-        SampleFormat::U16 => {
-            device.build_output_stream(config, move |d: &mut [u16], _c| render.render(d), err_fn)
-        }
-        SampleFormat::I16 => {
-            device.build_output_stream(config, move |d: &mut [i16], _c| render.render(d), err_fn)
-        }
+        SampleFormat::F32 => device.build_output_stream(
+            config,
+            move |d: &mut [f32], c| {
+                render_output(&render, d, c, sample_duration, &xruns, &latency);
+            },
+            err_fn,
+        ),
+        SampleFormat::U16 => device.build_output_stream(
+            config,
+            move |d: &mut [u16], c| {
+                render_output(&render, d, c, sample_duration, &xruns, &latency);
+            },
+            err_fn,
+        ),
+        SampleFormat::I16 => device.build_output_stream(
+            config,
+            move |d: &mut [i16], c| {
+                render_output(&render, d, c, sample_duration, &xruns, &latency);
+            },
+            err_fn,
+        ),
+    }
+}
+
+/// Render the audio graph into the output buffer, counting missed deadlines as xruns
+///
+/// # Arguments:
+///
+/// * `render` - the render thread which process the audio data
+/// * `data` - interleaved output buffer of the stream
+/// * `info` - timestamps of the callback
+/// * `sample_duration` - real time duration of a single sample in `data`
+/// * `xruns` - counter of render calls that missed their deadline
+/// * `latency` - latencies of the stream
+fn render_output<S: Sample>(
+    render: &Mutex<RenderThread>,
+    data: &mut [S],
+    info: &cpal::OutputCallbackInfo,
+    sample_duration: f64,
+    xruns: &AtomicU64,
+    latency: &OutputLatency,
+) {
+    let start = Instant::now();
+
+    match render.try_lock() {
+        Ok(mut render) => render.render(data),
+        // the stream is being rebuilt, emit silence
+        Err(_) => data.iter_mut().for_each(|s| *s = Sample::from(&0_f32)),
     }
+
+    // buffer lengths are small, no loss of precision
+    #[allow(clippy::cast_precision_loss)]
+    let deadline = data.len() as f64 * sample_duration;
+    if start.elapsed().as_secs_f64() > deadline {
+        xruns.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // the buffer duration is the time the graph renders ahead of the audio subsystem
+    latency.update(deadline, info);
 }
 
 /// Creates an input stream
@@ -99,38 +302,79 @@ struct StreamConfigsBuilder {
 
 impl StreamConfigsBuilder {
     /// creates the `StreamConfigBuilder`
-    fn new() -> Self {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .expect("no output device available");
+    ///
+    /// # Argument
+    ///
+    /// * `options` - options contains the requested device, sample rate and number of channels
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotFoundError` if the requested output device is not available
+    fn new(options: Option<&AudioContextOptions>) -> Result<Self, NotFoundError> {
+        let device_id = options.and_then(|opts| opts.device_id.as_deref());
+        let device = output_device(device_id)?;
 
-        log::info!("Host: {:?}", host.id());
+        log::info!("Host: {:?}", cpal::default_host().id());
         log::info!("Output device: {:?}", device.name());
 
-        let supported = Self::get_supported_config(&device);
+        let supported = Self::get_supported_config(&device, options);
 
-        Self {
+        Ok(Self {
             device,
             supported: supported.clone(),
             prefered: supported.into(),
-        }
+        })
     }
 
     /// returns the supported stream config from with other configs are derived
     ///
+    /// The first config range of the device matching the requested sample rate and number of
+    /// channels is selected. If none matches, the device default config is returned and the
+    /// requested config will be tried anyway, with the default as fallback.
+    ///
     /// # Argument
     ///
     /// * `device` - the audio device on which the stream is broadcast
-    fn get_supported_config(device: &cpal::Device) -> cpal::SupportedStreamConfig {
-        let mut supported_configs_range = device
+    /// * `options` - options contains the requested sample rate and number of channels
+    fn get_supported_config(
+        device: &cpal::Device,
+        options: Option<&AudioContextOptions>,
+    ) -> cpal::SupportedStreamConfig {
+        let default = device
             .supported_output_configs()
-            .expect("error while querying configs");
-
-        supported_configs_range
+            .expect("error while querying configs")
             .next()
             .expect("no supported config?!")
-            .with_max_sample_rate()
+            .with_max_sample_rate();
+
+        let (sample_rate, channels) = match options {
+            Some(opts) if opts.sample_rate.is_some() || opts.channels.is_some() => (
+                opts.sample_rate
+                    .map_or_else(|| default.sample_rate(), cpal::SampleRate),
+                opts.channels.unwrap_or_else(|| default.channels()),
+            ),
+            _ => return default,
+        };
+
+        let matching = device
+            .supported_output_configs()
+            .expect("error while querying configs")
+            .find(|range| {
+                range.channels() == channels
+                    && range.min_sample_rate() <= sample_rate
+                    && sample_rate <= range.max_sample_rate()
+            });
+
+        matching.map_or_else(
+            || {
+                warn!(
+                    "Output device does not list {} channels at {} Hz as supported",
+                    channels, sample_rate.0
+                );
+                default
+            },
+            |range| range.with_sample_rate(sample_rate),
+        )
     }
 
     /// returns the stream buffer size
@@ -248,6 +492,8 @@ struct StreamConfigs {
     /// in case of failure to build the stream with `prefered`
     /// a fallback config is used to spawn the stream
     fallback: cpal::StreamConfig,
+    /// the buffer sizes supported by the device
+    buffer_size_range: SupportedBufferSize,
 }
 
 impl StreamConfigs {
@@ -260,10 +506,12 @@ impl StreamConfigs {
         } = builder;
 
         let sample_format = supported.sample_format();
+        let buffer_size_range = supported.buffer_size().clone();
 
         Self {
             sample_format,
             prefered,
+            buffer_size_range,
             fallback: supported.into(),
         }
     }
@@ -273,42 +521,70 @@ impl StreamConfigs {
 struct OutputStreamer {
     /// The audio device on which the output stream is broadcast
     device: cpal::Device,
+    /// raised when the device is no longer available
+    device_lost: Arc<AtomicBool>,
+    /// latencies of the output stream
+    latency: Arc<OutputLatency>,
     /// The configs on which the output stream can be build
     configs: StreamConfigs,
     /// `frames_played` act as a time reference when processing
     frames_played: Arc<AtomicU64>,
+    /// notifies the context of sample rate changes of the device
+    on_sample_rate_change: SampleRateChangeHandler,
+    /// errors reported by the output stream, checked by the thread owning the stream
+    stream_errors: (Sender<()>, Receiver<()>),
+    /// number of render calls that missed their deadline
+    xruns: Arc<AtomicU64>,
     /// communication channel between control and render thread (sender part)
     sender: Option<Sender<ControlMessage>>,
+    /// the render thread, shared with the output stream
+    renderer: Option<Arc<Mutex<RenderThread>>>,
     /// the output stream
     stream: Option<Stream>,
     /// a flag to know if the output stream has been build with prefered config
     /// or fallback config
     falled_back: bool,
+    /// the output stream is paused, it stays paused when rebuilt
+    paused: bool,
 }
 
 impl OutputStreamer {
-    /// creates an `OutputStreamer`
-    fn new(configs: StreamConfigs, frames_played: Arc<AtomicU64>) -> Self {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .expect("no output device available");
+    /// creates an `OutputStreamer` on the device with the given id, or the default device
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotFoundError` if the output device is not available (anymore)
+    fn new(
+        configs: StreamConfigs,
+        device_id: Option<&str>,
+        frames_played: Arc<AtomicU64>,
+        on_sample_rate_change: SampleRateChangeHandler,
+    ) -> Result<Self, NotFoundError> {
+        let device = output_device(device_id)?;
 
-        Self {
+        Ok(Self {
             device,
+            device_lost: Arc::new(AtomicBool::new(false)),
+            latency: Arc::new(OutputLatency::new(&configs.prefered)),
             configs,
             frames_played,
+            on_sample_rate_change,
+            stream_errors: crossbeam_channel::bounded(1),
+            xruns: Arc::new(AtomicU64::new(0)),
             sender: None,
+            renderer: None,
             stream: None,
             falled_back: false,
-        }
+            paused: true,
+        })
     }
 
-    /// spawns the output stram with prefered config
-    fn spawn(mut self) -> Result<Self, Self> {
-        // try with prefered config
-        let config = &self.configs.prefered;
-
+    /// creates the render thread and spawns an output stream for the given config
+    ///
+    /// # Argument
+    ///
+    /// * `config` - the config of the output stream
+    fn spawn_with(&mut self, config: &StreamConfig) -> Result<Stream, BuildStreamError> {
         // Creates the render thread
         let sample_rate = SampleRate(config.sample_rate.0);
 
@@ -318,17 +594,33 @@ impl OutputStreamer {
         self.sender = Some(sender);
 
         // spawn the render thread
-        let renderer = RenderThread::new(
+        let renderer = Arc::new(Mutex::new(RenderThread::new(
             sample_rate,
             config.channels as usize,
             receiver,
             self.frames_played.clone(),
-        );
+        )));
 
-        let spawned =
-            spawn_output_stream(&self.device, self.configs.sample_format, config, renderer);
+        self.renderer = Some(renderer.clone());
 
-        match spawned {
+        spawn_output_stream(
+            &self.device,
+            self.configs.sample_format,
+            config,
+            renderer,
+            self.stream_errors.0.clone(),
+            self.xruns.clone(),
+            self.device_lost.clone(),
+            self.latency.clone(),
+        )
+    }
+
+    /// spawns the output stram with prefered config
+    fn spawn(mut self) -> Result<Self, Self> {
+        // try with prefered config
+        let config = self.configs.prefered.clone();
+
+        match self.spawn_with(&config) {
             Ok(stream) => {
                 self.stream = Some(stream);
                 Ok(self)
@@ -341,29 +633,131 @@ impl OutputStreamer {
     }
 
     /// playes the output stream
-    fn play(self) -> Self {
+    fn play(mut self) -> Self {
         self.stream
             .as_ref()
             .expect("Stream needs to exist to be played")
             .play()
             .expect("Stream refused to play");
+        self.paused = false;
+        self
+    }
+
+    /// pauses the output stream
+    ///
+    /// Some hosts start streams as soon as they are built, so this is needed to keep a new
+    /// stream silent.
+    fn pause(mut self) -> Self {
+        self.stream
+            .as_ref()
+            .expect("Stream needs to exist to be paused")
+            .pause()
+            .expect("Stream refused to pause");
+        self.paused = true;
         self
     }
 
-    /// returns the output stream infos
-    fn get_output_stream(self) -> (Stream, StreamConfig, Sender<ControlMessage>) {
+    /// returns the config of the running output stream
+    fn active_config(&self) -> &StreamConfig {
+        if self.falled_back {
+            &self.configs.fallback
+        } else {
+            &self.configs.prefered
+        }
+    }
+
+    /// returns the config of the running output stream, with a mutable borrow
+    fn active_config_mut(&mut self) -> &mut StreamConfig {
         if self.falled_back {
-            (
-                self.stream.unwrap(),
-                self.configs.fallback,
-                self.sender.unwrap(),
-            )
+            &mut self.configs.fallback
+        } else {
+            &mut self.configs.prefered
+        }
+    }
+
+    /// rebuilds the running output stream with an updated config
+    ///
+    /// The render thread is kept, so the audio graph and the timing are not
+    /// affected. If the new stream cannot be built, the previous config is
+    /// restored.
+    ///
+    /// Returns `true` if the stream runs with the updated config.
+    ///
+    /// # Argument
+    ///
+    /// * `update` - modifies the config of the output stream
+    fn rebuild(&mut self, update: impl FnOnce(&mut StreamConfig)) -> bool {
+        let Some(renderer) = self.renderer.clone() else {
+            return false;
+        };
+        if self.device_lost.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let previous = self.active_config().clone();
+        update(self.active_config_mut());
+
+        // release the device before building the new stream
+        self.stream = None;
+
+        let config = self.active_config().clone();
+        let spawned = spawn_output_stream(
+            &self.device,
+            self.configs.sample_format,
+            &config,
+            renderer.clone(),
+            self.stream_errors.0.clone(),
+            self.xruns.clone(),
+            self.device_lost.clone(),
+            self.latency.clone(),
+        );
+
+        let (stream, updated) = match spawned {
+            Ok(stream) => (stream, true),
+            Err(e) => {
+                warn!("Output stream rebuild failed with config {config:?}: {e}");
+                *self.active_config_mut() = previous;
+                let config = self.active_config().clone();
+                let stream = spawn_output_stream(
+                    &self.device,
+                    self.configs.sample_format,
+                    &config,
+                    renderer,
+                    self.stream_errors.0.clone(),
+                    self.xruns.clone(),
+                    self.device_lost.clone(),
+                    self.latency.clone(),
+                )
+                .expect("OutputStream rebuild failed with previous config");
+                (stream, false)
+            }
+        };
+
+        if self.paused {
+            stream.pause().expect("Stream refused to pause");
         } else {
-            (
-                self.stream.unwrap(),
-                self.configs.prefered,
-                self.sender.unwrap(),
-            )
+            stream.play().expect("Stream refused to play");
+        }
+        self.stream = Some(stream);
+
+        updated
+    }
+
+    /// rebuilds the output stream at the current sample rate of the device, if it changed
+    ///
+    /// Called after the stream reported an error. The context is notified once the stream runs
+    /// at the new rate, so the graph renders at the rate the device plays at.
+    fn follow_sample_rate(&mut self) {
+        let Ok(config) = self.device.default_output_config() else {
+            return;
+        };
+        let sample_rate = SampleRate(config.sample_rate().0);
+        if sample_rate.0 == self.active_config().sample_rate.0 {
+            return;
+        }
+
+        if self.rebuild(|config| config.sample_rate = cpal::SampleRate(sample_rate.0)) {
+            (self.on_sample_rate_change)(sample_rate);
         }
     }
 }
@@ -381,30 +775,13 @@ impl OrFallback for Result<OutputStreamer, OutputStreamer> {
             Err(mut streamer) => {
                 // try with fallback config
                 streamer.falled_back = true;
-                let config = &streamer.configs.fallback;
-
-                // Creates the renderer thread
-                let sample_rate = SampleRate(config.sample_rate.0);
-
-                // communication channel to the render thread
-                let (sender, receiver) = crossbeam_channel::unbounded();
-
-                streamer.sender = Some(sender);
-
-                // spawn the render thread
-                let renderer = RenderThread::new(
-                    sample_rate,
-                    config.channels as usize,
-                    receiver,
-                    streamer.frames_played.clone(),
+                let config = streamer.configs.fallback.clone();
+                warn!(
+                    "Falling back to the device default config: {} channels at {} Hz",
+                    config.channels, config.sample_rate.0
                 );
 
-                let spawned = spawn_output_stream(
-                    &streamer.device,
-                    streamer.configs.sample_format,
-                    config,
-                    renderer,
-                );
+                let spawned = streamer.spawn_with(&config);
                 let stream = spawned.expect("OutputStream build failed with default config");
                 streamer.stream = Some(stream);
                 streamer
@@ -413,40 +790,266 @@ impl OrFallback for Result<OutputStreamer, OutputStreamer> {
     }
 }
 
+/// How often the xrun counter is checked when adaptive latency is enabled
+const ADAPTIVE_LATENCY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Commands sent to the thread owning the output stream
+enum StreamCommand {
+    /// resume the output stream
+    Play(Sender<Result<(), PlayStreamError>>),
+    /// suspend the output stream
+    Pause(Sender<Result<(), PauseStreamError>>),
+    /// enable or disable adaptive latency
+    SetAdaptiveLatency(bool),
+    /// close the output stream and exit the thread
+    Close,
+}
+
+/// Handle to the output stream
+///
+/// The cpal stream is not `Send`, so it lives on a dedicated thread which
+/// also takes care of resizing its buffer when adaptive latency is enabled.
+/// Dropping the handle without closing it lets the thread close the stream in the background.
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) struct OutputStreamHandle {
+    /// communication channel to the thread owning the stream
+    commands: Sender<StreamCommand>,
+    /// the thread owning the stream, `None` once closed
+    thread: Mutex<Option<JoinHandle<()>>>,
+    /// raised when the device is no longer available
+    device_lost: Arc<AtomicBool>,
+    /// latencies of the output stream
+    latency: Arc<OutputLatency>,
+}
+
+impl OutputStreamHandle {
+    /// indicates if the output device is no longer available
+    pub fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::SeqCst)
+    }
+
+    /// duration of the buffer handed to the audio subsystem, in seconds
+    pub fn base_latency(&self) -> f64 {
+        self.latency.base.load()
+    }
+
+    /// delay until rendered audio is played, in seconds, `None` if the host does not report it
+    pub fn hardware_latency(&self) -> Option<f64> {
+        Some(self.latency.hardware.load()).filter(|&l| l >= 0.)
+    }
+
+    /// resumes the output stream
+    pub fn play(&self) -> Result<(), PlayStreamError> {
+        let (ack, done) = crossbeam_channel::bounded(1);
+        self.commands.send(StreamCommand::Play(ack)).unwrap();
+        done.recv().unwrap()
+    }
+
+    /// suspends the output stream
+    pub fn pause(&self) -> Result<(), PauseStreamError> {
+        let (ack, done) = crossbeam_channel::bounded(1);
+        self.commands.send(StreamCommand::Pause(ack)).unwrap();
+        done.recv().unwrap()
+    }
+
+    /// enables or disables adaptive latency
+    pub fn set_adaptive_latency(&self, enabled: bool) {
+        self.commands
+            .send(StreamCommand::SetAdaptiveLatency(enabled))
+            .unwrap();
+    }
+
+    /// closes the output stream and waits for its thread to exit
+    ///
+    /// The stream, and with it the render thread, is dropped on the owning thread, so once this
+    /// returns no more audio is rendered. Calling it again is a no-op.
+    pub fn close(&self) {
+        let thread = self.thread.lock().unwrap().take();
+        if let Some(thread) = thread {
+            // the thread only exits on `Close` or a disconnect, so it is still receiving
+            let _ = self.commands.send(StreamCommand::Close);
+            if thread.join().is_err() {
+                log::error!("the output stream thread panicked");
+            }
+        }
+    }
+}
+
+/// Runs the output stream until the handle is dropped
+///
+/// # Arguments:
+///
+/// * `streamer` - the spawned output streamer
+/// * `commands` - commands issued by the `OutputStreamHandle`
+fn run_output_stream(mut streamer: OutputStreamer, commands: &Receiver<StreamCommand>) {
+    let max_buffer_size = match streamer.configs.buffer_size_range {
+        SupportedBufferSize::Range { max, .. } => max,
+        SupportedBufferSize::Unknown => MAX_ADAPTIVE_BUFFER_SIZE,
+    };
+    let min_buffer_size = match streamer.configs.buffer_size_range {
+        SupportedBufferSize::Range { min, .. } => BUFFER_SIZE.max(min),
+        SupportedBufferSize::Unknown => BUFFER_SIZE,
+    };
+
+    let mut adaptive: Option<AdaptiveLatency> = None;
+    let stream_errors = streamer.stream_errors.1.clone();
+
+    loop {
+        let command = crossbeam_channel::select! {
+            recv(commands) -> command => match command {
+                Ok(command) => Some(command),
+                // the handle was dropped, close the stream
+                Err(_) => return,
+            },
+            recv(stream_errors) -> _ => {
+                streamer.follow_sample_rate();
+                continue;
+            }
+            default(ADAPTIVE_LATENCY_INTERVAL) => None,
+        };
+
+        match command {
+            Some(StreamCommand::Play(ack)) => {
+                let result = streamer.stream.as_ref().unwrap().play();
+                if result.is_ok() {
+                    streamer.paused = false;
+                }
+                let _ = ack.send(result);
+            }
+            Some(StreamCommand::Pause(ack)) => {
+                let result = streamer.stream.as_ref().unwrap().pause();
+                if result.is_ok() {
+                    streamer.paused = true;
+                }
+                let _ = ack.send(result);
+            }
+            Some(StreamCommand::SetAdaptiveLatency(true)) => {
+                if adaptive.is_none() {
+                    let policy = AdaptiveLatency::new(
+                        min_buffer_size,
+                        max_buffer_size,
+                        streamer.xruns.load(Ordering::SeqCst),
+                    );
+                    let buffer_size = policy.buffer_size();
+                    streamer.rebuild(|config| {
+                        config.buffer_size = cpal::BufferSize::Fixed(buffer_size);
+                    });
+                    adaptive = Some(policy);
+                }
+            }
+            Some(StreamCommand::SetAdaptiveLatency(false)) => adaptive = None,
+            // the handle was closed, close the stream
+            Some(StreamCommand::Close) => return,
+            None => {
+                if let Some(policy) = adaptive.as_mut() {
+                    if let Some(buffer_size) = policy.update(streamer.xruns.load(Ordering::SeqCst))
+                    {
+                        log::info!("Output buffer size raised to {buffer_size}");
+                        streamer.rebuild(|config| {
+                            config.buffer_size = cpal::BufferSize::Fixed(buffer_size);
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Builds the output
+///
+/// The output stream is left paused if `suspended` is set.
+///
+/// # Errors
+///
+/// Returns a `NotFoundError` if the requested output device is not available
+///
+/// # Panics
+///
+/// Will panic if the thread owning the output stream cannot be spawned, or the output stream
+/// cannot be built
 #[allow(clippy::redundant_pub_crate)]
 pub(crate) fn build_output(
     frames_played: Arc<AtomicU64>,
     options: Option<&AudioContextOptions>,
-) -> (Stream, StreamConfig, Sender<ControlMessage>) {
-    let configs = StreamConfigsBuilder::new()
+    on_sample_rate_change: SampleRateChangeHandler,
+    suspended: bool,
+) -> Result<(OutputStreamHandle, StreamConfig, Sender<ControlMessage>), NotFoundError> {
+    let configs = StreamConfigsBuilder::new(options)?
         .with_sample_rate(options)
         .with_latency_hint(options)
         .with_channels(options)
         .build();
 
-    let streamer = OutputStreamer::new(configs, frames_played)
-        .spawn()
-        .or_fallback()
-        .play();
+    let device_id = options.and_then(|opts| opts.device_id.clone());
+    let (commands, commands_rx) = crossbeam_channel::unbounded();
+    let (spawned, spawned_rx) = crossbeam_channel::bounded(1);
+
+    let thread = std::thread::Builder::new()
+        .name("web-audio-output".into())
+        .spawn(move || {
+            let streamer = match OutputStreamer::new(
+                configs,
+                device_id.as_deref(),
+                frames_played,
+                on_sample_rate_change,
+            ) {
+                Ok(streamer) => streamer.spawn().or_fallback(),
+                Err(e) => {
+                    spawned.send(Err(e)).unwrap();
+                    return;
+                }
+            };
+            let streamer = if suspended {
+                streamer.pause()
+            } else {
+                streamer.play()
+            };
+
+            let config = streamer.active_config().clone();
+            let sender = streamer.sender.clone().unwrap();
+            let device_lost = streamer.device_lost.clone();
+            let latency = streamer.latency.clone();
+            spawned
+                .send(Ok((config, sender, device_lost, latency)))
+                .unwrap();
 
-    streamer.get_output_stream()
+            run_output_stream(streamer, &commands_rx);
+        })
+        .expect("failed to spawn the output stream thread");
+
+    let (config, sender, device_lost, latency) = spawned_rx
+        .recv()
+        .expect("OutputStream build failed with default config")?;
+
+    let handle = OutputStreamHandle {
+        commands,
+        thread: Mutex::new(Some(thread)),
+        device_lost,
+        latency,
+    };
+
+    Ok((handle, config, sender))
 }
 
 /// Builds the input
-pub fn build_input() -> (Stream, StreamConfig, Receiver<AudioBuffer>) {
+///
+/// # Errors
+///
+/// Returns an error if there is no input device, or if it cannot be opened, e.g. because access
+/// was denied
+pub fn build_input() -> Result<(Stream, StreamConfig, Receiver<AudioBuffer>), MicrophoneError> {
     let host = cpal::default_host();
     let device = host
         .default_input_device()
-        .expect("no input device available");
+        .ok_or(MicrophoneError::NotFound)?;
     log::info!("Input device: {:?}", device.name());
 
     let mut supported_configs_range = device
         .supported_input_configs()
-        .expect("error while querying configs");
+        .map_err(MicrophoneError::unavailable)?;
     let supported_config = supported_configs_range
         .next()
-        .expect("no supported config?!")
+        .ok_or(MicrophoneError::NotFound)?
         .with_max_sample_rate();
 
     let sample_format = supported_config.sample_format();
@@ -489,12 +1092,12 @@ pub fn build_input() -> (Stream, StreamConfig, Receiver<AudioBuffer>) {
 
             let renderer = MicrophoneRender::new(channels, sample_rate, sender);
             spawn_input_stream(&device, sample_format, &default_config, renderer)
-                .expect("Unable to spawn input stream with default config")
+                .map_err(MicrophoneError::unavailable)?
         }
     };
 
     // Required because some hosts don't play the stream automatically
-    stream.play().expect("Input stream refused to play");
+    stream.play().map_err(MicrophoneError::unavailable)?;
 
-    (stream, config, receiver)
+    Ok((stream, config, receiver))
 }
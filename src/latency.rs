@@ -0,0 +1,99 @@
+//! Adaptive output latency: grow the device buffer size when dropouts occur
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::perf,
+    clippy::missing_docs_in_private_items
+)]
+
+use crate::BUFFER_SIZE;
+
+/// Back-off policy for the output buffer size, driven by the xrun counter
+///
+/// Playback starts at the smallest buffer size the device supports. Every time new xruns
+/// (buffer under- or overruns) are observed, the buffer size is doubled until the maximum is
+/// reached.
+#[derive(Debug)]
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) struct AdaptiveLatency {
+    /// current buffer size in sample-frames, always a multiple of `BUFFER_SIZE`
+    buffer_size: u32,
+    /// upper limit for the buffer size
+    max_buffer_size: u32,
+    /// xrun count at the previous update
+    xruns: u64,
+}
+
+impl AdaptiveLatency {
+    /// Start adapting from `min_buffer_size`
+    ///
+    /// Both buffer sizes are aligned to a multiple of `BUFFER_SIZE`: the minimum is rounded up,
+    /// the maximum is rounded down so it stays within the range supported by the device, but
+    /// never below the minimum.
+    pub fn new(min_buffer_size: u32, max_buffer_size: u32, xruns: u64) -> Self {
+        let buffer_size = min_buffer_size.max(1).div_ceil(BUFFER_SIZE) * BUFFER_SIZE;
+        let max_buffer_size = max_buffer_size / BUFFER_SIZE * BUFFER_SIZE;
+
+        Self {
+            buffer_size,
+            max_buffer_size: max_buffer_size.max(buffer_size),
+            xruns,
+        }
+    }
+
+    /// The current buffer size in sample-frames
+    pub const fn buffer_size(&self) -> u32 {
+        self.buffer_size
+    }
+
+    /// Process the total number of xruns so far, returns the new buffer size if it should grow
+    pub fn update(&mut self, xruns: u64) -> Option<u32> {
+        let new_xruns = xruns > self.xruns;
+        self.xruns = xruns;
+
+        if !new_xruns || self.buffer_size >= self.max_buffer_size {
+            return None;
+        }
+
+        self.buffer_size = (self.buffer_size * 2).min(self.max_buffer_size);
+        Some(self.buffer_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grows_under_xruns() {
+        let mut latency = AdaptiveLatency::new(100, 1000, 0);
+        assert_eq!(latency.buffer_size(), 128);
+
+        // stable playback
+        assert_eq!(latency.update(0), None);
+        assert_eq!(latency.buffer_size(), 128);
+
+        // induced xruns
+        assert_eq!(latency.update(3), Some(256));
+        assert_eq!(latency.update(3), None);
+        assert_eq!(latency.update(4), Some(512));
+        assert_eq!(latency.buffer_size(), 512);
+
+        // limited to the maximum buffer size, rounded down
+        assert_eq!(latency.update(5), Some(896));
+        assert_eq!(latency.update(6), None);
+        assert_eq!(latency.buffer_size(), 896);
+    }
+
+    #[test]
+    fn test_max_not_below_min() {
+        let mut latency = AdaptiveLatency::new(200, 300, 0);
+        assert_eq!(latency.buffer_size(), 256);
+        assert_eq!(latency.update(1), None);
+        assert_eq!(latency.buffer_size(), 256);
+
+        let latency = AdaptiveLatency::new(128, 100, 0);
+        assert_eq!(latency.buffer_size(), 128);
+    }
+}
@@ -46,6 +46,7 @@ pub const BUFFER_SIZE: u32 = 128;
 pub const MAX_CHANNELS: usize = 32;
 
 pub mod alloc;
+pub mod analysis;
 pub mod buffer;
 pub mod context;
 pub mod control;
@@ -54,19 +55,28 @@ pub mod node;
 pub mod param;
 pub mod process;
 pub mod spatial;
+pub mod worklet;
 
 #[cfg(test)]
 mod snapshot;
+#[cfg(test)]
+mod test_util;
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 mod io;
 
-mod analysis;
+mod filter;
+pub use filter::FilterQuality;
+
 mod graph;
+#[cfg(feature = "cpal")]
+mod latency;
 mod message;
+mod rng;
 
 /// Number of samples processed per second (Hertz) for a single channel of audio
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SampleRate(pub u32);
 
 /// Input/output with this index does not exist
@@ -83,6 +93,39 @@ impl fmt::Display for IndexSizeError {
 }
 impl std::error::Error for IndexSizeError {}
 
+/// The requested settings are not supported
+#[derive(Debug, Clone, Copy)]
+pub struct NotSupportedError {}
+
+impl fmt::Display for NotSupportedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for NotSupportedError {}
+
+/// The requested resource could not be found
+#[derive(Debug, Clone, Copy)]
+pub struct NotFoundError {}
+
+impl fmt::Display for NotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for NotFoundError {}
+
+/// The object is not in a state that allows the operation
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidStateError {}
+
+impl fmt::Display for InvalidStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for InvalidStateError {}
+
 /// Media stream buffering lags behind
 #[derive(Debug, Clone, Copy)]
 pub struct BufferDepletedError {}
@@ -1,24 +1,36 @@
 //! Microphone input and OGG, WAV and MP3 decoding
 
+use std::cell::Cell;
 use std::error::Error;
-use std::fs::File;
-use std::io::BufReader;
+use std::fmt;
+#[cfg(any(feature = "wav", feature = "ogg", feature = "mp3"))]
+use std::io::Cursor;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(any(feature = "wav", feature = "ogg"))]
+use std::{fs::File, io::BufReader};
 
+#[cfg(feature = "ogg")]
 use lewton::inside_ogg::OggStreamReader;
+#[cfg(feature = "ogg")]
 use lewton::VorbisError;
 
 use crate::buffer::{AudioBuffer, ChannelData};
 use crate::control::Controller;
 use crate::{BufferDepletedError, SampleRate, BUFFER_SIZE};
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 use crossbeam_channel::Sender;
-use crossbeam_channel::{self, Receiver, TryRecvError};
+#[cfg(any(test, feature = "cpal"))]
+use crossbeam_channel::TryRecvError;
+use crossbeam_channel::{self, Receiver};
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 use crate::io;
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 use cpal::{traits::StreamTrait, Sample, Stream};
 
 /// Interface for media streaming.
@@ -64,6 +76,50 @@ impl<M: Iterator<Item = Result<AudioBuffer, Box<dyn Error + Send>>> + Send + 'st
 {
 }
 
+/// Adapter to use an infallible [`AudioBuffer`] Iterator as a [`MediaStream`]
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::SampleRate;
+/// use web_audio_api::context::{AudioContext, AsBaseAudioContext};
+/// use web_audio_api::buffer::AudioBuffer;
+/// use web_audio_api::media::IterMediaStream;
+///
+/// let buffers = vec![AudioBuffer::new(1, 512, SampleRate(44_100)); 5];
+/// let media: IterMediaStream<_> = buffers.into_iter().into();
+///
+/// let context = AudioContext::new(None);
+/// let node = context.create_media_stream_source(media);
+/// ```
+pub struct IterMediaStream<I> {
+    iter: I,
+}
+
+impl<I: Iterator<Item = AudioBuffer>> IterMediaStream<I> {
+    pub fn new(iter: I) -> Self {
+        Self { iter }
+    }
+}
+
+impl<I: Iterator<Item = AudioBuffer>> From<I> for IterMediaStream<I> {
+    fn from(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+impl<I: Iterator<Item = AudioBuffer>> Iterator for IterMediaStream<I> {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(Ok)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
 /// Wrapper for [`MediaStream`]s, for buffering and playback controls.
 ///
 /// Currently, the media element will start a new thread to buffer all available media. (todo
@@ -233,6 +289,8 @@ impl Iterator for MediaElement {
     fn next(&mut self) -> Option<Self::Item> {
         // handle seeking
         if let Some(seek) = self.controller().should_seek() {
+            // the duration of the stream is unknown, seeks wrap at an explicit loop end only
+            let seek = self.controller().wrap_into_loop(seek, f64::INFINITY);
             println!("seek requested {}", seek);
             self.seek(seek);
         } else if let Some(seek) = self.seeking.take() {
@@ -284,6 +342,9 @@ impl Iterator for MediaElement {
 /// It implements the [`MediaStream`] trait so can be used inside a [`crate::node::MediaStreamAudioSourceNode`]
 ///
 /// Check the `microphone.rs` example for usage.
+///
+/// Available with the `cpal` feature (enabled by default).
+#[cfg(any(test, feature = "cpal"))]
 pub struct Microphone {
     receiver: Receiver<AudioBuffer>,
     channels: usize,
@@ -295,24 +356,47 @@ pub struct Microphone {
 
 // Todo, the Microphone struct is shipped to the render thread
 // but it contains a Stream which is not Send.
+#[cfg(any(test, feature = "cpal"))]
 unsafe impl Send for Microphone {}
 
+#[cfg(any(test, feature = "cpal"))]
 impl Microphone {
     /// Setup the default microphone input stream
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the input device cannot be opened, see [`try_new`](Self::try_new)
     #[cfg(not(test))]
     pub fn new() -> Self {
-        let (stream, config, receiver) = io::build_input();
+        Self::try_new().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Setup the default microphone input stream
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no input device, or if it cannot be opened, e.g. because
+    /// access was denied
+    #[cfg(not(test))]
+    pub fn try_new() -> Result<Self, MicrophoneError> {
+        let (stream, config, receiver) = io::build_input()?;
         log::debug!("Input {:?}", config);
 
         let sample_rate = SampleRate(config.sample_rate.0);
         let channels = config.channels as usize;
 
-        Self {
+        Ok(Self {
             receiver,
             channels,
             sample_rate,
             stream,
-        }
+        })
+    }
+
+    /// Sample rate of the input device, the audio is resampled when played in a context running
+    /// at another rate
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
     }
 
     /// Suspends the input stream, temporarily halting audio hardware access and reducing
@@ -329,13 +413,14 @@ impl Microphone {
     }
 }
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 impl Default for Microphone {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(any(test, feature = "cpal"))]
 impl Iterator for Microphone {
     type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
 
@@ -360,14 +445,213 @@ impl Iterator for Microphone {
     }
 }
 
-#[cfg(not(test))]
+/// Lock-free single producer, single consumer queue of interleaved sample-frames
+struct RingBuffer {
+    /// interleaved samples, as f32 bits
+    samples: Vec<AtomicU32>,
+    /// number of channels of the sample-frames
+    channels: usize,
+    /// number of sample-frames the queue can hold
+    capacity: usize,
+    /// total number of sample-frames written, only updated by the producer
+    written: AtomicUsize,
+    /// total number of sample-frames read, only updated by the consumer
+    read: AtomicUsize,
+    /// the producer is dropped, no more sample-frames will be written
+    closed: AtomicBool,
+}
+
+/// Producer side of a live [`MediaStream`], for pushing audio from another thread
+///
+/// This is the adapter to feed real-time input, e.g. from a network socket or a custom capture
+/// device, into the audio graph. The audio is queued in a lock-free ring buffer, so pushing
+/// never blocks and the render thread never waits on the producer. The consumer side, a
+/// [`MediaStreamSourceReader`], is used as the input of a
+/// [`MediaStreamAudioSourceNode`](crate::node::MediaStreamAudioSourceNode).
+///
+/// When the queue runs empty, the reader emits a [`BufferDepletedError`] and the node plays
+/// silence until more audio is pushed. Dropping the producer ends the stream once the queued
+/// audio is played.
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::SampleRate;
+/// use web_audio_api::buffer::AudioBuffer;
+/// use web_audio_api::context::{AudioContext, AsBaseAudioContext};
+/// use web_audio_api::media::MediaStreamSource;
+/// use web_audio_api::node::AudioNode;
+///
+/// let context = AudioContext::new(None);
+///
+/// // queue up to half a second of stereo audio
+/// let (source, reader) = MediaStreamSource::new(2, 22_050, SampleRate(44_100));
+/// let node = context.create_media_stream_source(reader);
+/// node.connect(&context.destination());
+///
+/// std::thread::spawn(move || loop {
+///     let received = AudioBuffer::new(2, 512, SampleRate(44_100));
+///     source.push(&received);
+///     std::thread::sleep(std::time::Duration::from_millis(10));
+/// });
+/// ```
+pub struct MediaStreamSource {
+    ring: Arc<RingBuffer>,
+    /// there is a single producer: the source can be sent to another thread, but not shared
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl MediaStreamSource {
+    /// Creates the producer and consumer sides of a live media stream
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_channels` - number of channels of the stream
+    /// * `capacity` - maximum number of sample-frames queued
+    /// * `sample_rate` - sample rate of the pushed audio
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the number of channels or the capacity is zero
+    pub fn new(
+        number_of_channels: usize,
+        capacity: usize,
+        sample_rate: SampleRate,
+    ) -> (Self, MediaStreamSourceReader) {
+        assert!(
+            number_of_channels > 0,
+            "NotSupportedError: the number of channels should not be zero"
+        );
+        assert!(
+            capacity > 0,
+            "NotSupportedError: the capacity should not be zero"
+        );
+
+        let ring = Arc::new(RingBuffer {
+            samples: (0..capacity * number_of_channels)
+                .map(|_| AtomicU32::new(0))
+                .collect(),
+            channels: number_of_channels,
+            capacity,
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        });
+
+        let source = Self {
+            ring: ring.clone(),
+            _not_sync: PhantomData,
+        };
+        let reader = MediaStreamSourceReader { ring, sample_rate };
+
+        (source, reader)
+    }
+
+    /// Queue the audio of the buffer, returns the number of sample-frames queued
+    ///
+    /// When the queue is full, the sample-frames that do not fit are dropped. Channels missing
+    /// from the buffer are silent, extra channels are ignored. The sample rate of the buffer is
+    /// assumed to match the sample rate of the stream.
+    pub fn push(&self, buffer: &AudioBuffer) -> usize {
+        let ring = &self.ring;
+        let written = ring.written.load(Ordering::Relaxed);
+        let free = ring.capacity - written.wrapping_sub(ring.read.load(Ordering::Acquire));
+        let frames = buffer.sample_len().min(free);
+        if frames < buffer.sample_len() {
+            log::debug!("media stream source overrun");
+        }
+
+        for c in 0..ring.channels {
+            let channel = buffer.try_channel_data(c).map(ChannelData::as_slice);
+            for i in 0..frames {
+                let value = channel.map_or(0., |channel| channel[i]);
+                let index = (written.wrapping_add(i) % ring.capacity) * ring.channels + c;
+                ring.samples[index].store(value.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        ring.written
+            .store(written.wrapping_add(frames), Ordering::Release);
+
+        frames
+    }
+
+    /// Number of sample-frames queued and not yet played
+    pub fn len(&self) -> usize {
+        let ring = &self.ring;
+        ring.written
+            .load(Ordering::Relaxed)
+            .wrapping_sub(ring.read.load(Ordering::Acquire))
+    }
+
+    /// Indicates if no sample-frames are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for MediaStreamSource {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Consumer side of a [`MediaStreamSource`], to be used as a [`MediaStream`]
+///
+/// It yields chunks of [`BUFFER_SIZE`] sample-frames, or a [`BufferDepletedError`] when not
+/// enough audio is queued. The stream ends when the producer is dropped and the queue is empty.
+pub struct MediaStreamSourceReader {
+    ring: Arc<RingBuffer>,
+    sample_rate: SampleRate,
+}
+
+impl Iterator for MediaStreamSourceReader {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ring = &self.ring;
+        // check closed before the queue length, so no frames written before closing are missed
+        let closed = ring.closed.load(Ordering::Acquire);
+        let read = ring.read.load(Ordering::Relaxed);
+        let available = ring.written.load(Ordering::Acquire).wrapping_sub(read);
+
+        let len = BUFFER_SIZE as usize;
+        let frames = if available >= len {
+            len
+        } else if !closed {
+            log::debug!("media stream source underrun");
+            return Some(Err(Box::new(BufferDepletedError {})));
+        } else if available > 0 {
+            // last chunk, padded with silence
+            available
+        } else {
+            return None;
+        };
+
+        let mut buffer = AudioBuffer::new(ring.channels, len, self.sample_rate);
+        for c in 0..ring.channels {
+            let channel = buffer.channel_data_mut(c).as_mut_slice();
+            for (i, value) in channel.iter_mut().take(frames).enumerate() {
+                let index = (read.wrapping_add(i) % ring.capacity) * ring.channels + c;
+                *value = f32::from_bits(ring.samples[index].load(Ordering::Relaxed));
+            }
+        }
+
+        ring.read
+            .store(read.wrapping_add(frames), Ordering::Release);
+
+        Some(Ok(buffer))
+    }
+}
+
+#[cfg(all(not(test), feature = "cpal"))]
 pub(crate) struct MicrophoneRender {
     channels: usize,
     sample_rate: SampleRate,
     sender: Sender<AudioBuffer>,
 }
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "cpal"))]
 impl MicrophoneRender {
     pub fn new(channels: usize, sample_rate: SampleRate, sender: Sender<AudioBuffer>) -> Self {
         Self {
@@ -426,10 +710,12 @@ impl MicrophoneRender {
 /// node.start();
 /// ```
 ///
+#[cfg(feature = "ogg")]
 pub struct OggVorbisDecoder {
     stream: OggStreamReader<BufReader<File>>,
 }
 
+#[cfg(feature = "ogg")]
 impl OggVorbisDecoder {
     /// Try to construct a new instance from a [`File`]
     pub fn try_new(file: File) -> Result<Self, VorbisError> {
@@ -437,6 +723,7 @@ impl OggVorbisDecoder {
     }
 }
 
+#[cfg(feature = "ogg")]
 impl Iterator for OggVorbisDecoder {
     type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
 
@@ -482,6 +769,7 @@ impl Iterator for OggVorbisDecoder {
 /// node.start();
 /// ```
 ///
+#[cfg(feature = "wav")]
 pub struct WavDecoder {
     //stream: hound::WavIntoSamples<BufReader<File>, f32>,
     stream: Box<dyn Iterator<Item = Result<f32, hound::Error>> + Send>,
@@ -489,6 +777,7 @@ pub struct WavDecoder {
     sample_rate: SampleRate,
 }
 
+#[cfg(feature = "wav")]
 impl WavDecoder {
     /// Try to construct a new instance from a [`File`]
     pub fn try_new(file: File) -> Result<Self, hound::Error> {
@@ -518,6 +807,7 @@ impl WavDecoder {
     }
 }
 
+#[cfg(feature = "wav")]
 impl Iterator for WavDecoder {
     type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
 
@@ -548,3 +838,238 @@ impl Iterator for WavDecoder {
         Some(Ok(result))
     }
 }
+
+/// Error returned when the microphone input stream cannot be set up
+#[derive(Debug)]
+pub enum MicrophoneError {
+    /// There is no input device
+    NotFound,
+    /// The input device could not be opened, e.g. because access was denied or it was
+    /// disconnected
+    Unavailable(String),
+}
+
+impl MicrophoneError {
+    /// Wraps an error of the audio backend
+    #[cfg(all(not(test), feature = "cpal"))]
+    pub(crate) fn unavailable<E: Error>(e: E) -> Self {
+        Self::Unavailable(e.to_string())
+    }
+}
+
+impl fmt::Display for MicrophoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "NotFoundError: no input device available"),
+            Self::Unavailable(e) => write!(f, "NotReadableError: {}", e),
+        }
+    }
+}
+
+impl Error for MicrophoneError {}
+
+/// Error returned when decoding an encoded audio file fails
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The encoded data could not be read
+    Io(std::io::Error),
+    /// The data is not in a supported format, or support for its format is not enabled
+    UnsupportedFormat,
+    /// The data is corrupt or uses an unsupported variant of its format
+    InvalidData(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read the encoded data: {}", e),
+            Self::UnsupportedFormat => write!(
+                f,
+                "EncodingError: unsupported audio format, enabled formats: {}",
+                ENABLED_FORMATS.join(", ")
+            ),
+            Self::InvalidData(e) => write!(f, "EncodingError: {}", e),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Audio formats supported by [`decode_audio_data`], depending on the enabled features
+const ENABLED_FORMATS: &[&str] = &[
+    #[cfg(feature = "wav")]
+    "wav",
+    #[cfg(feature = "ogg")]
+    "ogg",
+    #[cfg(feature = "mp3")]
+    "mp3",
+];
+
+/// Decode a complete WAV, Ogg Vorbis or MP3 file, and resample it to `sample_rate`
+///
+/// The format is detected from the content. The file is resampled at once rather than chunk by
+/// chunk (as a [`Resampler`](crate::buffer::Resampler) would), so no seams appear at the chunk
+/// boundaries of the decoder.
+pub(crate) fn decode_audio_data<R: Read>(
+    mut input: R,
+    sample_rate: SampleRate,
+) -> Result<AudioBuffer, DecodeError> {
+    let mut bytes = vec![];
+    input.read_to_end(&mut bytes)?;
+
+    let decoded: Result<AudioBuffer, _> = match bytes.get(..12) {
+        #[cfg(feature = "wav")]
+        Some(header) if &header[..4] == b"RIFF" && &header[8..] == b"WAVE" => decode_wav(bytes),
+        #[cfg(feature = "ogg")]
+        Some(header) if &header[..4] == b"OggS" => decode_ogg(bytes),
+        // ID3v2 tag or MPEG audio frame sync
+        #[cfg(feature = "mp3")]
+        Some(header) if &header[..3] == b"ID3" || (header[0] == 0xFF && header[1] >= 0xE0) => {
+            decode_mp3(bytes)
+        }
+        _ => Err(DecodeError::UnsupportedFormat),
+    };
+
+    let mut buffer = decoded?;
+
+    buffer.resample(sample_rate);
+    Ok(buffer)
+}
+
+#[cfg(feature = "wav")]
+fn decode_wav(bytes: Vec<u8>) -> Result<AudioBuffer, DecodeError> {
+    let invalid = |e: hound::Error| DecodeError::InvalidData(e.to_string());
+
+    let wav = hound::WavReader::new(Cursor::new(bytes)).map_err(invalid)?;
+    let spec = wav.spec();
+    let channels = usize::from(spec.channels);
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => wav.into_samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            // full scale integers map to [-1, 1)
+            let scale = 1. / (1_u64 << (spec.bits_per_sample - 1)) as f32;
+            wav.into_samples::<i32>()
+                .map(|r| r.map(|v| v as f32 * scale))
+                .collect()
+        }
+    }
+    .map_err(invalid)?;
+
+    let channel_data = (0..channels)
+        .map(|c| {
+            let data = samples.iter().skip(c).step_by(channels).copied().collect();
+            ChannelData::from(data)
+        })
+        .collect();
+
+    Ok(AudioBuffer::from_channels(
+        channel_data,
+        SampleRate(spec.sample_rate),
+    ))
+}
+
+#[cfg(feature = "ogg")]
+fn decode_ogg(bytes: Vec<u8>) -> Result<AudioBuffer, DecodeError> {
+    let invalid = |e: VorbisError| DecodeError::InvalidData(e.to_string());
+
+    let mut stream = OggStreamReader::new(Cursor::new(bytes)).map_err(invalid)?;
+    let channels = usize::from(stream.ident_hdr.audio_channels);
+    let sample_rate = SampleRate(stream.ident_hdr.audio_sample_rate);
+
+    let mut data = vec![vec![]; channels];
+    while let Some(packet) = stream
+        .read_dec_packet_generic::<Vec<Vec<f32>>>()
+        .map_err(invalid)?
+    {
+        data.iter_mut()
+            .zip(packet)
+            .for_each(|(channel, samples)| channel.extend(samples));
+    }
+
+    let channel_data = data.into_iter().map(ChannelData::from).collect();
+    Ok(AudioBuffer::from_channels(channel_data, sample_rate))
+}
+
+#[cfg(feature = "mp3")]
+fn decode_mp3(bytes: Vec<u8>) -> Result<AudioBuffer, DecodeError> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error as SymphoniaError;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let invalid = |e: SymphoniaError| DecodeError::InvalidData(e.to_string());
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    // trim the encoder delay and padding, if the file reports them
+    let format_options = FormatOptions {
+        enable_gapless: true,
+        ..FormatOptions::default()
+    };
+    let mut reader = symphonia::default::get_probe()
+        .format(
+            Hint::new().with_extension("mp3"),
+            source,
+            &format_options,
+            &MetadataOptions::default(),
+        )
+        .map_err(invalid)?
+        .format;
+
+    let params = reader
+        .default_track()
+        .ok_or_else(|| DecodeError::InvalidData("no audio track".into()))?
+        .codec_params
+        .clone();
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(invalid)?;
+
+    let mut data: Vec<Vec<f32>> = vec![];
+    let mut sample_rate = params.sample_rate;
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(invalid(e)),
+        };
+
+        let decoded = decoder.decode(&packet).map_err(invalid)?;
+        let spec = *decoded.spec();
+        let frames = decoded.frames();
+        sample_rate = Some(spec.rate);
+
+        // planar layout: the frames of each channel follow each other
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_planar_ref(decoded);
+        data.resize(spec.channels.count(), vec![]);
+        data.iter_mut()
+            .zip(samples.samples().chunks(frames))
+            .for_each(|(channel, samples)| channel.extend_from_slice(samples));
+    }
+
+    let sample_rate = sample_rate.ok_or_else(|| DecodeError::InvalidData("no audio".into()))?;
+    let channel_data = data.into_iter().map(ChannelData::from).collect();
+    Ok(AudioBuffer::from_channels(
+        channel_data,
+        SampleRate(sample_rate),
+    ))
+}
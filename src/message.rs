@@ -1,8 +1,65 @@
 use crate::buffer::ChannelConfig;
 use crate::param::AutomationEvent;
 use crate::process::AudioProcessor;
+#[cfg(any(test, feature = "cpal"))]
+use crate::SampleRate;
 
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, Sender};
+
+#[cfg(any(test, feature = "debug"))]
+use std::sync::{Arc, Mutex};
+
+/// Latency (in sample-frames) from each node id to the destination, published by the render thread
+pub(crate) type Latencies = Vec<(u64, f64)>;
+
+/// Render thread side of the latency handoff
+///
+/// Three tables circulate: one held by the render thread, one by the control thread, and one
+/// either published or recycled. The render thread never allocates nor blocks to publish.
+pub(crate) struct LatencyPublisher {
+    /// tables filled by the render thread
+    pub published: Sender<Latencies>,
+    /// tables returned by the control thread once replaced by a newer one
+    pub recycled: Receiver<Latencies>,
+}
+
+/// Control thread side of the latency handoff
+pub(crate) struct LatencyReader {
+    published: Receiver<Latencies>,
+    recycled: Sender<Latencies>,
+    current: Latencies,
+}
+
+impl LatencyReader {
+    /// The most recent table published by the render thread
+    pub fn latest(&mut self) -> &Latencies {
+        while let Ok(table) = self.published.try_recv() {
+            let previous = std::mem::replace(&mut self.current, table);
+            let _ = self.recycled.try_send(previous);
+        }
+
+        &self.current
+    }
+}
+
+/// Set up the handoff of the latency tables from the render to the control thread
+pub(crate) fn latency_channel() -> (LatencyPublisher, LatencyReader) {
+    let (published, published_rx) = crossbeam_channel::bounded(1);
+    let (recycled_tx, recycled) = crossbeam_channel::bounded(1);
+    recycled_tx.send(vec![]).unwrap();
+
+    let publisher = LatencyPublisher {
+        published,
+        recycled,
+    };
+    let reader = LatencyReader {
+        published: published_rx,
+        recycled: recycled_tx,
+        current: vec![],
+    };
+
+    (publisher, reader)
+}
 
 /// Commands from the control thread to the render thread
 pub(crate) enum ControlMessage {
@@ -21,9 +78,24 @@ pub(crate) enum ControlMessage {
         output: u32,
     },
 
+    /// Connect an AudioParam (or other control node) to the node it controls. It is rendered
+    /// before `to`, but its output is not mixed into the inputs of `to`.
+    ConnectParam {
+        from: u64,
+        to: u64,
+    },
+
+    /// Remove the connections from `from` to `to`, optionally only those of the given ports
     DisconnectNode {
         from: u64,
         to: u64,
+        output: Option<u32>,
+        input: Option<u32>,
+    },
+
+    DisconnectOutput {
+        from: u64,
+        output: u32,
     },
 
     DisconnectAll {
@@ -38,4 +110,28 @@ pub(crate) enum ControlMessage {
         to: Sender<AutomationEvent>,
         event: AutomationEvent,
     },
+
+    #[cfg(any(test, feature = "cpal"))]
+    SampleRateChange {
+        sample_rate: SampleRate,
+    },
+
+    /// Share a channel the render thread publishes the latency (in sample-frames) from each node
+    /// to the destination to, whenever they change
+    ShareLatencies {
+        latencies: LatencyPublisher,
+    },
+
+    /// Share a channel the render thread reports the ids of the nodes it frees to, so the control
+    /// thread can forget their connections
+    ShareFreedNodes {
+        freed: Sender<u64>,
+    },
+
+    /// Share a list the render thread fills with the node ids in processing order, each time
+    /// the graph is (re)ordered
+    #[cfg(any(test, feature = "debug"))]
+    ShareRenderOrder {
+        render_order: Arc<Mutex<Vec<u64>>>,
+    },
 }
@@ -1,7 +1,7 @@
 use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::analysis::Analyser;
+use crate::analysis::{Analyser, AnalyserRingBuffer, FftProcessor};
 use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration};
 use crate::process::{AudioParamValues, AudioProcessor};
@@ -15,11 +15,13 @@ use super::AudioNode;
 pub struct AnalyserOptions {
     pub fft_size: usize,
     pub smoothing_time_constant: f32,
-    /*
+    /// Upper bound of the range for the byte frequency data (dB)
     pub max_decibels: f32,
+    /// Lower bound of the range for the byte frequency data (dB)
     pub min_decibels: f32,
-    */
     pub channel_config: ChannelConfigOptions,
+    /// FFT backend for the frequency-domain analysis, `None` selects the default backend
+    pub fft_backend: Option<Box<dyn FftProcessor>>,
 }
 
 impl Default for AnalyserOptions {
@@ -27,24 +29,23 @@ impl Default for AnalyserOptions {
         Self {
             fft_size: 2048,
             smoothing_time_constant: 0.8,
-            /*
             max_decibels: -30.,
-            min_decibels: 100.,
-            */
+            min_decibels: -100.,
             channel_config: ChannelConfigOptions::default(),
+            fft_backend: None,
         }
     }
 }
 
 enum AnalyserRequest {
-    FloatTime {
-        sender: Sender<Vec<f32>>,
-        buffer: Vec<f32>,
-    },
     FloatFrequency {
         sender: Sender<Vec<f32>>,
         buffer: Vec<f32>,
     },
+    ByteFrequency {
+        sender: Sender<Vec<u8>>,
+        buffer: Vec<u8>,
+    },
 }
 
 /// Provides real-time frequency and time-domain analysis information
@@ -53,11 +54,10 @@ pub struct AnalyserNode {
     channel_config: ChannelConfig,
     fft_size: Arc<AtomicUsize>,
     smoothing_time_constant: Arc<AtomicU32>,
+    max_decibels: Arc<AtomicU32>,
+    min_decibels: Arc<AtomicU32>,
+    ring_buffer: Arc<AnalyserRingBuffer>,
     sender: Sender<AnalyserRequest>,
-    /*
-    max_decibels: f32,
-    min_decibels: f32,
-    */
 }
 
 impl AudioNode for AnalyserNode {
@@ -78,19 +78,37 @@ impl AudioNode for AnalyserNode {
 }
 
 impl AnalyserNode {
+    /// # Panics
+    ///
+    /// Will panic if the FFT size, the smoothing time constant or the decibels range are invalid,
+    /// see [`set_fft_size`](Self::set_fft_size),
+    /// [`set_smoothing_time_constant`](Self::set_smoothing_time_constant) and
+    /// [`set_decibels_range`](Self::set_decibels_range)
     pub fn new<C: AsBaseAudioContext>(context: &C, options: AnalyserOptions) -> Self {
+        assert_valid_fft_size(options.fft_size);
+        assert_valid_smoothing_time_constant(options.smoothing_time_constant);
+        assert_valid_decibels_range(options.min_decibels, options.max_decibels);
+
         context.base().register(move |registration| {
             let fft_size = Arc::new(AtomicUsize::new(options.fft_size));
-            let smoothing_time_constant = Arc::new(AtomicU32::new(
-                (options.smoothing_time_constant * 100.) as u32,
-            ));
+            let smoothing_time_constant =
+                Arc::new(AtomicU32::new(options.smoothing_time_constant.to_bits()));
+            let max_decibels = Arc::new(AtomicU32::new(options.max_decibels.to_bits()));
+            let min_decibels = Arc::new(AtomicU32::new(options.min_decibels.to_bits()));
 
+            let ring_buffer = Arc::new(AnalyserRingBuffer::new());
             let (sender, receiver) = crossbeam_channel::bounded(0);
 
             let render = AnalyserRenderer {
-                analyser: Analyser::new(options.fft_size),
+                analyser: match options.fft_backend {
+                    Some(fft) => Analyser::with_fft(options.fft_size, fft),
+                    None => Analyser::new(options.fft_size),
+                },
                 fft_size: fft_size.clone(),
                 smoothing_time_constant: smoothing_time_constant.clone(),
+                max_decibels: max_decibels.clone(),
+                min_decibels: min_decibels.clone(),
+                ring_buffer: ring_buffer.clone(),
                 receiver,
             };
 
@@ -99,6 +117,9 @@ impl AnalyserNode {
                 channel_config: options.channel_config.into(),
                 fft_size,
                 smoothing_time_constant,
+                max_decibels,
+                min_decibels,
+                ring_buffer,
                 sender,
             };
 
@@ -117,44 +138,153 @@ impl AnalyserNode {
     }
 
     /// This MUST be a power of two in the range 32 to 32768
+    ///
+    /// The render thread picks up the new size at the next render quantum, and restarts the
+    /// frequency analysis. Until `fft_size` samples have been received, the time domain data is
+    /// padded with zeros at the start.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `fft_size` is not a power of two in the range 32 to 32768
     pub fn set_fft_size(&self, fft_size: usize) {
-        // todo assert size
+        assert_valid_fft_size(fft_size);
         self.fft_size.store(fft_size, Ordering::SeqCst);
     }
 
     /// Time averaging parameter with the last analysis frame.
     pub fn smoothing_time_constant(&self) -> f32 {
-        self.smoothing_time_constant.load(Ordering::SeqCst) as f32 / 100.
+        f32::from_bits(self.smoothing_time_constant.load(Ordering::SeqCst))
     }
 
     /// Set smoothing time constant, this MUST be a value between 0 and 1
+    ///
+    /// Each frequency analysis frame is blended with the previous one: 0 disables the averaging,
+    /// values closer to 1 give a smoother but slower spectrum.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the value is outside the range `[0, 1]`
     pub fn set_smoothing_time_constant(&self, v: f32) {
-        // todo assert range
+        assert_valid_smoothing_time_constant(v);
         self.smoothing_time_constant
-            .store((v * 100.) as u32, Ordering::SeqCst);
+            .store(v.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Upper bound of the range for the byte frequency data (dB)
+    pub fn max_decibels(&self) -> f32 {
+        f32::from_bits(self.max_decibels.load(Ordering::SeqCst))
+    }
+
+    /// Lower bound of the range for the byte frequency data (dB)
+    pub fn min_decibels(&self) -> f32 {
+        f32::from_bits(self.min_decibels.load(Ordering::SeqCst))
+    }
+
+    /// Set the range for the byte frequency data (dB)
+    ///
+    /// Both bounds are set at once, so the range stays valid in between.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `min_decibels` is not less than `max_decibels`
+    pub fn set_decibels_range(&self, min_decibels: f32, max_decibels: f32) {
+        assert_valid_decibels_range(min_decibels, max_decibels);
+        self.min_decibels
+            .store(min_decibels.to_bits(), Ordering::SeqCst);
+        self.max_decibels
+            .store(max_decibels.to_bits(), Ordering::SeqCst);
     }
 
     /// Copies the current time domain data (waveform data) into the provided buffer
-    pub fn get_float_time_domain_data(&self, buffer: Vec<f32>) -> Vec<f32> {
+    ///
+    /// The most recent samples are copied, at most [`fft_size`](Self::fft_size) of them: if the
+    /// buffer is longer, the excess elements are left unaltered. When fewer samples were
+    /// received, e.g. shortly after connecting, the start of the buffer is padded with zeros.
+    ///
+    /// The samples are read from a history shared with the render thread, without waiting for
+    /// the next render quantum.
+    pub fn get_float_time_domain_data(&self, buffer: &mut [f32]) {
+        let len = buffer.len().min(self.fft_size());
+        self.ring_buffer.read(&mut buffer[..len]);
+    }
+
+    /// Copies the current time domain data (waveform data) into the provided buffer, as
+    /// unsigned bytes
+    ///
+    /// The range `[-1, 1]` is mapped to `[0, 255]`, values outside the range are clamped. Silence
+    /// and padding are 128. The length relationship to the FFT size is the same as for
+    /// [`get_float_time_domain_data`](Self::get_float_time_domain_data).
+    pub fn get_byte_time_domain_data(&self, buffer: &mut [u8]) {
+        let len = buffer.len().min(self.fft_size());
+        let mut samples = vec![0.; len];
+        self.ring_buffer.read(&mut samples);
+
+        buffer
+            .iter_mut()
+            .zip(samples)
+            .for_each(|(b, s)| *b = (128. * (1. + s)).floor().clamp(0., 255.) as u8);
+    }
+
+    /// Copies the current frequency data into the provided buffer, in dB
+    ///
+    /// The analysis yields [`frequency_bin_count`](Self::frequency_bin_count) values, i.e. half
+    /// the FFT size. If the buffer is shorter, the excess bins are dropped. If it is longer, the
+    /// excess elements are left unaltered.
+    pub fn get_float_frequency_data(&self, buffer: &mut [f32]) {
         let (sender, receiver) = crossbeam_channel::bounded(0);
-        let request = AnalyserRequest::FloatTime { sender, buffer };
+        let request = AnalyserRequest::FloatFrequency {
+            sender,
+            buffer: buffer.to_vec(),
+        };
         self.sender.send(request).unwrap();
-        receiver.recv().unwrap()
+        buffer.copy_from_slice(&receiver.recv().unwrap());
     }
 
-    /// Copies the current frequency data into the provided buffer
-    pub fn get_float_frequency_data(&self, buffer: Vec<f32>) -> Vec<f32> {
+    /// Copies the current frequency data into the provided buffer, as unsigned bytes
+    ///
+    /// The dB values are mapped from the [`min_decibels`](Self::min_decibels) to
+    /// [`max_decibels`](Self::max_decibels) range to `[0, 255]`, values outside the range are
+    /// clamped. The length relationship to the FFT size is the same as for
+    /// [`get_float_frequency_data`](Self::get_float_frequency_data).
+    pub fn get_byte_frequency_data(&self, buffer: &mut [u8]) {
         let (sender, receiver) = crossbeam_channel::bounded(0);
-        let request = AnalyserRequest::FloatFrequency { sender, buffer };
+        let request = AnalyserRequest::ByteFrequency {
+            sender,
+            buffer: buffer.to_vec(),
+        };
         self.sender.send(request).unwrap();
-        receiver.recv().unwrap()
+        buffer.copy_from_slice(&receiver.recv().unwrap());
     }
 }
 
+fn assert_valid_fft_size(fft_size: usize) {
+    assert!(
+        fft_size.is_power_of_two() && (32..=32768).contains(&fft_size),
+        "IndexSizeError: fft size should be a power of two in the range 32 to 32768"
+    );
+}
+
+fn assert_valid_smoothing_time_constant(v: f32) {
+    assert!(
+        (0. ..=1.).contains(&v),
+        "IndexSizeError: smoothing time constant should be in the range [0, 1]"
+    );
+}
+
+fn assert_valid_decibels_range(min_decibels: f32, max_decibels: f32) {
+    assert!(
+        min_decibels < max_decibels,
+        "IndexSizeError: min decibels should be less than max decibels"
+    );
+}
+
 struct AnalyserRenderer {
     pub analyser: Analyser,
     pub fft_size: Arc<AtomicUsize>,
     pub smoothing_time_constant: Arc<AtomicU32>,
+    pub max_decibels: Arc<AtomicU32>,
+    pub min_decibels: Arc<AtomicU32>,
+    pub ring_buffer: Arc<AnalyserRingBuffer>,
     pub receiver: Receiver<AnalyserRequest>,
 }
 
@@ -182,6 +312,7 @@ impl AudioProcessor for AnalyserRenderer {
         let mut mono = input.clone();
         mono.mix(1, ChannelInterpretation::Speakers);
         let mono_data = mono.channel_data(0).clone();
+        self.ring_buffer.write(&mono_data[..]);
         self.analyser.add_data(mono_data);
 
         // calculate frequency domain every `fft_size` samples
@@ -190,7 +321,7 @@ impl AudioProcessor for AnalyserRenderer {
         let complete_cycle = self.analyser.check_complete_cycle(fft_size);
         if resized || complete_cycle {
             let smoothing_time_constant =
-                self.smoothing_time_constant.load(Ordering::Relaxed) as f32 / 100.;
+                f32::from_bits(self.smoothing_time_constant.load(Ordering::Relaxed));
             self.analyser
                 .calculate_float_frequency(fft_size, smoothing_time_constant);
         }
@@ -198,14 +329,17 @@ impl AudioProcessor for AnalyserRenderer {
         // check if any information was requested from the control thread
         if let Ok(request) = self.receiver.try_recv() {
             match request {
-                AnalyserRequest::FloatTime { sender, mut buffer } => {
-                    self.analyser.get_float_time(&mut buffer[..], fft_size);
+                AnalyserRequest::FloatFrequency { sender, mut buffer } => {
+                    self.analyser.get_float_frequency(&mut buffer[..]);
 
                     // allow to fail when receiver is disconnected
                     let _ = sender.send(buffer);
                 }
-                AnalyserRequest::FloatFrequency { sender, mut buffer } => {
-                    self.analyser.get_float_frequency(&mut buffer[..]);
+                AnalyserRequest::ByteFrequency { sender, mut buffer } => {
+                    let min_decibels = f32::from_bits(self.min_decibels.load(Ordering::Relaxed));
+                    let max_decibels = f32::from_bits(self.max_decibels.load(Ordering::Relaxed));
+                    self.analyser
+                        .get_byte_frequency(&mut buffer[..], min_decibels, max_decibels);
 
                     // allow to fail when receiver is disconnected
                     let _ = sender.send(buffer);
@@ -218,3 +352,72 @@ impl AudioProcessor for AnalyserRenderer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::SampleRate;
+
+    #[test]
+    fn test_time_domain_data() {
+        let mut context = OfflineAudioContext::new(1, 256, SampleRate(44_100));
+        let analyser = context.create_analyser();
+        analyser.set_fft_size(512);
+
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+        src.connect(&analyser);
+        analyser.connect(&context.destination());
+
+        // nothing received yet
+        let mut floats = [-1.; 1024];
+        analyser.get_float_time_domain_data(&mut floats);
+        assert_float_eq!(floats[..512], [0.; 512][..], abs_all <= 0.);
+
+        context.start_rendering();
+
+        // the history is zero padded, and only fft_size samples are copied
+        analyser.get_float_time_domain_data(&mut floats);
+        assert_float_eq!(floats[..256], [0.; 256][..], abs_all <= 0.);
+        assert_float_eq!(floats[256..512], [0.5; 256][..], abs_all <= 0.);
+        assert_float_eq!(floats[512..], [-1.; 512][..], abs_all <= 0.);
+
+        // smaller buffers get the most recent samples
+        let mut bytes = [0; 300];
+        analyser.get_byte_time_domain_data(&mut bytes);
+        assert_eq!(bytes[..44], [128; 44]);
+        assert_eq!(bytes[44..], [192; 256]);
+    }
+
+    #[test]
+    fn test_fft_size() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let analyser = context.create_analyser();
+        assert_eq!(analyser.fft_size(), 2048);
+        assert_eq!(analyser.frequency_bin_count(), 1024);
+
+        analyser.set_fft_size(32);
+        assert_eq!(analyser.fft_size(), 32);
+        assert_eq!(analyser.frequency_bin_count(), 16);
+
+        analyser.set_fft_size(32768);
+        assert_eq!(analyser.frequency_bin_count(), 16384);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_fft_size_not_power_of_two() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        context.create_analyser().set_fft_size(1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_fft_size_out_of_range() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        context.create_analyser().set_fft_size(16);
+    }
+}
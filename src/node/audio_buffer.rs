@@ -1,12 +1,13 @@
-use crate::buffer::{AudioBuffer, ChannelConfig, ChannelConfigOptions, Resampler};
-use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use std::sync::Arc;
+
+use crate::buffer::{AudioBuffer, ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
 use crate::control::{Controller, Scheduler};
-use crate::media::MediaElement;
-use crate::{SampleRate, BUFFER_SIZE};
+use crate::param::{AudioParam, AudioParamOptions, AutomationRate};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{AtomicF64, SampleRate, BUFFER_SIZE};
 
-use super::{
-    AudioControllableSourceNode, AudioNode, AudioScheduledSourceNode, MediaStreamRenderer,
-};
+use super::{AudioControllableSourceNode, AudioNode, AudioScheduledSourceNode};
 
 /// Options for constructing a AudioBufferSourceNode
 #[derive(Default)]
@@ -18,10 +19,21 @@ pub struct AudioBufferSourceNodeOptions {
 /// An audio source from an in-memory audio asset in an AudioBuffer
 ///
 /// Note: do not forget to `start()` the node.
+///
+/// The `playback_rate` and `detune` params set the speed of the read head through the buffer,
+/// which is `playback_rate * 2^(detune / 1200)`. The buffer is read with linear interpolation
+/// between sample-frames.
+///
+/// With a negative rate the buffer is played in reverse, starting from the position set with
+/// `seek` (so without seeking, playback ends immediately). When looping, the read head wraps
+/// within the loop region in both directions.
 pub struct AudioBufferSourceNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
     controller: Controller,
+    playback_rate: AudioParam,
+    detune: AudioParam,
+    loop_crossfade: Arc<AtomicF64>,
 }
 
 impl AudioScheduledSourceNode for AudioBufferSourceNode {
@@ -60,28 +72,610 @@ impl AudioBufferSourceNode {
                 .buffer
                 .unwrap_or_else(|| AudioBuffer::new(1, BUFFER_SIZE as usize, SampleRate(44_100)));
 
-            // wrap input in resampler
-            let resampler = Resampler::new(
-                context.base().sample_rate(),
-                BUFFER_SIZE,
-                std::iter::once(Ok(buffer)),
-            );
+            let param_opts = AudioParamOptions {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (playback_rate, playback_rate_proc) = context
+                .base()
+                .create_audio_param(param_opts, registration.id());
+
+            let detune_opts = AudioParamOptions {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+            let (detune, detune_proc) = context
+                .base()
+                .create_audio_param(detune_opts, registration.id());
 
-            // wrap resampler in media-element (for loop/play/pause)
-            let media = MediaElement::new(resampler);
-            let controller = media.controller().clone();
-            let scheduler = controller.scheduler().clone();
+            let controller = Controller::new();
+            let loop_crossfade = Arc::new(AtomicF64::new(0.));
 
             // setup user facing audio node
             let node = AudioBufferSourceNode {
                 registration,
                 channel_config: options.channel_config.into(),
-                controller,
+                controller: controller.clone(),
+                playback_rate,
+                detune,
+                loop_crossfade: loop_crossfade.clone(),
             };
 
-            let render = MediaStreamRenderer::new(media, scheduler);
+            let render = AudioBufferSourceRenderer {
+                buffer,
+                controller,
+                playback_rate: playback_rate_proc,
+                detune: detune_proc,
+                loop_crossfade,
+                position: 0.,
+                finished: false,
+            };
 
             (node, Box::new(render))
         })
     }
+
+    /// Speed at which the buffer is played, negative values play the buffer in reverse (k-rate)
+    pub fn playback_rate(&self) -> &AudioParam {
+        &self.playback_rate
+    }
+
+    /// Pitch shift of the playback in cents, combined with the `playback_rate` (k-rate)
+    pub fn detune(&self) -> &AudioParam {
+        &self.detune
+    }
+
+    /// Duration in seconds of the crossfade at the end of the loop region
+    pub fn loop_crossfade(&self) -> f32 {
+        self.loop_crossfade.load() as f32
+    }
+
+    /// Crossfade the end of the loop region into its start, to avoid clicks when the loop
+    /// boundaries are not at zero crossings
+    ///
+    /// Over the last `seconds` of the loop region, the output fades linearly from the
+    /// buffer into the frames preceding the loop start, so the signal is continuous when the read
+    /// head wraps. The crossfade is limited to the length of the loop region and to the loop start
+    /// position, as there is nothing to fade in before the start of the buffer. The default of
+    /// zero disables the crossfade.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `seconds` is negative
+    pub fn set_loop_crossfade(&self, seconds: f32) {
+        assert!(
+            seconds >= 0.,
+            "RangeError: crossfade duration should not be negative"
+        );
+        self.loop_crossfade.store(f64::from(seconds));
+    }
+
+    /// Schedule playback of a slice of the buffer
+    ///
+    /// Playback starts at time `when`, reading the buffer from `offset` seconds. With a
+    /// `duration`, playback stops `duration` seconds later (in context time). An `offset` past
+    /// the end of the buffer results in silence. When looping, an `offset` past the loop end wraps
+    /// into the loop region.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `when`, `offset` or `duration` is negative
+    pub fn start_at_with_offset(&self, when: f64, offset: f64, duration: Option<f64>) {
+        assert!(when >= 0., "RangeError: start time should not be negative");
+        assert!(offset >= 0., "RangeError: offset should not be negative");
+
+        if let Some(duration) = duration {
+            assert!(
+                duration >= 0.,
+                "RangeError: duration should not be negative"
+            );
+            self.stop_at(when + duration);
+        }
+
+        self.seek(offset);
+        self.start_at(when);
+    }
+}
+
+struct AudioBufferSourceRenderer {
+    buffer: AudioBuffer,
+    controller: Controller,
+    playback_rate: AudioParamId,
+    detune: AudioParamId,
+    /// crossfade duration at the end of the loop region, in seconds
+    loop_crossfade: Arc<AtomicF64>,
+    /// position of the read head in the buffer, in (fractional) sample-frames
+    ///
+    /// Playing forward, the frame after the read head is played. In reverse, the frame before it.
+    position: f64,
+    finished: bool,
+}
+
+impl AudioBufferSourceRenderer {
+    /// Loop region in sample-frames of the buffer, `None` if looping is disabled
+    ///
+    /// An empty or invalid loop region spans the whole buffer.
+    fn loop_region(&self) -> Option<(f64, f64)> {
+        let len = self.buffer.sample_len() as f64;
+        if !self.controller.loop_() || len == 0. {
+            return None;
+        }
+
+        let sample_rate = f64::from(self.buffer.sample_rate().0);
+        let start = (self.controller.loop_start() * sample_rate)
+            .max(0.)
+            .min(len);
+        let end = (self.controller.loop_end() * sample_rate).max(0.).min(len);
+
+        if start < end {
+            Some((start, end))
+        } else {
+            Some((0., len))
+        }
+    }
+
+    /// Value of the channel at the fractional index, linearly interpolated
+    fn sample_at(&self, channel: usize, index: f64, region: Option<(f64, f64)>) -> f32 {
+        let data = self.buffer.channel_data(channel).as_slice();
+        let floor = index.floor();
+        let frac = (index - floor) as f32;
+        let current = floor as usize;
+
+        if frac == 0. {
+            return data[current];
+        }
+
+        // the next frame wraps around to the loop start
+        let next = match region {
+            Some((start, end)) if floor + 1. >= end => start as usize,
+            _ => (current + 1).min(data.len() - 1),
+        };
+
+        data[current] + frac * (data[next] - data[current])
+    }
+
+    /// Length of the loop crossfade in sample-frames, clamped to the loop region and to the
+    /// frames available before the loop start
+    fn crossfade_frames(&self, region: Option<(f64, f64)>) -> f64 {
+        region.map_or(0., |(start, end)| {
+            let sample_rate = f64::from(self.buffer.sample_rate().0);
+            (self.loop_crossfade.load() * sample_rate)
+                .min(end - start)
+                .min(start)
+        })
+    }
+
+    /// Value of the channel at the fractional index, crossfaded with the frames preceding the
+    /// loop start near the end of the loop region
+    fn looped_sample_at(
+        &self,
+        channel: usize,
+        index: f64,
+        region: Option<(f64, f64)>,
+        crossfade: f64,
+    ) -> f32 {
+        let value = self.sample_at(channel, index, region);
+
+        match region {
+            Some((start, end)) if crossfade > 0. && index >= end - crossfade => {
+                let gain = ((index - (end - crossfade)) / crossfade) as f32;
+                let fade_in = self.sample_at(channel, index - (end - start), None);
+                value + gain * (fade_in - value)
+            }
+            _ => value,
+        }
+    }
+}
+
+impl AudioProcessor for AudioBufferSourceRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        params: AudioParamValues,
+        timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single output node
+        let output = &mut outputs[0];
+
+        // todo, sub-quantum start
+        if self.finished || !self.controller.scheduler().is_active(timestamp) {
+            output.make_silent();
+            return;
+        }
+
+        let buffer_sample_rate = f64::from(self.buffer.sample_rate().0);
+        let playback_rate = f64::from(params.get_value(&self.playback_rate));
+        let detune = f64::from(params.get_value(&self.detune));
+        let rate = playback_rate * (detune / 1200.).exp2();
+        let step = rate * buffer_sample_rate / f64::from(sample_rate.0);
+        let reverse = step < 0.;
+        let region = self.loop_region();
+        let crossfade = self.crossfade_frames(region);
+        let len = self.buffer.sample_len() as f64;
+
+        if let Some(seek) = self.controller.should_seek() {
+            self.position = if reverse {
+                // reverse playback enters the loop at its end, from the end of the buffer at most
+                (seek * buffer_sample_rate).min(len)
+            } else {
+                self.controller.wrap_into_loop(seek, self.buffer.duration()) * buffer_sample_rate
+            };
+        }
+
+        // read positions for this render quantum, `None` after the end of playback
+        let mut read_positions = [None; BUFFER_SIZE as usize];
+        for read_position in read_positions.iter_mut() {
+            match region {
+                Some((start, end)) => {
+                    if !reverse && self.position >= end {
+                        self.position = start + (self.position - end).rem_euclid(end - start);
+                    } else if reverse && self.position <= start {
+                        self.position = end - (start - self.position).rem_euclid(end - start);
+                    }
+                }
+                None => {
+                    if (!reverse && self.position >= len) || (reverse && self.position <= 0.) {
+                        self.finished = true;
+                        self.controller.scheduler().mark_ended();
+                        break;
+                    }
+                }
+            }
+
+            let index = if reverse {
+                self.position - 1.
+            } else {
+                self.position
+            };
+            *read_position = Some(index.max(0.));
+            self.position += step;
+        }
+
+        output.set_number_of_channels(self.buffer.number_of_channels());
+        for channel in 0..self.buffer.number_of_channels() {
+            let values = read_positions.iter().map(|read_position| {
+                read_position.map_or(0., |index| {
+                    self.looped_sample_at(channel, index, region, crossfade)
+                })
+            });
+            output
+                .channel_data_mut(channel)
+                .iter_mut()
+                .zip(values)
+                .for_each(|(o, v)| *o = v);
+        }
+
+        // silence the remainder of the render quantum after a stop
+        if let Some(index) = self
+            .controller
+            .scheduler()
+            .stop_index(timestamp, sample_rate)
+        {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| channel[index..].iter_mut().for_each(|v| *v = 0.));
+        }
+    }
+
+    fn tail_time(&self) -> bool {
+        !self.finished
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.;
+        self.finished = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::buffer::ChannelData;
+    use crate::context::OfflineAudioContext;
+    use crate::test_util::render_mono;
+
+    const SAMPLE_RATE: SampleRate = SampleRate(44_100);
+
+    /// Source playing a buffer containing the ramp 0, 1, .., 9
+    fn ramp_source(context: &OfflineAudioContext) -> AudioBufferSourceNode {
+        let ramp = (0..10).map(|i| i as f32).collect();
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(AudioBuffer::from_channels(
+                vec![ChannelData::from(ramp)],
+                SAMPLE_RATE,
+            )),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        AudioBufferSourceNode::new(context, options)
+    }
+
+    /// Render 16 frames of a buffer containing the ramp 0, 1, .., 9
+    fn render_ramp(
+        playback_rate: f32,
+        seek: Option<f64>,
+        loop_region: Option<(f64, f64)>,
+    ) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, 16, SAMPLE_RATE);
+        let src = ramp_source(&context);
+        src.playback_rate().set_value(playback_rate);
+
+        let frames = |frames: f64| frames / f64::from(SAMPLE_RATE.0);
+        if let Some(position) = seek {
+            src.seek(frames(position));
+        }
+        if let Some((start, end)) = loop_region {
+            src.set_loop(true);
+            src.set_loop_start(frames(start));
+            src.set_loop_end(frames(end));
+        }
+
+        src.connect(&context.destination());
+        src.start();
+
+        render_mono(&mut context)
+    }
+
+    /// Number of sample-frames played from a buffer of 1024 frames
+    fn played_frames(playback_rate: f32, detune: f32) -> usize {
+        let mut context = OfflineAudioContext::new(1, 2048, SAMPLE_RATE);
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(AudioBuffer::from_channels(
+                vec![ChannelData::from(vec![1.; 1024])],
+                SAMPLE_RATE,
+            )),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        src.playback_rate().set_value(playback_rate);
+        src.detune().set_value(detune);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering();
+        output
+            .channel_data(0)
+            .as_slice()
+            .iter()
+            .take_while(|&&v| v != 0.)
+            .count()
+    }
+
+    #[test]
+    fn test_playback_rate_event_at_quantum_start() {
+        let mut context = OfflineAudioContext::new(1, 256, SAMPLE_RATE);
+        let ramp = (0..1024).map(|i| i as f32).collect();
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(AudioBuffer::from_channels(
+                vec![ChannelData::from(ramp)],
+                SAMPLE_RATE,
+            )),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+
+        // the k-rate playback rate reverses exactly at the second render quantum
+        src.playback_rate()
+            .set_value_at_time(-1., 128. / f64::from(SAMPLE_RATE.0));
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+        // forward up to the event, then reversed from the same read head for the whole quantum
+        assert_float_eq!(output[127], 127., abs <= 1e-4);
+        assert_float_eq!(output[128], 127., abs <= 1e-4);
+        assert_float_eq!(output[129], 126., abs <= 1e-4);
+        assert_float_eq!(output[255], 0., abs <= 1e-4);
+    }
+
+    #[test]
+    fn test_double_playback_rate_halves_duration() {
+        assert_eq!(played_frames(1., 0.), 1024);
+        assert_eq!(played_frames(2., 0.), 512);
+    }
+
+    #[test]
+    fn test_detune() {
+        // an octave up
+        assert_eq!(played_frames(1., 1200.), 512);
+        // combined with the playback rate
+        assert_eq!(played_frames(4., -1200.), 512);
+    }
+
+    #[test]
+    fn test_fractional_rate_interpolates() {
+        let output = render_ramp(0.5, None, None);
+        let expected: Vec<f32> = (0..16).map(|i| i as f32 / 2.).collect();
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_fractional_rate_loop() {
+        // the read position wraps within the loop region, interpolating towards the loop start
+        let output = render_ramp(1.5, None, Some((2., 6.)));
+        let expected = [
+            0., 1.5, 3., 4.5, 2., 3.5, 5., 2.5, 4., 3.5, 3., 4.5, 2., 3.5, 5., 2.5,
+        ];
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-5);
+    }
+
+    /// Render 16 frames of the ramp 0, 1, .., 9, started with an offset and duration in frames
+    fn render_ramp_slice(
+        offset: f64,
+        duration: Option<f64>,
+        loop_region: Option<(f64, f64)>,
+    ) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, 16, SAMPLE_RATE);
+        let src = ramp_source(&context);
+
+        let frames = |frames: f64| frames / f64::from(SAMPLE_RATE.0);
+        if let Some((start, end)) = loop_region {
+            src.set_loop(true);
+            src.set_loop_start(frames(start));
+            src.set_loop_end(frames(end));
+        }
+
+        src.connect(&context.destination());
+        src.start_at_with_offset(0., frames(offset), duration.map(frames));
+
+        render_mono(&mut context)
+    }
+
+    #[test]
+    fn test_start_with_offset() {
+        let output = render_ramp_slice(3., None, None);
+        let mut expected = [0.; 16];
+        expected[..7].copy_from_slice(&[3., 4., 5., 6., 7., 8., 9.]);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_start_with_offset_and_duration() {
+        let output = render_ramp_slice(3., Some(4.), None);
+        let mut expected = [0.; 16];
+        expected[..4].copy_from_slice(&[3., 4., 5., 6.]);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_start_with_offset_past_end() {
+        let output = render_ramp_slice(12., None, None);
+        assert_float_eq!(output[..], [0.; 16][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_start_with_offset_in_loop() {
+        // past the loop end, wraps into the loop region
+        let output = render_ramp_slice(7., Some(6.), Some((2., 6.)));
+        let mut expected = [0.; 16];
+        expected[..6].copy_from_slice(&[3., 4., 5., 2., 3., 4.]);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_negative_offset() {
+        let context = OfflineAudioContext::new(1, 16, SAMPLE_RATE);
+        context
+            .create_buffer_source()
+            .start_at_with_offset(0., -1., None);
+    }
+
+    #[test]
+    fn test_forward() {
+        let output = render_ramp(1., None, None);
+        let mut expected = [0.; 16];
+        (0..10).for_each(|i| expected[i] = i as f32);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_reverse_from_offset() {
+        let output = render_ramp(-1., Some(5.), None);
+        let mut expected = [0.; 16];
+        expected[..5].copy_from_slice(&[4., 3., 2., 1., 0.]);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_reverse_mirrors_forward() {
+        // from the end of the buffer, reverse playback is the forward output backwards
+        let forward = render_ramp(1., None, None);
+        let reverse = render_ramp(-1., Some(10.), None);
+
+        let mut expected = forward[..10].to_vec();
+        expected.reverse();
+        assert_float_eq!(reverse[..10], expected[..], abs_all <= 1e-6);
+        // playback ends when the read head crosses the start of the buffer
+        assert_float_eq!(reverse[10..], [0.; 6][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_seek_past_loop_end() {
+        // seeking to frame 13 wraps to frame 2 + (13 - 6) % 4 = 5 of the loop region
+        let output = render_ramp(1., Some(13.), Some((2., 6.)));
+        let expected = [5., 2., 3., 4., 5., 2., 3., 4.];
+        assert_float_eq!(output[..8], expected[..], abs_all <= 1e-4);
+
+        // in reverse, playback enters the loop at its end, starting from the end of the buffer
+        let output = render_ramp(-1., Some(13.), Some((2., 6.)));
+        let expected = [9., 8., 7., 6., 5., 4., 3., 2.];
+        assert_float_eq!(output[..8], expected[..], abs_all <= 1e-4);
+    }
+
+    #[test]
+    fn test_reverse_from_start() {
+        // there is nothing before the first sample-frame
+        let output = render_ramp(-1., None, None);
+        assert_float_eq!(output[..], [0.; 16][..], abs_all <= 0.);
+    }
+
+    /// Render 16 frames of the ramp 0, 1, .., 9, looping from frame 4 to 8 with a crossfade
+    fn render_ramp_crossfade(crossfade: f32) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, 16, SAMPLE_RATE);
+        let src = ramp_source(&context);
+        assert_float_eq!(src.loop_crossfade(), 0., ulps <= 0);
+
+        let frames = |frames: f64| frames / f64::from(SAMPLE_RATE.0);
+        src.set_loop(true);
+        src.set_loop_start(frames(4.));
+        src.set_loop_end(frames(8.));
+        src.set_loop_crossfade(crossfade / SAMPLE_RATE.0 as f32);
+
+        src.connect(&context.destination());
+        src.start();
+
+        render_mono(&mut context)
+    }
+
+    #[test]
+    fn test_loop_crossfade() {
+        // frames 6 and 7 fade into frames 2 and 3
+        let output = render_ramp_crossfade(2.);
+        let expected = [
+            0., 1., 2., 3., 4., 5., 6., 5., 4., 5., 6., 5., 4., 5., 6., 5.,
+        ];
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-5);
+
+        // clamped to the loop region, frames 4 to 7 fade into frames 0 to 3
+        let output = render_ramp_crossfade(100.);
+        let mut expected = [4.; 16];
+        expected[..4].copy_from_slice(&[0., 1., 2., 3.]);
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_reverse_loop() {
+        // play backward to the loop start, then wrap to the loop end
+        let output = render_ramp(-1., Some(4.), Some((2., 6.)));
+        let expected = [
+            3., 2., 5., 4., 3., 2., 5., 4., 3., 2., 5., 4., 3., 2., 5., 4.,
+        ];
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+
+        // start playing beyond the loop region
+        let output = render_ramp(-1., Some(9.), Some((2., 6.)));
+        let expected = [
+            8., 7., 6., 5., 4., 3., 2., 5., 4., 3., 2., 5., 4., 3., 2., 5.,
+        ];
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_forward_loop() {
+        let output = render_ramp(1., None, Some((2., 6.)));
+        let expected = [
+            0., 1., 2., 3., 4., 5., 2., 3., 4., 5., 2., 3., 4., 5., 2., 3.,
+        ];
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-6);
+    }
 }
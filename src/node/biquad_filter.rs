@@ -12,10 +12,8 @@ use std::{
         atomic::{AtomicU32, Ordering},
         Arc,
     },
-    time::Duration,
 };
 
-use crossbeam_channel::{Receiver, Sender};
 use num_complex::Complex;
 
 use crate::{
@@ -24,16 +22,11 @@ use crate::{
     context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId},
     param::{AudioParam, AudioParamOptions},
     process::{AudioParamValues, AudioProcessor},
-    SampleRate, MAX_CHANNELS,
+    IndexSizeError, SampleRate, MAX_CHANNELS,
 };
 
 use super::AudioNode;
 
-/// Coefficients request
-/// This request is send by the control thread and send back by the rendering thread with
-/// current coefficients array
-struct CoeffsReq(Sender<[f64; 5]>);
-
 /// enumerates all the biquad filter types
 #[derive(Debug, Clone, Copy, PartialEq)]
 // the naming comes from the web audio specfication
@@ -140,8 +133,6 @@ pub struct BiquadFilterNode {
     gain: AudioParam,
     /// `BiquadFilterType` repesented as u32
     type_: Arc<AtomicU32>,
-    /// sender used to send message to the rendering part of the node
-    sender: Sender<CoeffsReq>,
 }
 
 impl AudioNode for BiquadFilterNode {
@@ -240,8 +231,6 @@ impl BiquadFilterNode {
 
             let type_ = Arc::new(AtomicU32::new(t_value as u32));
 
-            let (sender, receiver) = crossbeam_channel::bounded(0);
-
             let config = RendererConfig {
                 sample_rate,
                 gain: g_proc,
@@ -249,7 +238,6 @@ impl BiquadFilterNode {
                 frequency: f_proc,
                 q: q_proc,
                 type_: type_.clone(),
-                receiver,
             };
 
             let renderer = BiquadFilterRenderer::new(config);
@@ -262,32 +250,31 @@ impl BiquadFilterNode {
                 detune: d_param,
                 frequency: f_param,
                 gain: g_param,
-                sender,
             };
 
             (node, Box::new(renderer))
         })
     }
 
-    /// Returns the gain audio paramter
+    /// Returns the gain audio paramter (a-rate)
     #[must_use]
     pub const fn gain(&self) -> &AudioParam {
         &self.gain
     }
 
-    /// Returns the frequency audio paramter
+    /// Returns the frequency audio paramter (a-rate)
     #[must_use]
     pub const fn frequency(&self) -> &AudioParam {
         &self.frequency
     }
 
-    /// Returns the detune audio paramter
+    /// Returns the detune audio paramter (a-rate)
     #[must_use]
     pub const fn detune(&self) -> &AudioParam {
         &self.detune
     }
 
-    /// Returns the Q audio paramter
+    /// Returns the Q audio paramter (a-rate)
     #[must_use]
     pub const fn q(&self) -> &AudioParam {
         &self.q
@@ -310,99 +297,107 @@ impl BiquadFilterNode {
 
     /// Returns the frequency response for the specified frequencies
     ///
+    /// The response is computed on the control thread, from the current values of the
+    /// frequency, detune, Q and gain params. Frequencies outside of the range `[0, Nyquist]` are
+    /// clamped in place.
+    ///
     /// # Arguments
     ///
     /// * `frequency_hz` - frequencies for which frequency response of the filter should be calculated
     /// * `mag_response` - magnitude of the frequency response of the filter
     /// * `phase_response` - phase of the frequency response of the filter
+    ///
+    /// # Errors
+    ///
+    /// Returns an `IndexSizeError` if the three slices do not have the same length
     #[allow(clippy::cast_possible_truncation)]
     pub fn get_frequency_response(
         &self,
         frequency_hz: &mut [f32],
         mag_response: &mut [f32],
         phase_response: &mut [f32],
-    ) {
-        self.validate_inputs(frequency_hz, mag_response, phase_response);
-        let (sender, receiver) = crossbeam_channel::bounded(0);
-        self.sender
-            .send(CoeffsReq(sender))
-            .expect("Sending CoeffsReq failed");
-
-        match receiver.recv_timeout(Duration::from_millis(10)) {
-            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
-                println!("Receiver Error: disconnected type ");
-            }
-            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
-                println!("Receiver Error: timeout type");
-            }
-            Ok([b0, b1, b2, a1, a2]) => {
-                for (i, &f) in frequency_hz.iter().enumerate() {
-                    let f = f64::from(f);
-                    let sample_rate = f64::from(self.sample_rate);
-                    let num = b0
-                        + Complex::from_polar(b1, -1.0 * 2.0 * PI * f / sample_rate)
-                        + Complex::from_polar(b2, -2.0 * 2.0 * PI * f / sample_rate);
-                    let denom = 1.0
-                        + Complex::from_polar(a1, -1.0 * 2.0 * PI * f / sample_rate)
-                        + Complex::from_polar(a2, -2.0 * 2.0 * PI * f / sample_rate);
-                    let h_f = num / denom;
-
-                    // Possible truncation is fine. f32 precision should be sufficients
-                    // And it is required by the specs
-                    mag_response[i] = h_f.norm() as f32;
-                    phase_response[i] = h_f.arg() as f32;
-                }
-            }
+    ) -> Result<(), IndexSizeError> {
+        if frequency_hz.len() != mag_response.len() || mag_response.len() != phase_response.len() {
+            return Err(IndexSizeError {});
         }
-    }
 
-    /// validates that the params given to `get_frequency_response` method
-    #[inline]
-    fn validate_inputs(
-        &self,
-        frequency_hz: &mut [f32],
-        mag_response: &mut [f32],
-        phase_response: &mut [f32],
-    ) {
-        assert_eq!(
-            frequency_hz.len(),
-            mag_response.len(),
-            " InvalidAccessError: All parameters should be the same length"
-        );
-        assert_eq!(
-            mag_response.len(),
-            phase_response.len(),
-            " InvalidAccessError: All parameters should be the same length"
+        let sample_rate = f64::from(self.sample_rate);
+        let frequency = computed_frequency(self.frequency.value(), self.detune.value());
+        let coeffs = coefficients(
+            self.type_(),
+            sample_rate,
+            f64::from(frequency),
+            f64::from(self.q.value()),
+            f64::from(self.gain.value()),
         );
 
-        // Ensures that given frequencies are in the correct range
-        let min = 0.;
-        let max = self.sample_rate / 2.;
-        for f in frequency_hz.iter_mut() {
-            *f = f.clamp(min, max);
+        let nyquist = self.sample_rate / 2.;
+        for ((f, mag), phase) in frequency_hz
+            .iter_mut()
+            .zip(mag_response.iter_mut())
+            .zip(phase_response.iter_mut())
+        {
+            *f = f.clamp(0., nyquist);
+            let h_f = frequency_response(coeffs, f64::from(*f), sample_rate);
+
+            // Possible truncation is fine. f32 precision should be sufficients
+            // And it is required by the specs
+            *mag = h_f.norm() as f32;
+            *phase = h_f.arg() as f32;
         }
-    }
 
-    /// Mock of `get_frequency_response`
-    /// This function is the same as `get_frequency_response` except it never send the `CoeffsReq`.
-    /// In tests, we use `OfflineAudioContext` and in this context the `CoeffsReq` is not sendable.
-    ///
-    /// # Arguments
-    ///
-    /// * `frequency_hz` - frequencies for which frequency response of the filter should be calculated
-    /// * `mag_response` - magnitude of the frequency response of the filter
-    /// * `phase_response` - phase of the frequency response of the filter
-    #[cfg(test)]
-    fn get_frequency_response_mock(
-        &self,
-        frequency_hz: &mut [f32],
-        mag_response: &mut [f32],
-        phase_response: &mut [f32],
-    ) {
-        self.validate_inputs(frequency_hz, mag_response, phase_response);
+        Ok(())
     }
 }
 
+/// Computes the `computedFrequency` of the filter, the frequency detuned by `detune` cents
+fn computed_frequency(frequency: f32, detune: f32) -> f32 {
+    frequency * (detune / 1200.).exp2()
+}
+
+/// Computes the biquad coefficients `[b0, b1, b2, a1, a2]`, normalized by `a0`
+///
+/// # Arguments
+///
+/// * `type_` - biquadfilter type
+/// * `sample_rate` - audio context sample rate
+/// * `frequency` - frequency where the filter is applied
+/// * `q` - Q factor
+/// * `gain` - filter gain
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) fn coefficients(
+    type_: BiquadFilterType,
+    sample_rate: f64,
+    frequency: f64,
+    q: f64,
+    gain: f64,
+) -> [f64; 5] {
+    BiquadFilterRenderer::coefficients(type_, sample_rate, frequency, q, gain)
+}
+
+/// Evaluates the transfer function of a biquad at the given frequency
+///
+/// # Arguments
+///
+/// * `coeffs` - the biquad coefficients `[b0, b1, b2, a1, a2]`, normalized by `a0`
+/// * `frequency` - frequency at which the response is evaluated
+/// * `sample_rate` - audio context sample rate
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) fn frequency_response(
+    [b0, b1, b2, a1, a2]: [f64; 5],
+    frequency: f64,
+    sample_rate: f64,
+) -> Complex<f64> {
+    let num = b0
+        + Complex::from_polar(b1, -1.0 * 2.0 * PI * frequency / sample_rate)
+        + Complex::from_polar(b2, -2.0 * 2.0 * PI * frequency / sample_rate);
+    let denom = 1.0
+        + Complex::from_polar(a1, -1.0 * 2.0 * PI * frequency / sample_rate)
+        + Complex::from_polar(a2, -2.0 * 2.0 * PI * frequency / sample_rate);
+
+    num / denom
+}
+
 /// Represents the audio parameters values required to compute
 /// the biquad coefficients
 #[derive(Debug)]
@@ -442,8 +437,6 @@ struct RendererConfig {
     gain: AudioParamId,
     /// `BiquadFilterType` repesented as u32
     type_: Arc<AtomicU32>,
-    /// receiver used to receive message from the control node part
-    receiver: Receiver<CoeffsReq>,
 }
 
 /// Biquad filter coefficients
@@ -485,8 +478,6 @@ struct BiquadFilterRenderer {
     ss2: [f64; MAX_CHANNELS],
     /// Biquad filter coefficients computed from freq, q, gain,...
     coeffs: Coefficients,
-    /// receiver used to receive message from the control node part
-    receiver: Receiver<CoeffsReq>,
 }
 
 impl AudioProcessor for BiquadFilterRenderer {
@@ -513,6 +504,11 @@ impl AudioProcessor for BiquadFilterRenderer {
     fn tail_time(&self) -> bool {
         true
     }
+
+    fn reset(&mut self) {
+        self.ss1 = [0.; MAX_CHANNELS];
+        self.ss2 = [0.; MAX_CHANNELS];
+    }
 }
 
 impl BiquadFilterRenderer {
@@ -528,7 +524,6 @@ impl BiquadFilterRenderer {
             frequency,
             gain,
             type_,
-            receiver,
         } = config;
 
         let coeffs = Coefficients {
@@ -552,7 +547,6 @@ impl BiquadFilterRenderer {
             ss1: s1,
             ss2: s2,
             coeffs,
-            receiver,
         }
     }
 
@@ -593,16 +587,6 @@ impl BiquadFilterRenderer {
                 *o = self.tick(i, channel_idx);
             }
         }
-
-        let Coefficients { b0, b1, b2, a1, a2 } = self.coeffs;
-
-        let coeffs_resp = [b0, b1, b2, a1, a2];
-
-        // Respond to request at K-rate following the specs
-        if let Ok(msg) = self.receiver.try_recv() {
-            let sender = msg.0;
-            sender.send(coeffs_resp).unwrap();
-        }
     }
 
     /// Generate an output sample by filtering an input sample
@@ -638,21 +622,46 @@ impl BiquadFilterRenderer {
             type_,
         } = params;
 
-        let computed_freq = frequency * 10_f32.powf(detune / 1200.);
+        let computed_freq = computed_frequency(*frequency, *detune);
 
-        let sample_rate = f64::from(self.sample_rate);
-        let computed_freq = f64::from(computed_freq);
-        let q = f64::from(*q);
-        let gain = f64::from(*gain);
+        let [b0, b1, b2, a1, a2] = Self::coefficients(
+            *type_,
+            f64::from(self.sample_rate),
+            f64::from(computed_freq),
+            f64::from(*q),
+            f64::from(*gain),
+        );
+
+        self.coeffs = Coefficients { a1, a2, b0, b1, b2 };
+    }
 
+    /// computes the biquad coefficients `[b0, b1, b2, a1, a2]`, normalized by `a0`
+    ///
+    /// # Arguments
+    ///
+    /// * `type_` - biquadfilter type
+    /// * `sample_rate` - audio context sample rate
+    /// * `computed_freq` - `computedOscFreq`
+    /// * `q` - Q factor
+    /// * `gain` - filter gain
+    #[inline]
+    fn coefficients(
+        type_: BiquadFilterType,
+        sample_rate: f64,
+        computed_freq: f64,
+        q: f64,
+        gain: f64,
+    ) -> [f64; 5] {
         // compute a0 first to normalize others coeffs by a0
-        let a0 = Self::a0(*type_, sample_rate, computed_freq, q, gain);
+        let a0 = Self::a0(type_, sample_rate, computed_freq, q, gain);
 
-        self.coeffs.b0 = Self::b0(*type_, sample_rate, computed_freq, q, gain) / a0;
-        self.coeffs.b1 = Self::b1(*type_, sample_rate, computed_freq, gain) / a0;
-        self.coeffs.b2 = Self::b2(*type_, sample_rate, computed_freq, q, gain) / a0;
-        self.coeffs.a1 = Self::a1(*type_, sample_rate, computed_freq, gain) / a0;
-        self.coeffs.a2 = Self::a2(*type_, sample_rate, computed_freq, q, gain) / a0;
+        [
+            Self::b0(type_, sample_rate, computed_freq, q, gain) / a0,
+            Self::b1(type_, sample_rate, computed_freq, gain) / a0,
+            Self::b2(type_, sample_rate, computed_freq, q, gain) / a0,
+            Self::a1(type_, sample_rate, computed_freq, gain) / a0,
+            Self::a2(type_, sample_rate, computed_freq, q, gain) / a0,
+        ]
     }
 
     /// calculates `b_0` numerator coefficient
@@ -1109,8 +1118,8 @@ mod test {
 
     use crate::{
         buffer::ChannelConfigOptions,
-        context::{AsBaseAudioContext, OfflineAudioContext},
-        node::{BiquadFilterOptions, BiquadFilterType},
+        context::{AsBaseAudioContext, ManualAudioContext, OfflineAudioContext},
+        node::{AudioNode, BiquadFilterOptions, BiquadFilterType},
         SampleRate,
     };
 
@@ -1255,8 +1264,7 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
-    fn panics_when_not_the_same_length() {
+    fn errors_when_not_the_same_length() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
         let biquad = BiquadFilterNode::new(&context, None);
 
@@ -1264,12 +1272,13 @@ mod test {
         let mut mag_response = [0., 1.0];
         let mut phase_response = [0.];
 
-        biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert!(biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .is_err());
     }
 
     #[test]
-    #[should_panic]
-    fn panics_when_not_the_same_length_2() {
+    fn errors_when_not_the_same_length_2() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
         let biquad = BiquadFilterNode::new(&context, None);
 
@@ -1277,28 +1286,72 @@ mod test {
         let mut mag_response = [0.];
         let mut phase_response = [0., 1.0];
 
-        biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert!(biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .is_err());
     }
 
     #[test]
     fn frequencies_are_clamped() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
         let biquad = BiquadFilterNode::new(&context, None);
-        // It will be fine for the usual fs
-        #[allow(clippy::cast_precision_loss)]
-        let niquyst = context.sample_rate().0 as f32 / 2.0;
-
-        let mut frequency_hz = [-100., 1_000_000.];
-        let mut mag_response = [0., 0.];
-        let mut phase_response = [0., 0.];
-
-        biquad.get_frequency_response_mock(
-            &mut frequency_hz,
-            &mut mag_response,
-            &mut phase_response,
-        );
 
-        let ref_arr = [0., niquyst];
-        assert_float_eq!(frequency_hz, ref_arr, ulps_all <= 0);
+        let mut frequency_hz = [-100., 1_000., 1_000_000.];
+        let mut mag_response = [0.; 3];
+        let mut phase_response = [0.; 3];
+
+        biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
+
+        // the response is evaluated at the clamped frequencies
+        assert_float_eq!(frequency_hz, [0., 1_000., 22_050.], ulps_all <= 0);
+        let mut mag_ref = [0.; 3];
+        let mut phase_ref = [0.; 3];
+        biquad
+            .get_frequency_response(&mut [0., 1_000., 22_050.], &mut mag_ref, &mut phase_ref)
+            .unwrap();
+        assert_float_eq!(mag_response, mag_ref, ulps_all <= 0);
+        assert_float_eq!(phase_response, phase_ref, ulps_all <= 0);
+    }
+
+    #[test]
+    fn frequency_response_follows_current_param_values() {
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+        let mut biquad = BiquadFilterNode::new(&context, None);
+        biquad.connect(&context.destination());
+
+        biquad.set_type(BiquadFilterType::Peaking);
+        biquad.frequency().set_value(1_000.);
+        biquad.q().set_value(1.);
+        biquad.gain().set_value(6.);
+
+        // the param values are updated by the render thread
+        let mut output = [0.; 128];
+        context.render(&mut output);
+
+        let mut frequency_hz = [0., 1_000., 22_050.];
+        let mut mag_response = [0.; 3];
+        let mut phase_response = [0.; 3];
+        biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
+
+        // 6 dB boost at the center frequency, flat at both ends
+        assert_float_eq!(20. * mag_response[1].log10(), 6., abs <= 1e-3);
+        assert_float_eq!(phase_response[1], 0., abs <= 1e-3);
+        assert_float_eq!(mag_response[0], 1., abs <= 1e-3);
+        assert_float_eq!(mag_response[2], 1., abs <= 1e-3);
+
+        // detuned an octave down, the boost moves to 500 Hz
+        biquad.detune().set_value(-1200.);
+        context.render(&mut output);
+        let mut frequency_hz = [500.];
+        let mut mag_response = [0.];
+        let mut phase_response = [0.];
+        biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
+        assert_float_eq!(20. * mag_response[0].log10(), 6., abs <= 1e-3);
     }
 }
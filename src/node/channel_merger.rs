@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration};
 use crate::process::{AudioParamValues, AudioProcessor};
-use crate::SampleRate;
+use crate::{SampleRate, MAX_CHANNELS};
 
 use super::AudioNode;
 
@@ -27,9 +27,13 @@ impl Default for ChannelMergerOptions {
 }
 
 /// AudioNode for combining channels from multiple audio streams into a single audio stream.
+///
+/// Each input is down-mixed to mono, and provides one channel of the output. The output has as
+/// many channels as the node has inputs.
 pub struct ChannelMergerNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
+    number_of_inputs: u32,
 }
 
 impl AudioNode for ChannelMergerNode {
@@ -51,21 +55,38 @@ impl AudioNode for ChannelMergerNode {
     }
 
     fn number_of_inputs(&self) -> u32 {
-        self.channel_count() as _
+        self.number_of_inputs
     }
     fn number_of_outputs(&self) -> u32 {
         1
     }
+
+    fn max_channel_count(&self) -> usize {
+        1
+    }
 }
 
 impl ChannelMergerNode {
+    /// # Panics
+    ///
+    /// Will panic with an `IndexSizeError` if the number of inputs is zero or exceeds
+    /// `MAX_CHANNELS`
     pub fn new<C: AsBaseAudioContext>(context: &C, mut options: ChannelMergerOptions) -> Self {
+        assert!(
+            options.number_of_inputs > 0 && options.number_of_inputs as usize <= MAX_CHANNELS,
+            "IndexSizeError: the number of inputs should be in the range [1, {}]",
+            MAX_CHANNELS
+        );
+
         context.base().register(move |registration| {
-            options.channel_config.count = options.number_of_inputs as _;
+            // every input is down-mixed to a single channel of the output
+            options.channel_config.count = 1;
+            options.channel_config.mode = ChannelCountMode::Explicit;
 
             let node = ChannelMergerNode {
                 registration,
                 channel_config: options.channel_config.into(),
+                number_of_inputs: options.number_of_inputs,
             };
 
             let render = ChannelMergerRenderer {};
@@ -91,6 +112,7 @@ impl AudioProcessor for ChannelMergerRenderer {
         let output = &mut outputs[0];
         output.set_number_of_channels(inputs.len());
 
+        // the inputs are mono, share their channel data with the output
         inputs.iter().enumerate().for_each(|(i, input)| {
             *output.channel_data_mut(i) = input.channel_data(0).clone();
         });
@@ -100,3 +122,102 @@ impl AudioProcessor for ChannelMergerRenderer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::{AudioBuffer, ChannelData};
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::{
+        AudioBufferSourceNode, AudioBufferSourceNodeOptions, AudioNode, AudioScheduledSourceNode,
+    };
+    use crate::SampleRate;
+
+    #[test]
+    fn test_merge_mono_inputs() {
+        let mut context = OfflineAudioContext::new(4, 128, SampleRate(44_100));
+
+        let merger = context.create_channel_merger(4);
+        assert_eq!(merger.number_of_inputs(), 4);
+        assert_eq!(merger.channel_count(), 1);
+        merger.connect(&context.destination());
+
+        for (input, value) in [(0, 1.), (2, -0.5)] {
+            let src = context.create_constant_source();
+            src.offset().set_value(value);
+            src.connect_at(&merger, 0, input).unwrap();
+        }
+
+        let output = context.start_rendering();
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 1.));
+        assert!(output.channel_data(1).as_slice().iter().all(|&v| v == 0.));
+        assert!(output.channel_data(2).as_slice().iter().all(|&v| v == -0.5));
+        assert!(output.channel_data(3).as_slice().iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_down_mix_stereo_input() {
+        let mut context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+
+        let stereo = AudioBuffer::from_channels(
+            vec![
+                ChannelData::from(vec![1.; 128]),
+                ChannelData::from(vec![0.; 128]),
+            ],
+            SampleRate(44_100),
+        );
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(stereo),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        src.start();
+
+        let merger = context.create_channel_merger(2);
+        src.connect_at(&merger, 0, 1).unwrap();
+        merger.connect(&context.destination());
+
+        let output = context.start_rendering();
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 0.));
+        assert!(output.channel_data(1).as_slice().iter().all(|&v| v == 0.5));
+    }
+
+    #[test]
+    fn test_split_merge_round_trip() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(2, 256, sample_rate);
+
+        let left: Vec<f32> = (0..256).map(|i| (i as f32 * 0.1).sin()).collect();
+        let right: Vec<f32> = (0..256).map(|i| (i as f32 * 0.37).cos() * 0.5).collect();
+        let stereo = AudioBuffer::from_channels(
+            vec![
+                ChannelData::from(left.clone()),
+                ChannelData::from(right.clone()),
+            ],
+            sample_rate,
+        );
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(stereo),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        src.start();
+
+        let splitter = context.create_channel_splitter(2);
+        let merger = context.create_channel_merger(2);
+        src.connect(&splitter);
+        splitter.connect_at(&merger, 0, 0).unwrap();
+        splitter.connect_at(&merger, 1, 1).unwrap();
+        merger.connect(&context.destination());
+
+        let output = context.start_rendering();
+        assert_eq!(output.channel_data(0).as_slice(), &left[..]);
+        assert_eq!(output.channel_data(1).as_slice(), &right[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_zero_inputs() {
+        let context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+        context.create_channel_merger(0);
+    }
+}
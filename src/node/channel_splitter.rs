@@ -56,6 +56,10 @@ impl AudioNode for ChannelSplitterNode {
     fn number_of_outputs(&self) -> u32 {
         self.channel_count() as _
     }
+
+    fn max_channel_count(&self) -> usize {
+        self.number_of_outputs() as _
+    }
 }
 
 impl ChannelSplitterNode {
@@ -97,6 +101,8 @@ impl AudioProcessor for ChannelSplitterRenderer {
         // assert number of outputs was correctly set by renderer
         assert_eq!(self.number_of_outputs, outputs.len());
 
+        // This runs without heap allocations: `force_mono` only truncates the channel list, and
+        // the input channels are shared with the outputs by cloning their `Rc`
         for (i, output) in outputs.iter_mut().enumerate() {
             output.force_mono();
             if i < input.number_of_channels() {
@@ -112,3 +118,22 @@ impl AudioProcessor for ChannelSplitterRenderer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::{SampleRate, MAX_CHANNELS};
+
+    #[test]
+    fn test_max_channel_count() {
+        let context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+
+        let splitter = context.create_channel_splitter(4);
+        assert_eq!(splitter.max_channel_count(), 4);
+
+        let gain = context.create_gain();
+        assert_eq!(gain.max_channel_count(), MAX_CHANNELS);
+        assert_eq!(gain.max_channel_count(), 32);
+    }
+}
@@ -70,6 +70,7 @@ impl ConstantSourceNode {
         })
     }
 
+    /// Value emitted by the source (a-rate)
     pub fn offset(&self) -> &AudioParam {
         &self.offset
     }
@@ -101,3 +102,43 @@ impl AudioProcessor for ConstantSourceRenderer {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::SampleRate;
+
+    #[test]
+    fn test_offset_ramp_drives_param_per_sample() {
+        let sample_rate = SampleRate(44_100);
+        let length = 256;
+        let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let signal = context.create_constant_source();
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+        signal.connect(&gain);
+        gain.connect(&context.destination());
+
+        // ramp the offset from 0 to 1 over the rendered length
+        let modulator = context.create_constant_source();
+        modulator.offset().set_value_at_time(0., 0.);
+        modulator
+            .offset()
+            .linear_ramp_to_value_at_time(1., length as f64 / f64::from(sample_rate.0));
+        modulator.connect(gain.gain());
+
+        let output = context.start_rendering();
+
+        // the gain follows the ramp within each render quantum, not just per quantum
+        let expected: Vec<f32> = (0..length).map(|i| i as f32 / length as f32).collect();
+        assert_float_eq!(
+            output.channel_data(0).as_slice(),
+            &expected[..],
+            abs_all <= 1e-6
+        );
+    }
+}
@@ -0,0 +1,779 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam_channel::{Receiver, Sender};
+use num_complex::Complex;
+
+use crate::analysis::{FftProcessor, RealFftProcessor};
+use crate::buffer::{
+    AudioBuffer, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{SampleRate, BUFFER_SIZE};
+
+use super::AudioNode;
+
+/// The convolver processes mono or stereo input
+const MAX_CONVOLVER_CHANNELS: usize = 2;
+
+/// Trade-off between latency and CPU usage of the [`ConvolverNode`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ConvolverLatencyMode {
+    /// Partitions of a single render quantum, the output is not delayed
+    ZeroLatency,
+    /// Partitions of 512 sample-frames, for live processing
+    #[default]
+    LowLatency,
+    /// Partitions of 2048 sample-frames, for offline rendering or long impulse responses
+    Efficient,
+}
+
+impl ConvolverLatencyMode {
+    /// Partition size (in sample-frames) of this mode
+    pub fn partition_size(self) -> usize {
+        match self {
+            ConvolverLatencyMode::ZeroLatency => BUFFER_SIZE as usize,
+            ConvolverLatencyMode::LowLatency => 512,
+            ConvolverLatencyMode::Efficient => 2048,
+        }
+    }
+}
+
+/// Options for constructing a ConvolverNode
+pub struct ConvolverOptions {
+    /// Impulse response, only the first channel is used
+    pub buffer: Option<AudioBuffer>,
+    pub latency_mode: ConvolverLatencyMode,
+    pub channel_config: ChannelConfigOptions,
+    /// FFT backend for the convolution, `None` selects the default backend
+    pub fft_backend: Option<Box<dyn FftProcessor>>,
+}
+
+impl Default for ConvolverOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            latency_mode: ConvolverLatencyMode::default(),
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+            fft_backend: None,
+        }
+    }
+}
+
+/// Processing node which applies a linear convolution effect given an impulse response.
+///
+/// The convolution is computed in the frequency domain, with the impulse response split in
+/// partitions of equal size. Larger partitions use less CPU, but delay the output by the
+/// partition size minus one render quantum. In the zero latency mode, the first render quantum
+/// of the impulse response is convolved directly in the time domain, so the partitions of the
+/// remainder are not heard before they are ready. Without impulse response, the node outputs
+/// silence.
+///
+/// The node processes mono or stereo input, as in the specification. A reverb tail keeps
+/// ringing when the number of input channels changes.
+pub struct ConvolverNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    partition_size: AtomicUsize,
+    impulse_response: Vec<f32>,
+    sender: Sender<ConvolverEngine>,
+    /// engines replaced on the render thread, to be dropped here
+    replaced: Receiver<ConvolverEngine>,
+}
+
+impl AudioNode for ConvolverNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        1
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+
+    fn set_channel_count_mode(&self, v: ChannelCountMode) {
+        assert!(v != ChannelCountMode::Max, "NotSupportedError");
+        self.channel_config.set_count_mode(v);
+    }
+
+    fn set_channel_count(&self, v: usize) {
+        assert!(v <= MAX_CONVOLVER_CHANNELS, "NotSupportedError");
+        self.channel_config.set_count(v);
+    }
+
+    fn max_channel_count(&self) -> usize {
+        MAX_CONVOLVER_CHANNELS
+    }
+}
+
+impl ConvolverNode {
+    /// Creates a `ConvolverNode`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is more than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: ConvolverOptions) -> Self {
+        assert!(
+            options.channel_config.count <= MAX_CONVOLVER_CHANNELS,
+            "NotSupportedError: channel count"
+        );
+        assert!(
+            options.channel_config.mode != ChannelCountMode::Max,
+            "NotSupportedError: count mode"
+        );
+
+        context.base().register(move |registration| {
+            let partition_size = options.latency_mode.partition_size();
+
+            let impulse_response = options
+                .buffer
+                .map(|buffer| buffer.channel_data(0).as_slice().to_vec())
+                .unwrap_or_default();
+
+            // the backend prepares its transforms here, before moving to the render thread
+            let mut fft = options
+                .fft_backend
+                .unwrap_or_else(|| Box::new(RealFftProcessor::new()));
+            let engine = if impulse_response.is_empty() {
+                None
+            } else {
+                let mut engine = ConvolverEngine::new(&impulse_response, partition_size);
+                engine.prepare(&mut *fft);
+                Some(engine)
+            };
+
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let (replaced_sender, replaced) = crossbeam_channel::unbounded();
+
+            let render = ConvolverRenderer {
+                engine,
+                receiver,
+                replaced: replaced_sender,
+                fft,
+                number_of_channels: 0,
+                remaining_tail: 0,
+            };
+
+            let node = ConvolverNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                partition_size: AtomicUsize::new(partition_size),
+                impulse_response,
+                sender,
+                replaced,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Select the partition size of a predefined latency mode
+    pub fn set_latency_mode(&self, mode: ConvolverLatencyMode) {
+        self.set_partition_size(mode.partition_size());
+    }
+
+    /// Size of the partitions of the impulse response, in sample-frames
+    pub fn partition_size(&self) -> usize {
+        self.partition_size.load(Ordering::SeqCst)
+    }
+
+    /// Set the size of the partitions of the impulse response, in sample-frames
+    ///
+    /// The output is delayed by `partition_size - BUFFER_SIZE` sample-frames. The partitions are
+    /// allocated on the control thread, but their spectra are computed by the FFT backend of the
+    /// node on the render thread, and changing the partition size resets the internal state of
+    /// the convolution, so it is best done before rendering starts.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the size is not a power of two, or smaller than `BUFFER_SIZE`
+    pub fn set_partition_size(&self, partition_size: usize) {
+        assert!(
+            partition_size.is_power_of_two() && partition_size >= BUFFER_SIZE as usize,
+            "NotSupportedError: partition size should be a power of two of at least {}",
+            BUFFER_SIZE
+        );
+
+        self.partition_size.store(partition_size, Ordering::SeqCst);
+
+        // drop the engines replaced since the last change
+        self.replaced.try_iter().for_each(drop);
+
+        if !self.impulse_response.is_empty() {
+            let engine = ConvolverEngine::new(&self.impulse_response, partition_size);
+            self.sender.send(engine).unwrap();
+        }
+    }
+
+    /// Delay of the output caused by the partitioning, in sample-frames
+    pub fn latency(&self) -> usize {
+        self.partition_size() - BUFFER_SIZE as usize
+    }
+}
+
+/// Convolution state of a single channel
+///
+/// The head of the impulse response is convolved directly in the time domain, the tail with a
+/// uniformly partitioned overlap-save convolution.
+struct ChannelConvolver {
+    /// most recent input samples for the head, the current render quantum comes last
+    recent: Vec<f32>,
+    /// spectra of the most recent input blocks (frequency-domain delay line)
+    history: Vec<Vec<Complex<f32>>>,
+    /// index of the most recent block in `history`
+    history_index: usize,
+    /// previous and current input block
+    input: Vec<f32>,
+    /// number of samples collected in the current input block
+    input_len: usize,
+    /// computed output block, and the read position in it
+    output: Vec<f32>,
+    output_index: usize,
+}
+
+impl ChannelConvolver {
+    fn new(head_len: usize, tail_partition_size: usize, partitions: usize) -> Self {
+        Self {
+            recent: vec![0.; head_len + BUFFER_SIZE as usize],
+            history: vec![vec![Complex::default(); tail_partition_size + 1]; partitions.max(1)],
+            history_index: 0,
+            input: vec![0.; 2 * tail_partition_size],
+            input_len: 0,
+            output: vec![0.; tail_partition_size],
+            output_index: tail_partition_size,
+        }
+    }
+
+    /// Take over the state of another channel, without allocating
+    fn copy_from(&mut self, other: &Self) {
+        self.recent.copy_from_slice(&other.recent);
+        self.history
+            .iter_mut()
+            .zip(&other.history)
+            .for_each(|(block, other)| block.copy_from_slice(other));
+        self.history_index = other.history_index;
+        self.input.copy_from_slice(&other.input);
+        self.input_len = other.input_len;
+        self.output.copy_from_slice(&other.output);
+        self.output_index = other.output_index;
+    }
+
+    fn clear(&mut self) {
+        self.recent.iter_mut().for_each(|v| *v = 0.);
+        self.history
+            .iter_mut()
+            .for_each(|block| block.iter_mut().for_each(|v| *v = Complex::default()));
+        self.history_index = 0;
+        self.input.iter_mut().for_each(|v| *v = 0.);
+        self.input_len = 0;
+        self.output_index = self.output.len();
+    }
+}
+
+/// Convolution of up to two channels with the same impulse response
+///
+/// The engine is built on the control thread, the render thread never allocates.
+struct ConvolverEngine {
+    /// configured partition size, which determines the latency
+    partition_size: usize,
+    /// partition size of the tail convolution
+    tail_partition_size: usize,
+    /// start of the impulse response convolved directly in the time domain
+    head: Vec<f32>,
+    /// remainder of the impulse response, convolved in partitions
+    tail: Vec<f32>,
+    /// spectra of the partitions of the tail, computed by [`Self::prepare`]
+    filters: Vec<Vec<Complex<f32>>>,
+    channels: [ChannelConvolver; MAX_CONVOLVER_CHANNELS],
+    /// scratch space for the FFTs of size `2 * tail_partition_size`
+    time_scratch: Vec<f32>,
+    freq_scratch: Vec<Complex<f32>>,
+}
+
+impl ConvolverEngine {
+    /// Allocate the engine, the filter spectra are only available once it is prepared
+    fn new(impulse_response: &[f32], partition_size: usize) -> Self {
+        // Without latency, the tail partitions are twice a render quantum, so they are delayed
+        // by exactly one quantum. The first quantum of the impulse response is the head.
+        let (head_len, tail_partition_size) = if partition_size == BUFFER_SIZE as usize {
+            (
+                partition_size.min(impulse_response.len()),
+                2 * partition_size,
+            )
+        } else {
+            (0, partition_size)
+        };
+        let (head, tail) = impulse_response.split_at(head_len);
+
+        let partitions = tail.len().div_ceil(tail_partition_size);
+        let filters = vec![vec![Complex::default(); tail_partition_size + 1]; partitions];
+
+        let new_channel = || ChannelConvolver::new(head_len, tail_partition_size, partitions);
+
+        Self {
+            partition_size,
+            tail_partition_size,
+            head: head.to_vec(),
+            tail: tail.to_vec(),
+            channels: [new_channel(), new_channel()],
+            filters,
+            time_scratch: vec![0.; 2 * tail_partition_size],
+            freq_scratch: vec![Complex::default(); tail_partition_size + 1],
+        }
+    }
+
+    /// Compute the spectra of the tail partitions with the given backend, without allocating
+    ///
+    /// The backend warms up its transforms of this size on the first call.
+    fn prepare(&mut self, fft: &mut dyn FftProcessor) {
+        let partition_size = self.tail_partition_size;
+
+        for (partition, spectrum) in self.tail.chunks(partition_size).zip(&mut self.filters) {
+            self.time_scratch.iter_mut().for_each(|v| *v = 0.);
+            self.time_scratch[..partition.len()].copy_from_slice(partition);
+            fft.process(&mut self.time_scratch, spectrum);
+        }
+
+        // warm up the inverse transform as well
+        fft.process_inverse(&mut self.freq_scratch, &mut self.time_scratch);
+    }
+
+    /// Number of sample-frames a channel keeps ringing after its input was silenced
+    fn tail_len(&self) -> usize {
+        self.head.len() + (self.filters.len() + 2) * self.tail_partition_size
+    }
+
+    /// Continue the state of the first channel in the second one, after upmixing mono input
+    fn upmix(&mut self) {
+        let (first, second) = self.channels.split_at_mut(1);
+        second[0].copy_from(&first[0]);
+    }
+
+    fn clear(&mut self) {
+        self.channels.iter_mut().for_each(ChannelConvolver::clear);
+    }
+
+    /// Convolve a full partition of input, stored in the second half of `input`
+    fn process_partition(&mut self, channel: usize, fft: &mut dyn FftProcessor) {
+        let partition_size = self.tail_partition_size;
+        let state = &mut self.channels[channel];
+
+        // spectrum of the previous and current block
+        state.history_index = (state.history_index + 1) % state.history.len();
+        self.time_scratch.copy_from_slice(&state.input);
+        fft.process(
+            &mut self.time_scratch,
+            &mut state.history[state.history_index],
+        );
+
+        // multiply-accumulate with the filter partitions
+        self.freq_scratch
+            .iter_mut()
+            .for_each(|v| *v = Complex::new(0., 0.));
+        let len = state.history.len();
+        for (k, filter) in self.filters.iter().enumerate() {
+            let block = &state.history[(state.history_index + len - k) % len];
+            self.freq_scratch
+                .iter_mut()
+                .zip(block.iter().zip(filter.iter()))
+                .for_each(|(acc, (x, h))| *acc += x * h);
+        }
+
+        // keep the valid (second) half, the FFT is not normalized
+        fft.process_inverse(&mut self.freq_scratch, &mut self.time_scratch);
+        let norm = 1. / self.time_scratch.len() as f32;
+        state
+            .output
+            .iter_mut()
+            .zip(&self.time_scratch[partition_size..])
+            .for_each(|(o, v)| *o = v * norm);
+        state.output_index = 0;
+
+        // the current block becomes the previous block
+        state.input.copy_within(partition_size.., 0);
+        state.input_len = 0;
+    }
+
+    /// Process a render quantum of a single channel in place
+    ///
+    /// The output of a tail partition is emitted starting from the quantum that completes it, so
+    /// the tail is delayed by `tail_partition_size - BUFFER_SIZE` sample-frames
+    fn process(&mut self, channel: usize, data: &mut [f32], fft: &mut dyn FftProcessor) {
+        let partition_size = self.tail_partition_size;
+        let head_len = self.head.len();
+
+        let state = &mut self.channels[channel];
+        state.recent[head_len..].copy_from_slice(data);
+        let offset = partition_size + state.input_len;
+        state.input[offset..offset + data.len()].copy_from_slice(data);
+        state.input_len += data.len();
+
+        if state.input_len == partition_size {
+            self.process_partition(channel, fft);
+        }
+
+        let state = &mut self.channels[channel];
+        let start = state.output_index;
+        if start < partition_size {
+            data.copy_from_slice(&state.output[start..start + data.len()]);
+        } else {
+            // no partition completed yet
+            data.iter_mut().for_each(|v| *v = 0.);
+        }
+        state.output_index += data.len();
+
+        // direct convolution of the head
+        for (i, o) in data.iter_mut().enumerate() {
+            *o += self
+                .head
+                .iter()
+                .zip(state.recent[i + 1..=head_len + i].iter().rev())
+                .map(|(h, x)| h * x)
+                .sum::<f32>();
+        }
+        state.recent.copy_within(BUFFER_SIZE as usize.., 0);
+    }
+}
+
+struct ConvolverRenderer {
+    /// `None` without impulse response
+    engine: Option<ConvolverEngine>,
+    receiver: Receiver<ConvolverEngine>,
+    /// replaced engines are sent back to be dropped on the control thread
+    replaced: Sender<ConvolverEngine>,
+    fft: Box<dyn FftProcessor>,
+    /// number of channels of the output
+    number_of_channels: usize,
+    /// number of sample-frames the second channel keeps ringing after the input became mono
+    remaining_tail: usize,
+}
+
+impl AudioProcessor for ConvolverRenderer {
+    fn process(
+        &mut self,
+        inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // a new partition size, the state of the previous engine is discarded
+        if let Ok(mut engine) = self.receiver.try_recv() {
+            engine.prepare(&mut *self.fft);
+            if let Some(previous) = self.engine.replace(engine) {
+                // the node may be dropped already, then the engine is dropped here
+                let _ = self.replaced.send(previous);
+            }
+        }
+
+        let engine = match &mut self.engine {
+            Some(engine) => engine,
+            None => {
+                output.make_silent();
+                return;
+            }
+        };
+
+        let channels = input.number_of_channels().min(MAX_CONVOLVER_CHANNELS);
+        if channels == MAX_CONVOLVER_CHANNELS {
+            // the mono state continues in both channels
+            if self.number_of_channels == 1 {
+                engine.upmix();
+            }
+            self.number_of_channels = channels;
+            self.remaining_tail = engine.tail_len();
+        } else if self.number_of_channels == MAX_CONVOLVER_CHANNELS {
+            // the second channel is fed with the upmixed input, until the tail of the stereo
+            // input has faded out of both channels
+            self.remaining_tail = self.remaining_tail.saturating_sub(BUFFER_SIZE as usize);
+            if self.remaining_tail == 0 {
+                self.number_of_channels = channels;
+            }
+        } else {
+            self.number_of_channels = channels;
+        }
+
+        *output = input.clone();
+        output.mix(self.number_of_channels, ChannelInterpretation::Speakers);
+
+        let fft = &mut *self.fft;
+        output
+            .channels_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(c, data)| engine.process(c, &mut data[..], fft));
+    }
+
+    fn tail_time(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        if let Some(engine) = &mut self.engine {
+            engine.clear();
+        }
+        self.number_of_channels = 0;
+        self.remaining_tail = 0;
+    }
+
+    fn latency_samples(&self) -> f64 {
+        self.engine.as_ref().map_or(0., |engine| {
+            (engine.partition_size - BUFFER_SIZE as usize) as f64
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use float_eq::assert_float_eq;
+
+    use crate::buffer::ChannelData;
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::{ConstantSourceNode, ConstantSourceOptions};
+    use crate::test_util::{impulse, render_mono};
+
+    use super::*;
+
+    const LENGTH: usize = 8192;
+
+    /// Forwards to the default backend, records the requested FFT sizes
+    struct CountingFft {
+        inner: RealFftProcessor,
+        sizes: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl FftProcessor for CountingFft {
+        fn process(&mut self, input: &mut [f32], output: &mut [Complex<f32>]) {
+            self.sizes.lock().unwrap().push(input.len());
+            self.inner.process(input, output);
+        }
+
+        fn process_inverse(&mut self, input: &mut [Complex<f32>], output: &mut [f32]) {
+            self.sizes.lock().unwrap().push(output.len());
+            self.inner.process_inverse(input, output);
+        }
+    }
+
+    /// Render a unit impulse through a convolver with the given latency mode
+    fn render_impulse(mode: ConvolverLatencyMode) -> Vec<f32> {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, LENGTH, sample_rate);
+
+        // decaying, alternating impulse response
+        let ir: Vec<f32> = (0..3000)
+            .map(|i| 0.999_f32.powi(i) * if i % 3 == 0 { 1. } else { -0.5 })
+            .collect();
+        let ir = AudioBuffer::from_channels(vec![ChannelData::from(ir)], sample_rate);
+
+        let options = ConvolverOptions {
+            buffer: Some(ir),
+            latency_mode: mode,
+            ..ConvolverOptions::default()
+        };
+        let convolver = ConvolverNode::new(&context, options);
+        assert_eq!(convolver.partition_size(), mode.partition_size());
+        convolver.connect(&context.destination());
+
+        impulse(&context).connect(&convolver);
+
+        render_mono(&mut context)
+    }
+
+    #[test]
+    fn test_latency_modes() {
+        let reference = render_impulse(ConvolverLatencyMode::ZeroLatency);
+
+        // the impulse response appears without delay
+        assert_float_eq!(reference[0], 1., abs <= 1e-5);
+        assert_float_eq!(reference[1], -0.5 * 0.999, abs <= 1e-5);
+        assert_float_eq!(reference[3], 0.999_f32.powi(3), abs <= 1e-5);
+
+        for &mode in &[
+            ConvolverLatencyMode::LowLatency,
+            ConvolverLatencyMode::Efficient,
+        ] {
+            let output = render_impulse(mode);
+            let latency = mode.partition_size() - BUFFER_SIZE as usize;
+
+            // same output, delayed by the latency
+            assert_float_eq!(&output[..latency], &vec![0.; latency][..], abs_all <= 1e-5);
+            assert_float_eq!(
+                &output[latency..],
+                &reference[..LENGTH - latency],
+                abs_all <= 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_fft_backend() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 1024, sample_rate);
+
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let ir = AudioBuffer::from_channels(vec![ChannelData::from(vec![0.5; 300])], sample_rate);
+        let options = ConvolverOptions {
+            buffer: Some(ir),
+            latency_mode: ConvolverLatencyMode::LowLatency,
+            fft_backend: Some(Box::new(CountingFft {
+                inner: RealFftProcessor::new(),
+                sizes: sizes.clone(),
+            })),
+            ..ConvolverOptions::default()
+        };
+        let convolver = ConvolverNode::new(&context, options);
+        convolver.connect(&context.destination());
+
+        let src = ConstantSourceNode::new(&context, ConstantSourceOptions::default());
+        src.connect(&convolver);
+
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+
+        // all transforms are twice the partition size
+        let sizes = sizes.lock().unwrap();
+        assert!(!sizes.is_empty());
+        assert!(sizes.iter().all(|&size| size == 1024));
+
+        // a constant input converges to the sum of the impulse response
+        let latency = convolver.latency();
+        assert_float_eq!(output[latency - 1], 0., abs <= 1e-5);
+        assert_float_eq!(output[latency + 299], 150., abs <= 1e-3);
+    }
+
+    #[test]
+    fn test_partition_size_uses_fft_backend() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 4096, sample_rate);
+
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let ir = AudioBuffer::from_channels(vec![ChannelData::from(vec![0.5; 300])], sample_rate);
+        let options = ConvolverOptions {
+            buffer: Some(ir),
+            latency_mode: ConvolverLatencyMode::LowLatency,
+            fft_backend: Some(Box::new(CountingFft {
+                inner: RealFftProcessor::new(),
+                sizes: sizes.clone(),
+            })),
+            ..ConvolverOptions::default()
+        };
+        let convolver = ConvolverNode::new(&context, options);
+        convolver.connect(&context.destination());
+        sizes.lock().unwrap().clear();
+
+        convolver.set_latency_mode(ConvolverLatencyMode::Efficient);
+
+        let src = ConstantSourceNode::new(&context, ConstantSourceOptions::default());
+        src.connect(&convolver);
+
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+
+        // the new partitions are transformed by the configured backend
+        let sizes = sizes.lock().unwrap();
+        assert!(!sizes.is_empty());
+        assert!(sizes.iter().all(|&size| size == 4096));
+
+        let latency = convolver.latency();
+        assert_eq!(latency, 2048 - 128);
+        assert_float_eq!(output[latency - 1], 0., abs <= 1e-5);
+        assert_float_eq!(output[latency + 299], 150., abs <= 1e-3);
+
+        // the replaced engine is handed back to the control thread
+        assert_eq!(convolver.replaced.len(), 1);
+    }
+
+    #[test]
+    fn test_channel_change_keeps_tail() {
+        use crate::node::{
+            AudioBufferSourceNode, AudioBufferSourceNodeOptions, AudioScheduledSourceNode,
+        };
+
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(2, LENGTH, sample_rate);
+
+        let ir: Vec<f32> = (0..3000).map(|i| 0.999_f32.powi(i)).collect();
+        let options = ConvolverOptions {
+            buffer: Some(AudioBuffer::from_channels(
+                vec![ChannelData::from(ir.clone())],
+                sample_rate,
+            )),
+            latency_mode: ConvolverLatencyMode::ZeroLatency,
+            ..ConvolverOptions::default()
+        };
+        let convolver = ConvolverNode::new(&context, options);
+        convolver.connect(&context.destination());
+
+        // stereo impulse, the input is mono once the source has ended
+        let mut left = vec![0.; 128];
+        left[0] = 1.;
+        let mut right = vec![0.; 128];
+        right[0] = 0.5;
+        let impulse = AudioBuffer::from_channels(
+            vec![ChannelData::from(left), ChannelData::from(right)],
+            sample_rate,
+        );
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(impulse),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        src.connect(&convolver);
+        src.start();
+
+        // a mono source keeps the convolver connected
+        let silence = ConstantSourceNode::new(&context, ConstantSourceOptions::default());
+        silence.offset().set_value(0.);
+        silence.connect(&convolver);
+
+        let output = context.start_rendering();
+
+        // the whole tail rings out in both channels
+        let right: Vec<f32> = ir.iter().map(|v| 0.5 * v).collect();
+        assert_float_eq!(
+            &output.channel_data(0).as_slice()[..3000],
+            &ir[..],
+            abs_all <= 1e-4
+        );
+        assert_float_eq!(
+            &output.channel_data(1).as_slice()[..3000],
+            &right[..],
+            abs_all <= 1e-4
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+        let convolver = ConvolverNode::new(&context, ConvolverOptions::default());
+        convolver.set_channel_count(3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_partition_size() {
+        let context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+        let convolver = ConvolverNode::new(&context, ConvolverOptions::default());
+        convolver.set_partition_size(100);
+    }
+}
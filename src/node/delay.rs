@@ -1,4 +1,7 @@
-use crate::buffer::{ChannelConfig, ChannelConfigOptions};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
 use crate::param::{AudioParam, AudioParamOptions};
 use crate::process::{AudioParamValues, AudioProcessor};
@@ -24,10 +27,14 @@ impl Default for DelayOptions {
 }
 
 /// Node that delays the incoming audio signal by a certain amount
+///
+/// A `DelayNode` is required in a cycle of the audio graph, e.g. for a feedback loop. Nodes in a
+/// cycle without `DelayNode` are muted. The delay of a cycle is at least one render quantum.
 pub struct DelayNode {
     registration: AudioContextRegistration,
     delay_time: AudioParam,
     channel_config: ChannelConfig,
+    clear: Arc<AtomicBool>,
 }
 
 impl AudioNode for DelayNode {
@@ -62,43 +69,85 @@ impl DelayNode {
 
             param.set_value_at_time(options.delay_time, 0.);
 
-            // allocate large enough buffer to store all delayed samples
+            // allocate large enough buffers to store all delayed samples
             let max_samples = options.max_delay_time * context.base().sample_rate().0 as f32;
             let max_quanta = (max_samples.ceil() as u32 + BUFFER_SIZE - 1) / BUFFER_SIZE;
-            let delay_buffer = Vec::with_capacity(max_quanta as usize);
+            let max_delay = (max_quanta * BUFFER_SIZE) as usize;
+            let delay_lines = (0..options.channel_config.count)
+                .map(|_| DelayLine::new(max_delay))
+                .collect();
+
+            let clear = Arc::new(AtomicBool::new(false));
 
             let render = DelayRenderer {
                 delay_time: proc,
-                delay_buffer,
-                index: 0,
+                delay_lines,
+                max_delay,
+                number_of_channels: 0,
+                quanta: 0,
+                clear: clear.clone(),
+                breaking_cycle: false,
             };
 
             let node = DelayNode {
                 registration,
                 channel_config: options.channel_config.into(),
                 delay_time: param,
+                clear,
             };
 
             (node, Box::new(render))
         })
     }
 
+    /// Delay in seconds (a-rate)
+    ///
+    /// The delay line currently reads the value at the start of each render quantum only.
     pub fn delay_time(&self) -> &AudioParam {
         &self.delay_time
     }
+
+    /// Set the delay time to a musical duration, for echoes synced to the tempo
+    ///
+    /// The duration is expressed in beats (quarter notes), e.g. `0.75` for a dotted eighth note,
+    /// at a tempo of `bpm` beats per minute. The resulting delay time is clamped to the
+    /// `max_delay_time` of this node.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `bpm` is not strictly positive
+    pub fn set_delay_time_in_beats(&self, beats: f64, bpm: f64) {
+        assert!(bpm > 0., "RangeError: bpm should be strictly positive");
+
+        let seconds = beats * 60. / bpm;
+        self.delay_time.set_value(seconds as f32);
+    }
+
+    /// Discard the samples currently held in the delay line
+    ///
+    /// Use this when retriggering a voice or seeking its source, so no echo of the previous sound
+    /// leaks into the new one. The delay line is cleared at the start of the next render quantum.
+    pub fn clear(&self) {
+        self.clear.store(true, Ordering::SeqCst);
+    }
 }
 
 struct DelayRenderer {
     delay_time: AudioParamId,
-    delay_buffer: Vec<crate::alloc::AudioBuffer>,
-    index: usize,
+    /// one delay line per channel, more are added when the number of input channels grows
+    delay_lines: Vec<DelayLine>,
+    /// capacity of the delay lines, in samples
+    max_delay: usize,
+    /// number of channels of the delayed signal, it does not shrink until the node is reset
+    number_of_channels: usize,
+    /// delay applied in the last render quantum, in render quanta
+    quanta: usize,
+    /// flag raised by the control thread to empty the delay line
+    clear: Arc<AtomicBool>,
+    /// the node breaks a cycle, its input is provided one render quantum late
+    breaking_cycle: bool,
 }
 
-// SAFETY:
-// AudioBuffers are not Send but we promise the `delay_buffer` Vec is emtpy before we ship it to
-// the render thread.
-unsafe impl Send for DelayRenderer {}
-
 impl AudioProcessor for DelayRenderer {
     fn process(
         &mut self,
@@ -112,29 +161,230 @@ impl AudioProcessor for DelayRenderer {
         let input = &inputs[0];
         let output = &mut outputs[0];
 
+        if self.clear.swap(false, Ordering::SeqCst) {
+            self.delay_lines.iter_mut().for_each(DelayLine::clear);
+        }
+
         // todo: a-rate processing
         let delay = params.get(&self.delay_time)[0];
 
         // calculate the delay in chunks of BUFFER_SIZE (todo: sub quantum delays)
-        let quanta = (delay * sample_rate.0 as f32) as usize / BUFFER_SIZE as usize;
-
-        if quanta == 0 {
-            // when no delay is set, simply copy input to output
-            *output = input.clone();
-        } else if self.delay_buffer.len() < quanta {
-            // still filling buffer
-            self.delay_buffer.push(input.clone());
-            // clear output, it may have been re-used
-            output.make_silent();
-        } else {
-            *output = std::mem::replace(&mut self.delay_buffer[self.index], input.clone());
-            // progress index
-            self.index = (self.index + 1) % quanta;
+        let mut quanta = (delay * sample_rate.0 as f32) as usize / BUFFER_SIZE as usize;
+        if self.breaking_cycle {
+            // the delay in a cycle cannot be shorter than a render quantum
+            quanta = quanta.max(1);
+        }
+        self.quanta = quanta;
+
+        // the input of a node breaking a cycle is already one render quantum late
+        if self.breaking_cycle {
+            quanta -= 1;
         }
+
+        let delay = quanta * BUFFER_SIZE as usize;
+
+        // keep delaying all channels seen so far, e.g. the stereo tail of a source that stopped
+        self.number_of_channels = self.number_of_channels.max(input.number_of_channels());
+        if self.delay_lines.len() < self.number_of_channels {
+            let max_delay = self.max_delay;
+            self.delay_lines
+                .resize_with(self.number_of_channels, || DelayLine::new(max_delay));
+        }
+
+        *output = input.clone();
+        output.mix(self.number_of_channels, ChannelInterpretation::Speakers);
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.delay_lines.iter_mut())
+            .for_each(|(channel, line)| {
+                channel.iter_mut().for_each(|v| *v = line.tick(*v, delay));
+            });
     }
 
     fn tail_time(&self) -> bool {
         // todo: return false when all inputs disconnected and buffer exhausted
         true
     }
+
+    fn reset(&mut self) {
+        self.delay_lines.iter_mut().for_each(DelayLine::clear);
+        self.number_of_channels = 0;
+    }
+
+    fn latency_samples(&self) -> f64 {
+        (self.quanta * BUFFER_SIZE as usize) as f64
+    }
+
+    fn can_break_cycle(&self) -> bool {
+        true
+    }
+
+    fn set_breaking_cycle(&mut self, breaking: bool) {
+        self.breaking_cycle = breaking;
+        // the length of the delay line changes
+        self.reset();
+    }
+}
+
+/// Sample accurate single channel delay line, the ring buffer of `DelayNode` and of the short
+/// delay effects
+pub(crate) struct DelayLine {
+    buffer: Vec<f32>,
+    write_index: usize,
+}
+
+impl DelayLine {
+    /// Create a delay line that can delay up to `max_delay` samples
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![0.; max_delay + 1],
+            write_index: 0,
+        }
+    }
+
+    /// Push the next input sample and return the sample pushed `delay` samples ago
+    ///
+    /// The delay is clamped to the maximum delay of this line.
+    pub fn tick(&mut self, input: f32, delay: usize) -> f32 {
+        let len = self.buffer.len();
+        let delay = delay.min(len - 1);
+
+        self.buffer[self.write_index] = input;
+        let output = self.buffer[(self.write_index + len - delay) % len];
+        self.write_index = (self.write_index + 1) % len;
+
+        output
+    }
+
+    /// Fill the delay line with silence
+    pub fn clear(&mut self) {
+        self.buffer.iter_mut().for_each(|v| *v = 0.);
+        self.write_index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, ManualAudioContext};
+    use crate::node::AudioNode;
+    use crate::test_util::impulse;
+    use crate::SampleRate;
+
+    #[test]
+    fn test_clear() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(1, sample_rate);
+
+        // signal during the first two render quanta only
+        let src = context.create_constant_source();
+        src.offset()
+            .set_value_at_time(0., 256. / f64::from(sample_rate.0));
+
+        // delay of two render quanta
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(300. / sample_rate.0 as f32);
+
+        src.connect(&delay);
+        delay.connect(&context.destination());
+
+        let mut output = [1.; 256];
+        context.render(&mut output);
+        assert_float_eq!(output[..], [0.; 256][..], abs_all <= 0.);
+
+        // the delayed signal would appear now
+        delay.clear();
+        let mut output = [1.; 512];
+        context.render(&mut output);
+        assert_float_eq!(output[..], [0.; 512][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_feedback_cycle() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(1, sample_rate);
+
+        let src = impulse(&context);
+
+        // feedback loop of one render quantum, halving the signal each round
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(128. / sample_rate.0 as f32);
+        let feedback = context.create_gain();
+        feedback.gain().set_value(0.5);
+
+        src.connect(&delay);
+        delay.connect(&feedback);
+        feedback.connect(&delay);
+        delay.connect(&context.destination());
+
+        let mut output = [0.; 512];
+        context.render(&mut output);
+
+        let mut expected = [0.; 512];
+        expected[128] = 1.;
+        expected[256] = 0.5;
+        expected[384] = 0.25;
+        assert_float_eq!(output[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_stereo_tail() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(2, sample_rate);
+
+        let left = context.create_constant_source();
+        let right = context.create_constant_source();
+        right.offset().set_value(0.5);
+        let merger = context.create_channel_merger(2);
+        left.connect_at(&merger, 0, 0).unwrap();
+        right.connect_at(&merger, 0, 1).unwrap();
+
+        // delay of two render quanta
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(256. / sample_rate.0 as f32);
+        merger.connect(&delay);
+        delay.connect(&context.destination());
+
+        // a single render quantum of stereo input, then the input falls back to mono silence
+        let mut output = [0.; 2 * 128];
+        context.render(&mut output);
+        merger.disconnect(&delay);
+
+        let mut output = [1.; 2 * 384];
+        context.render(&mut output);
+
+        let mut expected = [0.; 2 * 384];
+        expected[2 * 128..2 * 256]
+            .chunks_mut(2)
+            .for_each(|frame| frame.copy_from_slice(&[1., 0.5]));
+        assert_float_eq!(output[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_delay_time_in_beats() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(1, sample_rate);
+
+        let delay = context.create_delay(2.);
+        delay.connect(&context.destination());
+
+        let mut output = [0.; 128];
+
+        // quarter note at 120 BPM
+        delay.set_delay_time_in_beats(1., 120.);
+        context.render(&mut output);
+        assert_float_eq!(delay.delay_time().value(), 0.5, abs <= 0.);
+
+        // dotted eighth note at 100 BPM
+        delay.set_delay_time_in_beats(0.75, 100.);
+        context.render(&mut output);
+        assert_float_eq!(delay.delay_time().value(), 0.45, abs <= 1e-7);
+
+        // whole note at 60 BPM exceeds the max delay time
+        delay.set_delay_time_in_beats(4., 60.);
+        context.render(&mut output);
+        assert_float_eq!(delay.delay_time().value(), 2., abs <= 0.);
+    }
 }
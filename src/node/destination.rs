@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
 use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration};
 use crate::process::{AudioParamValues, AudioProcessor};
@@ -11,7 +14,74 @@ pub struct DestinationNode {
     pub(crate) channel_count: usize,
 }
 
-struct DestinationRenderer {}
+/// Output level above which the limiter starts to soft-clip
+const LIMITER_THRESHOLD: f32 = 0.9;
+
+/// Soft-clip the sample to the range (-1, 1), leaving values below the threshold untouched
+fn soft_clip(v: f32) -> f32 {
+    let abs = v.abs();
+    if abs <= LIMITER_THRESHOLD {
+        return v;
+    }
+
+    let headroom = 1. - LIMITER_THRESHOLD;
+    let clipped = LIMITER_THRESHOLD + headroom * ((abs - LIMITER_THRESHOLD) / headroom).tanh();
+    clipped.copysign(v)
+}
+
+/// Maximum number of output channels that can be muted individually
+const MAX_MUTED_CHANNELS: usize = 32;
+
+/// Settings and state of the runaway detection, shared with the destination renderer
+#[derive(Default)]
+pub(crate) struct RunawayDetection {
+    /// RMS level above which the output is running away, as f32 bits (0 = disabled)
+    threshold: AtomicU32,
+    /// number of consecutive render quanta above the threshold before the flag trips
+    quanta: AtomicU32,
+    /// the output level exceeded the threshold for the configured duration
+    detected: AtomicBool,
+}
+
+struct DestinationRenderer {
+    output_limiter: Arc<AtomicBool>,
+    muted_channels: Arc<AtomicU32>,
+    runaway_detection: Arc<RunawayDetection>,
+    /// number of consecutive render quanta above the runaway threshold
+    loud_quanta: u32,
+}
+
+impl DestinationRenderer {
+    /// Track the level of the mix, and raise the flag when it keeps exceeding the threshold
+    fn detect_runaway(&mut self, input: &crate::alloc::AudioBuffer) {
+        let threshold = f32::from_bits(self.runaway_detection.threshold.load(Ordering::SeqCst));
+        if threshold <= 0. {
+            self.loud_quanta = 0;
+            return;
+        }
+
+        let max_rms = input
+            .channels()
+            .iter()
+            .map(|channel| {
+                let sum: f32 = channel.iter().map(|v| v * v).sum();
+                (sum / channel.len() as f32).sqrt()
+            })
+            .fold(0., f32::max);
+
+        // a non finite level is the most extreme case of runaway
+        if max_rms > threshold || !max_rms.is_finite() {
+            self.loud_quanta += 1;
+            if self.loud_quanta >= self.runaway_detection.quanta.load(Ordering::SeqCst) {
+                self.runaway_detection
+                    .detected
+                    .store(true, Ordering::SeqCst);
+            }
+        } else {
+            self.loud_quanta = 0;
+        }
+    }
+}
 
 impl AudioProcessor for DestinationRenderer {
     fn process(
@@ -28,6 +98,24 @@ impl AudioProcessor for DestinationRenderer {
 
         // todo, actually fill cpal buffer here
         *output = input.clone();
+
+        // measure before limiting, which would hide the runaway
+        self.detect_runaway(input);
+
+        if self.output_limiter.load(Ordering::SeqCst) {
+            output.modify_channels(|channel| channel.iter_mut().for_each(|v| *v = soft_clip(*v)));
+        }
+
+        // hard mute, regardless of the mix
+        let muted = self.muted_channels.load(Ordering::SeqCst);
+        if muted != 0 {
+            output
+                .channels_mut()
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| muted & (1 << i) != 0)
+                .for_each(|(_, channel)| *channel = channel.silence());
+        }
     }
 
     fn tail_time(&self) -> bool {
@@ -71,18 +159,38 @@ impl AudioNode for DestinationNode {
     fn channel_count(&self) -> usize {
         self.channel_count
     }
+
+    fn max_channel_count(&self) -> usize {
+        self.max_channels_count() as _
+    }
 }
 
 impl DestinationNode {
     pub fn new<C: AsBaseAudioContext>(context: &C, channel_count: usize) -> Self {
+        Self::new_with_processor(context, channel_count, None)
+    }
+
+    /// Creates the destination, rendered by `processor` instead of the default renderer if given
+    pub(crate) fn new_with_processor<C: AsBaseAudioContext>(
+        context: &C,
+        channel_count: usize,
+        processor: Option<Box<dyn AudioProcessor>>,
+    ) -> Self {
         context.base().register(move |registration| {
             let node = Self {
                 registration,
                 channel_count,
             };
-            let proc = DestinationRenderer {};
+            let proc = processor.unwrap_or_else(|| {
+                Box::new(DestinationRenderer {
+                    output_limiter: context.base().output_limiter().clone(),
+                    muted_channels: context.base().muted_channels().clone(),
+                    runaway_detection: context.base().runaway_detection().clone(),
+                    loud_quanta: 0,
+                })
+            });
 
-            (node, Box::new(proc))
+            (node, proc)
         })
     }
 
@@ -91,4 +199,125 @@ impl DestinationNode {
     pub fn max_channels_count(&self) -> u32 {
         self.registration.context().base().channels()
     }
+
+    /// Enable or disable the output limiter (disabled by default)
+    ///
+    /// When enabled, the final mix is soft-clipped to the range [-1, 1] before it is sent to the
+    /// audio hardware, protecting the speakers (and ears) against accidental high levels. Signals
+    /// below 0.9 are left untouched.
+    pub fn set_output_limiter(&self, enabled: bool) {
+        self.registration
+            .context()
+            .base()
+            .output_limiter()
+            .store(enabled, Ordering::SeqCst);
+    }
+
+    /// Indicates if the output limiter is enabled
+    pub fn output_limiter(&self) -> bool {
+        self.registration
+            .context()
+            .base()
+            .output_limiter()
+            .load(Ordering::SeqCst)
+    }
+
+    /// Enable the detection of runaway output levels (disabled by default)
+    ///
+    /// Feedback loops with a gain of 1 or more grow silently until the output clips. With this
+    /// detection enabled, [`runaway_detected`](Self::runaway_detected) is raised once the RMS
+    /// level of an output channel exceeds `rms_threshold` during at least `duration` seconds.
+    /// The level is measured before the output limiter.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rms_threshold` is not strictly positive or if `duration` is
+    /// negative
+    pub fn enable_runaway_detection(&self, rms_threshold: f32, duration: f64) {
+        assert!(
+            rms_threshold > 0.,
+            "RangeError: the threshold should be strictly positive"
+        );
+        assert!(
+            duration >= 0.,
+            "RangeError: the duration should not be negative"
+        );
+
+        let base = self.registration.context().base();
+        let sample_rate = f64::from(base.sample_rate().0);
+        let quanta = (duration * sample_rate / f64::from(crate::BUFFER_SIZE)).ceil() as u32;
+
+        let detection = base.runaway_detection();
+        detection.quanta.store(quanta.max(1), Ordering::SeqCst);
+        detection
+            .threshold
+            .store(rms_threshold.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Disable the detection of runaway output levels, the flag keeps its value
+    pub fn disable_runaway_detection(&self) {
+        self.registration
+            .context()
+            .base()
+            .runaway_detection()
+            .threshold
+            .store(0f32.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Indicates if a runaway output level was detected since the last reset
+    pub fn runaway_detected(&self) -> bool {
+        self.registration
+            .context()
+            .base()
+            .runaway_detection()
+            .detected
+            .load(Ordering::SeqCst)
+    }
+
+    /// Clear the runaway flag, e.g. after fixing the gain of a feedback loop
+    pub fn reset_runaway_detected(&self) {
+        self.registration
+            .context()
+            .base()
+            .runaway_detection()
+            .detected
+            .store(false, Ordering::SeqCst);
+    }
+
+    /// Mute or unmute an output channel (all channels are unmuted by default)
+    ///
+    /// A muted channel outputs silence to the audio hardware, regardless of the mix. This is a
+    /// hard mute applied after the output limiter, useful to test speaker routing. Unlike a
+    /// per-channel gain, it cannot be automated.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `channel` is not lower than 32
+    pub fn set_channel_muted(&self, channel: usize, muted: bool) {
+        assert!(
+            channel < MAX_MUTED_CHANNELS,
+            "IndexSizeError: channel index should be lower than {}",
+            MAX_MUTED_CHANNELS
+        );
+
+        let mask = self.registration.context().base().muted_channels();
+        if muted {
+            mask.fetch_or(1 << channel, Ordering::SeqCst);
+        } else {
+            mask.fetch_and(!(1 << channel), Ordering::SeqCst);
+        }
+    }
+
+    /// Indicates if the output channel is muted
+    pub fn channel_muted(&self, channel: usize) -> bool {
+        channel < MAX_MUTED_CHANNELS
+            && self
+                .registration
+                .context()
+                .base()
+                .muted_channels()
+                .load(Ordering::SeqCst)
+                & (1 << channel)
+                != 0
+    }
 }
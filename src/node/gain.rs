@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use crate::buffer::{ChannelConfig, ChannelConfigOptions};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
 use crate::param::{AudioParam, AudioParamOptions};
@@ -26,6 +29,7 @@ pub struct GainNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
     gain: AudioParam,
+    muted: Arc<AtomicBool>,
 }
 
 impl AudioNode for GainNode {
@@ -60,25 +64,65 @@ impl GainNode {
 
             param.set_value_at_time(options.gain, 0.);
 
-            let render = GainRenderer { gain: proc };
+            let muted = Arc::new(AtomicBool::new(false));
+
+            let render = GainRenderer {
+                gain: proc,
+                muted: muted.clone(),
+            };
 
             let node = GainNode {
                 registration,
                 channel_config: options.channel_config.into(),
                 gain: param,
+                muted,
             };
 
             (node, Box::new(render))
         })
     }
 
+    /// Linear gain applied to the input (a-rate)
     pub fn gain(&self) -> &AudioParam {
         &self.gain
     }
+
+    /// Current gain in decibels, `f32::NEG_INFINITY` for a gain of zero
+    ///
+    /// The sign of the linear gain is ignored. Like [`AudioParam::value`], this reflects the value
+    /// of the last rendered quantum.
+    pub fn gain_db(&self) -> f32 {
+        20. * self.gain.value().abs().log10()
+    }
+
+    /// Set the gain in decibels, converted to a linear gain of `10^(db / 20)`
+    ///
+    /// Automation on the [`gain`](Self::gain) param always operates on the linear value, so a
+    /// `linear_ramp_to_value_at_time` between two gains converted from decibels is linear in
+    /// amplitude, not in decibels. Such a ramp sounds like it changes quickly at the start of a
+    /// fade-out and slowly at the end.
+    pub fn set_gain_db(&self, db: f32) {
+        self.gain.set_value(10_f32.powf(db / 20.));
+    }
+
+    /// Silence the output of the node, or restore it
+    ///
+    /// Muting is applied on top of the computed [`gain`](Self::gain): the automation of the param
+    /// keeps running while muted, so unmuting resumes at its current value. The change takes
+    /// effect at the next render quantum.
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::SeqCst);
+    }
+
+    /// Indicates if the output of the node is muted
+    pub fn muted(&self) -> bool {
+        self.muted.load(Ordering::SeqCst)
+    }
 }
 
 struct GainRenderer {
     gain: AudioParamId,
+    muted: Arc<AtomicBool>,
 }
 
 impl AudioProcessor for GainRenderer {
@@ -96,6 +140,14 @@ impl AudioProcessor for GainRenderer {
 
         let gain_values = params.get(&self.gain);
 
+        // keep the channel count of the input when muted
+        if self.muted.load(Ordering::SeqCst) {
+            *output = input.clone();
+            output.make_silent();
+            output.set_number_of_channels(input.number_of_channels());
+            return;
+        }
+
         *output = input.clone();
 
         output.modify_channels(|channel| {
@@ -110,3 +162,64 @@ impl AudioProcessor for GainRenderer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, ManualAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::SampleRate;
+
+    #[test]
+    fn test_gain_db() {
+        let mut context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+
+        let src = context.create_constant_source();
+        let gain = context.create_gain();
+        gain.set_gain_db(-6.);
+
+        src.connect(&gain);
+        gain.connect(&context.destination());
+
+        let output = context.start_rendering();
+        assert_float_eq!(
+            output.channel_data(0).as_slice(),
+            &[0.501; 128][..],
+            abs_all <= 1e-3
+        );
+
+        assert_float_eq!(gain.gain().value(), 0.501, abs <= 1e-3);
+        assert_float_eq!(gain.gain_db(), -6., abs <= 1e-5);
+    }
+
+    #[test]
+    fn test_muted_keeps_automation() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(1, sample_rate);
+
+        // ramp the gain from 0 to 1 over four render quanta
+        let src = context.create_constant_source();
+        let gain = context.create_gain();
+        gain.gain().set_value_at_time(0., 0.);
+        gain.gain()
+            .linear_ramp_to_value_at_time(1., 512. / f64::from(sample_rate.0));
+
+        src.connect(&gain);
+        gain.connect(&context.destination());
+
+        let mut output = [0.; 128];
+        context.render(&mut output);
+        assert_float_eq!(output[64], 64. / 512., abs <= 1e-5);
+
+        gain.set_muted(true);
+        assert!(gain.muted());
+        context.render(&mut output);
+        assert_float_eq!(output[..], [0.; 128][..], abs_all <= 0.);
+
+        // the ramp went on while muted
+        gain.set_muted(false);
+        context.render(&mut output);
+        assert_float_eq!(output[64], 320. / 512., abs <= 1e-5);
+    }
+}
@@ -0,0 +1,511 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::buffer::{AudioBuffer, ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::control::Scheduler;
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::rng::XorShift;
+use crate::{AtomicF64, SampleRate, BUFFER_SIZE};
+
+use super::{AudioNode, AudioScheduledSourceNode};
+
+/// Maximum number of simultaneously sounding grains per grain size
+const MAX_OVERLAP: f64 = 16.;
+
+/// Options for constructing a GrainPlayerNode
+pub struct GrainPlayerOptions {
+    /// Source audio, resampled to the sample rate of the context if needed
+    pub buffer: Option<AudioBuffer>,
+    /// Duration of a single grain, in seconds
+    pub grain_size: f64,
+    /// Number of grains sounding at the same time
+    pub overlap: f64,
+    /// Maximum random offset of the grain start positions, in seconds
+    pub jitter: f64,
+    /// Playback rate of the grains, 2 plays them an octave higher
+    pub pitch: f64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for GrainPlayerOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            grain_size: 0.1,
+            overlap: 2.,
+            jitter: 0.,
+            pitch: 1.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Grain settings shared between the node and the renderer
+struct GrainSettings {
+    grain_size: AtomicF64,
+    overlap: AtomicF64,
+    jitter: AtomicF64,
+    pitch: AtomicF64,
+}
+
+/// Source node for granular synthesis, firing short overlapping grains from an AudioBuffer
+///
+/// This is an extension to the Web Audio API specification. A playhead scans the buffer at
+/// normal speed, looping at its end, and a new grain is started from the playhead position every
+/// `grain_size / overlap` seconds. Each grain is shaped with a Hann window. The start positions
+/// can be randomized with `jitter` and the grains can be transposed with `pitch`, which turns
+/// even a very short buffer into an endless texture.
+///
+/// Note: do not forget to `start()` the node.
+pub struct GrainPlayerNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    scheduler: Scheduler,
+    settings: Arc<GrainSettings>,
+}
+
+impl AudioScheduledSourceNode for GrainPlayerNode {
+    fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+}
+
+impl AudioNode for GrainPlayerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        0
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl GrainPlayerNode {
+    /// Creates a `GrainPlayerNode`
+    ///
+    /// # Panics
+    ///
+    /// This function panics if any of the grain settings is out of range, see the setters
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: GrainPlayerOptions) -> Self {
+        assert_valid_grain_size(options.grain_size);
+        assert_valid_overlap(options.overlap);
+        assert_valid_jitter(options.jitter);
+        assert_valid_pitch(options.pitch);
+
+        context.base().register(move |registration| {
+            let sample_rate = context.base().sample_rate();
+            let mut buffer = options
+                .buffer
+                .unwrap_or_else(|| AudioBuffer::new(1, 0, sample_rate));
+            buffer.resample(sample_rate);
+
+            let scheduler = Scheduler::new();
+            let settings = Arc::new(GrainSettings {
+                grain_size: AtomicF64::new(options.grain_size),
+                overlap: AtomicF64::new(options.overlap),
+                jitter: AtomicF64::new(options.jitter),
+                pitch: AtomicF64::new(options.pitch),
+            });
+
+            let render = GrainPlayerRenderer {
+                buffer,
+                scheduler: scheduler.clone(),
+                settings: settings.clone(),
+                grains: Vec::with_capacity(MAX_OVERLAP as usize + 1),
+                playhead: 0,
+                next_grain: 0.,
+                rng: XorShift::new(XorShift::DEFAULT_SEED),
+            };
+
+            let node = GrainPlayerNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                scheduler,
+                settings,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Duration of a single grain, in seconds
+    pub fn grain_size(&self) -> f64 {
+        self.settings.grain_size.load()
+    }
+
+    /// Set the duration of a single grain, in seconds
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the grain size is not strictly positive
+    pub fn set_grain_size(&self, grain_size: f64) {
+        assert_valid_grain_size(grain_size);
+        self.settings.grain_size.store(grain_size);
+    }
+
+    /// Number of grains sounding at the same time
+    pub fn overlap(&self) -> f64 {
+        self.settings.overlap.load()
+    }
+
+    /// Set the number of grains sounding at the same time
+    ///
+    /// The grains are spaced `grain_size / overlap` seconds apart. With an overlap of 1 the
+    /// windowed grains leave audible gaps, 2 or more gives a continuous texture.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the overlap is not in the range `[1, 16]`
+    pub fn set_overlap(&self, overlap: f64) {
+        assert_valid_overlap(overlap);
+        self.settings.overlap.store(overlap);
+    }
+
+    /// Maximum random offset of the grain start positions, in seconds
+    pub fn jitter(&self) -> f64 {
+        self.settings.jitter.load()
+    }
+
+    /// Set the maximum random offset of the grain start positions, in seconds
+    ///
+    /// Each grain starts at the playhead position, offset by a random amount in the range
+    /// `[-jitter, jitter]`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the jitter is negative
+    pub fn set_jitter(&self, jitter: f64) {
+        assert_valid_jitter(jitter);
+        self.settings.jitter.store(jitter);
+    }
+
+    /// Playback rate of the grains
+    pub fn pitch(&self) -> f64 {
+        self.settings.pitch.load()
+    }
+
+    /// Set the playback rate of the grains, 2 plays them an octave higher
+    ///
+    /// The pitch does not affect the speed of the playhead nor the duration of the grains.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the pitch is not strictly positive
+    pub fn set_pitch(&self, pitch: f64) {
+        assert_valid_pitch(pitch);
+        self.settings.pitch.store(pitch);
+    }
+}
+
+fn assert_valid_grain_size(grain_size: f64) {
+    assert!(
+        grain_size > 0. && grain_size.is_finite(),
+        "RangeError: grain size should be strictly positive"
+    );
+}
+
+fn assert_valid_overlap(overlap: f64) {
+    assert!(
+        (1. ..=MAX_OVERLAP).contains(&overlap),
+        "RangeError: overlap should be in the range [1, {}]",
+        MAX_OVERLAP
+    );
+}
+
+fn assert_valid_jitter(jitter: f64) {
+    assert!(
+        jitter >= 0. && jitter.is_finite(),
+        "RangeError: jitter should be positive"
+    );
+}
+
+fn assert_valid_pitch(pitch: f64) {
+    assert!(
+        pitch > 0. && pitch.is_finite(),
+        "RangeError: pitch should be strictly positive"
+    );
+}
+
+/// A sounding grain
+struct Grain {
+    /// position in the source of the first sample-frame, may be fractional
+    start: f64,
+    /// number of sample-frames rendered so far
+    age: usize,
+    /// total number of sample-frames
+    len: usize,
+    /// playback rate
+    pitch: f64,
+}
+
+struct GrainPlayerRenderer {
+    buffer: AudioBuffer,
+    scheduler: Scheduler,
+    settings: Arc<GrainSettings>,
+    grains: Vec<Grain>,
+    /// position in the source the next grain starts from, in sample-frames
+    playhead: usize,
+    /// number of sample-frames until the next grain starts
+    next_grain: f64,
+    /// random generator for the jitter
+    rng: XorShift,
+}
+
+impl GrainPlayerRenderer {
+    fn spawn_grain(&mut self, sample_rate: f64) {
+        let len = self.buffer.sample_len() as f64;
+        let jitter = self.settings.jitter.load() * sample_rate * self.rng.next_f64();
+        let start = (self.playhead as f64 + jitter).rem_euclid(len);
+        // very short grains are clamped to a single sample-frame, so the spawn loop always advances
+        let grain_len = ((self.settings.grain_size.load() * sample_rate).round() as usize).max(1);
+
+        // the oldest grain is dropped when the overlap was lowered meanwhile
+        if self.grains.len() == self.grains.capacity() {
+            self.grains.remove(0);
+        }
+
+        self.grains.push(Grain {
+            start,
+            age: 0,
+            len: grain_len,
+            pitch: self.settings.pitch.load(),
+        });
+
+        self.next_grain += grain_len as f64 / self.settings.overlap.load();
+    }
+}
+
+impl AudioProcessor for GrainPlayerRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single output node
+        let output = &mut outputs[0];
+
+        let len = self.buffer.sample_len();
+        if len == 0 || !self.scheduler.is_active(timestamp) {
+            output.make_silent();
+            return;
+        }
+
+        let stop_index = self.scheduler.stop_index(timestamp, sample_rate);
+        let sample_rate = f64::from(sample_rate.0);
+        let number_of_channels = self.buffer.number_of_channels();
+        output.set_number_of_channels(number_of_channels);
+        output
+            .channels_mut()
+            .iter_mut()
+            .for_each(|channel| channel.iter_mut().for_each(|v| *v = 0.));
+
+        for i in 0..BUFFER_SIZE as usize {
+            while self.next_grain <= 0. {
+                self.spawn_grain(sample_rate);
+            }
+
+            // the periodic Hann windows of the grains sum up to overlap / 2
+            let gain = 2. / self.settings.overlap.load().max(2.);
+
+            for grain in &mut self.grains {
+                let phase = grain.age as f64 / grain.len as f64;
+                let window = gain * (0.5 - 0.5 * (2. * PI * phase).cos());
+
+                let position = (grain.start + grain.age as f64 * grain.pitch) % len as f64;
+                let index = position as usize;
+                let next = (index + 1) % len;
+                let frac = (position - index as f64) as f32;
+
+                for (c, channel) in output.channels_mut().iter_mut().enumerate() {
+                    let data = self.buffer.channel_data(c).as_slice();
+                    let value = data[index] + frac * (data[next] - data[index]);
+                    channel[i] += window as f32 * value;
+                }
+
+                grain.age += 1;
+            }
+            self.grains.retain(|grain| grain.age < grain.len);
+
+            self.playhead = (self.playhead + 1) % len;
+            self.next_grain -= 1.;
+        }
+
+        // silence the remainder of the render quantum after a stop
+        if let Some(index) = stop_index {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| channel[index..].iter_mut().for_each(|v| *v = 0.));
+        }
+    }
+
+    fn tail_time(&self) -> bool {
+        // no grains are rendered anymore once playback is stopped
+        !self.scheduler.ended()
+    }
+
+    fn reset(&mut self) {
+        self.grains.clear();
+        self.playhead = 0;
+        self.next_grain = 0.;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::buffer::ChannelData;
+    use crate::context::OfflineAudioContext;
+
+    /// RMS level of each block of `size` sample-frames
+    fn block_levels(signal: &[f32], size: usize) -> Vec<f32> {
+        signal
+            .chunks_exact(size)
+            .map(|block| (block.iter().map(|v| v * v).sum::<f32>() / size as f32).sqrt())
+            .collect()
+    }
+
+    #[test]
+    fn test_continuous_output() {
+        let sample_rate = SampleRate(44_100);
+
+        // 100 ms of a 440 Hz tone
+        let tone = (0..4_410)
+            .map(|i| (2. * PI * 440. * i as f32 / 44_100.).sin())
+            .collect();
+        let buffer = AudioBuffer::from_channels(vec![ChannelData::from(tone)], sample_rate);
+
+        // coherent grains rebuild the tone, randomized grains may partially cancel each other
+        for (jitter, min_level) in [(0., 0.65), (0.01, 0.05)] {
+            let mut context = OfflineAudioContext::new(1, 44_100, sample_rate);
+            let options = GrainPlayerOptions {
+                buffer: Some(buffer.clone()),
+                grain_size: 0.04,
+                overlap: 4.,
+                jitter,
+                ..GrainPlayerOptions::default()
+            };
+            let node = GrainPlayerNode::new(&context, options);
+            assert_eq!(node.overlap(), 4.);
+            node.connect(&context.destination());
+            node.start();
+
+            let output = context.start_rendering();
+            let output = output.channel_data(0).as_slice();
+
+            // after the first grain faded in, the output never drops out during the whole second
+            let grain_len = 1_764;
+            let levels = block_levels(&output[grain_len..], 441);
+            assert_eq!(levels.len(), 96);
+            levels
+                .iter()
+                .for_each(|&level| assert!(level > min_level, "{}", level));
+        }
+    }
+
+    #[test]
+    fn test_stop_within_render_quantum() {
+        let sample_rate = SampleRate(44_100);
+        let buffer =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![1.; 4_410])], sample_rate);
+
+        let mut context = OfflineAudioContext::new(1, 3 * 128, sample_rate);
+        let options = GrainPlayerOptions {
+            buffer: Some(buffer),
+            ..GrainPlayerOptions::default()
+        };
+        let node = GrainPlayerNode::new(&context, options);
+        node.connect(&context.destination());
+        node.start();
+        // halfway between sample-frames 200 and 201, in the second render quantum
+        node.stop_at(200.5 / 44_100.);
+
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+
+        assert!(output[..201].iter().skip(1).all(|&v| v != 0.));
+        assert!(output[201..].iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_grain_shorter_than_a_sample_frame() {
+        let sample_rate = SampleRate(44_100);
+        let buffer =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![1.; 441])], sample_rate);
+
+        let mut context = OfflineAudioContext::new(1, 128, sample_rate);
+        let options = GrainPlayerOptions {
+            buffer: Some(buffer),
+            grain_size: 1e-6,
+            ..GrainPlayerOptions::default()
+        };
+        let node = GrainPlayerNode::new(&context, options);
+        node.connect(&context.destination());
+        node.start();
+
+        // renders without spinning forever on zero length grains
+        let output = context.start_rendering();
+        assert_eq!(output.sample_len(), 128);
+    }
+
+    #[test]
+    fn test_tail_time_after_stop() {
+        let sample_rate = SampleRate(44_100);
+        let buffer =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![1.; 441])], sample_rate);
+
+        let context = OfflineAudioContext::new(1, 128, sample_rate);
+        let options = GrainPlayerOptions {
+            buffer: Some(buffer.clone()),
+            ..GrainPlayerOptions::default()
+        };
+        let node = GrainPlayerNode::new(&context, options);
+
+        let mut renderer = GrainPlayerRenderer {
+            buffer,
+            scheduler: node.scheduler.clone(),
+            settings: node.settings.clone(),
+            grains: Vec::with_capacity(MAX_OVERLAP as usize + 1),
+            playhead: 0,
+            next_grain: 0.,
+            rng: XorShift::new(1),
+        };
+        node.start();
+        node.stop_at(0.);
+
+        let alloc = crate::alloc::Alloc::with_capacity(1);
+        let mut outputs = [crate::alloc::AudioBuffer::new(alloc.silence())];
+        let nodes = std::collections::HashMap::new();
+
+        assert!(renderer.tail_time());
+        renderer.process(
+            &[],
+            &mut outputs,
+            AudioParamValues::from(&nodes),
+            0.,
+            sample_rate,
+        );
+        assert!(!renderer.tail_time());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_overlap() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let node = GrainPlayerNode::new(&context, GrainPlayerOptions::default());
+        node.set_overlap(0.5);
+    }
+}
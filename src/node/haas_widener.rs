@@ -0,0 +1,275 @@
+use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
+use crate::param::{AudioParam, AudioParamOptions};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::SampleRate;
+
+use super::{AudioNode, DelayLine};
+
+/// Maximum delay of the widened channel, in seconds
+const MAX_HAAS_DELAY: f32 = 0.03;
+
+/// Options for constructing a HaasWidenerNode
+pub struct HaasWidenerOptions {
+    /// delay of the right channel, in seconds (clamped to 0 - 30 ms)
+    pub delay_time: f32,
+    /// amount of widening, from 0 (mono) to 1 (fully delayed right channel)
+    pub width: f32,
+    /// attenuation of the delayed channel in dB
+    pub level_difference: f32,
+}
+
+impl Default for HaasWidenerOptions {
+    fn default() -> Self {
+        Self {
+            delay_time: 0.015,
+            width: 1.,
+            level_difference: 0.,
+        }
+    }
+}
+
+/// Node that widens a mono source to stereo using the precedence (Haas) effect
+///
+/// The input is downmixed to mono and copied to both output channels. The right channel is
+/// delayed by a few milliseconds (typically 5 - 30 ms) and optionally attenuated, which makes the
+/// sound appear wider while it is still perceived as coming from the left.
+///
+/// Beware: the widened signal collapses poorly to mono. Summing both channels results in comb
+/// filtering, so avoid this effect on material that will be played back on mono devices.
+pub struct HaasWidenerNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    delay_time: AudioParam,
+    width: AudioParam,
+}
+
+impl AudioNode for HaasWidenerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        1
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl HaasWidenerNode {
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: HaasWidenerOptions) -> Self {
+        context.base().register(move |registration| {
+            let delay_opts = AudioParamOptions {
+                min_value: 0.,
+                max_value: MAX_HAAS_DELAY,
+                default_value: 0.015,
+                automation_rate: crate::param::AutomationRate::A,
+            };
+            let (delay_param, delay_proc) = context
+                .base()
+                .create_audio_param(delay_opts, registration.id());
+            delay_param.set_value(options.delay_time);
+
+            let width_opts = AudioParamOptions {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 1.,
+                automation_rate: crate::param::AutomationRate::A,
+            };
+            let (width_param, width_proc) = context
+                .base()
+                .create_audio_param(width_opts, registration.id());
+            width_param.set_value(options.width);
+
+            let sample_rate = context.base().sample_rate().0 as f32;
+            let max_delay = (MAX_HAAS_DELAY * sample_rate).ceil() as usize;
+
+            let render = HaasWidenerRenderer {
+                delay_time: delay_proc,
+                width: width_proc,
+                delayed_gain: 10_f32.powf(-options.level_difference / 20.),
+                delay_line: DelayLine::new(max_delay),
+            };
+
+            // the input is always downmixed to mono
+            let channel_config = ChannelConfigOptions {
+                count: 1,
+                mode: ChannelCountMode::Explicit,
+                interpretation: ChannelInterpretation::Speakers,
+            };
+
+            let node = HaasWidenerNode {
+                registration,
+                channel_config: channel_config.into(),
+                delay_time: delay_param,
+                width: width_param,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Delay of the right channel, in seconds (a-rate)
+    pub fn delay_time(&self) -> &AudioParam {
+        &self.delay_time
+    }
+
+    /// Amount of widening, from 0 (both channels equal) to 1 (right channel fully delayed)
+    /// (a-rate)
+    pub fn width(&self) -> &AudioParam {
+        &self.width
+    }
+}
+
+struct HaasWidenerRenderer {
+    delay_time: AudioParamId,
+    width: AudioParamId,
+    delayed_gain: f32,
+    delay_line: DelayLine,
+}
+
+impl AudioProcessor for HaasWidenerRenderer {
+    fn process(
+        &mut self,
+        inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        params: AudioParamValues,
+        _timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let delay_values = params.get(&self.delay_time);
+        let width_values = params.get(&self.width);
+
+        *output = input.clone();
+        output.set_number_of_channels(2);
+
+        let right = output.channel_data_mut(1);
+        right
+            .iter_mut()
+            .zip(input.channel_data(0).iter())
+            .zip(delay_values.iter().zip(width_values.iter()))
+            .for_each(|((r, &i), (&delay, &width))| {
+                let delay = (delay * sample_rate.0 as f32).round() as usize;
+                let delayed = self.delay_line.tick(i, delay) * self.delayed_gain;
+                *r = (1. - width) * i + width * delayed;
+            });
+    }
+
+    fn tail_time(&self) -> bool {
+        // the delay line needs to drain
+        true
+    }
+
+    fn reset(&mut self) {
+        self.delay_line.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::test_util::impulse;
+    use crate::SampleRate;
+
+    use super::*;
+
+    const LENGTH: usize = 1024;
+
+    /// Render a single unit impulse through a widener with the given width
+    fn render_impulse(width: f32) -> crate::buffer::AudioBuffer {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(2, LENGTH, sample_rate);
+
+        let options = HaasWidenerOptions {
+            delay_time: 0.01,
+            width,
+            ..HaasWidenerOptions::default()
+        };
+        let widener = HaasWidenerNode::new(&context, options);
+
+        impulse(&context).connect(&widener);
+        widener.connect(&context.destination());
+
+        context.start_rendering()
+    }
+
+    #[test]
+    fn test_channels_differ_by_delay() {
+        let output = render_impulse(1.);
+        let left = output.channel_data(0).as_slice();
+        let right = output.channel_data(1).as_slice();
+
+        // 10 ms at 44.1 kHz
+        let delay = 441;
+
+        let mut expected_left = vec![0.; LENGTH];
+        expected_left[0] = 1.;
+        let mut expected_right = vec![0.; LENGTH];
+        expected_right[delay] = 1.;
+
+        assert_float_eq!(left, &expected_left[..], ulps_all <= 0);
+        assert_float_eq!(right, &expected_right[..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_mono_sum_preserved() {
+        // 10 ms at 44.1 kHz
+        let delay = 441;
+        let mut mono = vec![0.; LENGTH];
+        mono[0] = 1.;
+
+        for &width in &[0., 0.5, 1.] {
+            let output = render_impulse(width);
+            let left = output.channel_data(0).as_slice();
+            let right = output.channel_data(1).as_slice();
+
+            // each sample of the mono sum is the input plus its delayed copy, weighted by width
+            let sum: Vec<f32> = left.iter().zip(right).map(|(l, r)| l + r).collect();
+            let expected: Vec<f32> = (0..LENGTH)
+                .map(|i| {
+                    let delayed = if i >= delay { mono[i - delay] } else { 0. };
+                    (2. - width) * mono[i] + width * delayed
+                })
+                .collect();
+
+            assert_float_eq!(&sum[..], &expected[..], abs_all <= 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_level_difference() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(2, LENGTH, sample_rate);
+
+        let source = context.create_constant_source();
+        let options = HaasWidenerOptions {
+            delay_time: 0.005,
+            level_difference: 6.,
+            ..HaasWidenerOptions::default()
+        };
+        let widener = HaasWidenerNode::new(&context, options);
+
+        source.connect(&widener);
+        widener.connect(&context.destination());
+
+        let output = context.start_rendering();
+        let right = output.channel_data(1).as_slice();
+
+        // 5 ms at 44.1 kHz is 220.5 samples, which rounds to 221
+        assert_float_eq!(&right[..221], &[0.; 221][..], ulps_all <= 0);
+        let gain = 10_f32.powf(-6. / 20.);
+        assert_float_eq!(right[221], gain, abs <= 1e-6);
+        assert_float_eq!(right[LENGTH - 1], gain, abs <= 1e-6);
+    }
+}
@@ -12,7 +12,7 @@ use crate::{
     buffer::{ChannelConfig, ChannelConfigOptions},
     context::{AsBaseAudioContext, AudioContextRegistration},
     process::{AudioParamValues, AudioProcessor},
-    SampleRate, MAX_CHANNELS,
+    IndexSizeError, SampleRate, MAX_CHANNELS,
 };
 use num_complex::Complex;
 use std::f64::consts::PI;
@@ -120,19 +120,26 @@ impl IirFilterNode {
 
     /// Returns the frequency response for the specified frequencies
     ///
+    /// The response is evaluated from the filter coefficients, which are fixed at construction.
+    /// Frequencies outside of the range `[0, Nyquist]` are clamped in place.
+    ///
     /// # Arguments
     ///
     /// * `frequency_hz` - frequencies for which frequency response of the filter should be calculated
     /// * `mag_response` - magnitude of the frequency response of the filter
     /// * `phase_response` - phase of the frequency response of the filter
+    ///
+    /// # Errors
+    ///
+    /// Returns an `IndexSizeError` if the three slices do not have the same length
     #[allow(clippy::cast_possible_truncation)]
     pub fn get_frequency_response(
         &self,
         frequency_hz: &mut [f32],
         mag_response: &mut [f32],
         phase_response: &mut [f32],
-    ) {
-        self.validate_inputs(frequency_hz, mag_response, phase_response);
+    ) -> Result<(), IndexSizeError> {
+        self.validate_inputs(frequency_hz, mag_response, phase_response)?;
 
         for (i, &f) in frequency_hz.iter().enumerate() {
             let mut num: Complex<f64> = Complex::new(0., 0.);
@@ -163,6 +170,8 @@ impl IirFilterNode {
             mag_response[i] = h_f.norm() as f32;
             phase_response[i] = h_f.arg() as f32;
         }
+
+        Ok(())
     }
 
     /// validates that the params given to `get_frequency_response` method
@@ -170,19 +179,12 @@ impl IirFilterNode {
     fn validate_inputs(
         &self,
         frequency_hz: &mut [f32],
-        mag_response: &mut [f32],
-        phase_response: &mut [f32],
-    ) {
-        assert_eq!(
-            frequency_hz.len(),
-            mag_response.len(),
-            " InvalidAccessError: All paramaters should be the same length"
-        );
-        assert_eq!(
-            mag_response.len(),
-            phase_response.len(),
-            " InvalidAccessError: All paramaters should be the same length"
-        );
+        mag_response: &[f32],
+        phase_response: &[f32],
+    ) -> Result<(), IndexSizeError> {
+        if frequency_hz.len() != mag_response.len() || mag_response.len() != phase_response.len() {
+            return Err(IndexSizeError {});
+        }
 
         // Ensures that given frequencies are in the correct range
         let min = 0.;
@@ -190,6 +192,8 @@ impl IirFilterNode {
         for f in frequency_hz.iter_mut() {
             *f = f.clamp(min, max);
         }
+
+        Ok(())
     }
 }
 
@@ -295,6 +299,12 @@ impl AudioProcessor for IirFilterRenderer {
     fn tail_time(&self) -> bool {
         true
     }
+
+    fn reset(&mut self) {
+        self.states
+            .iter_mut()
+            .for_each(|state| *state = [0.; MAX_CHANNELS]);
+    }
 }
 
 impl IirFilterRenderer {
@@ -357,15 +367,11 @@ impl IirFilterRenderer {
 #[cfg(test)]
 mod test {
     use float_eq::assert_float_eq;
-    use realfft::num_traits::Zero;
-    use std::{cmp::min, fs::File};
 
     use crate::{
         buffer::ChannelConfigOptions,
         context::{AsBaseAudioContext, OfflineAudioContext},
-        media::{MediaElement, OggVorbisDecoder},
-        node::{AudioNode, AudioScheduledSourceNode},
-        snapshot, SampleRate,
+        SampleRate,
     };
 
     use super::{IirFilterNode, IirFilterOptions};
@@ -471,8 +477,7 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
-    fn panics_when_not_the_same_length() {
+    fn errors_when_not_the_same_length() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
         let feedforward = vec![
             0.000_016_636_797_512_844_526,
@@ -492,12 +497,13 @@ mod test {
         let mut mag_response = [0., 1.0];
         let mut phase_response = [0.];
 
-        biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert!(biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .is_err());
     }
 
     #[test]
-    #[should_panic]
-    fn panics_when_not_the_same_length_2() {
+    fn errors_when_not_the_same_length_2() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
         let feedforward = vec![
             0.000_016_636_797_512_844_526,
@@ -517,7 +523,9 @@ mod test {
         let mut mag_response = [0.];
         let mut phase_response = [0., 1.0];
 
-        biquad.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        assert!(biquad
+            .get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .is_err());
     }
 
     #[test]
@@ -544,7 +552,8 @@ mod test {
         let mut mag_response = [0., 0.];
         let mut phase_response = [0., 0.];
 
-        iir.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        iir.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
 
         let ref_arr = [0., niquyst];
         assert_float_eq!(frequency_hz, ref_arr, ulps_all <= 0);
@@ -588,30 +597,62 @@ mod test {
         let mut mag_response = [0.; 10];
         let mut phase_response = [0.; 10];
 
-        iir.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response);
+        iir.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
 
         assert_float_eq!(mag_response, ref_mag, ulps_all <= 0);
     }
 
     #[test]
-    fn default_periodic_wave_rendering_should_match_snapshot() {
-        // the snapshot data has been verified by fft to make sure that the frequency
-        // response correspond to a HP filter with Fc 4000 Hz
-        let ref_filtered =
-            snapshot::read("./snapshots/white_hp.json").expect("Reading snapshot file failed");
-
-        let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+    fn one_pole_lowpass_frequency_response() {
+        // y[n] = (1 - a) * x[n] + a * y[n - 1]
+        let a = 0.5;
+        let context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+        let iir = context.create_iir_filter(vec![1. - a], vec![1., -a]);
+
+        // at DC, a quarter of the sample rate and Nyquist
+        let mut frequency_hz = [0., 11_025., 22_050.];
+        let mut mag_response = [0.; 3];
+        let mut phase_response = [0.; 3];
+        iir.get_frequency_response(&mut frequency_hz, &mut mag_response, &mut phase_response)
+            .unwrap();
+
+        // H(w) = (1 - a) / (1 - a * e^(-jw))
+        #[allow(clippy::cast_possible_truncation)]
+        let ref_mag = [
+            1.,
+            ((1. - a) / (1. + a * a).sqrt()) as f32,
+            ((1. - a) / (1. + a)) as f32,
+        ];
+        #[allow(clippy::cast_possible_truncation)]
+        let ref_phase = [0., -a.atan() as f32, 0.];
+        assert_float_eq!(mag_response, ref_mag, abs_all <= 1e-6);
+        assert_float_eq!(phase_response, ref_phase, abs_all <= 1e-6);
+    }
 
-        // setup background music:
-        // read from local file
-        let file = File::open("white.ogg").unwrap();
-        // decode file to media stream
+    #[test]
+    #[cfg(feature = "ogg")]
+    fn highpass_rendering_should_match_difference_equation() {
+        use crate::buffer::AudioBuffer;
+        use crate::media::OggVorbisDecoder;
+        use crate::node::{AudioBufferSourceNode, AudioBufferSourceNodeOptions};
+        use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+        // decode the whole file up front, a media element would yield silence whenever its
+        // decoding thread lags behind the offline rendering
+        let file = std::fs::File::open("white.ogg").unwrap();
         let stream = OggVorbisDecoder::try_new(file).unwrap();
-        // wrap stream in MediaElement, so we can control it (loop, play/pause)
-        let media = MediaElement::new(stream);
-        // register as media element in the audio context
-        let background = context.create_media_element_source(media);
+        let white: AudioBuffer = stream.map(Result::unwrap).collect();
+        assert_eq!(white.sample_rate(), SampleRate(44_100));
 
+        let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(white.clone()),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+
+        // high-pass filter with Fc 4000 Hz
         let feedforward = vec![
             0.019_618_022_238_052_212,
             -0.036_007_928_102_449_24,
@@ -620,33 +661,39 @@ mod test {
         let feedback = vec![1., 1.576_436_200_538_313_7, 0.651_680_173_116_867_3];
 
         let options = IirFilterOptions {
-            feedback,
-            feedforward,
+            feedback: feedback.clone(),
+            feedforward: feedforward.clone(),
             channel_config: ChannelConfigOptions::default(),
         };
         let iir = IirFilterNode::new(&context, options);
 
-        background.connect(&iir);
+        src.connect(&iir);
         iir.connect(&context.destination());
 
-        background.start();
+        src.start();
         let output = context.start_rendering();
 
-        // retrieve processed data by removing silence chunk
-        // These silence slices are inserted inconsistently from an test execution to another
-        // Without these processing the test would be brittle
-        let data_ch: Vec<f32> = output
-            .channel_data(0)
-            .as_slice()
-            .iter()
-            .filter(|x| !x.is_zero())
-            .copied()
-            .collect();
-
-        let ref_data_ch = ref_filtered.data;
-
-        let min_len = min(data_ch.len(), ref_data_ch.len());
+        // y[n] = sum(b[k] * x[n - k]) - sum(a[k] * y[n - k]), with a[0] = 1
+        let input = white.channel_data(0).as_slice();
+        let mut expected = vec![0.; LENGTH];
+        for n in 0..LENGTH {
+            let mut y = 0.;
+            for k in 0..feedforward.len().min(n + 1) {
+                y += feedforward[k] * f64::from(input[n - k]);
+            }
+            for k in 1..feedback.len().min(n + 1) {
+                y -= feedback[k] * expected[n - k];
+            }
+            expected[n] = y;
+        }
 
-        assert_float_eq!(data_ch[0..min_len], ref_data_ch[0..min_len], ulps_all <= 0);
+        #[allow(clippy::cast_possible_truncation)]
+        let expected: Vec<f32> = expected.iter().map(|&y| y as f32).collect();
+        assert!(expected.iter().any(|y| y.abs() > 1e-3));
+        assert_float_eq!(
+            output.channel_data(0).as_slice(),
+            &expected[..],
+            abs_all <= 1e-6
+        );
     }
 }
@@ -1,9 +1,17 @@
-use crate::buffer::{ChannelConfig, ChannelConfigOptions, Resampler};
+use std::error::Error;
+
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+
+use crate::buffer::{
+    AudioBuffer, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelData,
+    ChannelInterpretation, Resampler,
+};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration};
 use crate::control::Scheduler;
 use crate::media::MediaStream;
+use crate::process::{AudioParamValues, AudioProcessor};
 
-use crate::BUFFER_SIZE;
+use crate::{SampleRate, BUFFER_SIZE};
 
 use super::{AudioNode, MediaStreamRenderer};
 
@@ -64,3 +72,255 @@ impl MediaStreamAudioSourceNode {
         })
     }
 }
+
+/// Number of render quanta buffered for the consumer of a [`MediaStreamAudioDestinationNode`]
+const DESTINATION_CAPACITY: usize = 64;
+
+/// Options for constructing a MediaStreamAudioDestinationNode
+pub struct MediaStreamAudioDestinationNodeOptions {
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for MediaStreamAudioDestinationNodeOptions {
+    fn default() -> Self {
+        Self {
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                mode: ChannelCountMode::Explicit,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// An audio destination capturing its input as a [`MediaStream`], e.g. to encode it to a file
+///
+/// Every render quantum flowing into the node is sent to the [`stream`](Self::stream). The render
+/// thread never waits for the consumer: when it falls behind by more than 64 render quanta, the
+/// new quanta are dropped (and a warning is logged).
+///
+/// The render thread copies its input into buffers allocated by the consumer, so it does not
+/// allocate as long as the number of channels stays the same.
+pub struct MediaStreamAudioDestinationNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    receiver: Receiver<AudioBuffer>,
+    recycle: Sender<AudioBuffer>,
+}
+
+impl AudioNode for MediaStreamAudioDestinationNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        1
+    }
+    fn number_of_outputs(&self) -> u32 {
+        0
+    }
+}
+
+impl MediaStreamAudioDestinationNode {
+    pub fn new<C: AsBaseAudioContext>(
+        context: &C,
+        options: MediaStreamAudioDestinationNodeOptions,
+    ) -> Self {
+        context.base().register(move |registration| {
+            let (sender, receiver) = crossbeam_channel::bounded(DESTINATION_CAPACITY);
+
+            // fill the pool of empty buffers for the render thread
+            let (recycle, pool) = crossbeam_channel::bounded(DESTINATION_CAPACITY);
+            let channels = options.channel_config.count;
+            let sample_rate = context.base().sample_rate();
+            for _ in 0..DESTINATION_CAPACITY {
+                recycle.send(empty_buffer(channels, sample_rate)).unwrap();
+            }
+
+            let render = MediaStreamDestinationRenderer {
+                sender,
+                pool,
+                dropping: false,
+            };
+
+            let node = MediaStreamAudioDestinationNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                receiver,
+                recycle,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// The captured audio, one `AudioBuffer` per render quantum
+    ///
+    /// The iterator blocks until the next render quantum is available, and ends when the node is
+    /// removed from the audio graph. All streams of a node share the same queue, so each buffer is
+    /// yielded by a single one of them.
+    pub fn stream(&self) -> MediaStreamDestination {
+        MediaStreamDestination {
+            receiver: self.receiver.clone(),
+            recycle: self.recycle.clone(),
+        }
+    }
+}
+
+/// The [`MediaStream`] of a [`MediaStreamAudioDestinationNode`]
+pub struct MediaStreamDestination {
+    receiver: Receiver<AudioBuffer>,
+    /// hands a new empty buffer to the render thread for every buffer received
+    recycle: Sender<AudioBuffer>,
+}
+
+impl Iterator for MediaStreamDestination {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = self.receiver.recv().ok()?;
+
+        // allocate the replacement here rather than on the render thread, with the layout of
+        // the latest render quantum
+        let empty = empty_buffer(buffer.number_of_channels(), buffer.sample_rate());
+        let _ = self.recycle.try_send(empty);
+
+        Some(Ok(buffer))
+    }
+}
+
+/// A silent render quantum for the render thread to fill
+///
+/// Unlike `AudioBuffer::new`, the channels do not share their data, so writing to them does not
+/// allocate.
+fn empty_buffer(channels: usize, sample_rate: SampleRate) -> AudioBuffer {
+    let channels = (0..channels)
+        .map(|_| ChannelData::new(BUFFER_SIZE as usize))
+        .collect();
+    AudioBuffer::from_channels(channels, sample_rate)
+}
+
+struct MediaStreamDestinationRenderer {
+    sender: Sender<AudioBuffer>,
+    /// empty buffers provided by the consumer
+    pool: Receiver<AudioBuffer>,
+    /// the consumer is behind, log a warning only once per overrun
+    dropping: bool,
+}
+
+impl AudioProcessor for MediaStreamDestinationRenderer {
+    fn process(
+        &mut self,
+        inputs: &[crate::alloc::AudioBuffer],
+        _outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single input node
+        let input = &inputs[0];
+
+        // every pooled buffer is in the queue, or not yet replaced by the consumer
+        let mut buffer = match self.pool.try_recv() {
+            Ok(buffer) => buffer,
+            Err(_) => {
+                self.drop_quantum();
+                return;
+            }
+        };
+
+        if buffer.number_of_channels() == input.number_of_channels()
+            && buffer.sample_rate() == sample_rate
+        {
+            buffer
+                .channels_mut()
+                .iter_mut()
+                .zip(input.channels())
+                .for_each(|(dest, src)| dest.as_mut_slice().copy_from_slice(src.as_ref()));
+        } else {
+            // the channel layout or sample rate changed, the consumer follows the new layout
+            let channels = input
+                .channels()
+                .iter()
+                .map(|channel| ChannelData::from(channel.to_vec()))
+                .collect();
+            buffer = AudioBuffer::from_channels(channels, sample_rate);
+        }
+
+        match self.sender.try_send(buffer) {
+            Ok(()) => self.dropping = false,
+            Err(TrySendError::Full(_)) => self.drop_quantum(),
+            // no more streams, nothing to do
+            Err(TrySendError::Disconnected(_)) => (),
+        }
+    }
+
+    fn tail_time(&self) -> bool {
+        false
+    }
+}
+
+impl MediaStreamDestinationRenderer {
+    /// the consumer is behind, log a warning at the start of the overrun
+    fn drop_quantum(&mut self) {
+        if !self.dropping {
+            log::warn!("MediaStreamAudioDestinationNode: consumer is behind, dropping audio");
+            self.dropping = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+
+    #[test]
+    fn test_capture_graph_output() {
+        let mut context = OfflineAudioContext::new(1, 4 * BUFFER_SIZE as usize, SampleRate(44_100));
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0.5, 0.);
+        src.offset().set_value_at_time(1., 256. / 44_100.);
+
+        let dest = context.create_media_stream_destination();
+        src.connect(&dest);
+        src.connect(&context.destination());
+        let mut stream = dest.stream();
+
+        let output = context.start_rendering();
+
+        // up-mixed to stereo
+        let captured: Vec<_> = stream.by_ref().take(4).map(Result::unwrap).collect();
+        for buffer in &captured {
+            assert_eq!(buffer.number_of_channels(), 2);
+            assert_eq!(buffer.sample_len(), BUFFER_SIZE as usize);
+            assert_eq!(buffer.sample_rate(), SampleRate(44_100));
+        }
+
+        let left: Vec<f32> = captured
+            .iter()
+            .flat_map(|b| b.channel_data(0).as_slice().to_vec())
+            .collect();
+        assert_eq!(&left[..], output.channel_data(0).as_slice());
+    }
+
+    #[test]
+    fn test_drop_when_consumer_is_behind() {
+        let quanta = DESTINATION_CAPACITY + 8;
+        let mut context =
+            OfflineAudioContext::new(1, quanta * BUFFER_SIZE as usize, SampleRate(44_100));
+        let src = context.create_constant_source();
+
+        let dest = context.create_media_stream_destination();
+        src.connect(&dest);
+        let stream = dest.stream();
+
+        // the render thread does not block on the full queue
+        context.start_rendering();
+        assert_eq!(stream.receiver.len(), DESTINATION_CAPACITY);
+    }
+}
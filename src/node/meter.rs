@@ -0,0 +1,423 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::buffer::{ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::filter::Upsampler;
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{AtomicF64, FilterQuality, SampleRate, BUFFER_SIZE, MAX_CHANNELS};
+
+/// Oversampling factor of the true-peak measurement, as recommended by ITU-R BS.1770
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+
+use super::AudioNode;
+
+/// Options for constructing a MeterNode
+#[derive(Default)]
+pub struct MeterOptions {
+    /// Release time constant of the peak and RMS levels (seconds), zero disables smoothing
+    pub decay_time: f64,
+    /// Quality of the oversampling filters of the true-peak measurement
+    pub quality: FilterQuality,
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// Levels of a single channel, shared between the node and the renderer
+#[derive(Default)]
+struct ChannelLevels {
+    /// smoothed highest absolute sample value of the last render quantum, as f32 bits
+    peak: AtomicU32,
+    /// smoothed highest absolute value of the oversampled signal of the last render quantum, as
+    /// f32 bits
+    true_peak: AtomicU32,
+    /// smoothed root mean square of the last render quantum, as f32 bits
+    rms: AtomicU32,
+    /// highest absolute sample value since the last reset, as f32 bits
+    peak_hold: AtomicU32,
+    /// a sample reached or exceeded full scale since the last reset
+    clipped: AtomicBool,
+}
+
+/// Pass-through node measuring the signal levels, for VU-style meters
+///
+/// The audio is left untouched. The levels are updated by the render thread every render
+/// quantum and can be read at any time from the control thread. Unlike the `AnalyserNode`, no
+/// FFT is computed.
+///
+/// With a non-zero [`decay_time`](Self::decay_time), the [`peak`](Self::peak),
+/// [`true_peak`](Self::true_peak) and [`rms`](Self::rms) levels rise instantly but fall back
+/// exponentially, which gives smoother visual meters.
+pub struct MeterNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    levels: Arc<Vec<ChannelLevels>>,
+    decay_time: Arc<AtomicF64>,
+}
+
+impl AudioNode for MeterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        1
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl MeterNode {
+    /// # Panics
+    ///
+    /// Will panic if the decay time is negative
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: MeterOptions) -> Self {
+        assert_valid_decay_time(options.decay_time);
+
+        context.base().register(move |registration| {
+            let levels: Arc<Vec<_>> = Arc::new(
+                (0..MAX_CHANNELS)
+                    .map(|_| ChannelLevels::default())
+                    .collect(),
+            );
+
+            let decay_time = Arc::new(AtomicF64::new(options.decay_time));
+
+            let upsamplers = (0..MAX_CHANNELS)
+                .map(|_| Upsampler::new(TRUE_PEAK_OVERSAMPLING, options.quality.taps_per_phase()))
+                .collect();
+
+            let render = MeterRenderer {
+                levels: levels.clone(),
+                decay_time: decay_time.clone(),
+                upsamplers,
+                oversampled: vec![0.; BUFFER_SIZE as usize * TRUE_PEAK_OVERSAMPLING],
+            };
+
+            let node = MeterNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                levels,
+                decay_time,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Levels of the given channel
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `channel` is not lower than `MAX_CHANNELS`
+    fn levels(&self, channel: usize) -> &ChannelLevels {
+        assert!(
+            channel < MAX_CHANNELS,
+            "IndexSizeError: channel index should be lower than {}",
+            MAX_CHANNELS
+        );
+
+        &self.levels[channel]
+    }
+
+    /// Highest absolute sample value of the channel in the last render quantum (linear amplitude)
+    ///
+    /// This is smoothed by the [`decay_time`](Self::decay_time).
+    pub fn peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.levels(channel).peak.load(Ordering::SeqCst))
+    }
+
+    /// Highest absolute value of the channel in the last render quantum, including the peaks
+    /// between the samples (linear amplitude)
+    ///
+    /// The signal is oversampled 4 times to estimate the level it reaches once converted to
+    /// analog, which can exceed the sample [`peak`](Self::peak) by up to 3 dB. The oversampling
+    /// filters delay this measurement by a few samples. This is smoothed by the
+    /// [`decay_time`](Self::decay_time).
+    pub fn true_peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.levels(channel).true_peak.load(Ordering::SeqCst))
+    }
+
+    /// Root mean square of the channel in the last render quantum (linear amplitude)
+    ///
+    /// This is smoothed by the [`decay_time`](Self::decay_time).
+    pub fn rms(&self, channel: usize) -> f32 {
+        f32::from_bits(self.levels(channel).rms.load(Ordering::SeqCst))
+    }
+
+    /// Highest absolute sample value of the channel since the last reset (linear amplitude)
+    pub fn peak_hold(&self, channel: usize) -> f32 {
+        f32::from_bits(self.levels(channel).peak_hold.load(Ordering::SeqCst))
+    }
+
+    /// Indicates if a sample of the channel reached or exceeded ±1.0 since the last reset
+    pub fn clipped(&self, channel: usize) -> bool {
+        self.levels(channel).clipped.load(Ordering::SeqCst)
+    }
+
+    /// Clear the levels, peak hold and clip indicator of all channels
+    pub fn reset_meters(&self) {
+        self.levels.iter().for_each(|levels| {
+            levels.peak.store(0f32.to_bits(), Ordering::SeqCst);
+            levels.true_peak.store(0f32.to_bits(), Ordering::SeqCst);
+            levels.rms.store(0f32.to_bits(), Ordering::SeqCst);
+            levels.peak_hold.store(0f32.to_bits(), Ordering::SeqCst);
+            levels.clipped.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Release time constant of the peak, true-peak and RMS levels (seconds)
+    pub fn decay_time(&self) -> f64 {
+        self.decay_time.load()
+    }
+
+    /// Set the release time constant of the peak, true-peak and RMS levels (seconds)
+    ///
+    /// When a level drops, the reported value falls by a factor `e` every `decay_time` seconds
+    /// instead of following the signal instantly. Zero disables smoothing.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the decay time is negative
+    pub fn set_decay_time(&self, decay_time: f64) {
+        assert_valid_decay_time(decay_time);
+        self.decay_time.store(decay_time);
+    }
+}
+
+/// Panics if the decay time is negative or NaN
+fn assert_valid_decay_time(decay_time: f64) {
+    assert!(
+        decay_time >= 0.,
+        "RangeError: decay time should not be negative, got {}",
+        decay_time
+    );
+}
+
+struct MeterRenderer {
+    levels: Arc<Vec<ChannelLevels>>,
+    decay_time: Arc<AtomicF64>,
+    /// interpolators of the true-peak measurement, one per channel
+    upsamplers: Vec<Upsampler>,
+    /// oversampled signal of a single channel
+    oversampled: Vec<f32>,
+}
+
+impl AudioProcessor for MeterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        *output = input.clone();
+
+        // fraction of the previous levels kept after a render quantum
+        let decay_time = self.decay_time.load();
+        let release = if decay_time > 0. {
+            (-(BUFFER_SIZE as f64) / (decay_time * sample_rate.0 as f64)).exp() as f32
+        } else {
+            0.
+        };
+
+        // channels missing from the input are silent
+        let channels = input.channels();
+        let oversampled = &mut self.oversampled;
+        let upsamplers = self.upsamplers.iter_mut();
+        self.levels
+            .iter()
+            .zip(upsamplers)
+            .enumerate()
+            .for_each(|(i, (levels, upsampler))| {
+                let (peak, true_peak, rms) = match channels.get(i) {
+                    Some(channel) => {
+                        let peak = channel.iter().fold(0f32, |peak, v| peak.max(v.abs()));
+                        let sum = channel.iter().map(|v| v * v).sum::<f32>();
+
+                        let wave = &mut oversampled[..channel.len() * TRUE_PEAK_OVERSAMPLING];
+                        upsampler.process(&channel[..], wave);
+                        let true_peak = wave.iter().fold(peak, |peak, v| peak.max(v.abs()));
+
+                        (peak, true_peak, (sum / channel.len() as f32).sqrt())
+                    }
+                    None => {
+                        upsampler.reset();
+                        (0., 0., 0.)
+                    }
+                };
+
+                let smooth = |level: &AtomicU32, value: f32| {
+                    let previous = f32::from_bits(level.load(Ordering::SeqCst));
+                    level.store(value.max(previous * release).to_bits(), Ordering::SeqCst);
+                };
+                smooth(&levels.peak, peak);
+                smooth(&levels.true_peak, true_peak);
+                smooth(&levels.rms, rms);
+
+                // the bit representation of positive floats has the same ordering
+                levels.peak_hold.fetch_max(peak.to_bits(), Ordering::SeqCst);
+                if peak >= 1. {
+                    levels.clipped.store(true, Ordering::SeqCst);
+                }
+            });
+    }
+
+    fn tail_time(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        self.upsamplers.iter_mut().for_each(Upsampler::reset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, ManualAudioContext, OfflineAudioContext};
+    use crate::node::{
+        AudioBufferSourceNode, AudioBufferSourceNodeOptions, AudioNode, AudioScheduledSourceNode,
+    };
+    use crate::SampleRate;
+
+    use super::MeterOptions;
+
+    #[test]
+    fn test_peak_and_rms() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 128, sample_rate);
+
+        // half of the quantum at 0.5, the other half silent
+        let src = context.create_constant_source();
+        src.offset().set_value(-0.5);
+        src.offset()
+            .set_value_at_time(0., 64. / f64::from(sample_rate.0));
+
+        let meter = context.create_meter();
+        src.connect(&meter);
+        meter.connect(&context.destination());
+
+        let output = context.start_rendering();
+        assert_eq!(output.channel_data(0).as_slice()[0], -0.5);
+
+        assert_float_eq!(meter.peak(0), 0.5, abs <= 0.);
+        assert_float_eq!(meter.rms(0), 0.125_f32.sqrt(), abs <= 1e-6);
+        assert_eq!(meter.peak(1), 0.);
+        assert_eq!(meter.rms(1), 0.);
+    }
+
+    #[test]
+    fn test_decay_time() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = ManualAudioContext::new(1, sample_rate);
+
+        let src = context.create_constant_source();
+        let options = MeterOptions {
+            // levels fall by a factor e every render quantum
+            decay_time: 128. / f64::from(sample_rate.0),
+            ..MeterOptions::default()
+        };
+        let meter = super::MeterNode::new(&context, options);
+        src.connect(&meter);
+        meter.connect(&context.destination());
+
+        let mut buffer = [0.; 128];
+        context.render(&mut buffer);
+        assert_float_eq!(meter.peak(0), 1., abs <= 0.);
+        assert_float_eq!(meter.rms(0), 1., abs <= 0.);
+
+        src.offset().set_value(0.);
+        context.apply_control_messages();
+        context.render(&mut buffer);
+        assert_float_eq!(meter.peak(0), (-1_f32).exp(), abs <= 1e-6);
+        assert_float_eq!(meter.rms(0), (-1_f32).exp(), abs <= 1e-6);
+
+        // without smoothing, the levels follow the signal
+        meter.set_decay_time(0.);
+        context.render(&mut buffer);
+        assert_eq!(meter.peak(0), 0.);
+        assert_eq!(meter.rms(0), 0.);
+    }
+
+    #[test]
+    fn test_true_peak() {
+        let sample_rate = SampleRate(48_000);
+        let mut context = OfflineAudioContext::new(1, 512, sample_rate);
+
+        // a full scale sine at a quarter of the sample rate, sampled between its peaks
+        let data: Vec<f32> = (0..512)
+            .map(|n| (std::f32::consts::FRAC_PI_2 * n as f32 + std::f32::consts::FRAC_PI_4).sin())
+            .collect();
+        let mut buffer = context.create_buffer(1, 512, sample_rate).unwrap();
+        buffer.copy_to_channel(&data, 0).unwrap();
+        let options = AudioBufferSourceNodeOptions {
+            buffer: Some(buffer),
+            ..AudioBufferSourceNodeOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+
+        let meter = context.create_meter();
+        src.connect(&meter);
+        meter.connect(&context.destination());
+        src.start();
+
+        context.start_rendering();
+
+        assert_float_eq!(meter.peak(0), std::f32::consts::FRAC_1_SQRT_2, abs <= 1e-4);
+        // the oversampled signal is within an eighth of a sample period of the actual peaks
+        let true_peak = meter.true_peak(0);
+        assert!(true_peak >= (std::f32::consts::PI / 16.).cos() - 1e-3);
+        assert!(true_peak <= 1.01);
+        assert_eq!(meter.true_peak(1), 0.);
+
+        meter.reset_meters();
+        assert_eq!(meter.true_peak(0), 0.);
+    }
+
+    #[test]
+    fn test_peak_hold_and_clip() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 512, sample_rate);
+
+        // a single transient spike in a quiet signal
+        let src = context.create_constant_source();
+        src.offset().set_value(0.2);
+        src.offset()
+            .set_value_at_time(1.5, 100. / f64::from(sample_rate.0));
+        src.offset()
+            .set_value_at_time(-0.3, 101. / f64::from(sample_rate.0));
+
+        let meter = context.create_meter();
+        src.connect(&meter);
+        meter.connect(&context.destination());
+
+        assert_eq!(meter.peak_hold(0), 0.);
+        assert!(!meter.clipped(0));
+
+        let output = context.start_rendering();
+        assert_eq!(output.channel_data(0).as_slice()[100], 1.5);
+
+        // the spike is retained after later quanta with lower levels
+        assert_eq!(meter.peak_hold(0), 1.5);
+        assert!(meter.clipped(0));
+
+        // unused channels
+        assert_eq!(meter.peak_hold(1), 0.);
+        assert!(!meter.clipped(1));
+
+        meter.reset_meters();
+        assert_eq!(meter.peak_hold(0), 0.);
+        assert!(!meter.clipped(0));
+    }
+}
@@ -28,6 +28,8 @@ mod channel_merger;
 pub use channel_merger::*;
 mod constant;
 pub use constant::*;
+mod convolver;
+pub use convolver::*;
 mod panner;
 pub use panner::*;
 mod analyzer;
@@ -42,6 +44,20 @@ mod waveshaper;
 pub use waveshaper::*;
 mod stereo_panner;
 pub use stereo_panner::*;
+mod haas_widener;
+pub use haas_widener::*;
+mod meter;
+pub use meter::*;
+mod noise;
+pub use noise::*;
+mod time_stretch;
+pub use time_stretch::*;
+mod parametric_eq;
+pub use parametric_eq::*;
+mod grain_player;
+pub use grain_player::*;
+mod script_processor;
+pub use script_processor::*;
 
 const TABLE_LENGTH_USIZE: usize = 2048;
 const TABLE_LENGTH_BY_4_USIZE: usize = TABLE_LENGTH_USIZE / 4;
@@ -77,6 +93,10 @@ pub trait AudioNode {
         self.registration().id()
     }
     fn channel_config_raw(&self) -> &ChannelConfig;
+    /// The channel configuration handed to the render thread at registration.
+    ///
+    /// The default implementation shares the underlying atomics with `channel_config_raw`, so
+    /// changing the channel count, mode or interpretation takes effect at the next render quantum.
     fn channel_config_cloned(&self) -> ChannelConfig {
         self.channel_config_raw().clone()
     }
@@ -87,11 +107,43 @@ pub trait AudioNode {
     }
 
     /// Connect the output of this AudioNode to the input of another node.
+    ///
+    /// The destination is returned, so connections can be chained:
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{AsBaseAudioContext, AudioContext};
+    /// use web_audio_api::node::AudioNode;
+    ///
+    /// let context = AudioContext::new(None);
+    /// let osc = context.create_oscillator();
+    /// let filter = context.create_biquad_filter();
+    /// let gain = context.create_gain();
+    ///
+    /// osc.connect(&filter)
+    ///     .connect(&gain)
+    ///     .connect(&context.destination());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - the connection closes a cycle in the graph which does not contain a `DelayNode`
     fn connect<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
         self.connect_at(dest, 0, 0).unwrap()
     }
 
     /// Connect a specific output of this AudioNode to a specific input of another node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `IndexSizeError` if the output or input does not exist
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - the connection closes a cycle in the graph which does not contain a `DelayNode`
     fn connect_at<'a>(
         &self,
         dest: &'a dyn AudioNode,
@@ -122,6 +174,40 @@ pub trait AudioNode {
         dest
     }
 
+    /// Disconnects a specific output of the AudioNode from a specific input of another node.
+    ///
+    /// Other connections between the two nodes are left intact.
+    fn disconnect_at<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: u32,
+        input: u32,
+    ) -> Result<&'a dyn AudioNode, crate::IndexSizeError> {
+        if self.context() != dest.context() {
+            panic!("attempting to disconnect nodes from different contexts");
+        }
+
+        if self.number_of_outputs() <= output || dest.number_of_inputs() <= input {
+            return Err(crate::IndexSizeError {});
+        }
+
+        self.context()
+            .disconnect_at(self.id(), dest.id(), output, input);
+
+        Ok(dest)
+    }
+
+    /// Disconnects all outgoing connections from a specific output of the AudioNode.
+    fn disconnect_output(&self, output: u32) -> Result<(), crate::IndexSizeError> {
+        if self.number_of_outputs() <= output {
+            return Err(crate::IndexSizeError {});
+        }
+
+        self.context().disconnect_output(self.id(), output);
+
+        Ok(())
+    }
+
     /// Disconnects all outgoing connections from the AudioNode.
     fn disconnect_all(&self) {
         self.context().disconnect_all(self.id());
@@ -156,11 +242,18 @@ pub trait AudioNode {
     fn set_channel_count(&self, v: usize) {
         self.channel_config_raw().set_count(v)
     }
+    /// The upper bound of the channel count this node supports.
+    ///
+    /// This is an extension to the Web Audio API specification, useful to validate values before
+    /// calling `set_channel_count`. Most nodes support up to `MAX_CHANNELS` channels.
+    fn max_channel_count(&self) -> usize {
+        crate::MAX_CHANNELS
+    }
 }
 
 /// Interface of source nodes, controlling start and stop times.
 /// The node will emit silence before it is started, and after it has ended.
-pub trait AudioScheduledSourceNode {
+pub trait AudioScheduledSourceNode: AudioNode {
     fn scheduler(&self) -> &Scheduler;
 
     /// Schedule playback start at this timestamp
@@ -173,6 +266,23 @@ pub trait AudioScheduledSourceNode {
         self.scheduler().stop_at(stop)
     }
 
+    /// Schedule playback start at `offset` seconds after the scheduled start of `other`
+    ///
+    /// This allows for tight layering of sources without manual time bookkeeping. Note that
+    /// `start()` schedules a start at time 0, so layering relative to a node that was started
+    /// immediately results in a start at `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` has not been scheduled to start yet
+    fn start_relative_to(&self, other: &dyn AudioScheduledSourceNode, offset: f64) {
+        let start = other
+            .scheduler()
+            .start_time()
+            .expect("InvalidStateError: other node has not been scheduled to start");
+        self.start_at(start + offset);
+    }
+
     /// Play immediately
     fn start(&self) {
         self.start_at(0.);
@@ -182,6 +292,22 @@ pub trait AudioScheduledSourceNode {
     fn stop(&self) {
         self.stop_at(0.);
     }
+
+    /// Register a callback to run once playback has ended, replacing any previous callback
+    ///
+    /// Playback ends when the stop time is reached, or when a finite source (a buffer, a media
+    /// stream) runs out. The callback runs exactly once, on a helper thread shared by all
+    /// callbacks of the context, so it should not block. A `ManualAudioContext` runs it on the
+    /// rendering thread instead, before `render` returns. It is not run if the node is dropped
+    /// before playback ends.
+    fn set_onended<F: FnOnce() + Send + 'static>(&self, callback: F)
+    where
+        Self: Sized,
+    {
+        self.registration()
+            .context()
+            .set_onended(self.id(), self.scheduler(), Box::new(callback));
+    }
 }
 
 /// Interface of source nodes, controlling pause/loop/offsets.
@@ -240,12 +366,12 @@ impl<R: MediaStream> AudioProcessor for MediaStreamRenderer<R> {
         outputs: &mut [crate::alloc::AudioBuffer],
         _params: AudioParamValues,
         timestamp: f64,
-        _sample_rate: SampleRate,
+        sample_rate: SampleRate,
     ) {
         // single output node
         let output = &mut outputs[0];
 
-        // todo, sub-quantum start/stop
+        // todo, sub-quantum start
         if !self.scheduler.is_active(timestamp) {
             output.make_silent();
             return;
@@ -268,16 +394,26 @@ impl<R: MediaStream> AudioProcessor for MediaStreamRenderer<R> {
             Some(Err(e)) => {
                 log::warn!("Error playing audio stream: {}", e);
                 self.finished = true; // halt playback
+                self.scheduler.mark_ended();
                 output.make_silent()
             }
             None => {
                 if !self.finished {
                     log::debug!("Stream finished");
                     self.finished = true;
+                    self.scheduler.mark_ended();
                 }
                 output.make_silent()
             }
         }
+
+        // silence the remainder of the render quantum after a stop
+        if let Some(index) = self.scheduler.stop_index(timestamp, sample_rate) {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| channel[index..].iter_mut().for_each(|v| *v = 0.));
+        }
     }
 
     fn tail_time(&self) -> bool {
@@ -0,0 +1,227 @@
+use crate::buffer::{ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::rng::XorShift;
+use crate::SampleRate;
+
+use super::AudioNode;
+
+/// Spectrum of the noise emitted by a `NoiseNode`
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum NoiseType {
+    /// Equal power per frequency
+    #[default]
+    White,
+    /// Equal power per octave, the power density falls by 3 dB per octave
+    Pink,
+    /// Brownian noise, the power density falls by 6 dB per octave
+    Brown,
+}
+
+/// Options for constructing a NoiseNode
+pub struct NoiseOptions {
+    pub type_: NoiseType,
+    /// Seed of the random generator, the same seed always produces the same noise
+    pub seed: u64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for NoiseOptions {
+    fn default() -> Self {
+        Self {
+            type_: NoiseType::default(),
+            seed: XorShift::DEFAULT_SEED,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Audio source emitting mono white, pink or brown noise
+///
+/// The samples are drawn from a seeded pseudo random generator, so renders are reproducible.
+/// The output stays roughly in the range `[-1, 1]`.
+pub struct NoiseNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    type_: NoiseType,
+}
+
+impl AudioNode for NoiseNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        0
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl NoiseNode {
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: NoiseOptions) -> Self {
+        context.base().register(move |registration| {
+            let render = NoiseRenderer {
+                type_: options.type_,
+                rng: XorShift::new(options.seed),
+                filter: [0.; 7],
+            };
+
+            let node = NoiseNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                type_: options.type_,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Spectrum of the emitted noise
+    pub fn type_(&self) -> NoiseType {
+        self.type_
+    }
+}
+
+struct NoiseRenderer {
+    type_: NoiseType,
+    /// source of the white noise
+    rng: XorShift,
+    /// state of the pink noise filter, or of the brown noise integrator in the first slot
+    filter: [f32; 7],
+}
+
+impl NoiseRenderer {
+    /// Random value in the range `[-1, 1)`
+    fn white(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    /// Pink noise, white noise filtered with the refined method of Paul Kellet
+    fn pink(&mut self) -> f32 {
+        let white = self.white();
+        let b = &mut self.filter;
+
+        b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+        b[2] = 0.96900 * b[2] + white * 0.153_852;
+        b[3] = 0.86650 * b[3] + white * 0.310_485_6;
+        b[4] = 0.55000 * b[4] + white * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - white * 0.016_898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115_926;
+
+        // compensate the gain of the filter
+        pink * 0.11
+    }
+
+    /// Brown noise, leaky integration of white noise
+    fn brown(&mut self) -> f32 {
+        let white = self.white();
+        let last = &mut self.filter[0];
+
+        *last = (*last + 0.02 * white) / 1.02;
+
+        // compensate the gain of the integrator
+        *last * 3.5
+    }
+}
+
+impl AudioProcessor for NoiseRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) {
+        // single output node
+        let output = &mut outputs[0];
+        output.force_mono();
+
+        let generate = match self.type_ {
+            NoiseType::White => Self::white,
+            NoiseType::Pink => Self::pink,
+            NoiseType::Brown => Self::brown,
+        };
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .for_each(|o| *o = generate(self));
+    }
+
+    fn tail_time(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::test_util::render_mono;
+    use crate::SampleRate;
+
+    use super::{NoiseNode, NoiseOptions, NoiseType};
+
+    fn render(type_: NoiseType, seed: u64) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, 4096, SampleRate(44_100));
+        let options = NoiseOptions {
+            type_,
+            seed,
+            ..NoiseOptions::default()
+        };
+        let noise = NoiseNode::new(&context, options);
+        assert_eq!(noise.type_(), type_);
+        noise.connect(&context.destination());
+
+        render_mono(&mut context)
+    }
+
+    /// Mean absolute difference between consecutive samples
+    fn roughness(signal: &[f32]) -> f32 {
+        signal.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f32>() / signal.len() as f32
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        for &type_ in &[NoiseType::White, NoiseType::Pink, NoiseType::Brown] {
+            let first = render(type_, 42);
+            assert_eq!(first, render(type_, 42));
+            assert_ne!(first, render(type_, 43));
+
+            assert!(first.iter().any(|&v| v != 0.));
+        }
+
+        // only white noise is bounded, the filtered noises are normalized on average but may
+        // occasionally peak above 1
+        let white = render(NoiseType::White, 42);
+        assert!(white.iter().all(|v| v.abs() <= 1.));
+    }
+
+    #[test]
+    fn test_spectrum_tilt() {
+        // the lower the high frequency content, the smoother the signal
+        let white = roughness(&render(NoiseType::White, 1));
+        let pink = roughness(&render(NoiseType::Pink, 1));
+        let brown = roughness(&render(NoiseType::Brown, 1));
+
+        assert!(white > pink);
+        assert!(pink > brown);
+    }
+
+    #[test]
+    fn test_factory() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let noise = context.create_noise();
+        assert_eq!(noise.type_(), NoiseType::White);
+        assert_eq!(noise.number_of_inputs(), 0);
+    }
+}
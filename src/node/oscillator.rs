@@ -6,10 +6,9 @@
     clippy::perf,
     clippy::missing_docs_in_private_items
 )]
-use std::f32::consts::PI;
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 use crate::alloc::ChannelData;
 use crate::buffer::{ChannelConfig, ChannelConfigOptions};
@@ -20,8 +19,16 @@ use crate::process::{AudioParamValues, AudioProcessor};
 use crate::SampleRate;
 
 use crossbeam_channel::{self, Receiver, Sender};
+use realfft::num_complex::Complex;
+use realfft::num_traits::Zero;
+use realfft::RealFftPlanner;
+use std::convert::TryFrom;
+use std::f32::consts::PI;
 
-use super::{AudioNode, AudioScheduledSourceNode, SINETABLE, TABLE_LENGTH_F32, TABLE_LENGTH_USIZE};
+use super::{
+    AudioNode, AudioScheduledSourceNode, SINETABLE, TABLE_LENGTH_BY_4_USIZE, TABLE_LENGTH_F32,
+    TABLE_LENGTH_USIZE,
+};
 
 /// Options for constructing a periodic wave
 pub struct PeriodicWaveOptions {
@@ -170,7 +177,7 @@ impl PeriodicWave {
                     let i_len = i.len();
                     (vec![0.; i_len], i)
                 }
-                _ => (vec![0.0, 1.0], vec![0., 0.]),
+                _ => (vec![0., 0.], vec![0., 1.]),
             };
 
             Self {
@@ -180,12 +187,40 @@ impl PeriodicWave {
             }
         } else {
             Self {
-                real: vec![0., 1.],
-                imag: vec![0., 0.],
+                real: vec![0., 0.],
+                imag: vec![0., 1.],
                 disable_normalization: false,
             }
         }
     }
+
+    /// Computes one period of the waveform, sampled on `TABLE_LENGTH_USIZE` points
+    ///
+    /// The harmonics are read from `SINETABLE`, a quarter period ahead for the cosine terms.
+    /// Unless normalization is disabled, the table is scaled to a peak amplitude of 1.
+    fn wavetable(&self) -> Vec<f32> {
+        let mut table = vec![0.; TABLE_LENGTH_USIZE];
+
+        // the DC-offset (index 0) is ignored
+        for (k, (&real, &imag)) in self.real.iter().zip(&self.imag).enumerate().skip(1) {
+            for (n, sample) in table.iter_mut().enumerate() {
+                let idx = (k * n) % TABLE_LENGTH_USIZE;
+                let cos = SINETABLE[(idx + TABLE_LENGTH_BY_4_USIZE) % TABLE_LENGTH_USIZE];
+                *sample += real.mul_add(cos, imag * SINETABLE[idx]);
+            }
+        }
+
+        if !self.disable_normalization {
+            let peak = table.iter().fold(0_f32, |max, v| max.max(v.abs()));
+            if peak > 0. {
+                for v in &mut table {
+                    *v /= peak;
+                }
+            }
+        }
+
+        table
+    }
 }
 
 /// Options for constructing an `OscillatorNode`
@@ -261,15 +296,8 @@ impl From<u32> for OscillatorType {
 enum OscMsg {
     /// represents all data required to build a periodic wave processing
     PeriodicWaveMsg {
-        /// `computed_freq` is computed from `frequency` and `detune`
-        computed_freq: f32,
-        /// wavetable computed at runtime and following periodic wave charateristics
-        dyn_wavetable: Vec<f32>,
-        /// Peak normalization factor to apply to output
-        /// if `disable_normalization` is set to false
-        norm_factor: f32,
-        /// if set to false, normalization factor is applied
-        disable_normalization: bool,
+        /// one period of the waveform, following the periodic wave charateristics
+        wavetable: Vec<f32>,
     },
 }
 
@@ -281,8 +309,6 @@ pub struct OscillatorNode {
     registration: AudioContextRegistration,
     /// Infos about audio node channel configuration
     channel_config: ChannelConfig,
-    /// Sample rate (equals to audio context sample rate)
-    sample_rate: f32,
     /// The frequency of the fundamental frequency.
     frequency: AudioParam,
     /// A detuning value (in cents) which will offset the frequency by the given amount.
@@ -380,7 +406,7 @@ impl OscillatorNode {
 
             let scheduler = Scheduler::new();
 
-            let (sender, receiver) = crossbeam_channel::bounded(0);
+            let (sender, receiver) = crossbeam_channel::unbounded();
 
             let computed_freq = default_freq * (default_det / 1200.).exp2();
 
@@ -399,7 +425,6 @@ impl OscillatorNode {
             let node = Self {
                 registration,
                 channel_config: channel_config.unwrap_or_default().into(),
-                sample_rate,
                 frequency: f_param,
                 detune: det_param,
                 type_,
@@ -411,7 +436,7 @@ impl OscillatorNode {
         })
     }
 
-    /// Returns the frequency audio parameter
+    /// Returns the frequency audio parameter (a-rate)
     /// The oscillator frequency is calculated as follow:
     /// frequency * 2^(detune/1200)
     #[must_use]
@@ -419,7 +444,7 @@ impl OscillatorNode {
         &self.frequency
     }
 
-    /// Returns the detune audio parameter. detune unity is cents.
+    /// Returns the detune audio parameter (a-rate). detune unity is cents.
     /// The oscillator frequency is calculated as follow:
     /// frequency * 2^(detune/1200)
     #[must_use]
@@ -427,13 +452,6 @@ impl OscillatorNode {
         &self.detune
     }
 
-    /// Returns the `computedOscFrequency` which is the oscillator frequency.
-    fn computed_freq(&self) -> f32 {
-        let frequency = self.frequency().value();
-        let detune = self.detune().value();
-        frequency * (detune / 1200.).exp2()
-    }
-
     /// Returns the oscillator type
     #[must_use]
     pub fn type_(&self) -> OscillatorType {
@@ -480,167 +498,135 @@ impl OscillatorNode {
 
     /// set the oscillator type to custom and generate
     /// a perdioc waveform following the `PeriodicWave` characteristics
-    pub fn set_periodic_wave(&mut self, periodic_wave: PeriodicWave) {
+    ///
+    /// The waveform is precomputed here, the renderer only reads it at the oscillator frequency.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the renderer of the node was dropped, i.e. its context is closed
+    // takes the wave by value, like `setPeriodicWave` hands it over to the oscillator
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_periodic_wave(&self, periodic_wave: PeriodicWave) {
         // The oscillator type is set to custom following the spec
         self.change_type(OscillatorType::Custom);
 
-        let PeriodicWave {
-            real,
-            imag,
-            disable_normalization,
-        } = periodic_wave;
-
-        let mut cplxs = Vec::with_capacity(real.len());
-        let mut norms = Vec::with_capacity(real.len());
-        let mut phases = Vec::with_capacity(real.len());
-        let mut incr_phases = Vec::with_capacity(real.len());
-        let mut interpol_ratios = Vec::with_capacity(real.len());
-
-        // update cplxs
-        for cplx in real.into_iter().zip(imag) {
-            cplxs.push(cplx);
-        }
-
-        let computed_freq = self.computed_freq();
-
-        for (idx, (real, img)) in cplxs.iter().enumerate() {
-            // update norms
-            norms.push((f32::powi(*real, 2_i32) + f32::powi(*img, 2_i32)).sqrt());
-
-            // update phases
-            let phase = f32::atan2(*img, *real);
-            if phase < 0. {
-                phases.push(2.0_f32.mul_add(PI, phase) * (TABLE_LENGTH_F32 / (2.0 * PI)));
-            } else {
-                phases.push(phase * (TABLE_LENGTH_F32 / (2.0 * PI)));
-            }
-
-            // update incr_phases
-            // 0 through max value 8192 casts without loss of precision
-            #[allow(clippy::cast_precision_loss)]
-            incr_phases.push(TABLE_LENGTH_F32 * idx as f32 * (computed_freq / self.sample_rate));
-        }
-
-        // update interpol_ratios
-        for incr_phase in &incr_phases {
-            interpol_ratios.push((incr_phase - incr_phase.round()).abs());
-        }
-
-        // generate the wavetable following periodic wave characteristics
-        let dyn_wavetable =
-            Self::generate_wavetable(&norms, &mut phases, &incr_phases, &interpol_ratios);
-
-        // update norm_factor
-        let norm_factor = Self::norm_factor(&dyn_wavetable);
-
         self.sender
             .send(OscMsg::PeriodicWaveMsg {
-                computed_freq,
-                dyn_wavetable,
-                norm_factor,
-                disable_normalization,
+                wavetable: periodic_wave.wavetable(),
             })
             .expect("Sending periodic wave to the node renderer failed");
     }
+}
 
-    /// Generate the wavetable
-    ///
-    /// # Arguments
-    ///
-    /// * `norms` - the norm of each harmonics
-    /// * `phases` - the phase of each harmonics
-    /// * `incr_phases` - the phase to increment of each harmonics
-    /// * `interpol_ratios` - the interpolation ratio of each harmonics used by linear interpolation
-    fn generate_wavetable(
-        norms: &[f32],
-        phases: &mut [f32],
-        incr_phases: &[f32],
-        interpol_ratios: &[f32],
-    ) -> Vec<f32> {
-        let mut buffer = Vec::new();
-
-        while phases[1] <= TABLE_LENGTH_F32 {
-            let mut sample = 0.0;
-            for i in 1..phases.len() {
-                let gain = norms[i];
-                let phase = phases[i];
-                let incr_phase = incr_phases[i];
-                let mu = interpol_ratios[i];
-                // truncation is desired
-                #[allow(clippy::cast_possible_truncation)]
-                // phase + incr_phase is always positive
-                #[allow(clippy::cast_sign_loss)]
-                let idx = (phase + incr_phase) as usize;
-                let inf_idx = idx % TABLE_LENGTH_USIZE;
-                let sup_idx = (idx + 1) % TABLE_LENGTH_USIZE;
-                // Linear interpolation
-                sample += SINETABLE[inf_idx].mul_add(1. - mu, SINETABLE[sup_idx] * mu) * gain;
-                phases[i] = phase + incr_phase;
-            }
+/// Number of harmonics of the richest band-limited wavetable, the highest one below the Nyquist
+/// frequency of a table of `TABLE_LENGTH_USIZE` points
+const MAX_HARMONICS: u16 = 1023;
+
+/// Number of harmonics of each band-limited wavetable, two tables per octave
+///
+/// The harmonic counts decrease from `MAX_HARMONICS` down to the fundamental only.
+fn band_harmonics() -> Vec<u16> {
+    let mut harmonics = vec![MAX_HARMONICS];
+    let mut count = MAX_HARMONICS;
+
+    while count > 1 {
+        // half an octave lower, 181 / 256 is close to sqrt(0.5)
+        count = u16::try_from(u32::from(count) * 181 / 256).map_or(1, |c| c.clamp(1, count - 1));
+        harmonics.push(count);
+    }
 
-            buffer.push(sample);
-        }
+    harmonics
+}
 
-        buffer
-    }
+/// Index of the richest band-limited wavetable without harmonics above `max_harmonics`
+fn band_index(max_harmonics: f32) -> usize {
+    BAND_HARMONICS
+        .iter()
+        .position(|&count| f32::from(count) <= max_harmonics)
+        .unwrap_or(BAND_HARMONICS.len() - 1)
+}
 
-    /// Compute the normalization factor
-    ///
-    /// The normalization factor is applied as a gain to the periodic wave
-    /// to normalize the signal peak amplitude in the interval [-1.0,1.0].
+/// Wavetables of a waveform, with its harmonics limited for several frequency ranges
+///
+/// An oscillator reads the richest table without harmonics above the Nyquist frequency, so the
+/// square, sawtooth and triangle shapes do not alias.
+struct BandLimitedWavetables {
+    /// one period of the waveform for each of the `BAND_HARMONICS`, read like `SINETABLE`
+    tables: Vec<Vec<f32>>,
+}
+
+impl BandLimitedWavetables {
+    /// Synthesizes the tables from the sine terms of the Fourier series of the waveform
     ///
     /// # Arguments
     ///
-    /// * `buffer` - the wavetable generated from periodic wave charateristics
-    fn norm_factor(buffer: &[f32]) -> f32 {
-        1. / buffer
+    /// * `coefficient` - amplitude of the sine term of the k-th harmonic
+    fn new(coefficient: fn(u16) -> f32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let c2r = planner.plan_fft_inverse(TABLE_LENGTH_USIZE);
+        let mut spectrum = c2r.make_input_vec();
+
+        let tables = BAND_HARMONICS
             .iter()
-            .copied()
-            .reduce(f32::max)
-            .expect("Maximum value not found")
+            .map(|&count| {
+                // a sine term of amplitude b is the inverse transform of -b/2 i
+                spectrum.fill(Complex::zero());
+                for (k, c) in (1..=count).zip(spectrum.iter_mut().skip(1)) {
+                    *c = Complex::new(0., -coefficient(k) / 2.);
+                }
+
+                let mut table = c2r.make_output_vec();
+                c2r.process(&mut spectrum, &mut table)
+                    .expect("Synthesizing a band-limited wavetable failed");
+                table
+            })
+            .collect();
+
+        Self { tables }
     }
 }
 
-/// States relative to Sine `OscillatorType`
-struct SineState {
-    /// linear interpolation ratio
-    interpol_ratio: f32,
-    /// if set to true, requires sine parameters to be initialized  
-    needs_init: bool,
+/// Sine term of the k-th harmonic of a square wave: odd harmonics of amplitude `4 / (k PI)`
+fn square_coefficient(k: u16) -> f32 {
+    if k.is_multiple_of(2) {
+        0.
+    } else {
+        4. / (f32::from(k) * PI)
+    }
 }
 
-/// States relative to Triangle `OscillatorType`
-struct TriangleState {
-    /// this memory state is used in the leaky integrator
-    last_output: f32,
+/// Sine term of the k-th harmonic of a sawtooth wave rising from -1 to 1: `-2 / (k PI)`
+fn sawtooth_coefficient(k: u16) -> f32 {
+    -2. / (f32::from(k) * PI)
 }
 
-/// States relative to Custom `OscillatorType`
-struct PeriodicState {
-    /// incremental phase for each harmonics
-    incr_phases: Vec<f32>,
-    /// linear interpolation ratio for each harmonics
-    interpol_ratios: Vec<f32>,
-    /// Peak normalization factor
-    norm_factor: Option<f32>,
-    /// if set to false, apply `norm_factor`
-    disable_normalization: bool,
-    /// States required to generate the periodic wavetable
-    wavetable: WavetableState,
+/// Sine term of the k-th harmonic of a triangle wave: odd harmonics of alternating amplitude
+/// `8 / (k PI)^2`
+fn triangle_coefficient(k: u16) -> f32 {
+    let amplitude = 8. / (f32::from(k) * PI).powi(2);
+    match k % 4 {
+        1 => amplitude,
+        3 => -amplitude,
+        _ => 0.,
+    }
 }
 
-/// States required to generate the periodic wavetable
-struct WavetableState {
-    /// dynamic wavetable
-    /// computes each time periodic wave paramaters change
-    dyn_table: Vec<f32>,
-    /// current phase of the oscillator
-    phase: f32,
-    /// phase amount to add to the oscillator phase
-    /// which generates an output at `computed_freq` frequency
-    incr_phase: f32,
-    /// frequency for which `WavetableState` has been computed
-    ref_freq: f32,
+/// Number of harmonics of the band-limited wavetables
+static BAND_HARMONICS: LazyLock<Vec<u16>> = LazyLock::new(band_harmonics);
+/// Band-limited square waves
+static SQUARE_TABLES: LazyLock<BandLimitedWavetables> =
+    LazyLock::new(|| BandLimitedWavetables::new(square_coefficient));
+/// Band-limited sawtooth waves
+static SAWTOOTH_TABLES: LazyLock<BandLimitedWavetables> =
+    LazyLock::new(|| BandLimitedWavetables::new(sawtooth_coefficient));
+/// Band-limited triangle waves
+static TRIANGLE_TABLES: LazyLock<BandLimitedWavetables> =
+    LazyLock::new(|| BandLimitedWavetables::new(triangle_coefficient));
+
+/// States relative to Custom `OscillatorType`
+struct PeriodicState {
+    /// one period of the waveform, read like `SINETABLE`
+    wavetable: Vec<f32>,
 }
 
 /// Rendering component of the oscillator node
@@ -659,14 +645,12 @@ struct OscillatorRenderer {
     computed_freq: f32,
     /// channel between control and renderer parts (sender part)
     sample_rate: f32,
-    /// current phase of the oscillator
+    /// current phase of the oscillator, as an index in the wavetable
     phase: f32,
     /// phase amount to add to phase at each tick
     incr_phase: f32,
-    /// states required to build a sine wave
-    sine: SineState,
-    /// states required to build a triangle wave
-    triangle: TriangleState,
+    /// index of the band-limited wavetable for the current frequency
+    band: usize,
     /// states required to build a custom oscillator
     periodic: PeriodicState,
 }
@@ -678,7 +662,7 @@ impl AudioProcessor for OscillatorRenderer {
         outputs: &mut [crate::alloc::AudioBuffer],
         params: AudioParamValues,
         timestamp: f64,
-        _sample_rate: SampleRate,
+        sample_rate: SampleRate,
     ) {
         // single output node
         let output = &mut outputs[0];
@@ -686,7 +670,7 @@ impl AudioProcessor for OscillatorRenderer {
         // re-use previous buffer
         output.force_mono();
 
-        // todo, sub-quantum start/stop
+        // todo, sub-quantum start
         if !self.scheduler.is_active(timestamp) {
             output.make_silent();
             return;
@@ -719,26 +703,25 @@ impl AudioProcessor for OscillatorRenderer {
         // check if any message was send from the control thread
         if let Ok(msg) = self.receiver.try_recv() {
             match msg {
-                OscMsg::PeriodicWaveMsg {
-                    computed_freq,
-                    dyn_wavetable,
-                    norm_factor,
-                    disable_normalization,
-                } => self.set_periodic_wave(
-                    computed_freq,
-                    dyn_wavetable,
-                    norm_factor,
-                    disable_normalization,
-                ),
+                OscMsg::PeriodicWaveMsg { wavetable } => self.periodic.wavetable = wavetable,
             }
         }
 
         self.generate_output(type_, buffer, &computed_freqs[..]);
+
+        // silence the remainder of the render quantum after a stop
+        if let Some(index) = self.scheduler.stop_index(timestamp, sample_rate) {
+            buffer[index..].iter_mut().for_each(|v| *v = 0.);
+        }
     }
 
     fn tail_time(&self) -> bool {
         true
     }
+
+    fn reset(&mut self) {
+        self.phase = 0.;
+    }
 }
 
 /// Helper struct which regroups all parameters
@@ -782,71 +765,15 @@ impl OscillatorRenderer {
             sample_rate,
             periodic_wave,
         } = config;
-        let incr_phase = computed_freq / sample_rate;
-        let interpol_ratio = (incr_phase - incr_phase.floor()) * TABLE_LENGTH_F32;
-
-        let PeriodicWave {
-            real,
-            imag,
-            disable_normalization,
-        } = periodic_wave.map_or_else(
-            || PeriodicWave {
-                real: vec![0., 1.],
-                imag: vec![0., 0.],
-                disable_normalization: false,
-            },
-            |p_w| p_w,
-        );
+        let incr_phase = computed_freq / sample_rate * TABLE_LENGTH_F32;
+        let band = band_index(sample_rate / 2. / computed_freq.abs());
 
-        let cplxs: Vec<(f32, f32)> = real.iter().zip(&imag).map(|(&r, &i)| (r, i)).collect();
-
-        let norms: Vec<f32> = cplxs
-            .iter()
-            .map(|(r, i)| (f32::powi(*r, 2_i32) + f32::powi(*i, 2_i32)).sqrt())
-            .collect();
-
-        let mut phases: Vec<f32> = cplxs
-            .iter()
-            .map(|(r, i)| {
-                let phase = f32::atan2(*i, *r);
-                if phase < 0. {
-                    2.0_f32.mul_add(PI, phase) * (TABLE_LENGTH_F32 / (2.0 * PI))
-                } else {
-                    phase * (TABLE_LENGTH_F32 / (2.0 * PI))
-                }
-            })
-            .collect();
-
-        // 0 through max value 8192 casts without loss of precision
-        #[allow(clippy::cast_precision_loss)]
-        let incr_phases: Vec<f32> = cplxs
-            .iter()
-            .enumerate()
-            .map(|(idx, _)| TABLE_LENGTH_F32 * idx as f32 * (computed_freq / sample_rate))
-            .collect();
-
-        let interpol_ratios: Vec<f32> = incr_phases
-            .iter()
-            .map(|incr_phase| incr_phase - incr_phase.floor())
-            .collect();
-
-        let mut periodic_wavetable = Vec::with_capacity(2048);
-
-        Self::generate_wavetable(
-            &norms,
-            &mut phases,
-            &incr_phases,
-            &interpol_ratios,
-            &mut periodic_wavetable,
+        // the default periodic wave is a sine
+        let wavetable = periodic_wave.map_or_else(
+            || SINETABLE.clone(),
+            |periodic_wave| periodic_wave.wavetable(),
         );
 
-        let norm_factor = if disable_normalization {
-            None
-        } else {
-            let norm_factor = Self::norm_factor(&periodic_wavetable);
-            Some(norm_factor)
-        };
-
         Self {
             type_,
             frequency,
@@ -857,124 +784,28 @@ impl OscillatorRenderer {
             sample_rate,
             phase: 0.0,
             incr_phase,
-            sine: SineState {
-                interpol_ratio,
-                needs_init: true,
-            },
-            triangle: TriangleState { last_output: 0.0 },
-            periodic: PeriodicState {
-                incr_phases,
-                interpol_ratios,
-                norm_factor,
-                disable_normalization,
-                wavetable: WavetableState {
-                    dyn_table: periodic_wavetable,
-                    phase: 0.,
-                    incr_phase: 1.,
-                    ref_freq: computed_freq,
-                },
-            },
+            band,
+            periodic: PeriodicState { wavetable },
         }
     }
 
-    /// set periodic states
-    ///
-    /// # Arguments
-    ///
-    /// * `ref_freq` - the `computedOscFrequency` used to build the wavetable
-    /// * `dyn_wavetable` - wavetable following periodic wave characteristics
-    /// * `norm_factor` - normalization factor applied when `disable_normalization` is false
-    /// * `disable_normalization` - disable normalization. If false, the peak amplitude signal is 1.0
-    fn set_periodic_wave(
-        &mut self,
-        ref_freq: f32,
-        dyn_wavetable: Vec<f32>,
-        norm_factor: f32,
-        disable_normalization: bool,
-    ) {
-        self.periodic.wavetable.ref_freq = ref_freq;
-        self.periodic.wavetable.dyn_table = dyn_wavetable;
-        self.periodic.norm_factor = Some(norm_factor);
-        self.periodic.disable_normalization = disable_normalization;
-    }
-
-    /// Compute params at each audio sample for the following oscillator type:
-    /// * sine
-    /// * sawtooth
-    /// * triangle
-    /// * and square
+    /// Compute params at each audio sample: the phase increment and the band-limited wavetable
+    /// for the current frequency
     #[inline]
-    fn arate_params(&mut self, type_: OscillatorType, computed_freq: f32) {
+    fn arate_params(&mut self, computed_freq: f32) {
         // No need to compute if frequency has not changed
-        if type_ == OscillatorType::Sine {
-            if self.sine.needs_init {
-                self.sine.needs_init = false;
-                self.incr_phase = computed_freq / self.sample_rate * TABLE_LENGTH_F32;
-            }
-            if (self.computed_freq - computed_freq).abs() < 0.01 {
-                return;
-            }
-            self.computed_freq = computed_freq;
-            self.incr_phase = computed_freq / self.sample_rate * TABLE_LENGTH_F32;
-        }
         if (self.computed_freq - computed_freq).abs() < 0.01 {
             return;
         }
         self.computed_freq = computed_freq;
-        self.incr_phase = computed_freq / self.sample_rate;
-    }
-
-    /// Compute params at each audio sample for the custom oscillator type
-    #[inline]
-    fn arate_periodic_params(&mut self, new_comp_freq: f32) {
-        // No need to compute if frequency has not changed
-        if (self.computed_freq - new_comp_freq).abs() < 0.01 {
-            return;
-        }
-
-        for incr_phase in &mut self.periodic.incr_phases {
-            *incr_phase *= new_comp_freq / self.computed_freq;
-        }
-
-        for (r, incr_ph) in self
-            .periodic
-            .interpol_ratios
-            .iter_mut()
-            .zip(self.periodic.incr_phases.iter())
-        {
-            *r = incr_ph - incr_ph.floor();
-        }
-
-        self.periodic.wavetable.incr_phase = new_comp_freq / self.periodic.wavetable.ref_freq;
-        self.computed_freq = new_comp_freq;
+        self.incr_phase = computed_freq / self.sample_rate * TABLE_LENGTH_F32;
+        self.band = band_index(self.sample_rate / 2. / computed_freq.abs());
     }
 
     /// generate the audio data according to the oscillator type and frequency parameters
     /// buffer is filled with the generated audio data.
     ///
-    /// # Arguments
-    ///
-    /// * `type_` - oscillator type (sine,sawtooth,triangle,square, or custom)
-    /// * `buffer` - audio output buffer
-    /// * `freq_values` - frequencies at which each sample should be generated
-    #[inline]
-    fn generate_output(
-        &mut self,
-        type_: OscillatorType,
-        buffer: &mut ChannelData,
-        freq_values: &[f32],
-    ) {
-        match type_ {
-            OscillatorType::Sine => self.generate_sine(type_, buffer, freq_values),
-            OscillatorType::Square => self.generate_square(type_, buffer, freq_values),
-            OscillatorType::Sawtooth => self.generate_sawtooth(type_, buffer, freq_values),
-            OscillatorType::Triangle => self.generate_triangle(type_, buffer, freq_values),
-            OscillatorType::Custom => self.generate_custom(buffer, freq_values),
-        }
-    }
-
-    /// generate the audio data when oscillator is of type sine
-    /// buffer is filled with the sine audio data.
+    /// All types read a wavetable, the square, sawtooth and triangle types a band-limited one.
     ///
     /// # Arguments
     ///
@@ -982,26 +813,33 @@ impl OscillatorRenderer {
     /// * `buffer` - audio output buffer
     /// * `freq_values` - frequencies at which each sample should be generated
     #[inline]
-    fn generate_sine(
+    fn generate_output(
         &mut self,
         type_: OscillatorType,
         buffer: &mut ChannelData,
         freq_values: &[f32],
     ) {
         for (o, &computed_freq) in buffer.iter_mut().zip(freq_values) {
-            self.arate_params(type_, computed_freq);
+            self.arate_params(computed_freq);
+
+            let table: &[f32] = match type_ {
+                OscillatorType::Sine => &SINETABLE,
+                OscillatorType::Square => &SQUARE_TABLES.tables[self.band],
+                OscillatorType::Sawtooth => &SAWTOOTH_TABLES.tables[self.band],
+                OscillatorType::Triangle => &TRIANGLE_TABLES.tables[self.band],
+                OscillatorType::Custom => &self.periodic.wavetable,
+            };
+
             // truncation is desired
             #[allow(clippy::cast_possible_truncation)]
             // phase is always positive
             #[allow(clippy::cast_sign_loss)]
             let inf_idx = self.phase as usize;
             let sup_idx = (inf_idx + 1) % TABLE_LENGTH_USIZE;
+            let interpol_ratio = self.phase - self.phase.trunc();
 
             // Linear interpolation
-            *o = SINETABLE[inf_idx].mul_add(
-                1. - self.sine.interpol_ratio,
-                SINETABLE[sup_idx] * self.sine.interpol_ratio,
-            );
+            *o = table[inf_idx].mul_add(1. - interpol_ratio, table[sup_idx] * interpol_ratio);
 
             // Optimized float modulo op
             self.phase = if self.phase + self.incr_phase >= TABLE_LENGTH_F32 {
@@ -1011,240 +849,26 @@ impl OscillatorRenderer {
             };
         }
     }
-
-    /// generate the audio data when oscillator is of type sawtooth
-    /// buffer is filled with the sawtooth audio data.
-    ///
-    /// # Arguments
-    ///
-    /// * `type_` - oscillator type (sine,sawtooth,triangle,square, or custom)
-    /// * `buffer` - audio output buffer
-    /// * `freq_values` - frequencies at which each sample should be generated
-    #[inline]
-    fn generate_sawtooth(
-        &mut self,
-        type_: OscillatorType,
-        buffer: &mut ChannelData,
-        freq_values: &[f32],
-    ) {
-        for (o, &computed_freq) in buffer.iter_mut().zip(freq_values) {
-            self.arate_params(type_, computed_freq);
-            let mut sample = (2.0 * self.phase) - 1.0;
-            sample -= self.poly_blep(self.phase);
-
-            // Optimized float modulo op
-            self.phase += self.incr_phase;
-            while self.phase >= 1. {
-                self.phase -= 1.;
-            }
-
-            *o = sample;
-        }
-    }
-
-    /// generate the audio data when oscillator is of type square
-    /// buffer is filled with the square audio data.
-    ///
-    /// # Arguments
-    ///
-    /// * `type_` - oscillator type (sine,sawtooth,triangle,square, or custom)
-    /// * `buffer` - audio output buffer
-    /// * `freq_values` - frequencies at which each sample should be generated
-    #[inline]
-    fn generate_square(
-        &mut self,
-        type_: OscillatorType,
-        buffer: &mut ChannelData,
-        freq_values: &[f32],
-    ) {
-        for (o, &computed_freq) in buffer.iter_mut().zip(freq_values) {
-            self.arate_params(type_, computed_freq);
-            let mut sample = if self.phase <= 0.5 { 1.0 } else { -1.0 };
-
-            sample += self.poly_blep(self.phase);
-
-            // Optimized float modulo op
-            let mut shift_phase = self.phase + 0.5;
-            while shift_phase >= 1. {
-                shift_phase -= 1.;
-            }
-            sample -= self.poly_blep(shift_phase);
-
-            // Optimized float modulo op
-            self.phase += self.incr_phase;
-            while self.phase >= 1. {
-                self.phase -= 1.;
-            }
-            *o = sample;
-        }
-    }
-
-    /// generate the audio data when oscillator is of type triangle
-    /// buffer is filled with the triangle audio data.
-    ///
-    /// # Arguments
-    ///
-    /// * `type_` - oscillator type (sine,sawtooth,triangle,square, or custom)
-    /// * `buffer` - audio output buffer
-    /// * `freq_values` - frequencies at which each sample should be generated
-    #[inline]
-    fn generate_triangle(
-        &mut self,
-        type_: OscillatorType,
-        buffer: &mut ChannelData,
-        freq_values: &[f32],
-    ) {
-        for (o, &computed_freq) in buffer.iter_mut().zip(freq_values) {
-            self.arate_params(type_, computed_freq);
-            let mut sample = if self.phase <= 0.5 { 1.0 } else { -1.0 };
-
-            sample += self.poly_blep(self.phase);
-
-            // Optimized float modulo op
-            let mut shift_phase = self.phase + 0.5;
-            while shift_phase >= 1. {
-                shift_phase -= 1.;
-            }
-            sample -= self.poly_blep(shift_phase);
-
-            // Optimized float modulo op
-            self.phase += self.incr_phase;
-            while self.phase >= 1. {
-                self.phase -= 1.;
-            }
-
-            // Leaky integrator: y[n] = A * x[n] + (1 - A) * y[n-1]
-            // Classic integration cannot be used due to float errors accumulation over execution time
-            sample = self
-                .incr_phase
-                .mul_add(sample, (1.0 - self.incr_phase) * self.triangle.last_output);
-            self.triangle.last_output = sample;
-
-            // Normalized amplitude into intervall [-1.0,1.0]
-            *o = sample * 4.;
-        }
-    }
-
-    /// generate the audio data when oscillator is of type custom
-    /// buffer is filled with the periodic waveform audio data.
-    ///
-    /// # Arguments
-    ///
-    /// * `type_` - oscillator type (sine,sawtooth,triangle,square, or custom)
-    /// * `buffer` - audio output buffer
-    /// * `freq_values` - frequencies at which each sample should be generated
-    #[inline]
-    fn generate_custom(&mut self, output: &mut ChannelData, freq_values: &[f32]) {
-        for (o, &computed_freq) in output.iter_mut().zip(freq_values) {
-            self.arate_periodic_params(computed_freq);
-
-            let phase = self.periodic.wavetable.phase;
-            let incr_phase = self.periodic.wavetable.incr_phase;
-            let table_len = self.periodic.wavetable.dyn_table.len();
-
-            // 2048 casts without loss of precision
-            #[allow(clippy::cast_precision_loss)]
-            let table_len_f32 = table_len as f32;
-            let buffer = &self.periodic.wavetable.dyn_table;
-            // truncation is desired
-            #[allow(clippy::cast_possible_truncation)]
-            // phase is always positive
-            #[allow(clippy::cast_sign_loss)]
-            let inf_idx = phase as usize;
-            let sup_idx = (inf_idx + 1) % table_len;
-            let interpol_ratio = phase - phase.trunc();
-
-            *o = (1.0 - interpol_ratio).mul_add(buffer[inf_idx], interpol_ratio * buffer[sup_idx]);
-
-            // Update phase with optimized float modulo op
-            self.periodic.wavetable.phase = if phase + incr_phase >= table_len_f32 {
-                (phase + incr_phase) - table_len_f32
-            } else {
-                phase + incr_phase
-            };
-        }
-    }
-
-    /// Generate the wavetable
-    ///
-    /// # Arguments
-    ///
-    /// * `norms` - the norm of each harmonics
-    /// * `phases` - the phase of each harmonics
-    /// * `incr_phases` - the phase to increment of each harmonics
-    /// * `interpol_ratios` - the interpolation ratio of each harmonics used by linear interpolatio
-    /// * `buffer` - the buffer is filled with generated wavetable data (avoid allocation)
-    #[inline]
-    fn generate_wavetable(
-        norms: &[f32],
-        phases: &mut [f32],
-        incr_phases: &[f32],
-        interpol_ratios: &[f32],
-        buffer: &mut Vec<f32>,
-    ) {
-        buffer.clear();
-
-        while phases[1] <= TABLE_LENGTH_F32 {
-            let mut sample = 0.0;
-            for i in 1..phases.len() {
-                let gain = norms[i];
-                let phase = phases[i];
-                let incr_phase = incr_phases[i];
-                let mu = interpol_ratios[i];
-                // truncation is desired
-                #[allow(clippy::cast_possible_truncation)]
-                // phase + incr_phase is always positive
-                #[allow(clippy::cast_sign_loss)]
-                let idx = (phase + incr_phase) as usize;
-                let inf_idx = idx % TABLE_LENGTH_USIZE;
-                let sup_idx = (idx + 1) % TABLE_LENGTH_USIZE;
-                // Linear interpolation
-                sample += SINETABLE[inf_idx].mul_add(1. - mu, SINETABLE[sup_idx] * mu) * gain;
-                phases[i] = phase + incr_phase;
-            }
-
-            buffer.push(sample);
-        }
-    }
-
-    /// normalizes the given buffer
-    fn norm_factor(buffer: &[f32]) -> f32 {
-        1. / buffer
-            .iter()
-            .copied()
-            .reduce(f32::max)
-            .expect("Maximum value not found")
-    }
-
-    /// computes the `polyBLEP` corrections to apply to aliasing signal
-    ///
-    /// `polyBLEP` stands for `polyBandLimitedstEP`
-    fn poly_blep(&self, mut t: f32) -> f32 {
-        let dt = self.incr_phase;
-        if t < dt {
-            t /= dt;
-            t + t - t * t - 1.0
-        } else if t > 1.0 - dt {
-            t = (t - 1.0) / dt;
-            t.mul_add(t, t) + t + 1.0
-        } else {
-            0.0
-        }
-    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::convert::TryFrom;
 
     use float_eq::assert_float_eq;
 
-    use super::{PeriodicWave, PeriodicWaveOptions};
+    use super::{
+        sawtooth_coefficient, square_coefficient, triangle_coefficient, PeriodicWave,
+        PeriodicWaveOptions,
+    };
     use crate::{
         context::{AsBaseAudioContext, AudioContext, OfflineAudioContext},
         node::{
             AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, OscillatorType,
         },
-        snapshot, SampleRate,
+        snapshot,
+        test_util::render_mono,
+        SampleRate,
     };
 
     const LENGTH: usize = 555;
@@ -1374,8 +998,8 @@ mod tests {
         let periodic_wave = PeriodicWave::new(&context, Some(options));
 
         // the default has to be a sine signal
-        assert_float_eq!(periodic_wave.real, vec![0., 1.], ulps_all <= 0);
-        assert_float_eq!(periodic_wave.imag, vec![0., 0.], ulps_all <= 0);
+        assert_float_eq!(periodic_wave.real, vec![0., 0.], ulps_all <= 0);
+        assert_float_eq!(periodic_wave.imag, vec![0., 1.], ulps_all <= 0);
         assert!(!periodic_wave.disable_normalization);
     }
 
@@ -1503,6 +1127,140 @@ mod tests {
         );
     }
 
+    /// Render 0.1 second of a sine at `frequency`, detuned by a constant or by a signal
+    fn render_detuned_sine(frequency: f32, detune: f32, modulation: Option<f32>) -> Vec<f32> {
+        let mut context = OfflineAudioContext::new(1, 4410, SampleRate(44_100));
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.detune().set_value(detune);
+        if let Some(offset) = modulation {
+            let lfo = context.create_constant_source();
+            lfo.offset().set_value(offset);
+            lfo.connect(osc.detune());
+        }
+        osc.connect(&context.destination());
+        osc.start();
+
+        render_mono(&mut context)
+    }
+
+    fn zero_crossings(signal: &[f32]) -> usize {
+        signal
+            .windows(2)
+            .filter(|w| (w[0] < 0.) != (w[1] < 0.))
+            .count()
+    }
+
+    #[test]
+    fn detune_octave_doubles_frequency() {
+        let reference = render_detuned_sine(882., 0., None);
+        let detuned = render_detuned_sine(441., 1200., None);
+        assert_float_eq!(detuned[..], reference[..], abs_all <= 1e-5);
+
+        // 88.2 periods of 882 Hz, twice the crossings of 441 Hz
+        let crossings = zero_crossings(&detuned);
+        assert!((175..=177).contains(&crossings));
+        let crossings = zero_crossings(&render_detuned_sine(441., 0., None));
+        assert!((87..=89).contains(&crossings));
+    }
+
+    #[test]
+    fn detune_is_modulated_by_signal() {
+        let reference = render_detuned_sine(441., 1200., None);
+        let modulated = render_detuned_sine(441., 0., Some(1200.));
+        assert_float_eq!(modulated[..], reference[..], abs_all <= 1e-5);
+    }
+
+    /// Fraction of the energy of `signal` outside the harmonics of a fundamental that completes
+    /// `periods` cycles in the signal
+    // the test signals are far shorter than 2^52 sample-frames
+    #[allow(clippy::cast_precision_loss)]
+    fn inharmonic_energy(signal: &[f32], periods: usize) -> f64 {
+        let len = signal.len();
+        let total: f64 = signal.iter().map(|&v| f64::from(v).powi(2)).sum();
+
+        // Parseval: a harmonic at bin k and its mirror hold 2 |X_k|^2 / N of the energy
+        let harmonic: f64 = (periods..len / 2)
+            .step_by(periods)
+            .map(|bin| {
+                let (re, im) = signal
+                    .iter()
+                    .enumerate()
+                    .fold((0., 0.), |(re, im), (n, &v)| {
+                        let angle = -2. * std::f64::consts::PI * (bin * n) as f64 / len as f64;
+                        (
+                            f64::from(v).mul_add(angle.cos(), re),
+                            f64::from(v).mul_add(angle.sin(), im),
+                        )
+                    });
+                2. * re.mul_add(re, im * im) / len as f64
+            })
+            .sum();
+
+        (total - harmonic) / total
+    }
+
+    #[test]
+    fn band_limited_shapes_do_not_alias() {
+        for type_ in [
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ] {
+            // 300 periods of 3 kHz, the 8th harmonic would alias to 20.1 kHz
+            let mut context = OfflineAudioContext::new(1, 4410, SampleRate(44_100));
+            let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(3000.);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering();
+            let inharmonic = inharmonic_energy(output.channel_data(0).as_slice(), 300);
+            assert!(inharmonic < 1e-6, "{:?}: {}", type_, inharmonic);
+        }
+    }
+
+    #[test]
+    fn band_limited_tables_keep_the_waveform() {
+        // at low frequencies, the shapes are close to the ideal ones
+        let mut context = OfflineAudioContext::new(3, 441, SampleRate(44_100));
+        let merger = context.create_channel_merger(3);
+        merger.connect(&context.destination());
+        for (input, type_) in [
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ]
+        .iter()
+        .copied()
+        .enumerate()
+        {
+            let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(100.);
+            osc.connect_at(&merger, 0, u32::try_from(input).unwrap())
+                .unwrap();
+            osc.start();
+        }
+
+        let output = context.start_rendering();
+
+        // a quarter period after the edges, the Gibbs ringing has faded
+        let square = output.channel_data(0).as_slice();
+        assert_float_eq!(square[110], 1., abs <= 0.01);
+        assert_float_eq!(square[331], -1., abs <= 0.01);
+
+        let sawtooth = output.channel_data(1).as_slice();
+        assert_float_eq!(sawtooth[110], -0.5, abs <= 0.01);
+        assert_float_eq!(sawtooth[331], 0.5, abs <= 0.01);
+
+        let triangle = output.channel_data(2).as_slice();
+        assert_float_eq!(triangle[110], 1., abs <= 0.01);
+        assert_float_eq!(triangle[331], -1., abs <= 0.01);
+        assert_float_eq!(triangle[220], 0., abs <= 0.01);
+    }
+
     #[test]
     fn default_sine_rendering_should_match_snapshot() {
         let ref_sine =
@@ -1607,6 +1365,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn harmonic_coefficients_should_follow_fourier_series() {
+        use std::f32::consts::PI;
+
+        // odd harmonics only, with alternating signs for the triangle
+        let square: Vec<f32> = (1..=5).map(square_coefficient).collect();
+        let triangle: Vec<f32> = (1..=5).map(triangle_coefficient).collect();
+        assert_float_eq!(
+            square[..],
+            [4. / PI, 0., 4. / (3. * PI), 0., 4. / (5. * PI)][..],
+            ulps_all <= 1
+        );
+        assert_float_eq!(
+            triangle[..],
+            [
+                8. / PI.powi(2),
+                0.,
+                -8. / (3. * PI).powi(2),
+                0.,
+                8. / (5. * PI).powi(2)
+            ][..],
+            ulps_all <= 1
+        );
+
+        // every harmonic for the sawtooth
+        assert_float_eq!(sawtooth_coefficient(2), -1. / PI, ulps <= 1);
+    }
+
+    #[test]
+    // the snapshots are short, and the expected values are rounded to f32 on purpose
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn sine_snapshots_should_match_analytic_waveforms() {
+        // the wavetables are interpolated at the fractional part of the phase, so the rendered
+        // waveforms start at phase zero and follow the analytic ones closely
+        let phase = |n: usize| 2. * std::f64::consts::PI * 440. * n as f64 / 44_100.;
+        let sine: Vec<f32> = (0..LENGTH).map(|n| phase(n).sin() as f32).collect();
+        let cosines: Vec<f32> = (0..LENGTH)
+            .map(|n| 0.5_f64.mul_add(phase(n).cos(), 0.5 * (2. * phase(n)).cos()) as f32)
+            .collect();
+
+        for (path, expected) in [
+            ("./snapshots/sine.json", &sine),
+            ("./snapshots/default_periodic.json", &sine),
+            ("./snapshots/periodic_2f.json", &cosines),
+        ] {
+            let snapshot = snapshot::read(path).expect("Reading snapshot file failed");
+            assert_float_eq!(&snapshot.data[..], &expected[..], abs_all <= 2e-5);
+        }
+    }
+
     #[test]
     fn periodic_wave_rendering_should_match_snapshot() {
         let ref_sine =
@@ -1684,4 +1492,55 @@ mod tests {
             ulps_all <= 40
         );
     }
+
+    #[test]
+    fn single_harmonic_periodic_wave_should_match_sine() {
+        let render = |periodic_wave: bool| {
+            let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+            let osc = OscillatorNode::new(&context, None);
+
+            if periodic_wave {
+                // fundamental only, in phase with the sine
+                let options = PeriodicWaveOptions {
+                    real: Some(vec![0., 0.]),
+                    imag: Some(vec![0., 1.]),
+                    disable_normalization: Some(false),
+                };
+                osc.set_periodic_wave(context.create_periodic_wave(Some(options)));
+                assert_eq!(osc.type_(), OscillatorType::Custom);
+            }
+
+            osc.connect(&context.destination());
+            osc.start();
+            context.start_rendering()
+        };
+
+        let sine = render(false);
+        let custom = render(true);
+        assert_float_eq!(
+            custom.channel_data(0).as_slice(),
+            sine.channel_data(0).as_slice(),
+            abs_all <= 1e-3
+        );
+    }
+
+    #[test]
+    fn sub_quantum_stop_should_silence_remainder() {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 512, sample_rate);
+        let osc = OscillatorNode::new(&context, None);
+
+        osc.set_type(OscillatorType::Square);
+        osc.connect(&context.destination());
+        osc.start();
+        // stop between sample-frames 200 and 201, in the middle of the second render quantum
+        osc.stop_at(200.5 / f64::from(sample_rate.0));
+
+        let output = context.start_rendering();
+        let data = output.channel_data(0).as_slice();
+
+        // the signal runs up to the stop time
+        assert!(data[190..=200].iter().all(|v| v.abs() > 0.5));
+        assert_float_eq!(data[201..], [0.; 311][..], ulps_all <= 0);
+    }
 }
@@ -1,25 +1,218 @@
 use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
-use crate::param::AudioParam;
+use crate::param::{AudioParam, AudioParamOptions};
 use crate::process::{AudioParamValues, AudioProcessor};
-use crate::SampleRate;
+use crate::{SampleRate, BUFFER_SIZE};
 
 use super::AudioNode;
 
+/// How often the [`PannerNode`] recalculates its spatialization
+///
+/// Computing the azimuth and distance of the source is costly. For scenes with many sources,
+/// updating the gains once every few render quanta is usually sufficient. In between updates the
+/// gains are interpolated linearly, so moving sources do not produce clicks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum SpatialUpdateRate {
+    /// Recalculate for every sample-frame, following a-rate automation exactly
+    PerSample,
+    /// Recalculate once every render quantum
+    #[default]
+    PerQuantum,
+    /// Recalculate once every given number of render quanta
+    EveryNQuanta(u32),
+}
+
+impl SpatialUpdateRate {
+    /// Number of render quanta between updates, 0 for per sample updates
+    fn quanta(self) -> u32 {
+        match self {
+            SpatialUpdateRate::PerSample => 0,
+            SpatialUpdateRate::PerQuantum => 1,
+            SpatialUpdateRate::EveryNQuanta(n) => {
+                assert!(n > 0, "RangeError: number of quanta should be positive");
+                n
+            }
+        }
+    }
+
+    fn from_quanta(quanta: u32) -> Self {
+        match quanta {
+            0 => SpatialUpdateRate::PerSample,
+            1 => SpatialUpdateRate::PerQuantum,
+            n => SpatialUpdateRate::EveryNQuanta(n),
+        }
+    }
+}
+
+/// Algorithm to reduce the volume of a source as it moves away from the listener
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DistanceModel {
+    /// Linear decrease from `ref_distance` to `max_distance`:
+    /// `1 - rolloff_factor * (distance - ref_distance) / (max_distance - ref_distance)`
+    Linear,
+    /// Inverse law: `ref_distance / (ref_distance + rolloff_factor * (distance - ref_distance))`
+    #[default]
+    Inverse,
+    /// Exponential law: `(distance / ref_distance) ^ -rolloff_factor`
+    Exponential,
+}
+
+impl From<u32> for DistanceModel {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => DistanceModel::Linear,
+            1 => DistanceModel::Inverse,
+            2 => DistanceModel::Exponential,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Distance attenuation settings of a [`PannerNode`]
+#[derive(Copy, Clone, Debug)]
+struct DistanceAttenuation {
+    model: DistanceModel,
+    ref_distance: f32,
+    max_distance: f32,
+    rolloff_factor: f32,
+}
+
+impl DistanceAttenuation {
+    /// Gain applied to a source at the given distance from the listener
+    fn gain(&self, distance: f32) -> f32 {
+        let Self {
+            model,
+            ref_distance,
+            max_distance,
+            rolloff_factor,
+        } = *self;
+
+        match model {
+            DistanceModel::Linear => {
+                let rolloff_factor = rolloff_factor.min(1.);
+                let min = ref_distance.min(max_distance);
+                let max = ref_distance.max(max_distance);
+                if min == max {
+                    return 1. - rolloff_factor;
+                }
+                let distance = distance.clamp(min, max);
+                1. - rolloff_factor * (distance - min) / (max - min)
+            }
+            DistanceModel::Inverse => {
+                if ref_distance == 0. {
+                    return if distance == 0. { 1. } else { 0. };
+                }
+                let distance = distance.max(ref_distance);
+                ref_distance / (ref_distance + rolloff_factor * (distance - ref_distance))
+            }
+            DistanceModel::Exponential => {
+                if ref_distance == 0. {
+                    return if distance == 0. { 1. } else { 0. };
+                }
+                let distance = distance.max(ref_distance);
+                (distance / ref_distance).powf(-rolloff_factor)
+            }
+        }
+    }
+}
+
+/// Directivity of a [`PannerNode`] source
+///
+/// The source is not attenuated within the inner cone, attenuated by `outer_gain` outside the outer
+/// cone, and the gain is interpolated linearly in between. The angles are in degrees.
+#[derive(Copy, Clone, Debug)]
+struct SoundCone {
+    inner_angle: f32,
+    outer_angle: f32,
+    outer_gain: f32,
+}
+
+impl SoundCone {
+    /// Gain applied to a source with the given orientation
+    fn gain(
+        &self,
+        source_position: [f32; 3],
+        source_orientation: [f32; 3],
+        listener_position: [f32; 3],
+    ) -> f32 {
+        // omnidirectional source
+        if self.inner_angle == 360. && self.outer_angle == 360. {
+            return 1.;
+        }
+
+        let angle =
+            match crate::spatial::angle(source_position, source_orientation, listener_position) {
+                Some(angle) => angle,
+                None => return 1.,
+            };
+
+        let abs_inner_angle = self.inner_angle.abs() / 2.;
+        let abs_outer_angle = self.outer_angle.abs() / 2.;
+
+        if angle <= abs_inner_angle {
+            1.
+        } else if angle >= abs_outer_angle {
+            self.outer_gain
+        } else {
+            let x = (angle - abs_inner_angle) / (abs_outer_angle - abs_inner_angle);
+            (1. - x) + self.outer_gain * x
+        }
+    }
+}
+
 /// Options for constructing a PannerNode
-#[derive(Default)]
 pub struct PannerOptions {
     pub position_x: f32,
     pub position_y: f32,
     pub position_z: f32,
+    pub orientation_x: f32,
+    pub orientation_y: f32,
+    pub orientation_z: f32,
     pub forward_x: f32,
     pub forward_y: f32,
     pub forward_z: f32,
     pub up_x: f32,
     pub up_y: f32,
     pub up_z: f32,
+    pub update_rate: SpatialUpdateRate,
+    pub distance_model: DistanceModel,
+    pub ref_distance: f32,
+    pub max_distance: f32,
+    pub rolloff_factor: f32,
+    pub cone_inner_angle: f32,
+    pub cone_outer_angle: f32,
+    pub cone_outer_gain: f32,
+}
+
+impl Default for PannerOptions {
+    fn default() -> Self {
+        Self {
+            position_x: 0.,
+            position_y: 0.,
+            position_z: 0.,
+            orientation_x: 1.,
+            orientation_y: 0.,
+            orientation_z: 0.,
+            forward_x: 0.,
+            forward_y: 0.,
+            forward_z: 0.,
+            up_x: 0.,
+            up_y: 0.,
+            up_z: 0.,
+            update_rate: SpatialUpdateRate::default(),
+            distance_model: DistanceModel::default(),
+            ref_distance: 1.,
+            max_distance: 10000.,
+            rolloff_factor: 1.,
+            cone_inner_angle: 360.,
+            cone_outer_angle: 360.,
+            cone_outer_gain: 0.,
+        }
+    }
 }
 
 /// Positions / spatializes an incoming audio stream in three-dimensional space.
@@ -29,6 +222,17 @@ pub struct PannerNode {
     position_x: AudioParam,
     position_y: AudioParam,
     position_z: AudioParam,
+    orientation_x: AudioParam,
+    orientation_y: AudioParam,
+    orientation_z: AudioParam,
+    update_rate: Arc<AtomicU32>,
+    distance_model: Arc<AtomicU32>,
+    ref_distance: Arc<AtomicU32>,
+    max_distance: Arc<AtomicU32>,
+    rolloff_factor: Arc<AtomicU32>,
+    cone_inner_angle: Arc<AtomicU32>,
+    cone_outer_angle: Arc<AtomicU32>,
+    cone_outer_gain: Arc<AtomicU32>,
 }
 
 impl AudioNode for PannerNode {
@@ -41,7 +245,7 @@ impl AudioNode for PannerNode {
     }
 
     fn number_of_inputs(&self) -> u32 {
-        1 + 9 // todo, user should not be able to see these ports
+        1
     }
     fn number_of_outputs(&self) -> u32 {
         1
@@ -49,7 +253,18 @@ impl AudioNode for PannerNode {
 }
 
 impl PannerNode {
+    /// # Panics
+    ///
+    /// Will panic if the distance settings are invalid, see
+    /// [`set_ref_distance`](Self::set_ref_distance), [`set_max_distance`](Self::set_max_distance)
+    /// and [`set_rolloff_factor`](Self::set_rolloff_factor), or if the cone outer gain is
+    /// invalid, see [`set_cone_outer_gain`](Self::set_cone_outer_gain)
     pub fn new<C: AsBaseAudioContext>(context: &C, options: PannerOptions) -> Self {
+        assert_valid_ref_distance(options.ref_distance);
+        assert_valid_max_distance(options.max_distance);
+        assert_valid_rolloff_factor(options.rolloff_factor);
+        assert_valid_cone_outer_gain(options.cone_outer_gain);
+
         context.base().register(move |registration| {
             use crate::spatial::PARAM_OPTS;
             let id = registration.id();
@@ -61,10 +276,46 @@ impl PannerNode {
             position_y.set_value_at_time(options.position_y, 0.);
             position_z.set_value_at_time(options.position_z, 0.);
 
+            let orientation_x_opts = AudioParamOptions {
+                default_value: 1.,
+                ..PARAM_OPTS
+            };
+            let (orientation_x, render_ox) =
+                context.base().create_audio_param(orientation_x_opts, id);
+            let (orientation_y, render_oy) = context.base().create_audio_param(PARAM_OPTS, id);
+            let (orientation_z, render_oz) = context.base().create_audio_param(PARAM_OPTS, id);
+
+            orientation_x.set_value_at_time(options.orientation_x, 0.);
+            orientation_y.set_value_at_time(options.orientation_y, 0.);
+            orientation_z.set_value_at_time(options.orientation_z, 0.);
+
+            let update_rate = Arc::new(AtomicU32::new(options.update_rate.quanta()));
+            let distance_model = Arc::new(AtomicU32::new(options.distance_model as u32));
+            let ref_distance = Arc::new(AtomicU32::new(options.ref_distance.to_bits()));
+            let max_distance = Arc::new(AtomicU32::new(options.max_distance.to_bits()));
+            let rolloff_factor = Arc::new(AtomicU32::new(options.rolloff_factor.to_bits()));
+            let cone_inner_angle = Arc::new(AtomicU32::new(options.cone_inner_angle.to_bits()));
+            let cone_outer_angle = Arc::new(AtomicU32::new(options.cone_outer_angle.to_bits()));
+            let cone_outer_gain = Arc::new(AtomicU32::new(options.cone_outer_gain.to_bits()));
+
             let render = PannerRenderer {
                 position_x: render_px,
                 position_y: render_py,
                 position_z: render_pz,
+                orientation_x: render_ox,
+                orientation_y: render_oy,
+                orientation_z: render_oz,
+                update_rate: update_rate.clone(),
+                distance_model: distance_model.clone(),
+                ref_distance: ref_distance.clone(),
+                max_distance: max_distance.clone(),
+                rolloff_factor: rolloff_factor.clone(),
+                cone_inner_angle: cone_inner_angle.clone(),
+                cone_outer_angle: cone_outer_angle.clone(),
+                cone_outer_gain: cone_outer_gain.clone(),
+                quanta_until_update: 0,
+                gains: None,
+                gains_step: [0.; 2],
             };
 
             let node = PannerNode {
@@ -78,6 +329,17 @@ impl PannerNode {
                 position_x,
                 position_y,
                 position_z,
+                orientation_x,
+                orientation_y,
+                orientation_z,
+                update_rate,
+                distance_model,
+                ref_distance,
+                max_distance,
+                rolloff_factor,
+                cone_inner_angle,
+                cone_outer_angle,
+                cone_outer_gain,
             };
 
             context.base().connect_listener_to_panner(node.id());
@@ -86,23 +348,200 @@ impl PannerNode {
         })
     }
 
+    /// X coordinate of the position of the source (a-rate)
     pub fn position_x(&self) -> &AudioParam {
         &self.position_x
     }
 
+    /// Y coordinate of the position of the source (a-rate)
     pub fn position_y(&self) -> &AudioParam {
         &self.position_y
     }
 
+    /// Z coordinate of the position of the source (a-rate)
     pub fn position_z(&self) -> &AudioParam {
         &self.position_z
     }
+
+    /// X coordinate of the orientation of the source (a-rate)
+    pub fn orientation_x(&self) -> &AudioParam {
+        &self.orientation_x
+    }
+
+    /// Y coordinate of the orientation of the source (a-rate)
+    pub fn orientation_y(&self) -> &AudioParam {
+        &self.orientation_y
+    }
+
+    /// Z coordinate of the orientation of the source (a-rate)
+    pub fn orientation_z(&self) -> &AudioParam {
+        &self.orientation_z
+    }
+
+    /// How often the spatialization is recalculated
+    pub fn update_rate(&self) -> SpatialUpdateRate {
+        SpatialUpdateRate::from_quanta(self.update_rate.load(Ordering::SeqCst))
+    }
+
+    /// Set how often the spatialization is recalculated
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of quanta of `EveryNQuanta` is zero
+    pub fn set_update_rate(&self, update_rate: SpatialUpdateRate) {
+        self.update_rate
+            .store(update_rate.quanta(), Ordering::SeqCst);
+    }
+
+    /// The algorithm reducing the volume of the source as it moves away from the listener
+    pub fn distance_model(&self) -> DistanceModel {
+        self.distance_model.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the algorithm reducing the volume of the source as it moves away from the listener
+    pub fn set_distance_model(&self, model: DistanceModel) {
+        self.distance_model.store(model as u32, Ordering::SeqCst);
+    }
+
+    /// Reference distance for reducing the volume, closer sources are not attenuated
+    pub fn ref_distance(&self) -> f32 {
+        f32::from_bits(self.ref_distance.load(Ordering::SeqCst))
+    }
+
+    /// Set the reference distance for reducing the volume
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `ref_distance` is negative
+    pub fn set_ref_distance(&self, ref_distance: f32) {
+        assert_valid_ref_distance(ref_distance);
+        self.ref_distance
+            .store(ref_distance.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Maximum distance, beyond which the volume is not reduced further by the linear model
+    pub fn max_distance(&self) -> f32 {
+        f32::from_bits(self.max_distance.load(Ordering::SeqCst))
+    }
+
+    /// Set the maximum distance of the linear model
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `max_distance` is not positive
+    pub fn set_max_distance(&self, max_distance: f32) {
+        assert_valid_max_distance(max_distance);
+        self.max_distance
+            .store(max_distance.to_bits(), Ordering::SeqCst);
+    }
+
+    /// How quickly the volume is reduced as the source moves away from the listener
+    pub fn rolloff_factor(&self) -> f32 {
+        f32::from_bits(self.rolloff_factor.load(Ordering::SeqCst))
+    }
+
+    /// Set how quickly the volume is reduced as the source moves away from the listener
+    ///
+    /// The linear model clamps the factor to the range `[0, 1]`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `rolloff_factor` is negative
+    pub fn set_rolloff_factor(&self, rolloff_factor: f32) {
+        assert_valid_rolloff_factor(rolloff_factor);
+        self.rolloff_factor
+            .store(rolloff_factor.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Angle (in degrees) of the cone inside of which the source is not attenuated
+    pub fn cone_inner_angle(&self) -> f32 {
+        f32::from_bits(self.cone_inner_angle.load(Ordering::SeqCst))
+    }
+
+    /// Set the angle (in degrees) of the cone inside of which the source is not attenuated
+    pub fn set_cone_inner_angle(&self, angle: f32) {
+        self.cone_inner_angle
+            .store(angle.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Angle (in degrees) of the cone outside of which the source is attenuated by the
+    /// [`cone_outer_gain`](Self::cone_outer_gain)
+    pub fn cone_outer_angle(&self) -> f32 {
+        f32::from_bits(self.cone_outer_angle.load(Ordering::SeqCst))
+    }
+
+    /// Set the angle (in degrees) of the cone outside of which the source is attenuated by the
+    /// [`cone_outer_gain`](Self::cone_outer_gain)
+    pub fn set_cone_outer_angle(&self, angle: f32) {
+        self.cone_outer_angle
+            .store(angle.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Gain applied to the source outside of the outer cone
+    pub fn cone_outer_gain(&self) -> f32 {
+        f32::from_bits(self.cone_outer_gain.load(Ordering::SeqCst))
+    }
+
+    /// Set the gain applied to the source outside of the outer cone
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `gain` is outside the range `[0, 1]`
+    pub fn set_cone_outer_gain(&self, gain: f32) {
+        assert_valid_cone_outer_gain(gain);
+        self.cone_outer_gain.store(gain.to_bits(), Ordering::SeqCst);
+    }
+}
+
+fn assert_valid_ref_distance(ref_distance: f32) {
+    assert!(
+        ref_distance >= 0.,
+        "RangeError: ref distance should not be negative"
+    );
+}
+
+fn assert_valid_max_distance(max_distance: f32) {
+    assert!(
+        max_distance > 0.,
+        "RangeError: max distance should be positive"
+    );
+}
+
+fn assert_valid_rolloff_factor(rolloff_factor: f32) {
+    assert!(
+        rolloff_factor >= 0.,
+        "RangeError: rolloff factor should not be negative"
+    );
+}
+
+fn assert_valid_cone_outer_gain(gain: f32) {
+    assert!(
+        (0. ..=1.).contains(&gain),
+        "InvalidStateError: cone outer gain should be in the range [0, 1]"
+    );
 }
 
 struct PannerRenderer {
     position_x: AudioParamId,
     position_y: AudioParamId,
     position_z: AudioParamId,
+    orientation_x: AudioParamId,
+    orientation_y: AudioParamId,
+    orientation_z: AudioParamId,
+    update_rate: Arc<AtomicU32>,
+    distance_model: Arc<AtomicU32>,
+    ref_distance: Arc<AtomicU32>,
+    max_distance: Arc<AtomicU32>,
+    rolloff_factor: Arc<AtomicU32>,
+    cone_inner_angle: Arc<AtomicU32>,
+    cone_outer_angle: Arc<AtomicU32>,
+    cone_outer_gain: Arc<AtomicU32>,
+    /// render quanta left before the next recalculation of the gains
+    quanta_until_update: u32,
+    /// current left and right gains, `None` before the first render quantum
+    gains: Option<[f32; 2]>,
+    /// per sample increment of the gains, to interpolate towards the last calculated gains
+    gains_step: [f32; 2],
 }
 
 impl AudioProcessor for PannerRenderer {
@@ -119,68 +558,358 @@ impl AudioProcessor for PannerRenderer {
         // single output node
         let output = &mut outputs[0];
 
-        // a-rate processing for now
-
         // source parameters (Panner)
-        let source_position_x = params.get(&self.position_x)[0];
-        let source_position_y = params.get(&self.position_y)[0];
-        let source_position_z = params.get(&self.position_z)[0];
+        let source_position_x = params.get(&self.position_x);
+        let source_position_y = params.get(&self.position_y);
+        let source_position_z = params.get(&self.position_z);
+        let source_orientation_x = params.get(&self.orientation_x);
+        let source_orientation_y = params.get(&self.orientation_y);
+        let source_orientation_z = params.get(&self.orientation_z);
 
         // listener parameters (AudioListener)
-        let l_position_x = inputs[1].channel_data(0)[0];
-        let l_position_y = inputs[2].channel_data(0)[0];
-        let l_position_z = inputs[3].channel_data(0)[0];
-        let l_forward_x = inputs[4].channel_data(0)[0];
-        let l_forward_y = inputs[5].channel_data(0)[0];
-        let l_forward_z = inputs[6].channel_data(0)[0];
-        let l_up_x = inputs[7].channel_data(0)[0];
-        let l_up_y = inputs[8].channel_data(0)[0];
-        let l_up_z = inputs[9].channel_data(0)[0];
-
-        let (mut azimuth, _elevation) = crate::spatial::azimuth_and_elevation(
-            [source_position_x, source_position_y, source_position_z],
-            [l_position_x, l_position_y, l_position_z],
-            [l_forward_x, l_forward_y, l_forward_z],
-            [l_up_x, l_up_y, l_up_z],
-        );
-
-        // First, clamp azimuth to allowed range of [-180, 180].
-        azimuth = azimuth.max(-180.);
-        azimuth = azimuth.min(180.);
-        // Then wrap to range [-90, 90].
-        if azimuth < -90. {
-            azimuth = -180. - azimuth;
-        } else if azimuth > 90. {
-            azimuth = 180. - azimuth;
+        let [l_position_x, l_position_y, l_position_z, l_forward_x, l_forward_y, l_forward_z, l_up_x, l_up_y, l_up_z] =
+            params.listener_params();
+
+        let attenuation = DistanceAttenuation {
+            model: self.distance_model.load(Ordering::SeqCst).into(),
+            ref_distance: f32::from_bits(self.ref_distance.load(Ordering::SeqCst)),
+            max_distance: f32::from_bits(self.max_distance.load(Ordering::SeqCst)),
+            rolloff_factor: f32::from_bits(self.rolloff_factor.load(Ordering::SeqCst)),
+        };
+        let cone = SoundCone {
+            inner_angle: f32::from_bits(self.cone_inner_angle.load(Ordering::SeqCst)),
+            outer_angle: f32::from_bits(self.cone_outer_angle.load(Ordering::SeqCst)),
+            outer_gain: f32::from_bits(self.cone_outer_gain.load(Ordering::SeqCst)),
+        };
+
+        let gains_at = |i: usize| {
+            spatial_gains(
+                [
+                    source_position_x[i],
+                    source_position_y[i],
+                    source_position_z[i],
+                ],
+                [
+                    source_orientation_x[i],
+                    source_orientation_y[i],
+                    source_orientation_z[i],
+                ],
+                [l_position_x[i], l_position_y[i], l_position_z[i]],
+                [l_forward_x[i], l_forward_y[i], l_forward_z[i]],
+                [l_up_x[i], l_up_y[i], l_up_z[i]],
+                &attenuation,
+                &cone,
+            )
+        };
+
+        output.set_number_of_channels(2);
+        let update_quanta = self.update_rate.load(Ordering::SeqCst);
+
+        if update_quanta == 0 {
+            // a-rate processing
+            input.iter().enumerate().for_each(|(i, &v)| {
+                let [gain_l, gain_r] = gains_at(i);
+                output.channel_data_mut(0)[i] = v * gain_l;
+                output.channel_data_mut(1)[i] = v * gain_r;
+            });
+
+            self.gains = Some(gains_at(BUFFER_SIZE as usize - 1));
+            self.quanta_until_update = 0;
+            return;
         }
 
-        let x = (azimuth + 90.) / 180.;
-        let gain_l = (x * PI / 2.).cos();
-        let gain_r = (x * PI / 2.).sin();
+        if self.quanta_until_update == 0 {
+            let target = gains_at(0);
+            let ramp_len = (update_quanta * BUFFER_SIZE) as f32;
 
-        let distance = crate::spatial::distance(
-            [source_position_x, source_position_y, source_position_z],
-            [l_position_x, l_position_y, l_position_z],
-        );
-        let dist_gain = 1. / distance;
+            match self.gains {
+                // first quantum, nothing to interpolate from
+                None => self.gains = Some(target),
+                Some(gains) => {
+                    self.gains_step = [
+                        (target[0] - gains[0]) / ramp_len,
+                        (target[1] - gains[1]) / ramp_len,
+                    ];
+                }
+            }
 
-        let left = input.iter().map(|&v| v * gain_l * dist_gain);
-        let right = input.iter().map(|&v| v * gain_r * dist_gain);
+            self.quanta_until_update = update_quanta;
+        }
+        self.quanta_until_update -= 1;
 
-        output.set_number_of_channels(2);
-        output
-            .channel_data_mut(0)
-            .iter_mut()
-            .zip(left)
-            .for_each(|(o, i)| *o = i);
-        output
-            .channel_data_mut(1)
-            .iter_mut()
-            .zip(right)
-            .for_each(|(o, i)| *o = i);
+        let mut gains = self.gains.unwrap();
+        let step = self.gains_step;
+        input.iter().enumerate().for_each(|(i, &v)| {
+            gains[0] += step[0];
+            gains[1] += step[1];
+            output.channel_data_mut(0)[i] = v * gains[0];
+            output.channel_data_mut(1)[i] = v * gains[1];
+        });
+        self.gains = Some(gains);
     }
 
     fn tail_time(&self) -> bool {
         false // only for panning model HRTF
     }
+
+    fn reset(&mut self) {
+        self.quanta_until_update = 0;
+        self.gains = None;
+        self.gains_step = [0.; 2];
+    }
+}
+
+/// Left and right gains (equal-power panning, distance and cone attenuation) of a source
+fn spatial_gains(
+    source_position: [f32; 3],
+    source_orientation: [f32; 3],
+    listener_position: [f32; 3],
+    listener_forward: [f32; 3],
+    listener_up: [f32; 3],
+    attenuation: &DistanceAttenuation,
+    cone: &SoundCone,
+) -> [f32; 2] {
+    let (mut azimuth, _elevation) = crate::spatial::azimuth_and_elevation(
+        source_position,
+        listener_position,
+        listener_forward,
+        listener_up,
+    );
+
+    // First, clamp azimuth to allowed range of [-180, 180].
+    azimuth = azimuth.max(-180.);
+    azimuth = azimuth.min(180.);
+    // Then wrap to range [-90, 90].
+    if azimuth < -90. {
+        azimuth = -180. - azimuth;
+    } else if azimuth > 90. {
+        azimuth = 180. - azimuth;
+    }
+
+    let x = (azimuth + 90.) / 180.;
+    let gain_l = (x * PI / 2.).cos();
+    let gain_r = (x * PI / 2.).sin();
+
+    let distance = crate::spatial::distance(source_position, listener_position);
+    let dist_gain = attenuation.gain(distance);
+    let cone_gain = cone.gain(source_position, source_orientation, listener_position);
+    let gain = dist_gain * cone_gain;
+
+    [gain_l * gain, gain_r * gain]
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::context::OfflineAudioContext;
+
+    /// Render a constant source moving from left to right in front of the listener
+    fn render_moving_source(update_rate: SpatialUpdateRate) -> crate::buffer::AudioBuffer {
+        let sample_rate = SampleRate(44_100);
+        let length = 44_100;
+        let mut context = OfflineAudioContext::new(2, length, sample_rate);
+
+        let panner = context.create_panner();
+        panner.set_update_rate(update_rate);
+        assert_eq!(panner.update_rate(), update_rate);
+
+        panner.position_z().set_value_at_time(-1., 0.);
+        panner.position_x().set_value_at_time(-10., 0.);
+        panner.position_x().linear_ramp_to_value_at_time(10., 1.);
+
+        render_constant_source(&mut context, &panner)
+    }
+
+    /// Render a constant source through the panner
+    fn render_constant_source(
+        context: &mut OfflineAudioContext,
+        panner: &PannerNode,
+    ) -> crate::buffer::AudioBuffer {
+        let src = context.create_constant_source();
+        src.connect(panner);
+        panner.connect(&context.destination());
+        context.start_rendering()
+    }
+
+    /// Largest difference between subsequent samples, skipping the start of the source
+    fn max_step(buffer: &crate::buffer::AudioBuffer, channel: usize) -> f32 {
+        buffer.channel_data(channel).as_slice()[1..]
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max)
+    }
+
+    #[test]
+    fn test_update_rate_is_smooth() {
+        let reference = render_moving_source(SpatialUpdateRate::PerSample);
+        // the source passes in front of the listener, so the gains change considerably
+        assert!(reference.channel_data(1).as_slice()[22_050] > 0.5);
+        let reference_step = max_step(&reference, 0).max(max_step(&reference, 1));
+
+        for update_rate in [
+            SpatialUpdateRate::PerQuantum,
+            SpatialUpdateRate::EveryNQuanta(4),
+        ] {
+            let output = render_moving_source(update_rate);
+            for channel in 0..2 {
+                // without interpolation, the gains would jump at quantum boundaries
+                let step = max_step(&output, channel);
+                assert!(
+                    step < 2. * reference_step,
+                    "{:?}: step {} exceeds {}",
+                    update_rate,
+                    step,
+                    reference_step
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse_distance_model() {
+        let ref_distance = 2.;
+
+        for (multiple, expected) in [(1., 1.), (2., 0.5), (4., 0.25)] {
+            let mut context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+            let src = context.create_constant_source();
+            let panner = context.create_panner();
+            assert_eq!(panner.distance_model(), DistanceModel::Inverse);
+            panner.set_ref_distance(ref_distance);
+
+            // straight in front of the listener, both channels get the same panning gain
+            panner
+                .position_z()
+                .set_value_at_time(-multiple * ref_distance, 0.);
+
+            src.connect(&panner);
+            panner.connect(&context.destination());
+            let output = context.start_rendering();
+
+            let expected = expected * (PI / 4.).cos();
+            for channel in 0..2 {
+                let data = output.channel_data(channel).as_slice();
+                assert!(data.iter().all(|v| (v - expected).abs() < 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn test_distance_models() {
+        let attenuation = |model, rolloff_factor| DistanceAttenuation {
+            model,
+            ref_distance: 1.,
+            max_distance: 11.,
+            rolloff_factor,
+        };
+
+        // not attenuated within the reference distance
+        for model in [
+            DistanceModel::Linear,
+            DistanceModel::Inverse,
+            DistanceModel::Exponential,
+        ] {
+            assert_float_eq!(attenuation(model, 1.).gain(0.5), 1., abs <= 1e-6);
+        }
+
+        let linear = attenuation(DistanceModel::Linear, 0.5);
+        assert_float_eq!(linear.gain(6.), 0.75, abs <= 1e-6);
+        assert_float_eq!(linear.gain(11.), 0.5, abs <= 1e-6);
+        // clamped to the max distance
+        assert_float_eq!(linear.gain(100.), 0.5, abs <= 1e-6);
+        // rolloff factor clamped to 1
+        let linear = attenuation(DistanceModel::Linear, 2.);
+        assert_float_eq!(linear.gain(11.), 0., abs <= 1e-6);
+
+        let inverse = attenuation(DistanceModel::Inverse, 2.);
+        assert_float_eq!(inverse.gain(3.), 0.2, abs <= 1e-6);
+
+        let exponential = attenuation(DistanceModel::Exponential, 2.);
+        assert_float_eq!(exponential.gain(4.), 1. / 16., abs <= 1e-6);
+    }
+
+    /// Render a constant source in front of the listener, with the given orientation
+    fn render_oriented_source(
+        orientation: [f32; 3],
+        outer_gain: f32,
+    ) -> crate::buffer::AudioBuffer {
+        let mut context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+
+        let options = PannerOptions {
+            position_z: -1.,
+            orientation_x: orientation[0],
+            orientation_y: orientation[1],
+            orientation_z: orientation[2],
+            cone_inner_angle: 60.,
+            cone_outer_angle: 120.,
+            cone_outer_gain: outer_gain,
+            ..PannerOptions::default()
+        };
+        let panner = PannerNode::new(&context, options);
+
+        render_constant_source(&mut context, &panner)
+    }
+
+    #[test]
+    fn test_cone_gain() {
+        let panning_gain = (PI / 4.).cos();
+
+        // pointing at the listener, inside the inner cone
+        let output = render_oriented_source([0., 0., 1.], 0.25);
+        for channel in 0..2 {
+            let data = output.channel_data(channel).as_slice();
+            assert!(data.iter().all(|v| (v - panning_gain).abs() < 1e-6));
+        }
+
+        // pointing away from the listener, clamped to the outer gain
+        let output = render_oriented_source([0., 0., -1.], 0.25);
+        for channel in 0..2 {
+            let data = output.channel_data(channel).as_slice();
+            assert!(data.iter().all(|v| (v - 0.25 * panning_gain).abs() < 1e-6));
+        }
+    }
+
+    #[test]
+    fn test_cone_interpolation() {
+        let cone = SoundCone {
+            inner_angle: 60.,
+            outer_angle: 120.,
+            outer_gain: 0.5,
+        };
+        let listener = [0., 0., 0.];
+        let source = [0., 0., -1.];
+
+        // pointing sideways, halfway between the inner (30°) and outer (60°) half angles
+        let orientation = [(PI / 4.).sin(), 0., (PI / 4.).cos()];
+        assert_float_eq!(cone.gain(source, orientation, listener), 0.75, abs <= 1e-5);
+
+        // outside the outer cone
+        assert_float_eq!(cone.gain(source, [1., 0., 0.], listener), 0.5, abs <= 1e-6);
+
+        // no orientation
+        assert_float_eq!(cone.gain(source, [0., 0., 0.], listener), 1., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_cone_outer_gain() {
+        let context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+        context.create_panner().set_cone_outer_gain(1.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_negative_ref_distance() {
+        let context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+        context.create_panner().set_ref_distance(-1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_quanta() {
+        let context = OfflineAudioContext::new(2, 128, SampleRate(44_100));
+        let panner = context.create_panner();
+        panner.set_update_rate(SpatialUpdateRate::EveryNQuanta(0));
+    }
 }
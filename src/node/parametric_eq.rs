@@ -0,0 +1,318 @@
+use crate::buffer::{ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{SampleRate, MAX_CHANNELS};
+
+use super::biquad_filter::{coefficients, frequency_response};
+use super::{AudioNode, BiquadFilterType};
+
+/// Settings of a single band of a `ParametricEqNode`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandSpec {
+    /// Shape of the band
+    pub type_: BiquadFilterType,
+    /// Center or cutoff frequency, in Hz
+    pub frequency: f32,
+    /// Quality factor, its impact depends on the `type_`
+    pub q: f32,
+    /// Boost or attenuation (dB), for the peaking and shelving types
+    pub gain: f32,
+}
+
+/// Options for constructing a ParametricEqNode
+#[derive(Default)]
+pub struct ParametricEqOptions {
+    /// The bands, applied in series
+    pub bands: Vec<BandSpec>,
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// Multi-band equalizer, cascading fixed biquad sections within a single node
+///
+/// This is an extension to the Web Audio API specification. Each band behaves like a
+/// `BiquadFilterNode` with constant parameters, all bands are processed in series. Without bands,
+/// the audio passes unchanged.
+pub struct ParametricEqNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    sample_rate: f32,
+    bands: Vec<BandSpec>,
+    /// biquad coefficients `[b0, b1, b2, a1, a2]` of each band
+    coeffs: Vec<[f64; 5]>,
+}
+
+impl AudioNode for ParametricEqNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        1
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl ParametricEqNode {
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: ParametricEqOptions) -> Self {
+        context.base().register(move |registration| {
+            let sample_rate = context.base().sample_rate().0 as f32;
+
+            let coeffs: Vec<_> = options
+                .bands
+                .iter()
+                .map(|band| {
+                    coefficients(
+                        band.type_,
+                        f64::from(sample_rate),
+                        f64::from(band.frequency),
+                        f64::from(band.q),
+                        f64::from(band.gain),
+                    )
+                })
+                .collect();
+
+            let render = ParametricEqRenderer {
+                coeffs: coeffs.clone(),
+                states: vec![[[0.; 2]; MAX_CHANNELS]; coeffs.len()],
+            };
+
+            let node = ParametricEqNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                sample_rate,
+                bands: options.bands,
+                coeffs,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// The bands of the equalizer, in processing order
+    pub fn bands(&self) -> &[BandSpec] {
+        &self.bands
+    }
+
+    /// Returns the frequency response of the whole cascade for the specified frequencies
+    ///
+    /// The magnitudes of the bands multiply (their gains in dB add up) and their phases add up.
+    /// Frequencies are clamped to the range `[0, sample_rate / 2]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency_hz` - frequencies for which frequency response of the filter should be calculated
+    /// * `mag_response` - magnitude of the frequency response of the filter
+    /// * `phase_response` - phase of the frequency response of the filter
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the three slices do not have the same length
+    pub fn get_frequency_response(
+        &self,
+        frequency_hz: &mut [f32],
+        mag_response: &mut [f32],
+        phase_response: &mut [f32],
+    ) {
+        assert_eq!(
+            frequency_hz.len(),
+            mag_response.len(),
+            "InvalidAccessError: All parameters should be the same length"
+        );
+        assert_eq!(
+            mag_response.len(),
+            phase_response.len(),
+            "InvalidAccessError: All parameters should be the same length"
+        );
+
+        let sample_rate = f64::from(self.sample_rate);
+        for ((f, mag), phase) in frequency_hz
+            .iter_mut()
+            .zip(mag_response.iter_mut())
+            .zip(phase_response.iter_mut())
+        {
+            *f = f.clamp(0., self.sample_rate / 2.);
+
+            let h_f = self
+                .coeffs
+                .iter()
+                .map(|&coeffs| frequency_response(coeffs, f64::from(*f), sample_rate))
+                .product::<num_complex::Complex<f64>>();
+
+            *mag = h_f.norm() as f32;
+            *phase = h_f.arg() as f32;
+        }
+    }
+}
+
+struct ParametricEqRenderer {
+    /// biquad coefficients `[b0, b1, b2, a1, a2]` of each band
+    coeffs: Vec<[f64; 5]>,
+    /// transposed direct form II state of each band, per channel
+    states: Vec<[[f64; 2]; MAX_CHANNELS]>,
+}
+
+impl AudioProcessor for ParametricEqRenderer {
+    fn process(
+        &mut self,
+        inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        *output = input.clone();
+
+        for (channel_idx, channel) in output.channels_mut().iter_mut().enumerate() {
+            for sample in channel.iter_mut() {
+                let mut value = f64::from(*sample);
+
+                for (coeffs, states) in self.coeffs.iter().zip(self.states.iter_mut()) {
+                    let [b0, b1, b2, a1, a2] = *coeffs;
+                    let [s1, s2] = &mut states[channel_idx];
+
+                    let out = b0.mul_add(value, *s1);
+                    *s1 = b1.mul_add(value, *s2) - a1 * out;
+                    *s2 = b2 * value - a2 * out;
+                    value = out;
+                }
+
+                *sample = value as f32;
+            }
+        }
+    }
+
+    fn tail_time(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.states
+            .iter_mut()
+            .for_each(|states| *states = [[0.; 2]; MAX_CHANNELS]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode, BiquadFilterType};
+    use crate::SampleRate;
+
+    use super::*;
+
+    fn to_db(mag: f32) -> f32 {
+        20. * mag.log10()
+    }
+
+    #[test]
+    fn test_three_band_response() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+
+        let bands = vec![
+            BandSpec {
+                type_: BiquadFilterType::Lowshelf,
+                frequency: 100.,
+                q: 1.,
+                gain: 6.,
+            },
+            BandSpec {
+                type_: BiquadFilterType::Peaking,
+                frequency: 1000.,
+                q: 2.,
+                gain: -9.,
+            },
+            BandSpec {
+                type_: BiquadFilterType::Highshelf,
+                frequency: 10_000.,
+                q: 1.,
+                gain: 3.,
+            },
+        ];
+        let eq = context.create_parametric_eq(bands);
+        assert_eq!(eq.bands().len(), 3);
+
+        let mut frequencies = [20., 1000., 3000., 20_000., 30_000.];
+        let mut mag = [0.; 5];
+        let mut phase = [0.; 5];
+        eq.get_frequency_response(&mut frequencies, &mut mag, &mut phase);
+
+        // boosted lows, cut mids, boosted highs, flat in between
+        assert_float_eq!(to_db(mag[0]), 6., abs <= 0.1);
+        assert_float_eq!(to_db(mag[1]), -9., abs <= 0.1);
+        assert!(to_db(mag[2]).abs() < 1.);
+        assert_float_eq!(to_db(mag[3]), 3., abs <= 0.1);
+
+        // clamped to nyquist
+        assert_float_eq!(frequencies[4], 22_050., abs <= 0.);
+    }
+
+    #[test]
+    fn test_cascade_matches_biquads_in_series() {
+        let sample_rate = SampleRate(44_100);
+        let bands = [
+            BandSpec {
+                type_: BiquadFilterType::Lowpass,
+                frequency: 2000.,
+                q: 1.,
+                gain: 0.,
+            },
+            BandSpec {
+                type_: BiquadFilterType::Peaking,
+                frequency: 500.,
+                q: 1.,
+                gain: 12.,
+            },
+        ];
+
+        let render = |use_eq: bool| {
+            let mut context = OfflineAudioContext::new(1, 512, sample_rate);
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(440.);
+            osc.start();
+
+            if use_eq {
+                let options = ParametricEqOptions {
+                    bands: bands.to_vec(),
+                    ..ParametricEqOptions::default()
+                };
+                let eq = ParametricEqNode::new(&context, options);
+                osc.connect(&eq);
+                eq.connect(&context.destination());
+            } else {
+                let mut previous: Box<dyn AudioNode> = Box::new(osc);
+                for band in bands {
+                    let mut biquad = context.create_biquad_filter();
+                    biquad.set_type(band.type_);
+                    biquad.frequency().set_value(band.frequency);
+                    biquad.q().set_value(band.q);
+                    biquad.gain().set_value(band.gain);
+                    previous.connect(&biquad);
+                    previous = Box::new(biquad);
+                }
+                previous.connect(&context.destination());
+            }
+
+            context.start_rendering()
+        };
+
+        let expected = render(false);
+        let output = render(true);
+        assert_float_eq!(
+            output.channel_data(0).as_slice(),
+            expected.channel_data(0).as_slice(),
+            abs_all <= 1e-5
+        );
+    }
+}
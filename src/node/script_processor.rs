@@ -0,0 +1,254 @@
+use crate::alloc::AudioBuffer;
+use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{SampleRate, MAX_CHANNELS};
+
+use super::AudioNode;
+
+/// Options for constructing a ScriptProcessorNode
+pub struct ScriptProcessorOptions {
+    /// Buffer size of the browser API, zero lets the implementation choose
+    pub buffer_size: usize,
+    pub number_of_input_channels: usize,
+    pub number_of_output_channels: usize,
+}
+
+impl Default for ScriptProcessorOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: 0,
+            number_of_input_channels: 2,
+            number_of_output_channels: 2,
+        }
+    }
+}
+
+/// Node processing audio with a user supplied closure
+///
+/// The closure receives the input buffers (one per input, empty if the node has no input
+/// channels) and the output buffer, and is invoked on the render thread for every render quantum.
+/// The output buffer holds `number_of_output_channels` silent channels when the closure is called.
+/// A node without output channels has no output, the closure then receives an empty slice.
+///
+/// Unlike the browser API, the audio is not buffered so the node adds no latency. The
+/// `buffer_size` is validated for compatibility only.
+///
+/// The closure runs on the render thread: allocating memory, locking or otherwise blocking in it
+/// can cause audible glitches. Keeping it real-time safe is the responsibility of the user.
+pub struct ScriptProcessorNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    buffer_size: usize,
+    number_of_input_channels: usize,
+    number_of_output_channels: usize,
+}
+
+impl AudioNode for ScriptProcessorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        if self.number_of_input_channels == 0 {
+            0
+        } else {
+            1
+        }
+    }
+    fn number_of_outputs(&self) -> u32 {
+        if self.number_of_output_channels == 0 {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl ScriptProcessorNode {
+    /// # Panics
+    ///
+    /// Will panic with an `IndexSizeError` if the `buffer_size` is not zero or a power of two in
+    /// the range `[256, 16384]`, if both channel counts are zero, or if a channel count exceeds
+    /// `MAX_CHANNELS`
+    pub fn new<C, F>(context: &C, options: ScriptProcessorOptions, callback: F) -> Self
+    where
+        C: AsBaseAudioContext,
+        F: FnMut(&[AudioBuffer], &mut [AudioBuffer]) + Send + 'static,
+    {
+        let ScriptProcessorOptions {
+            buffer_size,
+            number_of_input_channels,
+            number_of_output_channels,
+        } = options;
+
+        assert!(
+            buffer_size == 0 || (buffer_size.is_power_of_two() && (256..=16384).contains(&buffer_size)),
+            "IndexSizeError: buffer size should be zero or a power of two in the range [256, 16384]"
+        );
+        assert!(
+            number_of_input_channels > 0 || number_of_output_channels > 0,
+            "IndexSizeError: the number of input and output channels should not both be zero"
+        );
+        assert!(
+            number_of_input_channels <= MAX_CHANNELS && number_of_output_channels <= MAX_CHANNELS,
+            "IndexSizeError: the number of channels should not exceed {}",
+            MAX_CHANNELS
+        );
+
+        context.base().register(move |registration| {
+            let render = ScriptProcessorRenderer {
+                callback: Box::new(callback),
+                number_of_output_channels,
+            };
+
+            let node = ScriptProcessorNode {
+                registration,
+                channel_config: ChannelConfigOptions {
+                    count: number_of_input_channels.max(1),
+                    mode: ChannelCountMode::Explicit,
+                    interpretation: ChannelInterpretation::Speakers,
+                }
+                .into(),
+                buffer_size: if buffer_size == 0 { 256 } else { buffer_size },
+                number_of_input_channels,
+                number_of_output_channels,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Buffer size of the browser API, this node processes every render quantum
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
+
+type ScriptProcessorCallback = Box<dyn FnMut(&[AudioBuffer], &mut [AudioBuffer]) + Send>;
+
+struct ScriptProcessorRenderer {
+    callback: ScriptProcessorCallback,
+    number_of_output_channels: usize,
+}
+
+impl AudioProcessor for ScriptProcessorRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioBuffer],
+        outputs: &mut [AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) {
+        // single output node, or no output at all
+        if let Some(output) = outputs.first_mut() {
+            output.make_silent();
+            output.set_number_of_channels(self.number_of_output_channels);
+        }
+
+        (self.callback)(inputs, outputs);
+    }
+
+    fn tail_time(&self) -> bool {
+        // the closure can produce sound without input
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+    use crate::SampleRate;
+
+    #[test]
+    fn test_callback_processes_input() {
+        let mut context = OfflineAudioContext::new(2, 256, SampleRate(44_100));
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+
+        // mono in, stereo out: the right channel is inverted
+        let script = context.create_script_processor(0, 1, 2, |inputs, outputs| {
+            let input = inputs[0].channel_data(0).clone();
+            let output = &mut outputs[0];
+            output
+                .channel_data_mut(0)
+                .iter_mut()
+                .zip(input.iter())
+                .for_each(|(o, i)| *o = *i);
+            output
+                .channel_data_mut(1)
+                .iter_mut()
+                .zip(input.iter())
+                .for_each(|(o, i)| *o = -*i);
+        });
+        assert_eq!(script.number_of_inputs(), 1);
+        assert_eq!(script.buffer_size(), 256);
+
+        src.connect(&script);
+        script.connect(&context.destination());
+        let output = context.start_rendering();
+
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 0.5));
+        assert!(output.channel_data(1).as_slice().iter().all(|&v| v == -0.5));
+    }
+
+    #[test]
+    fn test_callback_without_input() {
+        let mut context = OfflineAudioContext::new(1, 256, SampleRate(44_100));
+
+        // generate a ramp, counting the samples across render quanta
+        let mut count = 0.;
+        let script = context.create_script_processor(1024, 0, 1, move |inputs, outputs| {
+            assert!(inputs.is_empty());
+            outputs[0].channel_data_mut(0).iter_mut().for_each(|o| {
+                *o = count;
+                count += 1.;
+            });
+        });
+        assert_eq!(script.number_of_inputs(), 0);
+
+        script.connect(&context.destination());
+        let output = context.start_rendering();
+
+        let expected: Vec<f32> = (0..256).map(|i| i as f32).collect();
+        assert_eq!(output.channel_data(0).as_slice(), &expected[..]);
+    }
+
+    #[test]
+    fn test_callback_without_output() {
+        let mut context = OfflineAudioContext::new(1, 4 * 128, SampleRate(44_100));
+        let src = context.create_constant_source();
+
+        // an analysis sink, counting the rendered quanta
+        let quanta = Arc::new(AtomicUsize::new(0));
+        let quanta_clone = quanta.clone();
+        let script = context.create_script_processor(0, 1, 0, move |inputs, outputs| {
+            assert!(outputs.is_empty());
+            assert!(inputs[0].channel_data(0).iter().all(|&v| v == 1.));
+            quanta_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(script.number_of_outputs(), 0);
+
+        src.connect(&script);
+        let output = context.start_rendering();
+
+        assert_eq!(quanta.load(Ordering::SeqCst), 4);
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_invalid_buffer_size() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        context.create_script_processor(1000, 1, 1, |_, _| {});
+    }
+}
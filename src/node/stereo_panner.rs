@@ -88,6 +88,10 @@ impl AudioNode for StereoPannerNode {
         assert!(v <= 2, "NotSupportedError");
         self.channel_config.set_count(v);
     }
+
+    fn max_channel_count(&self) -> usize {
+        2
+    }
 }
 
 impl StereoPannerNode {
@@ -144,7 +148,7 @@ impl StereoPannerNode {
         })
     }
 
-    /// Returns the pan audio paramter
+    /// Returns the pan audio paramter (a-rate)
     #[must_use]
     pub const fn pan(&self) -> &AudioParam {
         &self.pan
@@ -245,19 +249,32 @@ impl StereoPannerRenderer {
     }
 
     /// Generates the stereo gains for a specific x derived from pan
+    ///
+    /// Follows the equal-power law of the spec, `(cos(x * PI / 2), sin(x * PI / 2))`, with x in
+    /// `[0, 1]`. The gains are linearly interpolated from the first quarter of the sine wavetable.
     #[inline]
     fn stereo_gains(x: f32) -> (f32, f32) {
+        let x = x.clamp(0., 1.);
+        let position = x * TABLE_LENGTH_BY_4_F32;
         // truncation is the intented behavior
         #[allow(clippy::cast_possible_truncation)]
-        // no sign loss: x is always positive
+        // no sign loss: position is always positive
         #[allow(clippy::cast_sign_loss)]
-        let idx = (x * TABLE_LENGTH_BY_4_F32) as usize;
-        let gain_l = SINETABLE[idx + TABLE_LENGTH_BY_4_USIZE];
-        let gain_r = SINETABLE[idx];
+        let idx = position as usize;
+        // idx is at most TABLE_LENGTH_BY_4_USIZE, cast without loss of precision
+        #[allow(clippy::cast_precision_loss)]
+        let frac = position - idx as f32;
+
+        let interpolate = |idx: usize| {
+            let (a, b) = (SINETABLE[idx], SINETABLE[idx + 1]);
+            (b - a).mul_add(frac, a)
+        };
+        let gain_l = interpolate(idx + TABLE_LENGTH_BY_4_USIZE);
+        let gain_r = interpolate(idx);
 
         // Assert correctness of wavetable optimization
-        debug_assert_float_eq!(gain_l, (x * PI / 2.).cos(), abs <= 0.1, "gain_l panicked");
-        debug_assert_float_eq!(gain_r, (x * PI / 2.).sin(), abs <= 0.1, "gain_r panicked");
+        debug_assert_float_eq!(gain_l, (x * PI / 2.).cos(), abs <= 1e-5, "gain_l panicked");
+        debug_assert_float_eq!(gain_r, (x * PI / 2.).sin(), abs <= 1e-5, "gain_r panicked");
 
         (gain_l, gain_r)
     }
@@ -265,10 +282,13 @@ impl StereoPannerRenderer {
 
 #[cfg(test)]
 mod test {
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
     use float_eq::assert_float_eq;
 
     use crate::{
         context::{AsBaseAudioContext, OfflineAudioContext},
+        node::AudioNode,
         SampleRate,
     };
 
@@ -357,6 +377,73 @@ mod test {
         assert_float_eq!(i_r, 1.0, ulps <= 0);
     }
 
+    #[test]
+    fn mono_input_follows_equal_power_law() {
+        for &pan in &[-1., 0., 0.5, 1.] {
+            let (i_l, i_r) = StereoPannerRenderer::mono_tick(1., pan);
+
+            let x = (pan + 1.) * PI / 4.;
+            assert_float_eq!(i_l, x.cos(), abs <= 1e-5);
+            assert_float_eq!(i_r, x.sin(), abs <= 1e-5);
+        }
+
+        // the mono source is spread evenly at pan = 0
+        let (i_l, i_r) = StereoPannerRenderer::mono_tick(1., 0.);
+        assert_float_eq!(i_l, FRAC_1_SQRT_2, abs <= 1e-5);
+        assert_float_eq!(i_r, FRAC_1_SQRT_2, abs <= 1e-5);
+    }
+
+    #[test]
+    fn stereo_input_follows_equal_power_law() {
+        let input = (1., 0.5);
+
+        // full left: the right channel is folded into the left one
+        let (i_l, i_r) = StereoPannerRenderer::stereo_tick(input, -1.);
+        assert_float_eq!(i_l, 1.5, abs <= 1e-5);
+        assert_float_eq!(i_r, 0., abs <= 1e-5);
+
+        // center: both channels pass through at unity gain
+        let (i_l, i_r) = StereoPannerRenderer::stereo_tick(input, 0.);
+        assert_float_eq!(i_l, 1., abs <= 1e-5);
+        assert_float_eq!(i_r, 0.5, abs <= 1e-5);
+
+        // the left channel is attenuated and partly moved to the right
+        let (i_l, i_r) = StereoPannerRenderer::stereo_tick(input, 0.5);
+        let x = 0.5 * PI / 2.;
+        assert_float_eq!(i_l, x.cos(), abs <= 1e-5);
+        assert_float_eq!(i_r, 0.5 + x.sin(), abs <= 1e-5);
+
+        // full right: the left channel is folded into the right one
+        let (i_l, i_r) = StereoPannerRenderer::stereo_tick(input, 1.);
+        assert_float_eq!(i_l, 0., abs <= 1e-5);
+        assert_float_eq!(i_r, 1.5, abs <= 1e-5);
+    }
+
+    #[test]
+    fn mono_source_is_spread_in_the_graph() {
+        let mut context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
+
+        let src = context.create_constant_source();
+        let panner = context.create_stereo_panner();
+        panner.pan().set_value(0.5);
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering();
+
+        let x = 1.5 * PI / 4.;
+        assert!(output
+            .channel_data(0)
+            .as_slice()
+            .iter()
+            .all(|&v| (v - x.cos()).abs() < 1e-5));
+        assert!(output
+            .channel_data(1)
+            .as_slice()
+            .iter()
+            .all(|&v| (v - x.sin()).abs() < 1e-5));
+    }
+
     #[test]
     #[should_panic]
     fn setting_pan_more_than_1_should_fail() {
@@ -0,0 +1,567 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::buffer::{AudioBuffer, ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration};
+use crate::control::Scheduler;
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::{AtomicF64, SampleRate, BUFFER_SIZE};
+
+use super::{AudioNode, AudioScheduledSourceNode};
+
+/// Size of the analysis and synthesis frames of the phase vocoder
+const FRAME_SIZE: usize = 2048;
+/// Distance between subsequent synthesis frames (75% overlap)
+const SYNTHESIS_HOP: usize = FRAME_SIZE / 4;
+/// Spectral flux (relative to the previous frame energy) above which a frame is a transient
+const TRANSIENT_THRESHOLD: f32 = 1.;
+
+/// Options for constructing a TimeStretchNode
+pub struct TimeStretchOptions {
+    /// Source audio, resampled to the sample rate of the context if needed
+    pub buffer: Option<AudioBuffer>,
+    /// Ratio of the output duration to the source duration
+    pub stretch_factor: f64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for TimeStretchOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            stretch_factor: 1.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Source node playing an AudioBuffer slowed down or sped up, while preserving its pitch
+///
+/// The stretching is performed with a phase vocoder, which works best on tonal material and
+/// allows for extreme factors (e.g. 8x or more, for ambient textures). Transients get smeared by
+/// this method, so onsets are detected and the phases are reset on them to keep attacks sharp.
+///
+/// Note: do not forget to `start()` the node.
+pub struct TimeStretchNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    scheduler: Scheduler,
+    stretch_factor: Arc<AtomicF64>,
+}
+
+impl AudioScheduledSourceNode for TimeStretchNode {
+    fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+}
+
+impl AudioNode for TimeStretchNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        0
+    }
+    fn number_of_outputs(&self) -> u32 {
+        1
+    }
+}
+
+impl TimeStretchNode {
+    /// Creates a `TimeStretchNode`
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the stretch factor is not strictly positive
+    pub fn new<C: AsBaseAudioContext>(context: &C, options: TimeStretchOptions) -> Self {
+        assert_valid_stretch_factor(options.stretch_factor);
+
+        context.base().register(move |registration| {
+            let sample_rate = context.base().sample_rate();
+            let mut buffer = options
+                .buffer
+                .unwrap_or_else(|| AudioBuffer::new(1, 0, sample_rate));
+            buffer.resample(sample_rate);
+
+            let scheduler = Scheduler::new();
+            let stretch_factor = Arc::new(AtomicF64::new(options.stretch_factor));
+
+            let render =
+                TimeStretchRenderer::new(buffer, scheduler.clone(), stretch_factor.clone());
+
+            let node = TimeStretchNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                scheduler,
+                stretch_factor,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Ratio of the output duration to the source duration
+    pub fn stretch_factor(&self) -> f64 {
+        self.stretch_factor.load()
+    }
+
+    /// Set the ratio of the output duration to the source duration
+    ///
+    /// Values above 1 slow the playback down, values below 1 speed it up. The pitch is not
+    /// affected. The new factor applies from the next frame of the vocoder.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the stretch factor is not strictly positive
+    pub fn set_stretch_factor(&self, stretch_factor: f64) {
+        assert_valid_stretch_factor(stretch_factor);
+        self.stretch_factor.store(stretch_factor);
+    }
+}
+
+fn assert_valid_stretch_factor(stretch_factor: f64) {
+    assert!(
+        stretch_factor > 0. && stretch_factor.is_finite(),
+        "RangeError: stretch factor should be strictly positive"
+    );
+}
+
+/// Phase vocoder state of a single channel
+struct ChannelState {
+    /// analysis phases of the previous frame
+    prev_phase: Vec<f32>,
+    /// magnitudes of the previous frame, for transient detection
+    prev_magnitude: Vec<f32>,
+    /// accumulated phases of the synthesis frames
+    synthesis_phase: Vec<f32>,
+    /// overlap-add accumulator, starting at the next output sample
+    overlap: Vec<f32>,
+    /// finished output samples, ready to be rendered
+    ready: VecDeque<f32>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        let bins = FRAME_SIZE / 2 + 1;
+        Self {
+            prev_phase: vec![0.; bins],
+            prev_magnitude: vec![0.; bins],
+            synthesis_phase: vec![0.; bins],
+            overlap: vec![0.; FRAME_SIZE],
+            ready: VecDeque::with_capacity(FRAME_SIZE),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.prev_phase.iter_mut().for_each(|v| *v = 0.);
+        self.prev_magnitude.iter_mut().for_each(|v| *v = 0.);
+        self.synthesis_phase.iter_mut().for_each(|v| *v = 0.);
+        self.overlap.iter_mut().for_each(|v| *v = 0.);
+        self.ready.clear();
+    }
+}
+
+struct TimeStretchRenderer {
+    buffer: AudioBuffer,
+    scheduler: Scheduler,
+    stretch_factor: Arc<AtomicF64>,
+    channels: Vec<ChannelState>,
+    /// position of the next analysis frame in the source, in (fractional) sample-frames
+    analysis_position: f64,
+    /// start of the previous analysis frame, `None` before the first frame
+    prev_start: Option<i64>,
+    /// number of leading output samples to drop, produced by the frames before the source start
+    discard: usize,
+    /// all frames covering the source have been processed
+    input_done: bool,
+    finished: bool,
+    r2c: Arc<dyn RealToComplex<f32>>,
+    c2r: Arc<dyn ComplexToReal<f32>>,
+    window: Vec<f32>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl TimeStretchRenderer {
+    fn new(buffer: AudioBuffer, scheduler: Scheduler, stretch_factor: Arc<AtomicF64>) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let r2c = planner.plan_fft_forward(FRAME_SIZE);
+        let c2r = planner.plan_fft_inverse(FRAME_SIZE);
+        let scratch_len = r2c.get_scratch_len().max(c2r.get_scratch_len());
+
+        // periodic Hann window
+        let window = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2. * PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+
+        // let the first frames fade in before the source start, so the output starts at full level
+        let lead_in = FRAME_SIZE - SYNTHESIS_HOP;
+        let analysis_position = -(lead_in as f64) / stretch_factor.load();
+
+        Self {
+            channels: (0..buffer.number_of_channels())
+                .map(|_| ChannelState::new())
+                .collect(),
+            buffer,
+            scheduler,
+            stretch_factor,
+            analysis_position,
+            prev_start: None,
+            discard: lead_in,
+            input_done: false,
+            finished: false,
+            spectrum: r2c.make_output_vec(),
+            frame: vec![0.; FRAME_SIZE],
+            scratch: vec![Complex::default(); scratch_len],
+            window,
+            r2c,
+            c2r,
+        }
+    }
+
+    /// Analyse the next frame of the source and add its resynthesis to the output
+    fn process_frame(&mut self) {
+        let start = self.analysis_position.round() as i64;
+        let len = self.buffer.sample_len() as i64;
+
+        if start >= len {
+            // the remaining overlap is the tail of the last frame
+            self.channels.iter_mut().for_each(|state| {
+                state
+                    .ready
+                    .extend(state.overlap.drain(..FRAME_SIZE - SYNTHESIS_HOP));
+            });
+            self.input_done = true;
+            return;
+        }
+
+        // actual analysis hop, the positions are rounded to whole sample-frames
+        let analysis_hop = self.prev_start.map(|prev| (start - prev) as f32);
+        self.prev_start = Some(start);
+
+        // sum of the squared windows of overlapping frames, and the unnormalized inverse FFT
+        let normalization = 1. / (1.5 * FRAME_SIZE as f32);
+
+        for (channel, state) in self.channels.iter_mut().enumerate() {
+            let data = self.buffer.channel_data(channel).as_slice();
+            self.frame
+                .iter_mut()
+                .zip(&self.window)
+                .enumerate()
+                .for_each(|(i, (f, w))| {
+                    let index = start + i as i64;
+                    *f = if index >= 0 && index < len {
+                        data[index as usize] * w
+                    } else {
+                        0.
+                    };
+                });
+
+            self.r2c
+                .process_with_scratch(&mut self.frame, &mut self.spectrum, &mut self.scratch)
+                .unwrap();
+
+            // onset detection: a strong increase of energy compared to the previous frame
+            let previous_energy: f32 = state.prev_magnitude.iter().sum();
+            let flux: f32 = self
+                .spectrum
+                .iter()
+                .zip(&state.prev_magnitude)
+                .map(|(c, prev)| (c.norm() - prev).max(0.))
+                .sum();
+            let transient = flux > TRANSIENT_THRESHOLD * previous_energy + f32::EPSILON;
+
+            for (k, c) in self.spectrum.iter_mut().enumerate() {
+                let (magnitude, phase) = c.to_polar();
+                let bin_frequency = 2. * PI * k as f32 / FRAME_SIZE as f32;
+
+                match analysis_hop {
+                    // keep the phase relations of the source intact at the start and at onsets
+                    None => state.synthesis_phase[k] = phase,
+                    Some(_) if transient => state.synthesis_phase[k] = phase,
+                    Some(hop) if hop <= 0. => {
+                        state.synthesis_phase[k] += bin_frequency * SYNTHESIS_HOP as f32
+                    }
+                    Some(hop) => {
+                        // deviation from the phase advance expected for the bin frequency
+                        let deviation = phase - state.prev_phase[k] - bin_frequency * hop;
+                        let deviation = deviation - 2. * PI * (deviation / (2. * PI)).round();
+                        let frequency = bin_frequency + deviation / hop;
+                        state.synthesis_phase[k] += frequency * SYNTHESIS_HOP as f32;
+                    }
+                }
+                state.synthesis_phase[k] %= 2. * PI;
+
+                state.prev_phase[k] = phase;
+                state.prev_magnitude[k] = magnitude;
+                *c = Complex::from_polar(magnitude, state.synthesis_phase[k]);
+            }
+
+            // the DC and Nyquist bins of a real signal have no imaginary part
+            self.spectrum[0].im = 0.;
+            self.spectrum[FRAME_SIZE / 2].im = 0.;
+
+            self.c2r
+                .process_with_scratch(&mut self.spectrum, &mut self.frame, &mut self.scratch)
+                .unwrap();
+
+            state
+                .overlap
+                .iter_mut()
+                .zip(self.frame.iter().zip(&self.window))
+                .for_each(|(o, (f, w))| *o += f * w * normalization);
+
+            // the first hop of the accumulator is complete now
+            let discard = self.discard.min(SYNTHESIS_HOP);
+            state
+                .ready
+                .extend(state.overlap.drain(..SYNTHESIS_HOP).skip(discard));
+            state.overlap.resize(FRAME_SIZE, 0.);
+        }
+
+        self.discard -= self.discard.min(SYNTHESIS_HOP);
+        self.analysis_position += SYNTHESIS_HOP as f64 / self.stretch_factor.load();
+    }
+}
+
+impl AudioProcessor for TimeStretchRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[crate::alloc::AudioBuffer],
+        outputs: &mut [crate::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        // single output node
+        let output = &mut outputs[0];
+
+        if self.finished || self.channels.is_empty() || !self.scheduler.is_active(timestamp) {
+            output.make_silent();
+            return;
+        }
+
+        let quantum = BUFFER_SIZE as usize;
+        while !self.input_done && self.channels[0].ready.len() < quantum {
+            self.process_frame();
+        }
+
+        output.set_number_of_channels(self.channels.len());
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.channels.iter_mut())
+            .for_each(|(channel, state)| {
+                let available = state.ready.len().min(quantum);
+                channel
+                    .iter_mut()
+                    .zip(state.ready.drain(..available).chain(std::iter::repeat(0.)))
+                    .for_each(|(o, v)| *o = v);
+            });
+
+        // silence the remainder of the render quantum after a stop
+        if let Some(index) = self.scheduler.stop_index(timestamp, sample_rate) {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| channel[index..].iter_mut().for_each(|v| *v = 0.));
+        }
+
+        if self.input_done && self.channels[0].ready.is_empty() {
+            self.finished = true;
+            self.scheduler.mark_ended();
+        }
+    }
+
+    fn tail_time(&self) -> bool {
+        // the scheduler ends when playback is stopped, or when the output is drained
+        !self.scheduler.ended()
+    }
+
+    fn reset(&mut self) {
+        let lead_in = FRAME_SIZE - SYNTHESIS_HOP;
+        self.analysis_position = -(lead_in as f64) / self.stretch_factor.load();
+        self.prev_start = None;
+        self.discard = lead_in;
+        self.input_done = false;
+        self.finished = false;
+        self.channels.iter_mut().for_each(ChannelState::clear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::buffer::ChannelData;
+    use crate::context::OfflineAudioContext;
+    use crate::test_util::render_mono;
+
+    /// Render a 440 Hz tone of half a second, stretched with the given factor
+    fn render_stretched_tone(stretch_factor: f64) -> Vec<f32> {
+        let sample_rate = SampleRate(44_100);
+        let tone = (0..22_050)
+            .map(|i| 0.5 * (2. * PI * 440. * i as f32 / 44_100.).sin())
+            .collect();
+        let buffer = AudioBuffer::from_channels(vec![ChannelData::from(tone)], sample_rate);
+
+        let mut context = OfflineAudioContext::new(1, 2 * 44_100, sample_rate);
+        let options = TimeStretchOptions {
+            buffer: Some(buffer),
+            stretch_factor,
+            ..TimeStretchOptions::default()
+        };
+        let node = TimeStretchNode::new(&context, options);
+        assert_float_eq!(node.stretch_factor(), stretch_factor, ulps <= 0);
+
+        node.connect(&context.destination());
+        node.start();
+
+        render_mono(&mut context)
+    }
+
+    /// Frequency estimated from the number of rising zero crossings
+    fn frequency(signal: &[f32], sample_rate: f32) -> f32 {
+        let crossings = signal
+            .windows(2)
+            .filter(|w| w[0] < 0. && w[1] >= 0.)
+            .count();
+        crossings as f32 * sample_rate / signal.len() as f32
+    }
+
+    #[test]
+    fn test_stretch_tone() {
+        let output = render_stretched_tone(2.);
+
+        // the output lasts twice as long, up to the size of the frames
+        let duration = output.iter().rposition(|v| v.abs() > 0.01).unwrap();
+        assert!(
+            (44_100 - FRAME_SIZE..44_100 + FRAME_SIZE).contains(&duration),
+            "duration {}",
+            duration
+        );
+
+        // the pitch is preserved
+        let steady = &output[4_410..39_690];
+        assert_float_eq!(frequency(steady, 44_100.), 440., abs <= 5.);
+
+        // and so is the level
+        let peak = steady.iter().fold(0., |peak: f32, v| peak.max(v.abs()));
+        assert_float_eq!(peak, 0.5, abs <= 0.05);
+    }
+
+    #[test]
+    fn test_compress_tone() {
+        let output = render_stretched_tone(0.5);
+
+        let duration = output.iter().rposition(|v| v.abs() > 0.01).unwrap();
+        assert!(
+            (11_025 - FRAME_SIZE..11_025 + FRAME_SIZE).contains(&duration),
+            "duration {}",
+            duration
+        );
+
+        let steady = &output[2_205..8_820];
+        assert_float_eq!(frequency(steady, 44_100.), 440., abs <= 10.);
+    }
+
+    #[test]
+    fn test_stop_within_render_quantum() {
+        let sample_rate = SampleRate(44_100);
+        let buffer =
+            AudioBuffer::from_channels(vec![ChannelData::from(vec![0.5; 4_410])], sample_rate);
+
+        let mut context = OfflineAudioContext::new(1, 3 * 128, sample_rate);
+        let options = TimeStretchOptions {
+            buffer: Some(buffer),
+            ..TimeStretchOptions::default()
+        };
+        let node = TimeStretchNode::new(&context, options);
+        node.connect(&context.destination());
+        node.start();
+        // halfway between sample-frames 200 and 201, in the second render quantum
+        node.stop_at(200.5 / 44_100.);
+
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+
+        assert!(output[..201].iter().all(|&v| v != 0.));
+        assert!(output[201..].iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_tail_time() {
+        let sample_rate = SampleRate(44_100);
+        let context = OfflineAudioContext::new(1, 128, sample_rate);
+        let alloc = crate::alloc::Alloc::with_capacity(1);
+        let mut outputs = [crate::alloc::AudioBuffer::new(alloc.silence())];
+        let nodes = std::collections::HashMap::new();
+
+        let new_renderer = || {
+            let buffer =
+                AudioBuffer::from_channels(vec![ChannelData::from(vec![1.; 441])], sample_rate);
+            let node = TimeStretchNode::new(&context, TimeStretchOptions::default());
+            let renderer =
+                TimeStretchRenderer::new(buffer, node.scheduler.clone(), node.stretch_factor);
+            (renderer, node.scheduler)
+        };
+
+        // stopped before the output is drained
+        let (mut renderer, scheduler) = new_renderer();
+        scheduler.start_at(0.);
+        scheduler.stop_at(0.);
+        assert!(renderer.tail_time());
+        renderer.process(
+            &[],
+            &mut outputs,
+            AudioParamValues::from(&nodes),
+            0.,
+            sample_rate,
+        );
+        assert!(!renderer.tail_time());
+
+        // played until the output is drained
+        let (mut renderer, scheduler) = new_renderer();
+        scheduler.start_at(0.);
+        let quantum = f64::from(BUFFER_SIZE) / f64::from(sample_rate.0);
+        let mut timestamp = 0.;
+        while renderer.tail_time() {
+            assert!(timestamp < 1., "the output is never drained");
+            renderer.process(
+                &[],
+                &mut outputs,
+                AudioParamValues::from(&nodes),
+                timestamp,
+                sample_rate,
+            );
+            timestamp += quantum;
+        }
+        renderer.process(
+            &[],
+            &mut outputs,
+            AudioParamValues::from(&nodes),
+            timestamp,
+            sample_rate,
+        );
+        assert!(outputs[0].channel_data(0).iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_stretch_factor() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let node = TimeStretchNode::new(&context, TimeStretchOptions::default());
+        node.set_stretch_factor(0.);
+    }
+}
@@ -12,14 +12,14 @@ use std::sync::{
 };
 
 use crossbeam_channel::{Receiver, Sender};
-use rubato::{FftFixedInOut, Resampler};
 
 use crate::{
     alloc::AudioBuffer,
     buffer::{ChannelConfig, ChannelConfigOptions},
     context::{AsBaseAudioContext, AudioContextRegistration},
+    filter::{Downsampler, Upsampler},
     process::{AudioParamValues, AudioProcessor},
-    SampleRate,
+    FilterQuality, SampleRate, BUFFER_SIZE, MAX_CHANNELS,
 };
 
 use super::AudioNode;
@@ -68,6 +68,8 @@ pub struct WaveShaperOptions {
     pub curve: Option<Vec<f32>>,
     /// Oversampling rate - default to `None`
     pub oversample: Option<OverSampleType>,
+    /// Quality of the oversampling filters - default to `Medium`
+    pub quality: FilterQuality,
     /// audio node options
     pub channel_config: Option<ChannelConfigOptions>,
 }
@@ -77,6 +79,7 @@ impl Default for WaveShaperOptions {
         Self {
             curve: Default::default(),
             oversample: Some(OverSampleType::None),
+            quality: FilterQuality::default(),
             channel_config: Default::default(),
         }
     }
@@ -97,6 +100,8 @@ pub struct WaveShaperNode {
     set_curve: bool,
     /// ovesample type
     oversample: Arc<AtomicU32>,
+    /// quality of the oversampling filters
+    quality: FilterQuality,
     /// Channel between node and renderer (sender part)
     sender: Sender<CurveMessage>,
 }
@@ -130,12 +135,9 @@ impl WaveShaperNode {
             let WaveShaperOptions {
                 curve,
                 oversample,
+                quality,
                 channel_config,
             } = options.unwrap_or_default();
-            // cannot guarantee that the cast will be without loss of precision for all fs
-            // but for usual sample rate (44.1kHz, 48kHz, 96kHz) it is
-            #[allow(clippy::cast_precision_loss)]
-            let sample_rate = context.base().sample_rate().0 as usize;
             let channel_config = channel_config.unwrap_or_default().into();
             let oversample = Arc::new(AtomicU32::new(
                 oversample.expect("oversample should be OversampleType variant") as u32,
@@ -145,9 +147,9 @@ impl WaveShaperNode {
             let (sender, receiver) = crossbeam_channel::bounded(0);
 
             let config = RendererConfig {
-                sample_rate,
                 curve: curve.clone(),
                 oversample: oversample.clone(),
+                quality,
                 receiver,
             };
 
@@ -158,6 +160,7 @@ impl WaveShaperNode {
                 curve,
                 set_curve,
                 oversample,
+                quality,
                 sender,
             };
 
@@ -225,15 +228,21 @@ impl WaveShaperNode {
     pub fn set_oversample(&mut self, oversample: OverSampleType) {
         self.oversample.store(oversample as u32, Ordering::SeqCst);
     }
+
+    /// Returns the quality of the oversampling filters, set at construction
+    #[must_use]
+    pub const fn quality(&self) -> FilterQuality {
+        self.quality
+    }
 }
 
 /// Helper struct which regroups all parameters
 /// required to build `WaveShaperRenderer`
 struct RendererConfig {
-    /// Sample rate (equals to audio context sample rate)
-    sample_rate: usize,
     /// oversample factor
     oversample: Arc<AtomicU32>,
+    /// quality of the oversampling filters
+    quality: FilterQuality,
     /// distortion curve
     curve: Option<Vec<f32>>,
     /// Channel between node and renderer (receiver part)
@@ -242,22 +251,18 @@ struct RendererConfig {
 
 /// `WaveShaperRenderer` represents the rendering part of `WaveShaperNode`
 struct WaveShaperRenderer {
-    /// Sample rate (equals to audio context sample rate)
-    sample_rate: usize,
     /// oversample factor
     oversample: Arc<AtomicU32>,
-    /// Number of channels used to build the up/down sampler X2
-    channels_x2: usize,
-    /// Number of channels used to build the up/down sampler X4
-    channels_x4: usize,
-    // up sampler configured to multiply by 2 the input fs
-    upsampler_x2: FftFixedInOut<f32>,
-    // up sampler configured to multiply by 4 the input fs
-    upsampler_x4: FftFixedInOut<f32>,
-    // down sampler configured to divide by 4 the input fs
-    downsampler_x2: FftFixedInOut<f32>,
-    // down sampler configured to divide by 4 the input fs
-    downsampler_x4: FftFixedInOut<f32>,
+    /// up samplers multiplying by 2 the input fs, one per channel
+    upsamplers_x2: Vec<Upsampler>,
+    /// up samplers multiplying by 4 the input fs, one per channel
+    upsamplers_x4: Vec<Upsampler>,
+    /// down samplers dividing by 2 the oversampled fs, one per channel
+    downsamplers_x2: Vec<Downsampler>,
+    /// down samplers dividing by 4 the oversampled fs, one per channel
+    downsamplers_x4: Vec<Downsampler>,
+    /// oversampled signal of a single channel
+    oversampled: Vec<f32>,
     /// distortion curve
     curve: Vec<f32>,
     /// set to true if curve is not None
@@ -282,33 +287,44 @@ impl AudioProcessor for WaveShaperRenderer {
         // Respond to request at K-rate
         if let Ok(msg) = self.receiver.try_recv() {
             self.curve = msg.0;
+            self.curve_set = !self.curve.is_empty();
         }
 
+        // the input passes unchanged until a curve is set
         if !self.curve_set {
-            self.no_process(input, output);
+            *output = input.clone();
+            return;
         }
 
+        output.set_number_of_channels(input.number_of_channels());
+
         use OverSampleType::*;
         match self.oversample.load(Ordering::SeqCst).into() {
             None => self.process_none(input, output),
-            X2 => {
-                if input.channels().len() != self.channels_x2 {
-                    self.update_2x(input.channels().len());
-                }
-                self.process_2x(input, output)
-            }
-            X4 => {
-                if input.channels().len() != self.channels_x4 {
-                    self.update_4x(input.channels().len());
-                }
-                self.process_4x(input, output)
-            }
+            X2 => self.process_2x(input, output),
+            X4 => self.process_4x(input, output),
         }
     }
 
     fn tail_time(&self) -> bool {
         true
     }
+
+    fn latency_samples(&self) -> f64 {
+        use OverSampleType::*;
+        match self.oversample.load(Ordering::SeqCst).into() {
+            None => 0.,
+            X2 => self.upsamplers_x2[0].latency() + self.downsamplers_x2[0].latency(),
+            X4 => self.upsamplers_x4[0].latency() + self.downsamplers_x4[0].latency(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.upsamplers_x2.iter_mut().for_each(Upsampler::reset);
+        self.upsamplers_x4.iter_mut().for_each(Upsampler::reset);
+        self.downsamplers_x2.iter_mut().for_each(Downsampler::reset);
+        self.downsamplers_x4.iter_mut().for_each(Downsampler::reset);
+    }
 }
 
 impl WaveShaperRenderer {
@@ -318,8 +334,8 @@ impl WaveShaperRenderer {
     #[allow(clippy::missing_const_for_fn)]
     fn new(config: RendererConfig) -> Self {
         let RendererConfig {
-            sample_rate,
             oversample,
+            quality,
             curve,
             receiver,
         } = config;
@@ -329,110 +345,72 @@ impl WaveShaperRenderer {
             None => (Vec::new(), false),
         };
 
-        let channels_x2 = 1;
-        let channels_x4 = 1;
-
-        let upsampler_x2 = FftFixedInOut::<f32>::new(
-            sample_rate as usize,
-            sample_rate as usize * 2,
-            256,
-            channels_x2,
-        );
-
-        let downsampler_x2 = FftFixedInOut::<f32>::new(
-            sample_rate as usize,
-            sample_rate as usize / 2,
-            128,
-            channels_x2,
-        );
-
-        let upsampler_x4 = FftFixedInOut::<f32>::new(
-            sample_rate as usize,
-            sample_rate as usize * 4,
-            512,
-            channels_x4,
-        );
-
-        let downsampler_x4 = FftFixedInOut::<f32>::new(
-            sample_rate as usize,
-            sample_rate as usize / 4,
-            128,
-            channels_x4,
-        );
-
         Self {
-            sample_rate,
             oversample,
             curve,
             curve_set,
-            upsampler_x2,
-            upsampler_x4,
-            downsampler_x2,
-            downsampler_x4,
-            channels_x2,
-            channels_x4,
+            upsamplers_x2: new_upsamplers(2, quality),
+            upsamplers_x4: new_upsamplers(4, quality),
+            downsamplers_x2: new_downsamplers(2, quality),
+            downsamplers_x4: new_downsamplers(4, quality),
+            oversampled: vec![0.; BUFFER_SIZE as usize * 4],
             receiver,
         }
     }
 
+    /// Apply the curve at the input rate
     #[inline]
-    fn no_process(&self, input: &AudioBuffer, output: &mut AudioBuffer) {
+    fn process_none(&self, input: &AudioBuffer, output: &mut AudioBuffer) {
         for (i_data, o_data) in input.channels().iter().zip(output.channels_mut()) {
             for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
-                *o = i;
+                *o = self.tick(i);
             }
         }
     }
 
-    #[inline]
-    fn process_none(&self, input: &AudioBuffer, output: &mut AudioBuffer) {
-        for (i_data, o_data) in input.channels().iter().zip(output.channels_mut()) {
-            o_data.copy_from_slice(&i_data[..]);
-        }
-    }
-
     #[inline]
     fn process_2x(&mut self, input: &AudioBuffer, output: &mut AudioBuffer) {
-        let wave_in = input.channels();
-
-        let up_wave_in = self.upsampler_x2.process(wave_in).unwrap();
-        let mut up_wave_out = up_wave_in.clone();
-
-        for (i_data, o_data) in up_wave_in.iter().zip(&mut up_wave_out) {
-            for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
-                *o = self.tick(i);
-            }
-        }
-
-        let wave_out = self.downsampler_x2.process(&up_wave_out).unwrap();
-
-        for (i_data, o_data) in wave_out.iter().zip(output.channels_mut()) {
-            for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
-                *o = i;
-            }
-        }
+        let mut upsamplers = std::mem::take(&mut self.upsamplers_x2);
+        let mut downsamplers = std::mem::take(&mut self.downsamplers_x2);
+        self.process_oversampled(input, output, &mut upsamplers, &mut downsamplers);
+        self.upsamplers_x2 = upsamplers;
+        self.downsamplers_x2 = downsamplers;
     }
 
     #[inline]
     fn process_4x(&mut self, input: &AudioBuffer, output: &mut AudioBuffer) {
-        let wave_in = input.channels();
-
-        let up_wave_in = self.upsampler_x4.process(wave_in).unwrap();
-        let mut up_wave_out = up_wave_in.clone();
+        let mut upsamplers = std::mem::take(&mut self.upsamplers_x4);
+        let mut downsamplers = std::mem::take(&mut self.downsamplers_x4);
+        self.process_oversampled(input, output, &mut upsamplers, &mut downsamplers);
+        self.upsamplers_x4 = upsamplers;
+        self.downsamplers_x4 = downsamplers;
+    }
 
-        for (i_data, o_data) in up_wave_in.iter().zip(&mut up_wave_out) {
-            for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
-                *o = self.tick(i);
-            }
+    /// Upsample each channel, apply the curve at the higher rate and downsample again
+    #[inline]
+    fn process_oversampled(
+        &mut self,
+        input: &AudioBuffer,
+        output: &mut AudioBuffer,
+        upsamplers: &mut [Upsampler],
+        downsamplers: &mut [Downsampler],
+    ) {
+        let mut oversampled = std::mem::take(&mut self.oversampled);
+
+        for (((i_data, o_data), upsampler), downsampler) in input
+            .channels()
+            .iter()
+            .zip(output.channels_mut())
+            .zip(upsamplers)
+            .zip(downsamplers)
+        {
+            let wave = &mut oversampled[..i_data.len() * upsampler.factor()];
+            upsampler.process(&i_data[..], wave);
+            wave.iter_mut().for_each(|v| *v = self.tick(*v));
+            downsampler.process(wave, &mut o_data[..]);
         }
 
-        let wave_out = self.downsampler_x4.process(&up_wave_out).unwrap();
-
-        for (i_data, o_data) in wave_out.iter().zip(output.channels_mut()) {
-            for (&i, o) in i_data.iter().zip(o_data.iter_mut()) {
-                *o = i;
-            }
-        }
+        self.oversampled = oversampled;
     }
 
     #[inline]
@@ -454,28 +432,20 @@ impl WaveShaperRenderer {
             (1. - f) * self.curve[k as usize] + f * self.curve[(k + 1.) as usize]
         }
     }
+}
 
-    #[inline]
-    fn update_2x(&mut self, channels_x2: usize) {
-        self.channels_x2 = channels_x2;
-
-        self.upsampler_x2 =
-            FftFixedInOut::<f32>::new(self.sample_rate, self.sample_rate * 2, 256, channels_x2);
-
-        self.downsampler_x2 =
-            FftFixedInOut::<f32>::new(self.sample_rate, self.sample_rate / 2, 128, channels_x2);
-    }
-
-    #[inline]
-    fn update_4x(&mut self, channels_x4: usize) {
-        self.channels_x4 = channels_x4;
-
-        self.upsampler_x4 =
-            FftFixedInOut::<f32>::new(self.sample_rate, self.sample_rate * 4, 512, channels_x4);
+/// Build an up sampler for each possible channel
+fn new_upsamplers(factor: usize, quality: FilterQuality) -> Vec<Upsampler> {
+    (0..MAX_CHANNELS)
+        .map(|_| Upsampler::new(factor, quality.taps_per_phase()))
+        .collect()
+}
 
-        self.downsampler_x4 =
-            FftFixedInOut::<f32>::new(self.sample_rate, self.sample_rate / 4, 128, channels_x4);
-    }
+/// Build a down sampler for each possible channel
+fn new_downsamplers(factor: usize, quality: FilterQuality) -> Vec<Downsampler> {
+    (0..MAX_CHANNELS)
+        .map(|_| Downsampler::new(factor, quality.taps_per_phase()))
+        .collect()
 }
 
 #[cfg(test)]
@@ -483,13 +453,101 @@ mod test {
     use crate::{
         context::{AsBaseAudioContext, OfflineAudioContext},
         node::WaveShaperOptions,
-        SampleRate,
+        test_util::render_mono,
+        FilterQuality, SampleRate,
     };
 
+    use std::f32::consts::PI;
+
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
     use super::{OverSampleType, WaveShaperNode};
 
+    #[test]
+    fn apply_curve_without_oversampling() {
+        let mut context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+
+        let options = WaveShaperOptions {
+            curve: Some(vec![-1., 0., 0.]),
+            ..Default::default()
+        };
+        let shaper = WaveShaperNode::new(&context, Some(options));
+        src.connect(&shaper);
+        shaper.connect(&context.destination());
+
+        let output = context.start_rendering();
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn oversampling_reduces_aliasing() {
+        let sample_rate = 44_100.;
+        let nyquist = sample_rate / 2.;
+
+        let aliased = render_clipped_sine(OverSampleType::None);
+        let oversampled = render_clipped_sine(OverSampleType::X4);
+
+        // the harmonics of the 8 kHz sine lie above nyquist, they alias below it
+        let aliased_energy = band_energy(&aliased, sample_rate, nyquist / 2., nyquist);
+        let oversampled_energy = band_energy(&oversampled, sample_rate, nyquist / 2., nyquist);
+        assert!(oversampled_energy < aliased_energy / 10.);
+
+        // the fundamental is preserved
+        let fundamental = |s: &[f32]| band_energy(s, sample_rate, 7990., 8010.);
+        assert!(fundamental(&oversampled) > fundamental(&aliased) / 2.);
+    }
+
     const LENGTH: usize = 555;
 
+    /// Energy of the signal between `min_freq` and `max_freq`, computed with a naive DFT
+    fn band_energy(signal: &[f32], sample_rate: f32, min_freq: f32, max_freq: f32) -> f32 {
+        let n = signal.len();
+        let min_bin = (min_freq / sample_rate * n as f32).ceil() as usize;
+        let max_bin = (max_freq / sample_rate * n as f32).floor() as usize;
+
+        (min_bin..=max_bin)
+            .map(|k| {
+                let (re, im) = signal
+                    .iter()
+                    .enumerate()
+                    .fold((0., 0.), |(re, im), (i, &v)| {
+                        let phase = -2. * PI * (k * i % n) as f32 / n as f32;
+                        (re + v * phase.cos(), im + v * phase.sin())
+                    });
+                re * re + im * im
+            })
+            .sum()
+    }
+
+    /// Render a loud sine through a hard-clip curve
+    fn render_clipped_sine(oversample: OverSampleType) -> Vec<f32> {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(1, 4096 + 512, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(8000.);
+        let gain = context.create_gain();
+        gain.gain().set_value(4.);
+
+        // identity in [-1, 1], clamped outside
+        let options = WaveShaperOptions {
+            curve: Some(vec![-1., 0., 1.]),
+            oversample: Some(oversample),
+            ..Default::default()
+        };
+        let shaper = WaveShaperNode::new(&context, Some(options));
+
+        osc.connect(&gain);
+        gain.connect(&shaper);
+        shaper.connect(&context.destination());
+        osc.start();
+
+        // skip the filters warm-up
+        render_mono(&mut context).split_off(512)
+    }
+
     #[test]
     fn build_with_new() {
         let context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
@@ -511,6 +569,7 @@ mod test {
 
         assert_eq!(shaper.curve(), default_curve);
         assert_eq!(shaper.oversample(), default_oversample);
+        assert_eq!(shaper.quality(), FilterQuality::Medium);
     }
 
     #[test]
@@ -525,6 +584,7 @@ mod test {
 
         assert_eq!(shaper.curve(), default_curve);
         assert_eq!(shaper.oversample(), default_oversample);
+        assert_eq!(shaper.quality(), FilterQuality::Medium);
     }
 
     #[test]
@@ -534,6 +594,7 @@ mod test {
         let options = WaveShaperOptions {
             curve: Some(vec![1.0]),
             oversample: Some(OverSampleType::X2),
+            quality: FilterQuality::High,
             ..Default::default()
         };
 
@@ -543,6 +604,7 @@ mod test {
 
         assert_eq!(shaper.curve(), Some(&[1.0][..]));
         assert_eq!(shaper.oversample(), OverSampleType::X2);
+        assert_eq!(shaper.quality(), FilterQuality::High);
     }
 
     #[test]
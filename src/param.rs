@@ -15,11 +15,21 @@ use crossbeam_channel::{Receiver, Sender};
 use AutomationEvent::*;
 
 /// Precision of value calculation per render quantum
+///
+/// The rate of the params of the built-in nodes is fixed by the spec, and documented on their
+/// accessors. For both rates, the processor receives one value per sample-frame from
+/// [`AudioParamValues::get`], a k-rate param simply holds the same value for the whole quantum.
+/// The single value of a k-rate param is available with [`AudioParamValues::get_value`].
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum AutomationRate {
     /// sampled for each sample-frame of the block
     A,
     /// sampled at the time of the very first sample-frame, then used for the entire block
+    ///
+    /// Events scheduled at or before the start of a render quantum are applied before sampling,
+    /// so they affect that whole quantum. Events scheduled later within the quantum take effect
+    /// from the next one. Ramps are sampled at the start of each quantum, so a k-rate param steps
+    /// towards the ramp target once per quantum.
     K,
 }
 
@@ -31,10 +41,49 @@ pub struct AudioParamOptions {
     pub max_value: f32,
 }
 
+/// Closure providing the value of an [`AudioParam`] for each render quantum
+pub(crate) struct ParamSource(Box<dyn FnMut() -> f32 + Send>);
+
+impl std::fmt::Debug for ParamSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParamSource").finish()
+    }
+}
+
 #[derive(Debug)]
+// names follow the AudioParam methods of the spec
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum AutomationEvent {
-    SetValueAtTime { v: f32, start: f64 },
-    LinearRampToValueAtTime { v: f32, end: f64 },
+    SetValueAtTime {
+        v: f32,
+        start: f64,
+    },
+    LinearRampToValueAtTime {
+        v: f32,
+        end: f64,
+    },
+    ExponentialRampToValueAtTime {
+        v: f32,
+        end: f64,
+    },
+    SCurveRampToValueAtTime {
+        v: f32,
+        end: f64,
+    },
+    SetTargetAtTime {
+        v: f32,
+        start: f64,
+        time_constant: f64,
+    },
+    SetValueCurveAtTime {
+        values: Vec<f32>,
+        start: f64,
+        duration: f64,
+    },
+    // bind (or unbind with `None`) an external value source, never stored in the event queue
+    BindSource(Option<ParamSource>),
+    // scale factor of the connected inputs, never stored in the event queue
+    ModulationGain(f32),
 }
 
 impl AutomationEvent {
@@ -42,6 +91,12 @@ impl AutomationEvent {
         match &self {
             SetValueAtTime { start, .. } => *start,
             LinearRampToValueAtTime { end, .. } => *end,
+            ExponentialRampToValueAtTime { end, .. } => *end,
+            SCurveRampToValueAtTime { end, .. } => *end,
+            SetTargetAtTime { start, .. } => *start,
+            SetValueCurveAtTime { start, .. } => *start,
+            BindSource(_) => unreachable!("BindSource is not a timeline event"),
+            ModulationGain(_) => unreachable!("ModulationGain is not a timeline event"),
         }
     }
 }
@@ -66,11 +121,70 @@ impl std::cmp::Ord for AutomationEvent {
     }
 }
 
+/// Exponential approach of a target value, running until the next event
+#[derive(Debug, Clone, Copy)]
+struct TargetApproach {
+    target: f32,
+    start: f64,
+    time_constant: f64,
+    /// value at the start of the approach
+    start_value: f32,
+}
+
+impl TargetApproach {
+    fn value_at(&self, time: f64) -> f32 {
+        if self.time_constant == 0. {
+            return self.target;
+        }
+        let decay = (-(time - self.start).max(0.) / self.time_constant).exp();
+        (f64::from(self.target) + f64::from(self.start_value - self.target) * decay) as f32
+    }
+}
+
+/// Values of a set value curve automation, linearly interpolated over its duration
+#[derive(Debug)]
+struct ValueCurve {
+    values: Vec<f32>,
+    start: f64,
+    duration: f64,
+}
+
+impl ValueCurve {
+    fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+
+    fn value_at(&self, time: f64) -> f32 {
+        let last = self.values.len() - 1;
+        let position = (time - self.start).max(0.) / self.duration * last as f64;
+        let k = position.floor() as usize;
+        if k >= last {
+            return self.values[last];
+        }
+
+        let frac = (position - k as f64) as f32;
+        self.values[k] + (self.values[k + 1] - self.values[k]) * frac
+    }
+}
+
+/// Control thread parts of an [`AudioParam`], detached from its registration
+pub(crate) type AudioParamRaw = (
+    Arc<AtomicF64>,
+    Sender<AutomationEvent>,
+    Receiver<ParamSource>,
+    f32,
+    AutomationRate,
+);
+
 /// AudioParam controls an individual aspect of an AudioNode's functionality, such as volume.
 pub struct AudioParam {
     registration: AudioContextRegistration,
     value: Arc<AtomicF64>,
     sender: Sender<AutomationEvent>,
+    /// sources replaced on the render thread, to be dropped here
+    replaced_sources: Receiver<ParamSource>,
+    default_value: f32,
+    automation_rate: AutomationRate,
 }
 
 impl AudioNode for AudioParam {
@@ -122,6 +236,19 @@ pub(crate) struct AudioParamProcessor {
     min_value: f32,
     max_value: f32,
     events: BinaryHeap<AutomationEvent>,
+    /// start value and time of the currently running ramp, or of the previous event a new ramp
+    /// starts from (`None` if no event was processed yet)
+    ramp_start: Option<(f32, f64)>,
+    /// running set target automation, if any
+    target: Option<TargetApproach>,
+    /// running set value curve automation, if any
+    curve: Option<ValueCurve>,
+    /// external source setting the value at the start of each render quantum
+    source: Option<ParamSource>,
+    /// replaced sources are sent back to be dropped on the control thread
+    replaced_sources: Sender<ParamSource>,
+    /// scale factor applied to the connected inputs before summing
+    modulation_gain: f32,
     buffer: Vec<f32>,
 }
 
@@ -145,7 +272,32 @@ impl AudioProcessor for AudioParamProcessor {
         buffer.force_mono();
         buffer.channel_data_mut(0).copy_from_slice(intrinsic);
 
-        buffer.add(input, ChannelInterpretation::Discrete);
+        if self.modulation_gain == 1. {
+            buffer.add(input, ChannelInterpretation::Discrete);
+        } else {
+            // scale the inputs while summing, the mono intrinsic value is mixed up to the
+            // channel count of the inputs like `add` does
+            let gain = self.modulation_gain;
+            let channels = input.number_of_channels();
+            buffer.mix(channels, ChannelInterpretation::Discrete);
+            buffer
+                .channels_mut()
+                .iter_mut()
+                .zip(input.channels())
+                .for_each(|(channel, input)| {
+                    channel
+                        .iter_mut()
+                        .zip(input.iter())
+                        .for_each(|(v, i)| *v += gain * i);
+                });
+        }
+
+        // the connected inputs of a k-rate param are sampled at the first sample-frame
+        if self.automation_rate == AutomationRate::K {
+            let channel = buffer.channel_data_mut(0);
+            let first = channel[0];
+            channel.iter_mut().for_each(|v| *v = first);
+        }
 
         outputs[0] = buffer;
     }
@@ -160,12 +312,16 @@ pub(crate) fn audio_param_pair(
     registration: AudioContextRegistration,
 ) -> (AudioParam, AudioParamProcessor) {
     let (sender, receiver) = crossbeam_channel::unbounded();
+    let (replaced_sender, replaced_sources) = crossbeam_channel::unbounded();
     let shared_value = Arc::new(AtomicF64::new(opts.default_value as f64));
 
     let param = AudioParam {
         registration,
         value: shared_value.clone(),
         sender,
+        replaced_sources,
+        default_value: opts.default_value,
+        automation_rate: opts.automation_rate,
     };
 
     let render = AudioParamProcessor {
@@ -177,6 +333,12 @@ pub(crate) fn audio_param_pair(
         min_value: opts.min_value,
         max_value: opts.max_value,
         events: BinaryHeap::new(),
+        ramp_start: None,
+        target: None,
+        curve: None,
+        source: None,
+        replaced_sources: replaced_sender,
+        modulation_gain: 1.,
         buffer: Vec::with_capacity(BUFFER_SIZE as usize),
     };
 
@@ -188,6 +350,22 @@ impl AudioParam {
         self.value.load() as _
     }
 
+    /// The initial value of the param, independent of its current value
+    pub fn default_value(&self) -> f32 {
+        self.default_value
+    }
+
+    /// Whether the param is sampled for each sample-frame (a-rate) or once per render quantum
+    /// (k-rate)
+    pub fn automation_rate(&self) -> AutomationRate {
+        self.automation_rate
+    }
+
+    /// Set the value of the param back to its [`default_value`](Self::default_value)
+    pub fn reset_to_default(&self) {
+        self.set_value(self.default_value);
+    }
+
     pub fn set_value(&self, v: f32) {
         let event = SetValueAtTime { v, start: 0. };
         self.context().pass_audio_param_event(&self.sender, event);
@@ -203,20 +381,150 @@ impl AudioParam {
         self.context().pass_audio_param_event(&self.sender, event);
     }
 
+    /// Schedules an exponential change of the value, starting at the previous event and ending
+    /// at `end`.
+    ///
+    /// The value at time `t` is `v0 * (v / v0)^((t - t0) / (end - t0))`, where `v0` and `t0` are
+    /// the value and time of the previous event. As in the specification, if `v0` is zero or has
+    /// the opposite sign of `v`, the value stays at `v0` until `end`, then jumps to `v`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `v` is zero, an exponential ramp can not reach zero
+    pub fn exponential_ramp_to_value_at_time(&self, v: f32, end: f64) {
+        assert!(
+            v != 0.,
+            "RangeError: an exponential ramp can not reach a value of zero"
+        );
+        let event = ExponentialRampToValueAtTime { v, end };
+        self.context().pass_audio_param_event(&self.sender, event);
+    }
+
+    /// Schedules an exponential approach of the value to `v`, starting at `start`.
+    ///
+    /// The value at time `t` is `v + (v0 - v) * exp(-(t - start) / time_constant)`, where `v0` is
+    /// the value at `start`. The approach continues until the next event, which picks up from the
+    /// value reached at that time. With a `time_constant` of zero, the value jumps to `v`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `time_constant` is negative
+    pub fn set_target_at_time(&self, v: f32, start: f64, time_constant: f32) {
+        assert!(
+            time_constant >= 0.,
+            "RangeError: the time constant should not be negative"
+        );
+        let event = SetTargetAtTime {
+            v,
+            start,
+            time_constant: f64::from(time_constant),
+        };
+        self.context().pass_audio_param_event(&self.sender, event);
+    }
+
+    /// Schedules the value to follow the `values` curve, from `start` during `duration` seconds.
+    ///
+    /// The value is linearly interpolated between the points of the curve, which are evenly
+    /// spaced over the duration. The last value is held after the end of the curve.
+    ///
+    /// The specification does not allow other events during the curve. This is not checked when
+    /// scheduling: events falling within the curve are ignored by the render thread, with a
+    /// warning.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if
+    /// - `values` contains less than two points
+    /// - `duration` is not strictly positive
+    pub fn set_value_curve_at_time(&self, values: &[f32], start: f64, duration: f64) {
+        assert!(
+            values.len() >= 2,
+            "InvalidStateError: the curve should contain at least two values"
+        );
+        assert!(
+            duration > 0.,
+            "RangeError: the duration of the curve should be strictly positive"
+        );
+        let event = SetValueCurveAtTime {
+            values: values.to_vec(),
+            start,
+            duration,
+        };
+        self.context().pass_audio_param_event(&self.sender, event);
+    }
+
+    /// Schedules an ease-in-ease-out (smoothstep) change of the value, starting at the previous
+    /// event and ending at `end`.
+    ///
+    /// This is an extension to the Web Audio API specification. Unlike the linear ramp, the rate
+    /// of change is zero at both ends of the ramp, which makes for natural sounding fades.
+    pub fn s_curve_ramp_to_value_at_time(&self, v: f32, end: f64) {
+        let event = SCurveRampToValueAtTime { v, end };
+        self.context().pass_audio_param_event(&self.sender, event);
+    }
+
+    /// Drive the value of this param with an external closure, e.g. for sensor input or game
+    /// state.
+    ///
+    /// This is an extension to the Web Audio API specification. The closure is evaluated once per
+    /// render quantum on the render thread, and its return value is used as the (k-rate) value
+    /// for that quantum. Scheduled automation events still apply on top of it.
+    ///
+    /// The closure runs in the realtime audio callback: it must return quickly and must not
+    /// block, lock, allocate or perform I/O, or the audio output will glitch. Use atomics or
+    /// lock-free queues to pass values from other threads.
+    pub fn bind_source(&self, source: Box<dyn FnMut() -> f32 + Send>) {
+        // drop the sources replaced since the last change
+        self.replaced_sources.try_iter().for_each(drop);
+
+        let event = BindSource(Some(ParamSource(source)));
+        self.context().pass_audio_param_event(&self.sender, event);
+    }
+
+    /// Remove the closure bound with [`bind_source`](Self::bind_source), the param keeps its
+    /// last value.
+    pub fn unbind_source(&self) {
+        // drop the sources replaced since the last change
+        self.replaced_sources.try_iter().for_each(drop);
+
+        self.context()
+            .pass_audio_param_event(&self.sender, BindSource(None));
+    }
+
+    /// Scale the signals connected to this param before they are summed onto its intrinsic
+    /// value
+    ///
+    /// This is an extension to the Web Audio API specification, which always sums the inputs
+    /// unscaled. It allows to control the modulation depth without an intermediate
+    /// [`GainNode`](crate::node::GainNode). The default of `1.0` is spec compliant.
+    pub fn set_modulation_gain(&self, gain: f32) {
+        self.context()
+            .pass_audio_param_event(&self.sender, ModulationGain(gain));
+    }
+
     // helper function to detach from context (for borrow reasons)
-    pub(crate) fn into_raw_parts(self) -> (Arc<AtomicF64>, Sender<AutomationEvent>) {
-        (self.value, self.sender)
+    pub(crate) fn into_raw_parts(self) -> AudioParamRaw {
+        (
+            self.value,
+            self.sender,
+            self.replaced_sources,
+            self.default_value,
+            self.automation_rate,
+        )
     }
 
     // helper function to attach to context (for borrow reasons)
     pub(crate) fn from_raw_parts(
         registration: AudioContextRegistration,
-        parts: (Arc<AtomicF64>, Sender<AutomationEvent>),
+        parts: AudioParamRaw,
     ) -> Self {
         Self {
             registration,
             value: parts.0,
             sender: parts.1,
+            replaced_sources: parts.2,
+            default_value: parts.3,
+            automation_rate: parts.4,
         }
     }
 }
@@ -230,10 +538,70 @@ impl AudioParamProcessor {
         }
     }
 
+    /// Fill the buffer up to `end_index` with the current value, or with the running set target
+    /// automation, and update the current value to the one at `end_index`
+    fn fill(&mut self, end_index: usize, ts: f64, dt: f64) {
+        if self.curve.is_none() && self.target.is_none() {
+            for _ in self.buffer.len()..end_index {
+                self.buffer.push(self.value());
+            }
+            return;
+        }
+
+        for index in self.buffer.len()..end_index {
+            let val = self.automation_value(ts + index as f64 * dt);
+            self.buffer.push(val.clamp(self.min_value, self.max_value));
+        }
+        self.value = self.automation_value(ts + end_index as f64 * dt);
+    }
+
+    /// Value of the running set value curve or set target automation at `time`
+    fn automation_value(&self, time: f64) -> f32 {
+        match (&self.curve, &self.target) {
+            (Some(curve), _) => curve.value_at(time),
+            (None, Some(target)) => target.value_at(time),
+            (None, None) => self.value,
+        }
+    }
+
+    /// Start the set value curve automation at the top of the event queue
+    fn start_curve(&mut self) {
+        if let Some(SetValueCurveAtTime {
+            values,
+            start,
+            duration,
+        }) = self.events.pop()
+        {
+            let curve = ValueCurve {
+                values,
+                start,
+                duration,
+            };
+            // a subsequent ramp continues from the end of the curve
+            self.ramp_start = Some((curve.values[curve.values.len() - 1], curve.end()));
+            self.target = None;
+            self.curve = Some(curve);
+        }
+    }
+
     fn tick(&mut self, ts: f64, dt: f64, count: usize) -> &[f32] {
         // store incoming automation events in sorted queue
         for event in self.receiver.try_iter() {
-            self.events.push(event);
+            match event {
+                BindSource(source) => {
+                    // the closure may own resources, do not drop it on the render thread
+                    if let Some(previous) = std::mem::replace(&mut self.source, source) {
+                        let _ = self.replaced_sources.send(previous);
+                    }
+                }
+                ModulationGain(gain) => self.modulation_gain = gain,
+                event => self.events.push(event),
+            }
+        }
+
+        // an external source sets the value once per render quantum
+        if let Some(source) = &mut self.source {
+            self.value = (source.0)();
         }
 
         // Clear the vec from previously buffered data
@@ -242,59 +610,190 @@ impl AudioParamProcessor {
         // setup return value buffer
         let a_rate = self.automation_rate == AutomationRate::A;
 
-        if !a_rate {
-            // filling the vec already, no expensive calculations are performed later
-            for _ in 0..count {
-                self.buffer.push(self.value())
-            }
-        };
-
         // end of the render quantum
         let max_ts = ts + dt * count as f64;
 
         loop {
+            if let Some(curve) = &self.curve {
+                let end = curve.end();
+
+                // other events are not allowed during the curve
+                if let Some(event) = self.events.peek() {
+                    if event.time() < end {
+                        log::warn!(
+                            "ignoring automation event during a value curve: {:?}",
+                            event
+                        );
+                        self.events.pop();
+                        continue;
+                    }
+                }
+
+                // if end time is outside this render quantum, return
+                if end > max_ts {
+                    self.fill(count, ts, dt);
+                    break;
+                }
+
+                let end_index = ((end - ts).max(0.) / dt).ceil() as usize;
+                self.fill(end_index.min(count), ts, dt);
+                self.value = self.automation_value(end);
+                self.curve = None;
+            }
+
             match self.events.peek() {
                 None => {
-                    // fill remaining buffer for K-rate processing
-                    for _ in self.buffer.len()..count {
-                        self.buffer.push(self.value());
-                    }
+                    self.fill(count, ts, dt);
                     break;
                 }
-                Some(SetValueAtTime { v, start }) => {
+                Some(SetValueCurveAtTime { start, .. }) => {
+                    let start_index = ((start - ts).max(0.) / dt) as usize;
+                    let start = *start;
+                    self.fill(start_index.min(count), ts, dt);
+
+                    // if start time is outside this render quantum, return
+                    if start > max_ts {
+                        break;
+                    }
+
+                    self.start_curve();
+                    continue;
+                }
+                Some(&SetValueAtTime { v, start }) => {
                     let end_index = ((start - ts).max(0.) / dt) as usize;
-                    let end_index = end_index.min(count);
+                    self.fill(end_index.min(count), ts, dt);
 
-                    // fill remaining buffer for K-rate processing
-                    for _ in self.buffer.len()..end_index {
-                        self.buffer.push(self.value());
+                    // if start time is outside this render quantum, return
+                    if start > max_ts {
+                        break;
                     }
 
+                    self.value = v;
+                    self.target = None;
+                    // a running ramp is interrupted, the next ramp starts from this event
+                    self.ramp_start = Some((v, start.max(ts)));
+                }
+                Some(&SetTargetAtTime {
+                    v,
+                    start,
+                    time_constant,
+                }) => {
+                    let start_index = ((start - ts).max(0.) / dt) as usize;
+                    self.fill(start_index.min(count), ts, dt);
+
                     // if start time is outside this render quantum, return
-                    if *start > max_ts {
+                    if start > max_ts {
                         break;
                     }
 
-                    self.value = *v;
+                    self.target = Some(TargetApproach {
+                        target: v,
+                        start,
+                        time_constant,
+                        start_value: self.value,
+                    });
+                    // a following ramp starts from the value reached when it is processed
+                    self.ramp_start = None;
                 }
                 Some(LinearRampToValueAtTime { v, end }) => {
-                    let end_index = ((end - ts).max(0.) / dt) as usize;
-                    if a_rate && end_index > self.buffer.len() {
-                        let start_index = self.buffer.len();
+                    // a ramp overrides a running set target automation
+                    self.target = None;
+                    // the ramp covers every sample before its end time, rounding up so a float
+                    // error on an end time landing on a sample does not cut the ramp short
+                    let end_index = ((end - ts).max(0.) / dt).ceil() as usize;
+                    if end_index > self.buffer.len() {
+                        // the ramp may span multiple render quanta, remember where it started and
+                        // compute each value from there in double precision, so long ramps do not
+                        // accumulate rounding errors
+                        let (start_value, start_time) = *self
+                            .ramp_start
+                            .get_or_insert((self.value, ts + self.buffer.len() as f64 * dt));
+                        let slope = f64::from(v - start_value) / (end - start_time);
+
+                        let value_at = |index: usize| {
+                            let elapsed = ts + index as f64 * dt - start_time;
+                            slope.mul_add(elapsed, f64::from(start_value)) as f32
+                        };
+
+                        let end_index_clipped = end_index.min(count);
+                        for index in self.buffer.len()..end_index_clipped {
+                            let val = value_at(index);
+                            self.buffer.push(val.clamp(self.min_value, self.max_value));
+                        }
+                        self.value = value_at(end_index_clipped);
+                    }
+
+                    // if end time is outside this render quantum, return
+                    if *end > max_ts {
+                        break;
+                    }
 
-                        let dv = v - self.value;
-                        let dt = end_index - start_index;
-                        let slope = dv / dt as f32;
+                    self.value = *v;
+                    // a subsequent ramp continues from the end of this one
+                    self.ramp_start = Some((*v, *end));
+                }
+                Some(ExponentialRampToValueAtTime { v, end }) => {
+                    // a ramp overrides a running set target automation
+                    self.target = None;
+                    let end_index = ((end - ts).max(0.) / dt).ceil() as usize;
+                    if end_index > self.buffer.len() {
+                        // the ramp may span multiple render quanta, remember where it started
+                        let (start_value, start_time) = *self
+                            .ramp_start
+                            .get_or_insert((self.value, ts + self.buffer.len() as f64 * dt));
+                        let duration = end - start_time;
+                        let ratio = f64::from(*v) / f64::from(start_value);
+
+                        let value_at = |index: usize| {
+                            // undefined for a zero start value or a sign change, hold the value
+                            if ratio <= 0. || !ratio.is_finite() {
+                                return start_value;
+                            }
+                            let x = (ts + index as f64 * dt - start_time) / duration;
+                            (f64::from(start_value) * ratio.powf(x.clamp(0., 1.))) as f32
+                        };
 
                         let end_index_clipped = end_index.min(count);
-                        let n_values = end_index_clipped - start_index;
+                        for index in self.buffer.len()..end_index_clipped {
+                            let val = value_at(index);
+                            self.buffer.push(val.clamp(self.min_value, self.max_value));
+                        }
+                        self.value = value_at(end_index_clipped);
+                    }
+
+                    // if end time is outside this render quantum, return
+                    if *end > max_ts {
+                        break;
+                    }
 
-                        let mut val = self.value;
-                        for _ in 0..n_values {
+                    self.value = *v;
+                    // a subsequent ramp continues from the end of this one
+                    self.ramp_start = Some((*v, *end));
+                }
+                Some(SCurveRampToValueAtTime { v, end }) => {
+                    // a ramp overrides a running set target automation
+                    self.target = None;
+                    let end_index = ((end - ts).max(0.) / dt).ceil() as usize;
+                    if end_index > self.buffer.len() {
+                        // the ramp may span multiple render quanta, remember where it started
+                        let (start_value, start_time) = *self
+                            .ramp_start
+                            .get_or_insert((self.value, ts + self.buffer.len() as f64 * dt));
+                        let duration = end - start_time;
+                        let dv = v - start_value;
+
+                        let value_at = |index: usize| {
+                            let x = ((ts + index as f64 * dt - start_time) / duration) as f32;
+                            let x = x.clamp(0., 1.);
+                            start_value + dv * x * x * (3. - 2. * x)
+                        };
+
+                        let end_index_clipped = end_index.min(count);
+                        for index in self.buffer.len()..end_index_clipped {
+                            let val = value_at(index);
                             self.buffer.push(val.clamp(self.min_value, self.max_value));
-                            val += slope;
                         }
-                        self.value = val;
+                        self.value = value_at(end_index_clipped);
                     }
 
                     // if end time is outside this render quantum, return
@@ -303,6 +802,12 @@ impl AudioParamProcessor {
                     }
 
                     self.value = *v;
+                    // a subsequent ramp continues from the end of this one
+                    self.ramp_start = Some((*v, *end));
+                }
+                Some(BindSource(_)) => unreachable!("BindSource is not a timeline event"),
+                Some(ModulationGain(_)) => {
+                    unreachable!("ModulationGain is not a timeline event")
                 }
             }
 
@@ -310,6 +815,13 @@ impl AudioParamProcessor {
             self.events.pop();
         }
 
+        // a k-rate param holds the value at the start of the render quantum, events and ramps
+        // within the quantum take effect from the next one
+        if !a_rate && count > 0 {
+            let value = self.buffer[0];
+            self.buffer.iter_mut().for_each(|v| *v = value);
+        }
+
         self.shared_value.store(self.value() as f64);
 
         assert_eq!(self.buffer.len(), count);
@@ -339,6 +851,37 @@ mod tests {
             let event = LinearRampToValueAtTime { v, end };
             self.sender.send(event).unwrap()
         }
+        pub fn exponential_ramp_to_value_at_time_direct(&self, v: f32, end: f64) {
+            let event = ExponentialRampToValueAtTime { v, end };
+            self.sender.send(event).unwrap()
+        }
+        pub fn set_target_at_time_direct(&self, v: f32, start: f64, time_constant: f64) {
+            let event = SetTargetAtTime {
+                v,
+                start,
+                time_constant,
+            };
+            self.sender.send(event).unwrap()
+        }
+        pub fn set_value_curve_at_time_direct(&self, values: &[f32], start: f64, duration: f64) {
+            let event = SetValueCurveAtTime {
+                values: values.to_vec(),
+                start,
+                duration,
+            };
+            self.sender.send(event).unwrap()
+        }
+        pub fn s_curve_ramp_to_value_at_time_direct(&self, v: f32, end: f64) {
+            let event = SCurveRampToValueAtTime { v, end };
+            self.sender.send(event).unwrap()
+        }
+        pub fn bind_source_direct(&self, source: Box<dyn FnMut() -> f32 + Send>) {
+            let event = BindSource(Some(ParamSource(source)));
+            self.sender.send(event).unwrap()
+        }
+        pub fn unbind_source_direct(&self) {
+            self.sender.send(BindSource(None)).unwrap()
+        }
     }
 
     #[test]
@@ -389,6 +932,88 @@ mod tests {
         assert_float_eq!(vs, &[8.; 10][..], ulps_all <= 0);
     }
 
+    #[test]
+    fn test_k_rate_value_at_quantum_start() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::K,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // events at the start of the quantum apply to the whole quantum
+        param.set_value_at_time_direct(5., 0.);
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(vs, &[5.; 10][..], ulps_all <= 0);
+
+        param.set_value_at_time_direct(-1., 12.);
+        let vs = render.tick(10., 1., 10);
+        assert_float_eq!(vs, &[5.; 10][..], ulps_all <= 0);
+        let vs = render.tick(20., 1., 10);
+        assert_float_eq!(vs, &[-1.; 10][..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_k_rate_events_within_quantum() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::K,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // only the last of the events due at the start of the quantum is visible
+        param.set_value_at_time_direct(1., 0.);
+        param.set_value_at_time_direct(2., 0.);
+        param.set_value_at_time_direct(3., 5.);
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(vs, &[2.; 10][..], ulps_all <= 0);
+
+        // an event exactly at the start of the next quantum applies to all of it
+        param.set_value_at_time_direct(4., 10.);
+        let vs = render.tick(10., 1., 10);
+        assert_float_eq!(vs, &[4.; 10][..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_k_rate_ramps() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::K,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // ramp to 8 over four quanta, each quantum holds the value at its start
+        param.set_value_at_time_direct(0., 0.);
+        param.linear_ramp_to_value_at_time_direct(8., 40.);
+        for (ts, expected) in [
+            (0., 0.),
+            (10., 2.),
+            (20., 4.),
+            (30., 6.),
+            (40., 8.),
+            (50., 8.),
+        ] {
+            let vs = render.tick(ts, 1., 10);
+            assert_float_eq!(vs, &[expected; 10][..], ulps_all <= 0);
+        }
+
+        // exponential ramp from 8 to 1 over three quanta
+        param.set_value_at_time_direct(8., 60.);
+        param.exponential_ramp_to_value_at_time_direct(1., 90.);
+        for (ts, expected) in [(60., 8.), (70., 4.), (80., 2.), (90., 1.)] {
+            let vs = render.tick(ts, 1., 10);
+            assert_float_eq!(vs, &[expected; 10][..], abs_all <= 1e-5);
+        }
+    }
+
     #[test]
     fn test_linear_ramp() {
         let context = OfflineAudioContext::new(1, 0, SampleRate(0));
@@ -477,4 +1102,298 @@ mod tests {
             ulps_all <= 0
         );
     }
+
+    #[test]
+    fn test_exponential_ramp() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 1.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // double every sample from t = 0 to t = 3, then a sign change holds the value
+        param.exponential_ramp_to_value_at_time_direct(8., 3.);
+        param.exponential_ramp_to_value_at_time_direct(-1., 6.);
+
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(
+            vs,
+            &[1., 2., 4., 8., 8., 8., -1., -1., -1., -1.][..],
+            ulps_all <= 1
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_exponential_ramp_to_zero() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+        let src = context.create_constant_source();
+        src.offset().exponential_ramp_to_value_at_time(0., 1.);
+    }
+
+    #[test]
+    fn test_set_target() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 1.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // approach 0 from t = 2, then set to 5 at t = 14
+        param.set_target_at_time_direct(0., 2., 4.);
+        param.set_value_at_time_direct(5., 14.);
+
+        let first = render.tick(0., 1., 10).to_vec();
+        let second = render.tick(10., 1., 10).to_vec();
+        let vs: Vec<f32> = first.into_iter().chain(second).collect();
+
+        assert_float_eq!(&vs[..3], &[1.; 3][..], ulps_all <= 0);
+        // continues across the render quantum boundary
+        for (i, &v) in vs.iter().enumerate().take(14).skip(2) {
+            let expected = (-(i as f32 - 2.) / 4.).exp();
+            assert_float_eq!(v, expected, abs <= 1e-6);
+        }
+        assert_float_eq!(&vs[14..], &[5.; 6][..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_set_target_zero_time_constant() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // jumps to the target
+        param.set_target_at_time_direct(3., 4., 0.);
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(
+            vs,
+            &[0., 0., 0., 0., 3., 3., 3., 3., 3., 3.][..],
+            ulps_all <= 0
+        );
+
+        // a ramp scheduled while the automation runs starts from the value reached
+        param.linear_ramp_to_value_at_time_direct(0., 13.);
+        let vs = render.tick(10., 1., 10);
+        assert_float_eq!(
+            vs,
+            &[3., 2., 1., 0., 0., 0., 0., 0., 0., 0.][..],
+            ulps_all <= 0
+        );
+    }
+
+    #[test]
+    fn test_value_curve_two_points() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // from 0 to 10 between t = 2 and t = 6, then hold
+        param.set_value_curve_at_time_direct(&[0., 10.], 2., 4.);
+
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(
+            vs,
+            &[0., 0., 0., 2.5, 5., 7.5, 10., 10., 10., 10.][..],
+            ulps_all <= 0
+        );
+    }
+
+    #[test]
+    fn test_value_curve_five_points() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // a point every 2 seconds from t = 4 to t = 12
+        param.set_value_curve_at_time_direct(&[0., 4., 2., 6., 1.], 4., 8.);
+        // not allowed during the curve, ignored
+        param.set_value_at_time_direct(-5., 7.);
+        param.linear_ramp_to_value_at_time_direct(-5., 9.);
+        // after the curve
+        param.set_value_at_time_direct(-1., 14.);
+
+        let first = render.tick(0., 1., 10).to_vec();
+        let second = render.tick(10., 1., 10).to_vec();
+        let vs: Vec<f32> = first.into_iter().chain(second).collect();
+
+        assert_float_eq!(
+            &vs[..16],
+            &[0., 0., 0., 0., 0., 2., 4., 3., 2., 4., 6., 3.5, 1., 1., -1., -1.][..],
+            ulps_all <= 0
+        );
+    }
+
+    #[test]
+    fn test_s_curve_ramp() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // ramp to 1 from t = 0 to t = 100
+        param.s_curve_ramp_to_value_at_time_direct(1., 100.);
+
+        let vs = render.tick(0., 1., 128).to_vec();
+        let linear_slope = 1. / 100.;
+
+        // flat start
+        assert_float_eq!(vs[0], 0., abs <= 0.);
+        assert!(vs[1] - vs[0] < 0.1 * linear_slope);
+        // steepest at the midpoint
+        assert_float_eq!(vs[50], 0.5, abs <= 1e-6);
+        assert!(vs[51] - vs[50] > linear_slope);
+        // flat end
+        assert!(vs[99] - vs[98] < 0.1 * linear_slope);
+        assert_float_eq!(&vs[100..], &[1.; 28][..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_s_curve_ramp_multiple_frames() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        // set to 2 at t = 5, then ramp down to 0 from t = 5 to t = 15
+        param.set_value_at_time_direct(2., 5.);
+        param.s_curve_ramp_to_value_at_time_direct(0., 15.);
+
+        let first = render.tick(0., 1., 10).to_vec();
+        let second = render.tick(10., 1., 10).to_vec();
+        let vs: Vec<f32> = first.into_iter().chain(second).collect();
+
+        assert_float_eq!(&vs[..5], &[0.; 5][..], ulps_all <= 0);
+        assert_float_eq!(vs[10], 1., abs <= 1e-6);
+        // symmetric around the midpoint, also across the render quantum boundary
+        for i in 0..5 {
+            assert_float_eq!(vs[5 + i], 2. - vs[15 - i], abs <= 1e-6);
+        }
+        assert_float_eq!(&vs[15..], &[0.; 5][..], ulps_all <= 0);
+    }
+
+    #[test]
+    fn test_bind_source() {
+        let context = OfflineAudioContext::new(1, 0, SampleRate(0));
+
+        let opts = AudioParamOptions {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        let mut counter = 0.;
+        param.bind_source_direct(Box::new(move || {
+            counter += 1.;
+            counter
+        }));
+
+        for expected in 1..=3 {
+            let vs = render.tick(0., 1., 10);
+            assert_float_eq!(vs, &[expected as f32; 10][..], ulps_all <= 0);
+            assert_float_eq!(param.value(), expected as f32, ulps_all <= 0);
+        }
+
+        // value is kept after unbinding
+        param.unbind_source_direct();
+        let vs = render.tick(0., 1., 10);
+        assert_float_eq!(vs, &[3.; 10][..], ulps_all <= 0);
+
+        // the unbound closure is handed back to the control thread
+        assert_eq!(param.replaced_sources.len(), 1);
+    }
+
+    #[test]
+    fn test_default_value() {
+        use crate::context::ManualAudioContext;
+
+        let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+        let gain = context.create_gain();
+        let mut buffer = [0.; BUFFER_SIZE as usize];
+
+        assert_float_eq!(gain.gain().default_value(), 1., ulps <= 0);
+
+        gain.gain().set_value(0.5);
+        context.render(&mut buffer);
+        assert_float_eq!(gain.gain().value(), 0.5, ulps <= 0);
+        assert_float_eq!(gain.gain().default_value(), 1., ulps <= 0);
+
+        gain.gain().reset_to_default();
+        context.render(&mut buffer);
+        assert_float_eq!(gain.gain().value(), 1., ulps <= 0);
+    }
+
+    #[test]
+    fn test_modulation_gain() {
+        use crate::node::AudioNode;
+
+        let render_with_modulation_gain = |modulation_gain: f32| {
+            let mut context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+
+            let signal = context.create_constant_source();
+            let gain = context.create_gain();
+            gain.gain().set_value(1.);
+            gain.gain().set_modulation_gain(modulation_gain);
+            signal.connect(&gain);
+            gain.connect(&context.destination());
+
+            let modulator = context.create_constant_source();
+            modulator.offset().set_value(0.5);
+            modulator.connect(gain.gain());
+
+            context.start_rendering()
+        };
+
+        // deviation of the gain from its intrinsic value of 1.
+        let full = render_with_modulation_gain(1.);
+        assert_float_eq!(
+            full.channel_data(0).as_slice(),
+            &[1.5; 128][..],
+            ulps_all <= 0
+        );
+
+        let half = render_with_modulation_gain(0.5);
+        assert_float_eq!(
+            half.channel_data(0).as_slice(),
+            &[1.25; 128][..],
+            ulps_all <= 0
+        );
+    }
 }
@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 
 use crate::alloc::AudioBuffer;
-use crate::context::AudioParamId;
+use crate::context::{AudioParamId, LISTENER_NODE_ID};
 use crate::graph::{Node, NodeIndex};
 use crate::SampleRate;
 
@@ -24,6 +24,40 @@ pub trait AudioProcessor: Send {
 
     /// Indicates if this node can have output when no inputs are connected
     fn tail_time(&self) -> bool;
+
+    /// Clear the internal state (phases, filter memories, delay lines, playback positions), so
+    /// rendering starts afresh
+    ///
+    /// Called when the render clock is reset, e.g. by
+    /// [`OfflineAudioContext::reset`](crate::context::OfflineAudioContext::reset). Stateless
+    /// processors can rely on the default implementation, which does nothing.
+    fn reset(&mut self) {}
+
+    /// Intrinsic delay of the output relative to the input, in (possibly fractional)
+    /// sample-frames
+    ///
+    /// Buffering processors (delay lines, partitioned convolution, oversampling filters) report
+    /// it so the total latency of a path through the graph can be computed, see
+    /// [`BaseAudioContext::graph_latency`](crate::context::BaseAudioContext::graph_latency).
+    /// Called on the render thread after every render quantum, the default is zero.
+    fn latency_samples(&self) -> f64 {
+        0.
+    }
+
+    /// Indicates if this processor can break a cycle in the audio graph
+    ///
+    /// Cycles are only rendered when they contain such a processor (a `DelayNode`), otherwise
+    /// their nodes are muted. The default is `false`.
+    fn can_break_cycle(&self) -> bool {
+        false
+    }
+
+    /// Called on the render thread when the processor starts or stops breaking a cycle
+    ///
+    /// While breaking a cycle, the inputs of the processor are mixed at the end of each render
+    /// quantum and provided in the next one, so they arrive one render quantum late. Only called
+    /// on processors that [`can_break_cycle`](Self::can_break_cycle).
+    fn set_breaking_cycle(&mut self, _breaking: bool) {}
 }
 
 /// Accessor for current [`crate::param::AudioParam`] values
@@ -42,10 +76,36 @@ impl<'a> AudioParamValues<'a> {
         self.nodes.get(&index.into()).unwrap().get_buffer()
     }
 
+    /// Get the current position, forward and up vectors of the [`crate::spatial::AudioListener`]
+    ///
+    /// Each value is provided as a slice of length [`crate::BUFFER_SIZE`]
+    pub(crate) fn listener_params(&self) -> [&[f32]; 9] {
+        let outputs = self
+            .nodes
+            .get(&NodeIndex(LISTENER_NODE_ID))
+            .unwrap()
+            .get_outputs();
+
+        let mut values: [&[f32]; 9] = [&[]; 9];
+        values
+            .iter_mut()
+            .zip(outputs)
+            .for_each(|(v, output)| *v = &output.channel_data(0)[..]);
+        values
+    }
+
     /// Get the computed values for the given [`crate::param::AudioParam`]
     ///
-    /// For both A & K-rate params, it will provide a slice of length [`crate::BUFFER_SIZE`]
+    /// For both A & K-rate params, it will provide a slice of length [`crate::BUFFER_SIZE`]. The
+    /// values of a K-rate param, including its connected inputs, are constant over the slice.
     pub fn get(&self, index: &AudioParamId) -> &[f32] {
         &self.get_raw(index).channel_data(0)[..]
     }
+
+    /// Get the value of a K-rate [`crate::param::AudioParam`] for the current render quantum
+    ///
+    /// For an A-rate param, this is the value at the first sample-frame of the quantum.
+    pub fn get_value(&self, index: &AudioParamId) -> f32 {
+        self.get_raw(index).channel_data(0)[0]
+    }
 }
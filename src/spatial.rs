@@ -6,15 +6,11 @@ use crate::alloc::AudioBuffer;
 use crate::buffer::{ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
 use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
 use crate::node::AudioNode;
-use crate::param::{AudioParam, AudioParamOptions, AutomationEvent, AutomationRate};
+use crate::param::{AudioParam, AudioParamOptions, AudioParamRaw, AutomationRate};
 use crate::process::{AudioParamValues, AudioProcessor};
-use crate::AtomicF64;
 use crate::SampleRate;
 
 use std::f32::consts::PI;
-use std::sync::Arc;
-
-use crossbeam_channel::Sender;
 
 /// AudioParam settings for the carthesian coordinates
 pub(crate) const PARAM_OPTS: AudioParamOptions = AudioParamOptions {
@@ -40,30 +36,39 @@ pub struct AudioListener {
 }
 
 impl AudioListener {
+    /// X coordinate of the position of the listener (a-rate)
     pub fn position_x(&self) -> &AudioParam {
         &self.position_x
     }
+    /// Y coordinate of the position of the listener (a-rate)
     pub fn position_y(&self) -> &AudioParam {
         &self.position_y
     }
+    /// Z coordinate of the position of the listener (a-rate)
     pub fn position_z(&self) -> &AudioParam {
         &self.position_z
     }
+    /// X coordinate of the forward direction of the listener (a-rate)
     pub fn forward_x(&self) -> &AudioParam {
         &self.forward_x
     }
+    /// Y coordinate of the forward direction of the listener (a-rate)
     pub fn forward_y(&self) -> &AudioParam {
         &self.forward_y
     }
+    /// Z coordinate of the forward direction of the listener (a-rate)
     pub fn forward_z(&self) -> &AudioParam {
         &self.forward_z
     }
+    /// X coordinate of the up direction of the listener (a-rate)
     pub fn up_x(&self) -> &AudioParam {
         &self.up_x
     }
+    /// Y coordinate of the up direction of the listener (a-rate)
     pub fn up_y(&self) -> &AudioParam {
         &self.up_y
     }
+    /// Z coordinate of the up direction of the listener (a-rate)
     pub fn up_z(&self) -> &AudioParam {
         &self.up_z
     }
@@ -72,7 +77,8 @@ impl AudioListener {
 /// Wrapper for the [`AudioListener`] so it can be placed in the audio graph.
 ///
 /// This node has no input, but takes the position/orientation AudioParams and copies them into the
-/// 9 outputs. The outputs are connected to the PannerNodes (via an AudioParam).
+/// 9 outputs. The PannerNodes read these outputs, the listener is connected to them with a param
+/// connection so it is rendered first.
 ///
 /// The AudioListener is always connected to the DestinationNode so each render quantum its
 /// positions are recalculated.
@@ -220,15 +226,15 @@ impl AudioProcessor for ListenerRenderer {
 
 /// Data holder for the BaseAudioContext so it can reconstruct the AudioListener on request
 pub(crate) struct AudioListenerParams {
-    pub position_x: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub position_y: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub position_z: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub forward_x: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub forward_y: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub forward_z: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub up_x: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub up_y: (Arc<AtomicF64>, Sender<AutomationEvent>),
-    pub up_z: (Arc<AtomicF64>, Sender<AutomationEvent>),
+    pub position_x: AudioParamRaw,
+    pub position_y: AudioParamRaw,
+    pub position_z: AudioParamRaw,
+    pub forward_x: AudioParamRaw,
+    pub forward_y: AudioParamRaw,
+    pub forward_z: AudioParamRaw,
+    pub up_x: AudioParamRaw,
+    pub up_y: AudioParamRaw,
+    pub up_z: AudioParamRaw,
 }
 
 use vecmath::{
@@ -308,6 +314,30 @@ pub fn distance(source_position: Vector3<f32>, listener_position: Vector3<f32>)
     vec3_len(vec3_sub(source_position, listener_position))
 }
 
+/// Angle (in degrees, `[0, 180]`) between the source orientation and the direction from the
+/// source to the listener
+///
+/// Returns `None` if the orientation is the zero vector or both positions are equal.
+pub fn angle(
+    source_position: Vector3<f32>,
+    source_orientation: Vector3<f32>,
+    listener_position: Vector3<f32>,
+) -> Option<f32> {
+    let source_to_listener = vec3_sub(listener_position, source_position);
+
+    if vec3_square_len(source_orientation) == 0. || vec3_square_len(source_to_listener) == 0. {
+        return None;
+    }
+
+    let cos = vec3_dot(
+        vec3_normalized(source_to_listener),
+        vec3_normalized(source_orientation),
+    );
+
+    // guard against rounding errors outside the domain of acos
+    Some(180. * cos.clamp(-1., 1.).acos() / PI)
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
@@ -319,6 +349,25 @@ mod tests {
     const LF: [f32; 3] = [0., 0., -1.];
     const LU: [f32; 3] = [0., 1., 0.];
 
+    #[test]
+    fn angle_to_listener() {
+        // pointing at the listener
+        let angle = super::angle([0., 0., -1.], [0., 0., 1.], LP).unwrap();
+        assert_float_eq!(angle, 0., abs <= 0.);
+
+        // pointing away from the listener
+        let angle = super::angle([0., 0., -1.], [0., 0., -2.], LP).unwrap();
+        assert_float_eq!(angle, 180., abs <= 0.);
+
+        // pointing sideways
+        let angle = super::angle([0., 0., -1.], [1., 0., 0.], LP).unwrap();
+        assert_float_eq!(angle, 90., abs <= 0.001);
+
+        // degenerate cases
+        assert!(super::angle([0., 0., -1.], [0., 0., 0.], LP).is_none());
+        assert!(super::angle(LP, [0., 0., 1.], LP).is_none());
+    }
+
     #[test]
     fn azimuth_elevation_equal_pos() {
         let pos = [0., 0., 0.];
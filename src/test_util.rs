@@ -0,0 +1,21 @@
+use crate::context::{AsBaseAudioContext, OfflineAudioContext};
+use crate::node::ConstantSourceNode;
+
+/// Source of a unit impulse: one sample-frame of value 1 at time 0, then silence
+pub fn impulse<C: AsBaseAudioContext>(context: &C) -> ConstantSourceNode {
+    let sample_rate = context.base().sample_rate();
+    let src = context.create_constant_source();
+    src.offset().set_value_at_time(1., 0.);
+    src.offset()
+        .set_value_at_time(0., 1. / f64::from(sample_rate.0));
+    src
+}
+
+/// Render the context and return its first channel
+pub fn render_mono(context: &mut OfflineAudioContext) -> Vec<f32> {
+    context
+        .start_rendering()
+        .channel_data(0)
+        .as_slice()
+        .to_vec()
+}
@@ -0,0 +1,414 @@
+//! User-defined audio processing, the counterpart of the `AudioWorklet` interfaces
+//!
+//! Implement the [`Worklet`] trait (`AudioWorkletProcessor`) and instantiate it with
+//! [`AsBaseAudioContext::register_worklet`], which returns an [`AudioWorkletNode`]. The
+//! [`AudioParam`]s declared by the [`Worklet::parameter_descriptors`] are created automatically.
+//!
+//! ```no_run
+//! use web_audio_api::alloc::AudioBuffer;
+//! use web_audio_api::context::{AsBaseAudioContext, AudioContext};
+//! use web_audio_api::node::AudioNode;
+//! use web_audio_api::param::{AudioParamOptions, AutomationRate};
+//! use web_audio_api::worklet::{AudioParamDescriptor, Worklet, WorkletParameters};
+//! use web_audio_api::SampleRate;
+//!
+//! /// Multiply the input by a gain
+//! struct Amplifier;
+//!
+//! impl Worklet for Amplifier {
+//!     type ProcessorOptions = ();
+//!
+//!     fn parameter_descriptors() -> Vec<AudioParamDescriptor> {
+//!         vec![AudioParamDescriptor {
+//!             name: "gain",
+//!             options: AudioParamOptions {
+//!                 automation_rate: AutomationRate::A,
+//!                 default_value: 1.,
+//!                 min_value: 0.,
+//!                 max_value: 1.,
+//!             },
+//!         }]
+//!     }
+//!
+//!     fn constructor(_options: ()) -> Self {
+//!         Amplifier
+//!     }
+//!
+//!     fn process(
+//!         &mut self,
+//!         inputs: &[AudioBuffer],
+//!         outputs: &mut [AudioBuffer],
+//!         params: WorkletParameters,
+//!         _timestamp: f64,
+//!         _sample_rate: SampleRate,
+//!     ) -> bool {
+//!         let gain = params.get("gain");
+//!         outputs[0] = inputs[0].clone();
+//!         outputs[0].modify_channels(|channel| {
+//!             channel.iter_mut().zip(gain).for_each(|(v, g)| *v *= g)
+//!         });
+//!         false
+//!     }
+//! }
+//!
+//! let context = AudioContext::new(None);
+//! let amplifier = context.register_worklet::<Amplifier>(Default::default());
+//! amplifier.parameter("gain").unwrap().set_value(0.5);
+//! amplifier.connect(&context.destination());
+//! ```
+
+use crate::alloc::AudioBuffer;
+use crate::buffer::{ChannelConfig, ChannelConfigOptions};
+use crate::context::{AsBaseAudioContext, AudioContextRegistration, AudioParamId};
+use crate::node::AudioNode;
+use crate::param::{AudioParam, AudioParamOptions};
+use crate::process::{AudioParamValues, AudioProcessor};
+use crate::SampleRate;
+
+/// Declaration of an [`AudioParam`] of a [`Worklet`]
+pub struct AudioParamDescriptor {
+    /// Name of the param, used to look it up on the node and in the processor
+    pub name: &'static str,
+    pub options: AudioParamOptions,
+}
+
+/// User-defined audio processor, running on the render thread
+///
+/// This is the equivalent of the `AudioWorkletProcessor` interface.
+pub trait Worklet: Send + Sized + 'static {
+    /// Settings passed from the control thread to the [`constructor`](Self::constructor)
+    type ProcessorOptions: Send + 'static;
+
+    /// The [`AudioParam`]s of the processor, created for each node
+    fn parameter_descriptors() -> Vec<AudioParamDescriptor> {
+        vec![]
+    }
+
+    /// Construct the processor, called once when the node is registered
+    fn constructor(options: Self::ProcessorOptions) -> Self;
+
+    /// Audio processing function, called for every render quantum
+    ///
+    /// Returns whether the processor should be kept alive when it has no connected inputs,
+    /// i.e. whether it can still produce output (sources, reverb tails).
+    fn process(
+        &mut self,
+        inputs: &[AudioBuffer],
+        outputs: &mut [AudioBuffer],
+        params: WorkletParameters,
+        timestamp: f64,
+        sample_rate: SampleRate,
+    ) -> bool;
+}
+
+/// Accessor for the current values of the [`AudioParam`]s of a [`Worklet`]
+pub struct WorkletParameters<'a> {
+    ids: &'a [(&'static str, AudioParamId)],
+    values: AudioParamValues<'a>,
+}
+
+impl<'a> WorkletParameters<'a> {
+    /// Get the computed values of the param with the given name
+    ///
+    /// For both A & K-rate params, it will provide a slice of length [`crate::BUFFER_SIZE`]
+    ///
+    /// # Panics
+    ///
+    /// Will panic if no param with this name is declared in the
+    /// [`Worklet::parameter_descriptors`]
+    pub fn get(&self, name: &str) -> &[f32] {
+        let (_, id) = self
+            .ids
+            .iter()
+            .find(|(n, _)| *n == name)
+            .unwrap_or_else(|| panic!("NotFoundError: unknown param {}", name));
+
+        self.values.get(id)
+    }
+}
+
+/// Options for constructing an [`AudioWorkletNode`]
+pub struct AudioWorkletNodeOptions<O> {
+    pub number_of_inputs: u32,
+    pub number_of_outputs: u32,
+    /// Initial values of the params, overriding the default values of the descriptors
+    pub parameter_data: Vec<(&'static str, f32)>,
+    /// Settings passed to the [`Worklet::constructor`]
+    pub processor_options: O,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl<O: Default> Default for AudioWorkletNodeOptions<O> {
+    fn default() -> Self {
+        Self {
+            number_of_inputs: 1,
+            number_of_outputs: 1,
+            parameter_data: vec![],
+            processor_options: O::default(),
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Audio node running a user-defined [`Worklet`] on the render thread
+pub struct AudioWorkletNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    number_of_inputs: u32,
+    number_of_outputs: u32,
+    parameters: Vec<(&'static str, AudioParam)>,
+}
+
+impl AudioNode for AudioWorkletNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config_raw(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> u32 {
+        self.number_of_inputs
+    }
+    fn number_of_outputs(&self) -> u32 {
+        self.number_of_outputs
+    }
+}
+
+impl AudioWorkletNode {
+    /// # Panics
+    ///
+    /// Will panic if the node has neither inputs nor outputs, or if the `parameter_data`
+    /// refers to an undeclared param
+    pub fn new<P: Worklet, C: AsBaseAudioContext>(
+        context: &C,
+        options: AudioWorkletNodeOptions<P::ProcessorOptions>,
+    ) -> Self {
+        let AudioWorkletNodeOptions {
+            number_of_inputs,
+            number_of_outputs,
+            parameter_data,
+            processor_options,
+            channel_config,
+        } = options;
+
+        assert!(
+            number_of_inputs > 0 || number_of_outputs > 0,
+            "NotSupportedError: the node should have at least one input or output"
+        );
+
+        let descriptors = P::parameter_descriptors();
+        for (name, _) in &parameter_data {
+            assert!(
+                descriptors.iter().any(|d| d.name == *name),
+                "NotFoundError: unknown param {}",
+                name
+            );
+        }
+
+        context.base().register(move |registration| {
+            let mut ids = Vec::with_capacity(descriptors.len());
+            let mut parameters = Vec::with_capacity(descriptors.len());
+
+            for descriptor in descriptors {
+                let (param, id) = context
+                    .base()
+                    .create_audio_param(descriptor.options, registration.id());
+                ids.push((descriptor.name, id));
+                parameters.push((descriptor.name, param));
+            }
+
+            for (name, value) in parameter_data {
+                parameters
+                    .iter()
+                    .filter(|(n, _)| *n == name)
+                    .for_each(|(_, param)| param.set_value(value));
+            }
+
+            let render = WorkletRenderer {
+                processor: P::constructor(processor_options),
+                ids,
+                active: true,
+            };
+
+            let node = AudioWorkletNode {
+                registration,
+                channel_config: channel_config.into(),
+                number_of_inputs,
+                number_of_outputs,
+                parameters,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// The param with the given name, declared in the [`Worklet::parameter_descriptors`]
+    pub fn parameter(&self, name: &str) -> Option<&AudioParam> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, param)| param)
+    }
+
+    /// All params of the node, with their names
+    pub fn parameters(&self) -> impl Iterator<Item = (&'static str, &AudioParam)> {
+        self.parameters.iter().map(|(name, param)| (*name, param))
+    }
+}
+
+struct WorkletRenderer<P> {
+    processor: P,
+    ids: Vec<(&'static str, AudioParamId)>,
+    /// keep alive flag, as returned by the last call to `process`
+    active: bool,
+}
+
+impl<P: Worklet> AudioProcessor for WorkletRenderer<P> {
+    fn process(
+        &mut self,
+        inputs: &[AudioBuffer],
+        outputs: &mut [AudioBuffer],
+        params: AudioParamValues,
+        timestamp: f64,
+        sample_rate: SampleRate,
+    ) {
+        let params = WorkletParameters {
+            ids: &self.ids,
+            values: params,
+        };
+
+        self.active = self
+            .processor
+            .process(inputs, outputs, params, timestamp, sample_rate);
+    }
+
+    fn tail_time(&self) -> bool {
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+    use crate::param::AutomationRate;
+
+    /// Source node emitting the value of its `level` param, optionally offset
+    struct Level {
+        offset: f32,
+    }
+
+    impl Worklet for Level {
+        type ProcessorOptions = f32;
+
+        fn parameter_descriptors() -> Vec<AudioParamDescriptor> {
+            vec![AudioParamDescriptor {
+                name: "level",
+                options: AudioParamOptions {
+                    automation_rate: AutomationRate::K,
+                    default_value: 0.25,
+                    min_value: 0.,
+                    max_value: 1.,
+                },
+            }]
+        }
+
+        fn constructor(offset: f32) -> Self {
+            Self { offset }
+        }
+
+        fn process(
+            &mut self,
+            _inputs: &[AudioBuffer],
+            outputs: &mut [AudioBuffer],
+            params: WorkletParameters,
+            _timestamp: f64,
+            _sample_rate: SampleRate,
+        ) -> bool {
+            let level = params.get("level");
+            let offset = self.offset;
+            outputs[0].force_mono();
+            outputs[0].modify_channels(|channel| {
+                channel
+                    .iter_mut()
+                    .zip(level)
+                    .for_each(|(v, l)| *v = l + offset)
+            });
+            true
+        }
+    }
+
+    fn source_options(offset: f32) -> AudioWorkletNodeOptions<f32> {
+        AudioWorkletNodeOptions {
+            number_of_inputs: 0,
+            processor_options: offset,
+            ..AudioWorkletNodeOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_params_from_descriptors() {
+        let mut context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let node = context.register_worklet::<Level>(source_options(0.5));
+
+        let names: Vec<_> = node.parameters().map(|(name, _)| name).collect();
+        assert_eq!(names, ["level"]);
+        assert!(node.parameter("missing").is_none());
+
+        let level = node.parameter("level").unwrap();
+        assert_eq!(level.value(), 0.25);
+
+        node.connect(&context.destination());
+        let output = context.start_rendering();
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 0.75));
+    }
+
+    #[test]
+    fn test_parameter_data() {
+        let mut context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let options = AudioWorkletNodeOptions {
+            parameter_data: vec![("level", 1.)],
+            ..source_options(0.)
+        };
+        let node = context.register_worklet::<Level>(options);
+
+        node.connect(&context.destination());
+        let output = context.start_rendering();
+        assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 1.));
+    }
+
+    #[test]
+    fn test_k_rate_param_input() {
+        let mut context = OfflineAudioContext::new(1, 256, SampleRate(44_100));
+        let node = context.register_worklet::<Level>(source_options(0.));
+        let level = node.parameter("level").unwrap();
+        assert_eq!(level.automation_rate(), AutomationRate::K);
+        level.set_value(0.);
+
+        // a ramp connected to a k-rate param is sampled once per render quantum
+        let ramp = context.create_constant_source();
+        ramp.offset().set_value_at_time(0., 0.);
+        ramp.offset()
+            .linear_ramp_to_value_at_time(1., 256. / 44_100.);
+        ramp.connect(level);
+
+        node.connect(&context.destination());
+        let output = context.start_rendering();
+        let output = output.channel_data(0).as_slice();
+        assert!(output[..128].iter().all(|&v| v == 0.));
+        assert!(output[128..].iter().all(|&v| v == 0.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "NotFoundError")]
+    fn test_unknown_parameter_data() {
+        let context = OfflineAudioContext::new(1, 128, SampleRate(44_100));
+        let options = AudioWorkletNodeOptions {
+            parameter_data: vec![("missing", 1.)],
+            ..source_options(0.)
+        };
+        context.register_worklet::<Level>(options);
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "manual")]
+
+use alloc_counter::{count_alloc, AllocCounterSystem};
+
+use web_audio_api::context::{AsBaseAudioContext, ManualAudioContext};
+use web_audio_api::node::AudioNode;
+use web_audio_api::{SampleRate, BUFFER_SIZE};
+
+#[global_allocator]
+static A: AllocCounterSystem = AllocCounterSystem;
+
+#[test]
+fn test_splitter_steady_state_no_allocations() {
+    let mut context = ManualAudioContext::new(2, SampleRate(44_100));
+
+    // stereo signal, split and merged again with swapped channels
+    let merger = context.create_channel_merger(2);
+    let left = context.create_constant_source();
+    left.connect_at(&merger, 0, 0).unwrap();
+    let right = context.create_constant_source();
+    right.offset().set_value(2.);
+    right.connect_at(&merger, 0, 1).unwrap();
+
+    let splitter = context.create_channel_splitter(2);
+    merger.connect(&splitter);
+
+    let swap = context.create_channel_merger(2);
+    splitter.connect_at(&swap, 0, 1).unwrap();
+    splitter.connect_at(&swap, 1, 0).unwrap();
+    swap.connect(&context.destination());
+
+    let mut buffer = vec![0.; 2 * BUFFER_SIZE as usize];
+
+    // warm up: apply control messages, order the graph, fill the buffer pool
+    for _ in 0..4 {
+        context.render(&mut buffer);
+    }
+    assert_eq!(&buffer[..2], &[2., 1.]);
+
+    let ((allocs, reallocs, _deallocs), _) = count_alloc(|| {
+        for _ in 0..100 {
+            context.render(&mut buffer);
+        }
+    });
+
+    assert_eq!(allocs, 0);
+    assert_eq!(reallocs, 0);
+    assert_eq!(&buffer[..2], &[2., 1.]);
+}
+
+#[test]
+fn test_media_stream_destination_no_allocations() {
+    let mut context = ManualAudioContext::new(1, SampleRate(44_100));
+
+    let src = context.create_constant_source();
+    let dest = context.create_media_stream_destination();
+    src.connect(&dest);
+    let mut stream = dest.stream();
+
+    let mut buffer = vec![0.; BUFFER_SIZE as usize];
+
+    // warm up: apply control messages, order the graph, fill the buffer pool
+    for _ in 0..4 {
+        context.render(&mut buffer);
+        stream.next().unwrap().unwrap();
+    }
+
+    // the consumer allocates the buffers, the render thread only fills them
+    let mut allocs = 0;
+    let mut reallocs = 0;
+    for _ in 0..100 {
+        let ((a, r, _deallocs), _) = count_alloc(|| context.render(&mut buffer));
+        allocs += a;
+        reallocs += r;
+
+        let captured = stream.next().unwrap().unwrap();
+        assert_eq!(captured.number_of_channels(), 2);
+        assert_eq!(
+            captured.channel_data(1).as_slice(),
+            &[1.; BUFFER_SIZE as usize]
+        );
+    }
+
+    assert_eq!(allocs, 0);
+    assert_eq!(reallocs, 0);
+}
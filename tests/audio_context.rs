@@ -0,0 +1,86 @@
+#![cfg(feature = "cpal")]
+
+use std::time::{Duration, Instant};
+
+use web_audio_api::context::{AsBaseAudioContext, AudioContext, AudioContextOptions};
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+use web_audio_api::SampleRate;
+
+/// Names of the threads of this process
+#[cfg(target_os = "linux")]
+fn thread_names() -> Vec<String> {
+    std::fs::read_dir("/proc/self/task")
+        .unwrap()
+        .filter_map(|task| std::fs::read_to_string(task.unwrap().path().join("comm")).ok())
+        .map(|name| name.trim().to_string())
+        .collect()
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[ignore = "requires an audio output device"]
+fn test_drop_joins_output_thread() {
+    let context = AudioContext::new(None);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+    std::thread::sleep(Duration::from_millis(100));
+
+    let count = || {
+        thread_names()
+            .iter()
+            .filter(|name| *name == "web-audio-output")
+            .count()
+    };
+    assert_eq!(count(), 1);
+
+    let start = Instant::now();
+    drop(context);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // the thread is joined, not left behind
+    assert_eq!(count(), 0);
+}
+
+#[test]
+#[ignore = "requires an audio output device"]
+fn test_new_suspended() {
+    let context = AudioContext::new_suspended(None);
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+
+    // nothing is rendered while suspended
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(context.current_time(), 0.);
+
+    context.resume().unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(context.current_time() > 0.);
+}
+
+#[test]
+#[ignore = "requires an audio output device supporting 48 kHz stereo"]
+fn test_new_with_options() {
+    let context = AudioContext::new_with_options(AudioContextOptions {
+        sample_rate: Some(48_000),
+        channels: Some(2),
+        ..AudioContextOptions::default()
+    });
+
+    // the negotiated config is reported
+    assert_eq!(context.sample_rate(), SampleRate(48_000));
+    assert_eq!(context.destination().channel_count(), 2);
+
+    // and the device renders at that rate
+    let start = Instant::now();
+    std::thread::sleep(Duration::from_millis(500));
+    let rendered = context.current_time();
+    let elapsed = start.elapsed().as_secs_f64();
+    assert!(
+        (rendered - elapsed).abs() < 0.2,
+        "{} vs {}",
+        rendered,
+        elapsed
+    );
+}
@@ -1,8 +1,10 @@
 use float_eq::assert_float_eq;
 use web_audio_api::buffer::{AudioBuffer, ChannelData};
+#[cfg(feature = "wav")]
+use web_audio_api::buffer::{DitherType, SampleFormat};
 use web_audio_api::context::AsBaseAudioContext;
 use web_audio_api::context::OfflineAudioContext;
-use web_audio_api::media::MediaElement;
+use web_audio_api::media::{DecodeError, IterMediaStream, MediaElement, MediaStreamSource};
 use web_audio_api::node::{AudioControllableSourceNode, AudioNode, AudioScheduledSourceNode};
 use web_audio_api::{SampleRate, BUFFER_SIZE};
 
@@ -172,3 +174,267 @@ fn test_media_seeking() {
         ulps_all <= 0
     );
 }
+
+/// Stereo buffer of consecutive values starting at `start`, negated on the right channel
+fn ramp(start: usize, len: usize) -> AudioBuffer {
+    let left: Vec<f32> = (start..start + len).map(|v| v as f32).collect();
+    let right = left.iter().map(|v| -v).collect();
+    AudioBuffer::from_channels(
+        vec![ChannelData::from(left), ChannelData::from(right)],
+        SampleRate(44_100),
+    )
+}
+
+#[test]
+fn test_media_stream_source() {
+    let (source, mut reader) = MediaStreamSource::new(2, 300, SampleRate(44_100));
+    assert!(source.is_empty());
+
+    assert_eq!(source.push(&ramp(0, 200)), 200);
+    assert_eq!(source.len(), 200);
+
+    let chunk = reader.next().unwrap().unwrap();
+    assert_eq!(chunk.sample_len(), BUFFER_SIZE as usize);
+    assert_eq!(
+        chunk.channel_data(0).as_slice(),
+        ramp(0, 128).channel_data(0).as_slice()
+    );
+    assert_eq!(
+        chunk.channel_data(1).as_slice(),
+        ramp(0, 128).channel_data(1).as_slice()
+    );
+
+    // underrun
+    let err = reader.next().unwrap().unwrap_err();
+    assert!(err.is::<web_audio_api::BufferDepletedError>());
+
+    // overrun, the frames which do not fit are dropped
+    assert_eq!(source.push(&ramp(200, 300)), 228);
+    assert_eq!(source.len(), 300);
+
+    // wrapping around the ring buffer
+    let chunk = reader.next().unwrap().unwrap();
+    assert_eq!(
+        chunk.channel_data(0).as_slice(),
+        ramp(128, 128).channel_data(0).as_slice()
+    );
+    let chunk = reader.next().unwrap().unwrap();
+    assert_eq!(
+        chunk.channel_data(1).as_slice(),
+        ramp(256, 128).channel_data(1).as_slice()
+    );
+
+    // the remainder is padded once the producer is gone
+    drop(source);
+    let chunk = reader.next().unwrap().unwrap();
+    let mut expected = ramp(384, 44).channel_data(0).as_slice().to_vec();
+    expected.resize(BUFFER_SIZE as usize, 0.);
+    assert_eq!(chunk.channel_data(0).as_slice(), &expected[..]);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_media_stream_source_from_thread() {
+    const LENGTH: usize = BUFFER_SIZE as usize * 3;
+    let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+
+    let (source, reader) = MediaStreamSource::new(1, 1024, SampleRate(44_100));
+    let node = context.create_media_stream_source(reader);
+    node.connect(&context.destination());
+
+    thread::spawn(move || {
+        let channel = ChannelData::from(vec![0.5; 200]);
+        source.push(&AudioBuffer::from_channels(
+            vec![channel],
+            SampleRate(44_100),
+        ));
+    })
+    .join()
+    .unwrap();
+
+    let output = context.start_rendering();
+    let channel_data = output.channel_data(0).as_slice();
+
+    let mut expected = vec![0.5; 200];
+    expected.resize(LENGTH, 0.);
+    assert_float_eq!(channel_data, &expected[..], ulps_all <= 0);
+}
+
+#[test]
+fn test_iter_media_stream() {
+    const LENGTH: usize = BUFFER_SIZE as usize * 3;
+    let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+
+    {
+        let buffers: Vec<_> = (1..=2)
+            .map(|i| {
+                let channel = ChannelData::from(vec![i as f32; BUFFER_SIZE as usize]);
+                AudioBuffer::from_channels(vec![channel], SampleRate(44_100))
+            })
+            .collect();
+
+        let media: IterMediaStream<_> = buffers.into_iter().into();
+        let node = context.create_media_stream_source(media);
+        node.connect(&context.destination());
+    }
+
+    let output = context.start_rendering();
+    let channel_data = output.channel_data(0).as_slice();
+
+    let mut expected = vec![1.; BUFFER_SIZE as usize];
+    expected.append(&mut vec![2.; BUFFER_SIZE as usize]);
+    expected.append(&mut vec![0.; BUFFER_SIZE as usize]);
+
+    assert_float_eq!(channel_data, &expected[..], ulps_all <= 0);
+}
+
+#[test]
+#[cfg(feature = "wav")]
+fn test_decode_wav() {
+    // stereo 16 bit file at half the sample rate of the context
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 22_050,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut encoded = std::io::Cursor::new(vec![]);
+    {
+        let mut writer = hound::WavWriter::new(&mut encoded, spec).unwrap();
+        for i in 0..100_i16 {
+            writer.write_sample(i * 256).unwrap();
+            writer.write_sample(-i * 128).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    let context = OfflineAudioContext::new(1, 0, SampleRate(44_100));
+    let buffer = context
+        .decode_audio_data(encoded.get_ref().as_slice())
+        .unwrap();
+
+    assert_eq!(buffer.number_of_channels(), 2);
+    assert_eq!(buffer.sample_rate(), SampleRate(44_100));
+    assert_eq!(buffer.sample_len(), 200);
+
+    // full scale maps to 1., interpolated between the decoded samples
+    let left = buffer.channel_data(0).as_slice();
+    let right = buffer.channel_data(1).as_slice();
+    for i in 0..99 {
+        assert_float_eq!(left[2 * i], i as f32 / 128., abs <= 1e-6);
+        assert_float_eq!(left[2 * i + 1], (i as f32 + 0.5) / 128., abs <= 1e-6);
+        assert_float_eq!(right[2 * i], -(i as f32) / 256., abs <= 1e-6);
+    }
+}
+
+#[test]
+#[cfg(feature = "wav")]
+fn test_write_wav_round_trip() {
+    let sample_rate = SampleRate(44_100);
+    let left: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin()).collect();
+    let right: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.03).cos() * 0.5).collect();
+    let buffer = AudioBuffer::from_channels(
+        vec![ChannelData::from(left), ChannelData::from(right)],
+        sample_rate,
+    );
+
+    let context = OfflineAudioContext::new(1, 0, sample_rate);
+
+    // float samples are lossless
+    let mut encoded = vec![];
+    buffer.write_wav(&mut encoded, SampleFormat::F32).unwrap();
+    let decoded = context.decode_audio_data(encoded.as_slice()).unwrap();
+    assert_eq!(decoded.sample_rate(), sample_rate);
+    assert_eq!(decoded.number_of_channels(), 2);
+    for channel in 0..2 {
+        assert_eq!(
+            decoded.channel_data(channel).as_slice(),
+            buffer.channel_data(channel).as_slice()
+        );
+    }
+
+    // 16-bit samples are quantized
+    let mut encoded = vec![];
+    buffer
+        .write_wav(&mut encoded, SampleFormat::I16(DitherType::None))
+        .unwrap();
+    let decoded = context.decode_audio_data(encoded.as_slice()).unwrap();
+    assert_eq!(decoded.number_of_channels(), 2);
+    assert_eq!(decoded.sample_len(), 1000);
+    for channel in 0..2 {
+        assert_float_eq!(
+            decoded.channel_data(channel).as_slice(),
+            buffer.channel_data(channel).as_slice(),
+            abs_all <= 1. / 32_768.
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "ogg")]
+fn test_decode_ogg() {
+    let file = std::fs::File::open("sample.ogg").unwrap();
+
+    let context = OfflineAudioContext::new(1, 0, SampleRate(48_000));
+    let buffer = context.decode_audio_data(file).unwrap();
+
+    assert_eq!(buffer.number_of_channels(), 1);
+    assert_eq!(buffer.sample_rate(), SampleRate(48_000));
+    assert!(buffer.sample_len() > 0);
+    assert!(buffer.peak() > 0.);
+}
+
+#[test]
+#[cfg(feature = "mp3")]
+fn test_decode_mp3() {
+    // stereo file at 44.1 kHz, 40 frames of 1152 samples, with a steady tone on spectral line
+    // 24 (~919 Hz) of the left and line 48 (~1838 Hz) of the right channel
+    let file = std::fs::File::open("sample.mp3").unwrap();
+
+    let context = OfflineAudioContext::new(1, 0, SampleRate(44_100));
+    let buffer = context.decode_audio_data(file).unwrap();
+
+    assert_eq!(buffer.number_of_channels(), 2);
+    assert_eq!(buffer.sample_rate(), SampleRate(44_100));
+    assert_eq!(buffer.sample_len(), 40 * 1152);
+    assert!(buffer.peak() > 0.1 && buffer.peak() < 1.);
+
+    // half a second after the first two frames has one zero crossing per Hz
+    let crossings = |channel: usize| {
+        let data = &buffer.channel_data(channel).as_slice()[2304..2304 + 22_050];
+        data.windows(2)
+            .filter(|w| (w[0] < 0.) != (w[1] < 0.))
+            .count() as f32
+    };
+    assert_float_eq!(crossings(0), 44_100. / 1152. * 24., abs <= 5.);
+    assert_float_eq!(crossings(1), 44_100. / 1152. * 48., abs <= 5.);
+}
+
+#[test]
+fn test_decode_errors() {
+    let context = OfflineAudioContext::new(1, 0, SampleRate(44_100));
+
+    // MP3 file with an ID3 tag
+    let mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x00\xff\xfb\x90\x00";
+    let result = context.decode_audio_data(&mp3[..]);
+    #[cfg(feature = "mp3")]
+    assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+    #[cfg(not(feature = "mp3"))]
+    assert!(matches!(result, Err(DecodeError::UnsupportedFormat)));
+
+    // FLAC file
+    let flac = b"fLaC\x00\x00\x00\x22\x00\x00\x00\x00";
+    let result = context.decode_audio_data(&flac[..]);
+    assert!(matches!(result, Err(DecodeError::UnsupportedFormat)));
+
+    let result = context.decode_audio_data(&[][..]);
+    assert!(matches!(result, Err(DecodeError::UnsupportedFormat)));
+
+    // truncated header
+    #[cfg(feature = "wav")]
+    {
+        let wav = b"RIFF\x24\x00\x00\x00WAVEfmt ";
+        let result = context.decode_audio_data(&wav[..]);
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+    }
+}
@@ -0,0 +1,109 @@
+use float_eq::assert_float_eq;
+use web_audio_api::buffer::{AudioBuffer, ChannelCountMode, ChannelData, ChannelInterpretation};
+use web_audio_api::context::{AsBaseAudioContext, OfflineAudioContext};
+use web_audio_api::node::{
+    AudioBufferSourceNode, AudioBufferSourceNodeOptions, AudioNode, AudioScheduledSourceNode,
+};
+use web_audio_api::{SampleRate, BUFFER_SIZE};
+
+const LENGTH: usize = BUFFER_SIZE as usize;
+
+/// Plays a buffer holding a constant level per channel
+fn multichannel_source(context: &OfflineAudioContext, levels: &[f32]) -> AudioBufferSourceNode {
+    let channels = levels
+        .iter()
+        .map(|&level| ChannelData::from(vec![level; LENGTH]))
+        .collect();
+    let buffer = AudioBuffer::from_channels(channels, context.sample_rate());
+
+    let options = AudioBufferSourceNodeOptions {
+        buffer: Some(buffer),
+        ..AudioBufferSourceNodeOptions::default()
+    };
+    let src = AudioBufferSourceNode::new(context, options);
+    src.start();
+
+    src
+}
+
+/// Renders `levels` through a gain node mixing its input to `channels` speaker channels
+fn render_mix(levels: &[f32], channels: usize) -> AudioBuffer {
+    let mut context = OfflineAudioContext::new(channels as u32, LENGTH, SampleRate(44_100));
+    let src = multichannel_source(&context, levels);
+
+    let gain = context.create_gain();
+    gain.set_channel_count_mode(ChannelCountMode::Explicit);
+    gain.set_channel_interpretation(ChannelInterpretation::Speakers);
+    gain.set_channel_count(channels);
+    src.connect(&gain);
+    gain.connect(&context.destination());
+
+    context.start_rendering()
+}
+
+fn assert_levels(output: &AudioBuffer, levels: &[f32]) {
+    assert_eq!(output.number_of_channels(), levels.len());
+    for (channel, &level) in levels.iter().enumerate() {
+        assert_float_eq!(
+            output.channel_data(channel).as_slice(),
+            &[level; LENGTH][..],
+            abs_all <= 1e-6
+        );
+    }
+}
+
+#[test]
+fn test_mono_to_stereo_copies_without_gain() {
+    // each channel carries the mono signal at its original level, not +3 dB or -3 dB
+    let output = render_mix(&[0.5], 2);
+    assert_levels(&output, &[0.5, 0.5]);
+}
+
+#[test]
+fn test_mono_sums_with_stereo() {
+    let mut context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
+
+    let mono = context.create_constant_source();
+    mono.offset().set_value(0.25);
+    mono.connect(&context.destination());
+
+    let stereo = multichannel_source(&context, &[0.5, -0.5]);
+    stereo.connect(&context.destination());
+
+    let output = context.start_rendering();
+    assert_levels(&output, &[0.75, -0.25]);
+}
+
+#[test]
+fn test_stereo_to_mono_averages() {
+    let output = render_mix(&[1., 0.5], 1);
+    assert_levels(&output, &[0.75]);
+
+    // identical channels keep their level
+    let output = render_mix(&[0.5, 0.5], 1);
+    assert_levels(&output, &[0.5]);
+}
+
+#[test]
+fn test_quad_to_stereo_averages_front_and_surround() {
+    let output = render_mix(&[1., 0.5, 0., 0.5], 2);
+    assert_levels(&output, &[0.5, 0.5]);
+}
+
+#[test]
+fn test_surround_to_stereo_coefficients() {
+    // L, R, C, LFE, SL, SR: the center and surrounds are attenuated by sqrt(0.5), the LFE dropped
+    let output = render_mix(&[0.1, 0.2, 0.3, 1., 0.4, 0.5], 2);
+    let sqrt05 = 0.5_f32.sqrt();
+    assert_levels(
+        &output,
+        &[0.1 + sqrt05 * (0.3 + 0.4), 0.2 + sqrt05 * (0.3 + 0.5)],
+    );
+}
+
+#[test]
+fn test_surround_to_mono_coefficients() {
+    let output = render_mix(&[0.1, 0.2, 0.3, 1., 0.4, 0.5], 1);
+    let sqrt05 = 0.5_f32.sqrt();
+    assert_levels(&output, &[sqrt05 * (0.1 + 0.2) + 0.3 + 0.5 * (0.4 + 0.5)]);
+}
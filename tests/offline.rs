@@ -1,9 +1,14 @@
 use float_eq::assert_float_eq;
 use web_audio_api::context::AsBaseAudioContext;
-use web_audio_api::context::OfflineAudioContext;
+#[cfg(feature = "manual")]
+use web_audio_api::context::ManualAudioContext;
+use web_audio_api::context::{OfflineAudioContext, OfflineAudioContextOptions};
 use web_audio_api::node::{
-    AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, OscillatorType,
+    AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, PeriodicWave,
+    PeriodicWaveOptions,
 };
+use web_audio_api::param::AudioParam;
+use web_audio_api::process::{AudioParamValues, AudioProcessor};
 use web_audio_api::{SampleRate, BUFFER_SIZE};
 
 #[test]
@@ -42,6 +47,243 @@ fn test_offline_render() {
     );
 }
 
+#[test]
+fn test_output_limiter() {
+    const LENGTH: usize = 555;
+
+    let mut context = OfflineAudioContext::new(2, LENGTH, SampleRate(44_100));
+    assert!(!context.destination().output_limiter());
+    context.destination().set_output_limiter(true);
+    assert!(context.destination().output_limiter());
+
+    {
+        let loud = context.create_constant_source();
+        loud.offset().set_value(3.);
+        loud.connect(&context.destination());
+
+        let quiet = context.create_constant_source();
+        quiet.offset().set_value(-0.5);
+        quiet.connect(&context.destination());
+
+        // mix of 0.5 for the first 200 frames, -2. after that
+        let toggle = context.create_constant_source();
+        toggle.offset().set_value(-2.);
+        toggle.offset().set_value_at_time(-4.5, 200. / 44_100.);
+        toggle.connect(&context.destination());
+    }
+
+    let output = context.start_rendering();
+
+    for channel in 0..2 {
+        let data = output.channel_data(channel).as_slice();
+        // within range, the output is untouched
+        assert_float_eq!(&data[..200], &[0.5; 200][..], ulps_all <= 0);
+        // out of range, the output is limited
+        assert!(data.iter().all(|v| v.abs() <= 1.));
+        assert!(data[200..].iter().all(|&v| v < -0.9));
+    }
+}
+
+#[test]
+#[cfg(feature = "manual")]
+fn test_muted_channel() {
+    let mut context = ManualAudioContext::new(2, SampleRate(44_100));
+    assert!(!context.destination().channel_muted(1));
+    context.destination().set_channel_muted(1, true);
+    assert!(context.destination().channel_muted(1));
+    assert!(!context.destination().channel_muted(0));
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+
+    // interleaved device output
+    let mut buffer = vec![0.; 2 * BUFFER_SIZE as usize];
+    context.render(&mut buffer);
+    assert!(buffer.chunks(2).all(|frame| frame == [1., 0.]));
+
+    context.destination().set_channel_muted(1, false);
+    context.render(&mut buffer);
+    assert!(buffer.chunks(2).all(|frame| frame == [1., 1.]));
+}
+
+#[test]
+#[cfg(feature = "manual")]
+fn test_runaway_detection() {
+    let sample_rate = SampleRate(44_100);
+    let mut context = ManualAudioContext::new(1, sample_rate);
+    let quantum = f64::from(BUFFER_SIZE) / f64::from(sample_rate.0);
+    context
+        .destination()
+        .enable_runaway_detection(2., 4. * quantum);
+
+    // a feedback loop through a delay with a gain of 1.5, excited by a single render quantum
+    let feedback = context.create_gain();
+    feedback.gain().set_value(1.5);
+    let delay = context.create_delay(1.);
+    delay.delay_time().set_value(quantum as f32);
+    feedback.connect(&delay);
+    delay.connect(&feedback);
+    feedback.connect(&context.destination());
+
+    let impulse = context.create_constant_source();
+    impulse.connect(&feedback);
+    impulse.offset().set_value_at_time(0., quantum);
+
+    // the level grows every render quantum: 1.5, 2.25, 3.375, ..
+    let mut buffer = vec![0.; BUFFER_SIZE as usize];
+    for _ in 0..4 {
+        context.render(&mut buffer);
+    }
+    assert!(buffer.iter().all(|&v| v > 2.));
+    // only the last three quanta exceeded the threshold
+    assert!(!context.destination().runaway_detected());
+
+    context.render(&mut buffer);
+    assert!(context.destination().runaway_detected());
+
+    // the flag is kept until reset
+    feedback.gain().set_value(0.);
+    context.render(&mut buffer);
+    context.render(&mut buffer);
+    assert!(buffer.iter().all(|&v| v == 0.));
+    assert!(context.destination().runaway_detected());
+    context.destination().reset_runaway_detected();
+    assert!(!context.destination().runaway_detected());
+
+    // short bursts do not trip the detection
+    let src = context.create_constant_source();
+    src.offset().set_value(0.);
+    src.connect(&context.destination());
+    for _ in 0..10 {
+        src.offset().set_value(10.);
+        context.render(&mut buffer);
+        src.offset().set_value(0.);
+        context.render(&mut buffer);
+    }
+    assert!(!context.destination().runaway_detected());
+
+    // nor do sustained levels when disabled
+    context.destination().disable_runaway_detection();
+    src.offset().set_value(10.);
+    for _ in 0..10 {
+        context.render(&mut buffer);
+    }
+    assert!(!context.destination().runaway_detected());
+}
+
+#[test]
+fn test_deterministic_render() {
+    fn render() -> Vec<f32> {
+        let sample_rate = SampleRate(44_100);
+        let mut context = OfflineAudioContext::new(2, 4 * BUFFER_SIZE as usize, sample_rate);
+
+        // many inputs mixed into a single node, summation order matters for floats
+        let gain = context.create_gain();
+        gain.gain().set_value_at_time(0.3, 0.);
+        gain.gain().linear_ramp_to_value_at_time(0.9, 0.01);
+        gain.connect(&context.destination());
+
+        for i in 0..16 {
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(110. * (i + 1) as f32);
+            osc.frequency()
+                .linear_ramp_to_value_at_time(55. * (i + 3) as f32, 0.008);
+            osc.connect(&gain);
+            osc.start();
+
+            let src = context.create_constant_source();
+            src.offset().set_value(0.1 / (i + 1) as f32);
+            src.offset().set_value_at_time(0.7 / (i + 3) as f32, 0.005);
+            src.connect(&gain);
+        }
+
+        let output = context.start_rendering();
+        let mut data = output.channel_data(0).as_slice().to_vec();
+        data.extend_from_slice(output.channel_data(1).as_slice());
+        data
+    }
+
+    let first = render();
+    let second = render();
+
+    // bit identical output
+    let first_bits: Vec<_> = first.iter().map(|v| v.to_bits()).collect();
+    let second_bits: Vec<_> = second.iter().map(|v| v.to_bits()).collect();
+    assert_eq!(first_bits, second_bits);
+}
+
+#[test]
+fn test_render_in_background() {
+    const LENGTH: usize = 555;
+
+    fn context(offset: f32) -> OfflineAudioContext {
+        let context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+        let src = context.create_constant_source();
+        src.offset().set_value(offset);
+        src.connect(&context.destination());
+        context
+    }
+
+    // render several contexts in parallel
+    let handles: Vec<_> = (1..=4)
+        .map(|i| context(i as f32 / 4.).start_rendering_in_background())
+        .collect();
+
+    for (i, handle) in (1..=4).zip(handles) {
+        let output = handle.join().unwrap();
+        assert_eq!(output.sample_len(), LENGTH);
+        assert!(output
+            .channel_data(0)
+            .as_slice()
+            .iter()
+            .all(|&v| v == i as f32 / 4.));
+    }
+
+    // identical to a blocking render
+    let background = context(0.5).start_rendering_in_background().join().unwrap();
+    let blocking = context(0.5).start_rendering();
+    assert_eq!(
+        background.channel_data(0).as_slice(),
+        blocking.channel_data(0).as_slice()
+    );
+}
+
+#[test]
+fn test_onprogress() {
+    const LENGTH: usize = 40_000;
+
+    let mut context = OfflineAudioContext::new(1, LENGTH, SampleRate(44_100));
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+
+    let progress = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let progress_clone = progress.clone();
+    context.set_onprogress(move |p| progress_clone.lock().unwrap().push(p));
+
+    let output = context.start_rendering();
+    assert_eq!(output.sample_len(), LENGTH);
+    assert!(output.channel_data(0).as_slice().iter().all(|&v| v == 1.));
+
+    // every 128 render quanta, and at the end
+    let expected = [16_384. / 40_000., 32_768. / 40_000., 1.];
+    assert_eq!(progress.lock().unwrap().as_slice(), &expected[..]);
+}
+
+/// Options of an oscillator emitting a constant signal: a cosine of zero frequency
+fn constant_oscillator(context: &OfflineAudioContext) -> OscillatorOptions {
+    let cosine = PeriodicWaveOptions {
+        real: Some(vec![0., 1.]),
+        imag: Some(vec![0., 0.]),
+        disable_normalization: Some(false),
+    };
+
+    OscillatorOptions {
+        frequency: Some(0.),
+        periodic_wave: Some(PeriodicWave::new(context, Some(cosine))),
+        ..Default::default()
+    }
+}
+
 #[test]
 fn test_start_stop() {
     let len = (BUFFER_SIZE * 4) as usize;
@@ -49,12 +291,7 @@ fn test_start_stop() {
     assert_eq!(context.length(), len);
 
     {
-        let opts = OscillatorOptions {
-            type_: Some(OscillatorType::Square),
-            frequency: Some(0.), // constant signal
-            ..Default::default()
-        };
-        let osc = OscillatorNode::new(&context, Some(opts));
+        let osc = OscillatorNode::new(&context, Some(constant_oscillator(&context)));
         osc.connect(&context.destination());
 
         osc.start_at(1.);
@@ -75,6 +312,134 @@ fn test_start_stop() {
     assert_eq!(channel_data, expected.as_slice());
 }
 
+#[test]
+fn test_onended() {
+    let len = (BUFFER_SIZE * 4) as usize;
+    let mut context = OfflineAudioContext::new(1, len, SampleRate(BUFFER_SIZE));
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let osc = OscillatorNode::new(&context, Some(constant_oscillator(&context)));
+    osc.connect(&context.destination());
+    osc.start();
+    osc.stop_at(2.);
+    let osc_sender = sender.clone();
+    osc.set_onended(move || osc_sender.send("oscillator").unwrap());
+
+    // the default buffer of one render quantum runs out
+    let src = context.create_buffer_source();
+    src.connect(&context.destination());
+    src.start();
+    let src_sender = sender.clone();
+    src.set_onended(move || src_sender.send("buffer").unwrap());
+
+    // dropped before the end of playback
+    {
+        let dropped = OscillatorNode::new(&context, Some(constant_oscillator(&context)));
+        dropped.connect(&context.destination());
+        dropped.start();
+        dropped.stop_at(1.);
+        dropped.set_onended(move || sender.send("dropped").unwrap());
+    }
+
+    context.start_rendering();
+
+    let timeout = std::time::Duration::from_secs(1);
+    let mut ended = vec![
+        receiver.recv_timeout(timeout).unwrap(),
+        receiver.recv_timeout(timeout).unwrap(),
+    ];
+    ended.sort_unstable();
+    assert_eq!(ended, ["buffer", "oscillator"]);
+
+    // each callback runs once
+    let timeout = std::time::Duration::from_millis(100);
+    assert!(receiver.recv_timeout(timeout).is_err());
+}
+
+#[test]
+fn test_onended_many_nodes() {
+    let len = (BUFFER_SIZE * 4) as usize;
+    let mut context = OfflineAudioContext::new(1, len, SampleRate(BUFFER_SIZE));
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    // one-shot sources, all callbacks are run by the event thread of the context
+    let sources: Vec<_> = (0..500)
+        .map(|i| {
+            let src = context.create_buffer_source();
+            src.connect(&context.destination());
+            src.start();
+            let sender = sender.clone();
+            src.set_onended(move || sender.send(i).unwrap());
+            src
+        })
+        .collect();
+
+    context.start_rendering();
+
+    let timeout = std::time::Duration::from_secs(1);
+    let mut ended: Vec<_> = (0..sources.len())
+        .map(|_| receiver.recv_timeout(timeout).unwrap())
+        .collect();
+    ended.sort_unstable();
+    assert_eq!(ended, (0..500).collect::<Vec<_>>());
+}
+
+#[test]
+#[cfg(feature = "manual")]
+fn test_manual_context_runs_onended_inline() {
+    let mut context = ManualAudioContext::new(1, SampleRate(BUFFER_SIZE));
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+    osc.stop_at(1.);
+    osc.set_onended(move || sender.send(std::thread::current().id()).unwrap());
+
+    let mut buffer = [0.; BUFFER_SIZE as usize];
+    context.render(&mut buffer);
+    assert!(receiver.try_recv().is_err());
+
+    // the callback already ran when rendering returns, on the rendering thread: the context has
+    // no event thread
+    context.render(&mut buffer);
+    assert_eq!(receiver.try_recv(), Ok(std::thread::current().id()));
+
+    context.render(&mut buffer);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn test_start_relative_to() {
+    let len = (BUFFER_SIZE * 4) as usize;
+    let mut context = OfflineAudioContext::new(2, len, SampleRate(BUFFER_SIZE));
+
+    {
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+
+        let first = OscillatorNode::new(&context, Some(constant_oscillator(&context)));
+        first.connect_at(&merger, 0, 0).unwrap();
+        let second = OscillatorNode::new(&context, Some(constant_oscillator(&context)));
+        second.connect_at(&merger, 0, 1).unwrap();
+
+        first.start_at(1.);
+        second.start_relative_to(&first, 2.);
+    }
+
+    let output = context.start_rendering();
+
+    // first layer starts after one chunk
+    let mut expected = vec![0.; BUFFER_SIZE as usize];
+    expected.append(&mut vec![1.; 3 * BUFFER_SIZE as usize]);
+    assert_eq!(output.channel_data(0).as_slice(), expected.as_slice());
+
+    // second layer starts two chunks later
+    let mut expected = vec![0.; 3 * BUFFER_SIZE as usize];
+    expected.append(&mut vec![1.; BUFFER_SIZE as usize]);
+    assert_eq!(output.channel_data(1).as_slice(), expected.as_slice());
+}
+
 #[test]
 fn test_delayed_constant_source() {
     let len = (BUFFER_SIZE * 4) as usize;
@@ -136,6 +501,49 @@ fn test_audio_param_graph() {
     assert_eq!(channel_data, expected.as_slice());
 }
 
+#[test]
+fn test_audio_param_fan_out() {
+    let len = BUFFER_SIZE as usize * 2;
+    let mut context = OfflineAudioContext::new(2, len, SampleRate(BUFFER_SIZE));
+    {
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+
+        // one constant source controls the gain of both channels
+        let control = context.create_constant_source();
+        control.offset().set_value(0.5);
+        control.offset().set_value_at_time(0.25, 1.);
+
+        let left_gain = context.create_gain();
+        left_gain.gain().set_value(0.);
+        left_gain.connect_at(&merger, 0, 0).unwrap();
+        control.connect(left_gain.gain());
+
+        let right_gain = context.create_gain();
+        right_gain.gain().set_value(0.);
+        right_gain.connect_at(&merger, 0, 1).unwrap();
+        control.connect(right_gain.gain());
+
+        let left = context.create_constant_source();
+        left.connect(&left_gain);
+
+        let right = context.create_constant_source();
+        right.offset().set_value(2.);
+        right.connect(&right_gain);
+    }
+
+    let output = context.start_rendering();
+    assert_eq!(output.number_of_channels(), 2);
+
+    let mut expected_left = vec![0.5; BUFFER_SIZE as usize];
+    expected_left.append(&mut vec![0.25; BUFFER_SIZE as usize]);
+    assert_eq!(output.channel_data(0).as_slice(), expected_left.as_slice());
+
+    let mut expected_right = vec![1.; BUFFER_SIZE as usize];
+    expected_right.append(&mut vec![0.5; BUFFER_SIZE as usize]);
+    assert_eq!(output.channel_data(1).as_slice(), expected_right.as_slice());
+}
+
 #[test]
 fn test_listener() {
     let len = BUFFER_SIZE as usize;
@@ -156,34 +564,393 @@ fn test_listener() {
 }
 
 #[test]
+#[should_panic(expected = "NotSupportedError")]
 fn test_cycle() {
-    let len = BUFFER_SIZE as usize;
-    let mut context = OfflineAudioContext::new(1, len, SampleRate(44_100));
+    let context = OfflineAudioContext::new(1, BUFFER_SIZE as usize, SampleRate(44_100));
 
-    {
-        let cycle1 = context.create_gain();
-        cycle1.connect(&context.destination());
+    let cycle1 = context.create_gain();
+    cycle1.connect(&context.destination());
+
+    let cycle2 = context.create_gain();
+    cycle2.connect(&cycle1);
+
+    // a cycle without DelayNode is rejected
+    cycle1.connect(&cycle2);
+}
+
+#[test]
+fn test_cycle_with_delay() {
+    let context = OfflineAudioContext::new(1, BUFFER_SIZE as usize, SampleRate(44_100));
+
+    let gain = context.create_gain();
+    let delay = context.create_delay(1.);
+    gain.connect(&delay);
+    delay.connect(&gain);
+    delay.connect(&delay);
+
+    // the delay does not break a cycle through its delay time
+    let modulation = context.create_gain();
+    delay.connect(&modulation);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        modulation.connect(delay.delay_time());
+    }));
+    assert!(result.is_err());
+
+    // the cycle is allowed again once a connection is removed
+    delay.disconnect(&modulation);
+    modulation.connect(delay.delay_time());
+}
+
+/// Root processor applying a fixed gain to the final mix
+struct ScalingRoot(f32);
+
+impl AudioProcessor for ScalingRoot {
+    fn process(
+        &mut self,
+        inputs: &[web_audio_api::alloc::AudioBuffer],
+        outputs: &mut [web_audio_api::alloc::AudioBuffer],
+        _params: AudioParamValues,
+        _timestamp: f64,
+        _sample_rate: SampleRate,
+    ) {
+        let scale = self.0;
+        outputs[0] = inputs[0].clone();
+        outputs[0].modify_channels(|channel| channel.iter_mut().for_each(|v| *v *= scale));
+    }
+
+    fn tail_time(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_custom_root_processor() {
+    let mut context = OfflineAudioContext::new_with_options(OfflineAudioContextOptions {
+        number_of_channels: 2,
+        length: 300,
+        sample_rate: SampleRate(44_100),
+        root_processor: Some(Box::new(ScalingRoot(0.25))),
+    });
+    assert_eq!(context.length(), 300);
+
+    let constant = context.create_constant_source();
+    constant.offset().set_value(2.);
+    constant.connect(&context.destination());
+
+    let output = context.start_rendering();
+    assert_eq!(output.number_of_channels(), 2);
+    assert_eq!(output.sample_len(), 300);
+    assert_float_eq!(
+        output.channel_data(0).as_slice(),
+        &[0.5; 300][..],
+        ulps_all <= 0
+    );
+    assert_float_eq!(
+        output.channel_data(1).as_slice(),
+        &[0.5; 300][..],
+        ulps_all <= 0
+    );
+}
+
+#[test]
+fn test_reset_renders_identically() {
+    let sample_rate = SampleRate(44_100);
+    let mut context = OfflineAudioContext::new(1, 1_000, sample_rate);
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(310.);
+    let filter = context.create_biquad_filter();
+    let delay = context.create_delay(1.);
+    delay.delay_time().set_value(256. / sample_rate.0 as f32);
+
+    osc.connect(&filter);
+    filter.connect(&context.destination());
+    filter.connect(&delay);
+    delay.connect(&context.destination());
+    osc.start();
+
+    let first = context.start_rendering();
+    assert!(context.current_time() > 0.);
+
+    context.reset();
+    assert_eq!(context.current_time(), 0.);
+
+    let second = context.start_rendering();
+    let bits = |buffer: &web_audio_api::buffer::AudioBuffer| {
+        buffer
+            .channel_data(0)
+            .as_slice()
+            .iter()
+            .map(|v| v.to_bits())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(bits(&first), bits(&second));
+}
+
+#[test]
+fn test_long_ramp_precision() {
+    let sample_rate = SampleRate(44_100);
+    let length = 10 * sample_rate.0 as usize;
+    let duration = length as f64 / f64::from(sample_rate.0);
+
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+    let src = context.create_constant_source();
+    src.offset().set_value_at_time(0., 0.);
+    src.offset().linear_ramp_to_value_at_time(1., duration);
+    src.connect(&context.destination());
 
-        let cycle2 = context.create_gain();
-        cycle2.connect(&cycle1);
+    let output = context.start_rendering();
+    assert_eq!(output.number_of_channels(), 1);
+    assert_eq!(output.sample_len(), length);
+
+    // the ramp is within single precision rounding of the exact value over the whole render,
+    // while summing the per-sample increment in single precision would drift away from it
+    let slope = 1. / length as f64;
+    let mut accumulated = 0_f32;
+    let mut accumulated_error = 0_f64;
+    let mut error = 0_f64;
+    for (i, &v) in output.channel_data(0).as_slice().iter().enumerate() {
+        let expected = i as f64 * slope;
+        error = error.max((f64::from(v) - expected).abs());
+        accumulated_error = accumulated_error.max((f64::from(accumulated) - expected).abs());
+        accumulated += slope as f32;
+    }
 
-        // here we go
-        cycle1.connect(&cycle2);
+    assert!(error <= f64::from(f32::EPSILON), "{}", error);
+    assert!(accumulated_error > 100. * error, "{}", accumulated_error);
+}
 
-        let source_cycle = context.create_constant_source();
-        source_cycle.offset().set_value(1.);
-        source_cycle.connect(&cycle1);
+#[test]
+#[cfg(feature = "manual")]
+fn test_graph_latency() {
+    use web_audio_api::buffer::{AudioBuffer, ChannelData};
+    use web_audio_api::node::{ConvolverNode, ConvolverOptions};
+
+    let sample_rate = SampleRate(44_100);
+    let mut context = ManualAudioContext::new(1, sample_rate);
+
+    let src = context.create_constant_source();
+
+    // delay of two render quanta
+    let delay = context.create_delay(1.);
+    delay.delay_time().set_value(256. / sample_rate.0 as f32);
+
+    // partitions of 1024 sample-frames delay the output by 896 sample-frames
+    let impulse = ChannelData::from(vec![1., 0.5, 0.25]);
+    let options = ConvolverOptions {
+        buffer: Some(AudioBuffer::from_channels(vec![impulse], sample_rate)),
+        ..ConvolverOptions::default()
+    };
+    let convolver = ConvolverNode::new(&context, options);
+    convolver.set_partition_size(1024);
+
+    src.connect(&delay);
+    delay.connect(&convolver);
+    convolver.connect(&context.destination());
+
+    // not connected to the destination
+    let unconnected = context.create_delay(1.);
+    unconnected.delay_time().set_value(0.5);
+
+    let mut output = [0.; 128];
+    context.render(&mut output);
+
+    let seconds = |samples: f64| samples / f64::from(sample_rate.0);
+    let base = context.base();
+    assert_float_eq!(base.graph_latency(&src), seconds(256. + 896.), ulps <= 1);
+    assert_float_eq!(base.graph_latency(&delay), seconds(256. + 896.), ulps <= 1);
+    assert_float_eq!(base.graph_latency(&convolver), seconds(896.), ulps <= 1);
+    assert_float_eq!(base.graph_latency(&context.destination()), 0., ulps <= 0);
+    assert_float_eq!(base.graph_latency(&unconnected), 0., ulps <= 0);
+}
+
+#[test]
+fn test_linear_ramp() {
+    let sample_rate = SampleRate(44_100);
+    let length = sample_rate.0 as usize;
+    let duration = length as f64 / f64::from(sample_rate.0);
+
+    let render = |schedule: &dyn Fn(&AudioParam)| {
+        let mut context = OfflineAudioContext::new(1, length, sample_rate);
+        let src = context.create_constant_source();
+        schedule(src.offset());
+        src.connect(&context.destination());
+        context
+            .start_rendering()
+            .channel_data(0)
+            .as_slice()
+            .to_vec()
+    };
+
+    // one second fade in
+    let fade = render(&|offset| {
+        offset.set_value_at_time(0., 0.);
+        offset.linear_ramp_to_value_at_time(1., duration);
+    });
+
+    // sample accurate interpolation
+    let slope = 1. / length as f32;
+    assert_float_eq!(fade[length / 2], 0.5, abs <= 1e-6);
+    for (i, &v) in fade.iter().enumerate() {
+        assert_float_eq!(v, i as f32 * slope, abs <= 1e-6);
+    }
+
+    // without prior event, the ramp starts from the current value
+    let fade_out = render(&|offset| offset.linear_ramp_to_value_at_time(0., duration));
+    assert_float_eq!(fade_out[0], 1., abs <= 1e-6);
+    assert_float_eq!(fade_out[length / 2], 0.5, abs <= 1e-6);
+
+    // the second of back to back ramps continues from the end of the first one
+    let triangle = render(&|offset| {
+        offset.set_value_at_time(0., 0.);
+        offset.linear_ramp_to_value_at_time(1., duration / 2.);
+        offset.linear_ramp_to_value_at_time(0., duration);
+    });
+    assert_float_eq!(triangle[length / 4], 0.5, abs <= 1e-6);
+    assert_float_eq!(triangle[length / 2], 1., abs <= 1e-6);
+    assert_float_eq!(triangle[3 * length / 4], 0.5, abs <= 1e-6);
+    for w in triangle.windows(2) {
+        assert!((w[1] - w[0]).abs() <= 2. * slope + 1e-6);
+    }
+}
 
-        let other = context.create_constant_source();
-        other.offset().set_value(2.);
-        other.connect(&context.destination());
+#[test]
+#[cfg(feature = "manual")]
+fn test_exponential_frequency_sweep() {
+    let sample_rate = SampleRate(44_100);
+    let mut context = ManualAudioContext::new(1, sample_rate);
+
+    // two octaves up in 400 render quanta, quarter points fall on render quantum boundaries
+    let quanta = 400;
+    let duration = (quanta * 128) as f64 / f64::from(sample_rate.0);
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value_at_time(220., 0.);
+    osc.frequency()
+        .exponential_ramp_to_value_at_time(880., duration);
+    osc.connect(&context.destination());
+    osc.start();
+
+    // equal ratios in equal times, 220 * 4^(t / duration): one octave per half of the sweep
+    let mut output = [0.; 128];
+    for expected in [311.127, 440., 622.254, 880.] {
+        for _ in 0..quanta / 4 {
+            context.render(&mut output);
+        }
+        assert_float_eq!(osc.frequency().value(), expected, abs <= 1e-3);
     }
+}
+
+#[test]
+fn test_set_target() {
+    let sample_rate = SampleRate(44_100);
+    let length = sample_rate.0 as usize / 2;
+    let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+    // release from 1 to 0, starting at 0.1 second
+    let start = 0.1;
+    let time_constant = 0.1;
+    let src = context.create_constant_source();
+    src.offset()
+        .set_target_at_time(0., start, time_constant as f32);
+    src.connect(&context.destination());
 
     let output = context.start_rendering();
-    // cycle should be muted, and other source should be processed
+    let output = output.channel_data(0).as_slice();
+
+    let index = |t: f64| (t * f64::from(sample_rate.0)).round() as usize;
+    assert_float_eq!(output[index(start)], 1., abs <= 1e-6);
+    assert_float_eq!(
+        output[index(start + time_constant)],
+        (-1_f32).exp(),
+        abs <= 1e-5
+    );
+    assert_float_eq!(
+        output[index(start + 3. * time_constant)],
+        (-3_f32).exp(),
+        abs <= 1e-5
+    );
+}
+
+#[test]
+fn test_stereo_graph() {
+    let mut context = OfflineAudioContext::new(2, 512, SampleRate(44_100));
+
+    // mono oscillator, panned slightly to the right
+    let osc = context.create_oscillator();
+    let panner = context.create_stereo_panner();
+    panner.pan().set_value(0.5);
+    osc.connect(&panner);
+    panner.connect(&context.destination());
+    osc.start();
+
+    let output = context.start_rendering();
+    assert_eq!(output.number_of_channels(), 2);
+
+    let energy = |channel: usize| -> f32 {
+        output
+            .channel_data(channel)
+            .as_slice()
+            .iter()
+            .map(|v| v * v)
+            .sum()
+    };
+    let (left, right) = (energy(0), energy(1));
+
+    // both channels are populated, and the mono input was not duplicated
+    assert!(left > 1., "{}", left);
+    assert!(right > 1., "{}", right);
+    assert!(right > 2. * left, "{} {}", left, right);
+}
+
+#[test]
+fn test_suspend_at() {
+    let sample_rate = SampleRate(44_100);
+
+    let render = |suspend: bool| {
+        let mut context = OfflineAudioContext::new(1, 1_000, sample_rate);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(310.);
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(100. / sample_rate.0 as f32);
+        osc.connect(&delay);
+        delay.connect(&context.destination());
+        osc.start();
+
+        if suspend {
+            context.suspend_at(0.001, |_| ());
+            context.suspend_at(0.01, |_| ());
+        }
+
+        context.start_rendering()
+    };
+
+    // without changes, the render is identical to an uninterrupted one
+    let expected = render(false);
+    let output = render(true);
     assert_float_eq!(
         output.channel_data(0).as_slice(),
-        &[2.; BUFFER_SIZE as usize][..],
+        expected.channel_data(0).as_slice(),
         ulps_all <= 0
     );
+
+    // changes apply from the rounded suspension time on
+    let mut context = OfflineAudioContext::new(1, 1_000, sample_rate);
+    context.suspend_at(200. / sample_rate.0 as f64, |context| {
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+    });
+    let output = context.start_rendering();
+    let data = output.channel_data(0).as_slice();
+    assert_float_eq!(data[..256], [0.; 256][..], abs_all <= 0.);
+    assert_float_eq!(data[256..], [1.; 744][..], abs_all <= 0.);
+}
+
+#[test]
+#[should_panic(expected = "InvalidStateError")]
+fn test_suspend_at_same_time() {
+    let mut context = OfflineAudioContext::new(1, 1_000, SampleRate(44_100));
+    context.suspend_at(0.001, |_| ());
+    context.suspend_at(0.002, |_| ());
 }